@@ -0,0 +1,67 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kritisch::{game::Game, try_square_offset, Color, Square};
+
+const COMPLEX_FEN: &str = "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8";
+
+fn squares(c: &mut Criterion) {
+    c.bench_function("square_offset", |b| {
+        b.iter(|| try_square_offset(black_box(Square::E4), 1, -1))
+    });
+    c.bench_function("square_get_file", |b| {
+        b.iter(|| black_box(Square::E2).get_file())
+    });
+    c.bench_function("square_get_rank", |b| {
+        b.iter(|| black_box(Square::E2).get_rank())
+    });
+}
+
+fn queries(c: &mut Criterion) {
+    let game = Game::default();
+    c.bench_function("color_at", |b| {
+        b.iter(|| black_box(&game).color_at(Square::E2))
+    });
+    c.bench_function("type_at", |b| {
+        b.iter(|| black_box(&game).type_at(Square::E2))
+    });
+
+    let game = Game::from_fen(COMPLEX_FEN).unwrap();
+    c.bench_function("piece_at_all_squares", |b| {
+        b.iter(|| {
+            Square::all()
+                .filter_map(|s| black_box(&game).piece_at(s))
+                .count()
+        })
+    });
+    c.bench_function("to_fen", |b| b.iter(|| black_box(&game).to_fen()));
+    let mut buffer = String::with_capacity(96);
+    c.bench_function("write_fen", |b| {
+        b.iter(|| {
+            buffer.clear();
+            black_box(&game).write_fen(&mut buffer).unwrap();
+        })
+    });
+}
+
+fn attacks(c: &mut Criterion) {
+    let game =
+        Game::from_fen("rnbqkbnr/p1pppppp/8/1p6/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+    c.bench_function("attackers_simple", |b| {
+        b.iter(|| black_box(&game).is_attacked_by(Color::WHITE, Square::B5))
+    });
+
+    let game = Game::from_fen(COMPLEX_FEN).unwrap();
+    c.bench_function("attackers_complex", |b| {
+        b.iter(|| black_box(&game).is_attacked_by(Color::WHITE, Square::H5))
+    });
+    c.bench_function("attackers_to", |b| {
+        b.iter(|| black_box(&game).attackers_to(Square::H5, game.all_pieces()))
+    });
+    c.bench_function("attacked_squares", |b| {
+        b.iter(|| black_box(&game).attacked_squares(Color::WHITE))
+    });
+}
+
+criterion_group!(benches, squares, queries, attacks);
+criterion_main!(benches);
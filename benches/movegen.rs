@@ -0,0 +1,77 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kritisch::{
+    corpus,
+    game::Game,
+    movegen::{
+        all_legal_moves, get_blockers_from_position, king_moves, pawn_moves, perft_with,
+        pseudolegal_slider_moves, slider_moves, MakeStrategy,
+    },
+    Color, Piece, Square,
+};
+
+fn piece_moves(c: &mut Criterion) {
+    let game = Game::default();
+    c.bench_function("pawn_moves", |b| {
+        b.iter(|| pawn_moves(black_box(&game), Square::E2))
+    });
+    c.bench_function("blockers_from_pos", |b| {
+        b.iter(|| get_blockers_from_position(black_box(&game), Piece::BISHOP, Square::F1))
+    });
+
+    let game =
+        Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+    c.bench_function("slider_moves", |b| {
+        b.iter(|| slider_moves(black_box(&game), Square::F1))
+    });
+    c.bench_function("pseudo_slider_moves", |b| {
+        b.iter(|| pseudolegal_slider_moves(black_box(&game), Square::F1))
+    });
+
+    let game =
+        Game::from_fen("rnbq1bnr/pppp1ppp/6k1/4p3/4P3/1K6/PPPP1PPP/RNBQ1BNR b - - 7 5").unwrap();
+    c.bench_function("king_moves", |b| {
+        b.iter(|| king_moves(black_box(&game), Color::WHITE))
+    });
+}
+
+fn legal_moves(c: &mut Criterion) {
+    let game = Game::default();
+    c.bench_function("all_legal_from_default", |b| {
+        b.iter(|| all_legal_moves(black_box(&game)))
+    });
+
+    let game =
+        Game::from_fen("r2qkb1r/1ppn1ppp/p3bn2/3p2B1/3P4/2N1PN1P/PP3PP1/R2QKB1R b KQkq - 0 8")
+            .unwrap();
+    c.bench_function("all_legal_from_complex", |b| {
+        b.iter(|| all_legal_moves(black_box(&game)))
+    });
+
+    let mut group = c.benchmark_group("all_legal_from_corpus");
+    for position in corpus::MOVEGEN {
+        let game = position.game();
+        group.bench_function(position.name, |b| {
+            b.iter(|| all_legal_moves(black_box(&game)))
+        });
+    }
+    group.finish();
+}
+
+fn perft(c: &mut Criterion) {
+    let game =
+        Game::from_fen("r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8")
+            .unwrap();
+    let mut group = c.benchmark_group("perft_3");
+    group.bench_function("copy_make", |b| {
+        b.iter(|| perft_with(black_box(&game), 3, MakeStrategy::COPY_MAKE))
+    });
+    group.bench_function("make_unmake", |b| {
+        b.iter(|| perft_with(black_box(&game), 3, MakeStrategy::MAKE_UNMAKE))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, piece_moves, legal_moves, perft);
+criterion_main!(benches);
@@ -0,0 +1,7 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/kritisch.udl");
+    // The scaffolding of the Kotlin and Swift bindings, see the `ffi` module
+    #[cfg(feature = "ffi")]
+    uniffi::generate_scaffolding("src/kritisch.udl").expect("The UDL file is valid");
+}
@@ -115,15 +115,15 @@ impl std::ops::Not for Bitboard {
 }
 
 impl Bitboard {
-    pub fn empty() -> Self {
+    pub const fn empty() -> Self {
         Bitboard::from_u64(0)
     }
 
-    pub fn from_square(s: Square) -> Self {
-        Bitboard::from_u64(0) | s.to_u64()
+    pub const fn from_square(s: Square) -> Self {
+        Bitboard::from_u64(s.to_u64())
     }
 
-    pub fn from_squares(sq: Vec<Square>) -> Self {
+    pub fn from_squares(sq: impl IntoIterator<Item = Square>) -> Self {
         let mut out = Bitboard::empty();
         for s in sq {
             out |= s.to_u64();
@@ -131,27 +131,86 @@ impl Bitboard {
         out
     }
 
-    pub fn from_u64(v: u64) -> Self {
+    pub const fn from_u64(v: u64) -> Self {
         Self(v)
     }
 
-    pub fn contains(&self, s: Square) -> bool {
+    /// Returns `self` with `s` added. Unlike the `|` operator, this can be used in const
+    /// contexts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{bitboard::Bitboard, Square};
+    /// const CENTER: Bitboard = Bitboard::empty()
+    ///     .with(Square::D4)
+    ///     .with(Square::E4)
+    ///     .with(Square::D5)
+    ///     .with(Square::E5);
+    /// assert_eq!(CENTER.0, 0x0000001818000000);
+    /// ```
+    pub const fn with(self, s: Square) -> Self {
+        Self(self.0 | s.to_u64())
+    }
+
+    /// Const version of the `|` operator.
+    pub const fn union(self, other: Bitboard) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Const version of the `&` operator.
+    pub const fn intersection(self, other: Bitboard) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Returns the squares in `self` that are not in `other`.
+    pub const fn difference(self, other: Bitboard) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Const version of the `!` operator.
+    pub const fn complement(self) -> Self {
+        Self(!self.0)
+    }
+
+    pub const fn contains(&self, s: Square) -> bool {
         self.0 & 1 << s as u64 != 0
     }
 
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.0 == 0
     }
 
-    pub fn count_ones(&self) -> u32 {
+    pub const fn count_ones(&self) -> u32 {
         self.0.count_ones()
     }
 
-    pub fn trailing_zeros(&self) -> u32 {
+    pub const fn trailing_zeros(&self) -> u32 {
         self.0.trailing_zeros()
     }
 
-    pub fn clear_lsb(&mut self) {
+    pub const fn clear_lsb(&mut self) {
         self.0 &= self.0 - 1;
     }
+
+    /// Returns an iterator over the squares in `self`, from a1 to h8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{bitboard::Bitboard, Square};
+    /// let b = Bitboard::from_squares([Square::E4, Square::A1, Square::H8]);
+    /// let squares: Vec<Square> = b.squares().collect();
+    /// assert_eq!(squares, [Square::A1, Square::E4, Square::H8]);
+    /// ```
+    pub fn squares(mut self) -> impl Iterator<Item = Square> {
+        std::iter::from_fn(move || {
+            if self.is_empty() {
+                return None;
+            }
+            let s = Square::from_u8(self.trailing_zeros() as u8);
+            self.clear_lsb();
+            Some(s)
+        })
+    }
 }
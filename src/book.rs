@@ -0,0 +1,398 @@
+//! Opening books in the Polyglot format, which most chess programs read.
+//!
+//! A book file is a list of 16-byte entries sorted by the [`key`] of their position. Every
+//! entry holds, all big-endian, the key as a `u64`, a move as a `u16`, its weight as a `u16`
+//! and a `u32` for the learning data of the program that plays from the book. A move is
+//! stored as its target file and rank in bits 0 to 5, its starting file and rank in bits 6 to
+//! 11 and a promotion piece in bits 12 to 14, from 1 for a knight to 4 for a queen, with
+//! castling given as the king taking its own rook, e.g. `e1h1`.
+//!
+//! A [`Book`] reads and writes such files and [picks](Book::pick) moves from them, a
+//! [`BookBuilder`](builder::BookBuilder) makes one from played games and
+//! [`learn`](learn::learn) adapts its weights to the games played from it.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{book::{Book, Entry, encode_move, key}, game::Game, Move};
+//! let game = Game::default();
+//! let e4 = Move::from_uci("e2e4").unwrap();
+//! let raw_move = encode_move(&game, e4);
+//! let book = Book::new(vec![Entry { key: key(&game), raw_move, weight: 3, learn: 0 }]);
+//! assert_eq!(key(&game), 0x463b_9618_1691_fc9c);
+//! assert_eq!(book.moves(&game), [(e4, 3)]);
+//! assert_eq!(Book::from_bytes(&book.to_bytes()).unwrap(), book);
+//! ```
+
+pub mod builder;
+mod keys;
+pub mod learn;
+
+use crate::{
+    game::Game, movegen::all_legal_moves, CastlingSide, Color, ColoredPiece, Error, File, Move,
+    Piece, Rank, Result, Square,
+};
+use keys::RANDOMS;
+
+/// The size of an entry in a book file in bytes.
+pub const ENTRY_SIZE: usize = 16;
+
+/// Where the keys of the castling rights, the en passant files and White to move start in
+/// [`RANDOMS`].
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const WHITE_TO_MOVE: usize = 780;
+
+/// Returns the Polyglot key of `game`'s position. Unlike [`Game::key`] it is the same in every
+/// program, so books can be shared.
+///
+/// The en passant file only counts if a pawn of the side to move stands next to the pawn that
+/// can be taken.
+pub fn key(game: &Game) -> u64 {
+    let mut key = 0;
+    for square in Square::all() {
+        if let Some(ColoredPiece { piece, color }) = game.piece_at(square) {
+            let kind = 2 * piece as usize + (color == Color::WHITE) as usize;
+            key ^= RANDOMS[64 * kind + square as usize];
+        }
+    }
+    for (i, (color, side)) in [
+        (Color::WHITE, CastlingSide::KINGSIDE),
+        (Color::WHITE, CastlingSide::QUEENSIDE),
+        (Color::BLACK, CastlingSide::KINGSIDE),
+        (Color::BLACK, CastlingSide::QUEENSIDE),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if game.castling_rights.allows(color, side) {
+            key ^= RANDOMS[CASTLING_OFFSET + i];
+        }
+    }
+    if let Some(square) = game.en_passant_square {
+        if can_take_en_passant(game, square) {
+            key ^= RANDOMS[EN_PASSANT_OFFSET + square.get_file() as usize];
+        }
+    }
+    if game.to_move == Color::WHITE {
+        key ^= RANDOMS[WHITE_TO_MOVE];
+    }
+    key
+}
+
+/// Returns `true` if a pawn of the side to move stands next to the pawn that passed
+/// `square`.
+fn can_take_en_passant(game: &Game, square: Square) -> bool {
+    let rank = match game.to_move {
+        Color::WHITE => Rank::FIFTH,
+        Color::BLACK => Rank::FOURTH,
+    };
+    let file = square.get_file() as u8;
+    let pawn = ColoredPiece::new(Piece::PAWN, game.to_move);
+    [file.checked_sub(1), Some(file + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(File::new)
+        .any(|file| game.piece_at(Square::from_coords(file, rank)) == Some(pawn))
+}
+
+/// Returns `m`, which must be legal in `game`, as a move of the book format.
+pub fn encode_move(game: &Game, m: Move) -> u16 {
+    let end = match game.piece_at(m.start) {
+        // Chess960 castling takes the rook already
+        Some(ColoredPiece { piece, color })
+            if game.is_castle(m, piece, color) && !game.chess960 =>
+        {
+            let file = if m.end.get_file() == File::G {
+                File::H
+            } else {
+                File::A
+            };
+            Square::from_coords(file, m.end.get_rank())
+        }
+        _ => m.end,
+    };
+    // The promotion pieces are numbered like `Piece`, from 1 for a knight to 4 for a queen
+    let promotion = m.promotion.map_or(0, |piece| piece as u16);
+    (promotion << 12) | ((m.start as u16) << 6) | end as u16
+}
+
+/// Returns the legal move in `game` that `raw_move` of the book format stands for, or `None`
+/// if it isn't legal there.
+pub fn decode_move(game: &Game, raw_move: u16) -> Option<Move> {
+    all_legal_moves(game)
+        .iter()
+        .copied()
+        .find(|&m| encode_move(game, m) == raw_move)
+}
+
+/// How [`Book::pick`] chooses among the book moves of a position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PickOptions {
+    /// The last move number that is played from the book.
+    pub depth: usize,
+    /// How many percent of the best move's weight a move may fall short of and still be
+    /// played, from 0 for only the best moves to 100 for all moves. The moves that are left
+    /// are played in proportion to their weights.
+    pub variety: u32,
+}
+
+impl Default for PickOptions {
+    fn default() -> Self {
+        Self {
+            depth: 20,
+            variety: 50,
+        }
+    }
+}
+
+/// One move of a book in a position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The [`key`] of the position.
+    pub key: u64,
+    /// The move in the format of the book, see [`encode_move`].
+    pub raw_move: u16,
+    /// How often the move should be played relative to the other moves in the position.
+    pub weight: u16,
+    /// Free for the program that plays from the book to learn in.
+    pub learn: u32,
+}
+
+impl Entry {
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let [k0, k1, k2, k3, k4, k5, k6, k7, m0, m1, w0, w1, l0, l1, l2, l3] = *bytes;
+        Self {
+            key: u64::from_be_bytes([k0, k1, k2, k3, k4, k5, k6, k7]),
+            raw_move: u16::from_be_bytes([m0, m1]),
+            weight: u16::from_be_bytes([w0, w1]),
+            learn: u32::from_be_bytes([l0, l1, l2, l3]),
+        }
+    }
+}
+
+/// An opening book, its entries sorted by key and the entries of a position by descending
+/// weight.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    /// Creates a book from `entries` in any order.
+    pub fn new(mut entries: Vec<Entry>) -> Self {
+        entries.sort_by(|a, b| (a.key, b.weight, a.raw_move).cmp(&(b.key, a.weight, b.raw_move)));
+        Self { entries }
+    }
+
+    /// Reads a book from the contents of a book file, failing with [`Error::InvalidEncoding`]
+    /// if they aren't made of whole entries.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(Error::InvalidEncoding(format!(
+                "a book of {} bytes doesn't consist of {ENTRY_SIZE}-byte entries",
+                bytes.len()
+            )));
+        }
+        Ok(Self::new(
+            bytes
+                .chunks_exact(ENTRY_SIZE)
+                .map(|chunk| Entry::from_bytes(chunk.try_into().expect("Chunks are whole entries")))
+                .collect(),
+        ))
+    }
+
+    /// Returns the contents of the book file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.to_bytes())
+            .collect()
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries for `game`'s position, by descending weight.
+    pub fn probe(&self, game: &Game) -> &[Entry] {
+        let key = key(game);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let len = self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..start + len]
+    }
+
+    /// Returns the legal book moves in `game` with their weights, by descending weight.
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u16)> {
+        self.probe(game)
+            .iter()
+            .filter_map(|entry| Some((decode_move(game, entry.raw_move)?, entry.weight)))
+            .collect()
+    }
+
+    /// Picks a book move in `game` as set by `options`, drawing from the moves with `random`,
+    /// or returns `None` if the game is past the book's depth or the book has no move with
+    /// a weight for the position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{book::{Book, Entry, PickOptions, encode_move, key}, game::Game, Move};
+    /// let game = Game::default();
+    /// let [e4, d4] = ["e2e4", "d2d4"].map(|uci| Move::from_uci(uci).unwrap());
+    /// let entry = |m, weight| {
+    ///     Entry { key: key(&game), raw_move: encode_move(&game, m), weight, learn: 0 }
+    /// };
+    /// let book = Book::new(vec![entry(e4, 3), entry(d4, 1)]);
+    /// let options = PickOptions { variety: 0, ..PickOptions::default() };
+    /// assert_eq!(book.pick(&game, &options, 7), Some(e4));
+    /// ```
+    pub fn pick(&self, game: &Game, options: &PickOptions, random: u64) -> Option<Move> {
+        if game.fullmove_clock > options.depth {
+            return None;
+        }
+        let moves = self.moves(game);
+        let best = moves.first()?.1 as u64;
+        let min = (best * (100 - options.variety.min(100)) as u64)
+            .div_ceil(100)
+            .max(1);
+        let moves: Vec<(Move, u64)> = moves
+            .into_iter()
+            .map(|(m, weight)| (m, weight as u64))
+            .take_while(|&(_, weight)| weight >= min)
+            .collect();
+        let total: u64 = moves.iter().map(|&(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut left = random % total;
+        for (m, weight) in moves {
+            if left < weight {
+                return Some(m);
+            }
+            left -= weight;
+        }
+        unreachable!("The draw is below the total weight")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_match_the_reference() {
+        // The test positions of the Polyglot format specification
+        for (fen, expected) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c_9b50_fd11_4196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756_b944_61c5_0fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a4_8b5a_8e47_ff78,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fd_d303_c946_bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c81_23ea_7b06_7637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f_9b82_9b27_9560,
+            ),
+        ] {
+            assert_eq!(key(&Game::from_fen(fen).unwrap()), expected, "{fen}");
+        }
+    }
+
+    #[test]
+    fn encodes_castling_as_taking_the_rook() {
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for (uci, raw) in [("e1g1", "e1h1"), ("e1c1", "e1a1"), ("e1f1", "e1f1")] {
+            let m = Move::from_uci(uci).unwrap();
+            let raw_move = encode_move(&game, Move::from_uci(raw).unwrap());
+            assert_eq!(encode_move(&game, m), raw_move, "{uci}");
+            assert_eq!(decode_move(&game, raw_move), Some(m), "{uci}");
+        }
+        assert_eq!(
+            decode_move(&game, encode_move(&game, Move::from_uci("e1e3").unwrap())),
+            None
+        );
+    }
+
+    #[test]
+    fn encodes_promotions() {
+        let game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = Move::from_uci("b7b8n").unwrap();
+        let raw_move = encode_move(&game, m);
+        assert_eq!(
+            raw_move,
+            (1 << 12) | ((Square::B7 as u16) << 6) | Square::B8 as u16
+        );
+        assert_eq!(decode_move(&game, raw_move), Some(m));
+    }
+
+    #[test]
+    fn picks_by_weight_and_depth() {
+        let game = Game::default();
+        let [e4, d4, c4] = ["e2e4", "d2d4", "c2c4"].map(|uci| Move::from_uci(uci).unwrap());
+        let entry = |m, weight| Entry {
+            key: key(&game),
+            raw_move: encode_move(&game, m),
+            weight,
+            learn: 0,
+        };
+        let book = Book::new(vec![entry(e4, 6), entry(d4, 3), entry(c4, 0)]);
+        let mut options = PickOptions {
+            depth: 1,
+            variety: 100,
+        };
+        let picks: Vec<_> = (0..9)
+            .map(|random| book.pick(&game, &options, random).unwrap())
+            .collect();
+        assert_eq!(picks.iter().filter(|&&m| m == e4).count(), 6);
+        assert_eq!(picks.iter().filter(|&&m| m == d4).count(), 3);
+
+        // Half of the best weight drops d4 just below the line
+        options.variety = 49;
+        assert!((0..9).all(|random| book.pick(&game, &options, random) == Some(e4)));
+
+        let mut later = game;
+        later.fullmove_clock = 2;
+        assert_eq!(book.pick(&later, &options, 0), None);
+    }
+
+    #[test]
+    fn rejects_partial_entries() {
+        assert!(matches!(
+            Book::from_bytes(&[0; 17]),
+            Err(Error::InvalidEncoding(_))
+        ));
+        assert!(Book::from_bytes(&[]).unwrap().is_empty());
+    }
+}
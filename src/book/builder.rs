@@ -0,0 +1,208 @@
+//! Building an opening book from played games, e.g. from a PGN database read with
+//! [`pgn`](crate::pgn).
+//!
+//! The builder counts how often every move was played in every position within the first
+//! [`max_plies`](BuildOptions::max_plies) of the games. Moves played in fewer than
+//! [`min_games`](BuildOptions::min_games) games are left out, and the rest are weighted by how
+//! often they were played or by the points they scored, as in Polyglot: two for a win and one
+//! for a draw, counted for the side that played the move.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{book::builder::{BookBuilder, BuildOptions}, game::Game, pgn};
+//! let games = pgn::parse("1. e4 e5 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1/2-1/2\n");
+//! let mut builder = BookBuilder::new(BuildOptions { min_games: 2, ..BuildOptions::default() });
+//! for game in &games {
+//!     builder.add_pgn(game.as_ref().unwrap());
+//! }
+//! let book = builder.build();
+//! // Only 1. e4 was played twice, scoring 2 points for White
+//! let moves = book.moves(&Game::default());
+//! assert_eq!(moves.len(), 1);
+//! assert_eq!(moves[0].1, 2);
+//! ```
+
+use std::collections::HashMap;
+
+use super::{encode_move, key, Book, Entry};
+use crate::{
+    game::{Game, Outcome},
+    pgn::PgnGame,
+    Move,
+};
+
+/// Which moves [`BookBuilder`] keeps and how it weights them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// How many games a move must have been played in to make it into the book.
+    pub min_games: u32,
+    /// How many plies of every game to take into the book.
+    pub max_plies: usize,
+    /// Whether to weight moves by the points they scored instead of how often they were
+    /// played. Moves that only lost then get no weight and are left out.
+    pub by_results: bool,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            min_games: 1,
+            max_plies: 20,
+            by_results: true,
+        }
+    }
+}
+
+/// How often a move was played in a position and what it scored.
+#[derive(Debug, Default, Copy, Clone)]
+struct Tally {
+    games: u32,
+    /// Two for every win and one for every draw.
+    points: u32,
+}
+
+/// Collects the moves of games to build a [`Book`] from.
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    options: BuildOptions,
+    tallies: HashMap<(u64, u16), Tally>,
+}
+
+impl BookBuilder {
+    pub fn new(options: BuildOptions) -> Self {
+        Self {
+            options,
+            tallies: HashMap::new(),
+        }
+    }
+
+    /// Adds the `moves` of a game played from `start` that ended with `outcome`, `None` if
+    /// it is unfinished. The moves must be legal.
+    pub fn add_game(&mut self, start: &Game, moves: &[Move], outcome: Option<Outcome>) {
+        let mut game = *start;
+        for &m in moves.iter().take(self.options.max_plies) {
+            let tally = self
+                .tallies
+                .entry((key(&game), encode_move(&game, m)))
+                .or_default();
+            tally.games += 1;
+            tally.points += match outcome {
+                Some(Outcome::DECISIVE { winner }) if winner == game.to_move => 2,
+                Some(Outcome::DRAW) => 1,
+                _ => 0,
+            };
+            game.make_move(m);
+        }
+    }
+
+    /// Adds a game read from PGN, see [`add_game`](Self::add_game).
+    pub fn add_pgn(&mut self, game: &PgnGame) {
+        self.add_game(&game.start, &game.moves, game.outcome);
+    }
+
+    /// Returns how many different moves in different positions were added.
+    pub fn len(&self) -> usize {
+        self.tallies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tallies.is_empty()
+    }
+
+    /// Builds the book from the games added so far. If a weight doesn't fit into the book,
+    /// all weights are scaled down together.
+    pub fn build(&self) -> Book {
+        let weighted: Vec<(u64, u16, u32)> = self
+            .tallies
+            .iter()
+            .filter(|(_, tally)| tally.games >= self.options.min_games)
+            .map(|(&(key, raw_move), tally)| {
+                let weight = if self.options.by_results {
+                    tally.points
+                } else {
+                    tally.games
+                };
+                (key, raw_move, weight)
+            })
+            .filter(|&(_, _, weight)| weight > 0)
+            .collect();
+        let max = weighted
+            .iter()
+            .map(|&(_, _, weight)| weight)
+            .max()
+            .unwrap_or(0);
+        let scale = max.div_ceil(u16::MAX as u32).max(1);
+        Book::new(
+            weighted
+                .into_iter()
+                .map(|(key, raw_move, weight)| Entry {
+                    key,
+                    raw_move,
+                    weight: (weight / scale).max(1) as u16,
+                    learn: 0,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{book::ENTRY_SIZE, Color};
+
+    #[test]
+    fn weights_by_games_or_results() {
+        let [e4, d4] = ["e2e4", "d2d4"].map(|uci| Move::from_uci(uci).unwrap());
+        let start = Game::default();
+        let white_wins = Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        });
+        let black_wins = Some(Outcome::DECISIVE {
+            winner: Color::BLACK,
+        });
+        let mut builder = BookBuilder::new(BuildOptions::default());
+        builder.add_game(&start, &[e4], black_wins);
+        builder.add_game(&start, &[e4], black_wins);
+        builder.add_game(&start, &[d4], white_wins);
+        assert_eq!(builder.build().moves(&start), [(d4, 2)]);
+
+        builder.options.by_results = false;
+        assert_eq!(builder.build().moves(&start), [(e4, 2), (d4, 1)]);
+        builder.options.min_games = 3;
+        assert!(builder.build().is_empty());
+    }
+
+    #[test]
+    fn only_takes_the_first_plies() {
+        let options = BuildOptions {
+            max_plies: 2,
+            ..BuildOptions::default()
+        };
+        let mut builder = BookBuilder::new(options);
+        let moves = ["e2e4", "e7e5", "g1f3"].map(|uci| Move::from_uci(uci).unwrap());
+        builder.add_game(&Game::default(), &moves, Some(Outcome::DRAW));
+        assert_eq!(builder.len(), 2);
+        let book = builder.build();
+        let bytes = book.to_bytes();
+        assert_eq!(bytes.len(), 2 * ENTRY_SIZE);
+        assert_eq!(Book::from_bytes(&bytes).unwrap(), book);
+    }
+
+    #[test]
+    fn scales_down_large_weights() {
+        let mut builder = BookBuilder::new(BuildOptions::default());
+        let [e4, d4] = ["e2e4", "d2d4"].map(|uci| Move::from_uci(uci).unwrap());
+        builder.tallies.insert(
+            (key(&Game::default()), encode_move(&Game::default(), e4)),
+            Tally {
+                games: 100_000,
+                points: 200_000,
+            },
+        );
+        builder.add_game(&Game::default(), &[d4], Some(Outcome::DRAW));
+        let moves = builder.build().moves(&Game::default());
+        assert_eq!(moves, [(e4, 50_000), (d4, 1)]);
+    }
+}
@@ -0,0 +1,155 @@
+//! Learning from the games played out of a book, so that a repertoire drops the lines that
+//! lose and plays the ones that win more often.
+//!
+//! After a game, [`learn`] goes through its moves while they are in the book and changes the
+//! weight of every book move by the result for the side that played it: a win adds
+//! [`rate`](LearnOptions::rate) percent of the weight, a loss takes it away and a draw keeps
+//! it. A move that keeps losing ends up with no weight and isn't played anymore. The
+//! [`learn`](super::Entry::learn) field of the entry counts the games and points, see
+//! [`Entry::learned`]. Both are written with the book, so writing it back to its file keeps
+//! what was learned.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{
+//!     book::{builder::{BookBuilder, BuildOptions}, learn::{learn, LearnOptions}},
+//!     game::{Game, Outcome},
+//!     Color, Move,
+//! };
+//! let e4 = Move::from_uci("e2e4").unwrap();
+//! let options = BuildOptions { by_results: false, ..BuildOptions::default() };
+//! let mut builder = BookBuilder::new(options);
+//! for _ in 0..100 {
+//!     builder.add_game(&Game::default(), &[e4], None);
+//! }
+//! let mut book = builder.build();
+//! let lost = Some(Outcome::DECISIVE { winner: Color::BLACK });
+//! learn(&mut book, &Game::default(), &[e4], lost, &LearnOptions::default());
+//! assert_eq!(book.moves(&Game::default()), [(e4, 90)]);
+//! assert_eq!(book.probe(&Game::default())[0].learned(), (1, 0));
+//! ```
+
+use super::{encode_move, key, Book, Entry};
+use crate::{
+    game::{Game, Outcome},
+    Color, Move,
+};
+
+/// How [`learn`] changes the weights.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LearnOptions {
+    /// How many percent of its weight a move gains for a win and loses for a loss, at least 1.
+    pub rate: u32,
+    /// Only learns from the moves of this side, e.g. the engine's, or of both if `None`.
+    pub color: Option<Color>,
+}
+
+impl Default for LearnOptions {
+    fn default() -> Self {
+        Self {
+            rate: 10,
+            color: None,
+        }
+    }
+}
+
+impl Entry {
+    /// Returns how many games the move was learned from and the points it scored in them, two
+    /// for a win and one for a draw.
+    pub fn learned(&self) -> (u16, u16) {
+        ((self.learn >> 16) as u16, self.learn as u16)
+    }
+
+    fn record(&mut self, points: u16) {
+        let (games, total) = self.learned();
+        self.learn = (games.saturating_add(1) as u32) << 16 | total.saturating_add(points) as u32;
+    }
+}
+
+/// Changes the weights of the book moves of a game played from `start` that ended with
+/// `outcome`, up to the first move that isn't in the book. An unfinished game teaches
+/// nothing. The moves must be legal.
+pub fn learn(
+    book: &mut Book,
+    start: &Game,
+    moves: &[Move],
+    outcome: Option<Outcome>,
+    options: &LearnOptions,
+) {
+    let Some(outcome) = outcome else {
+        return;
+    };
+    let mut game = *start;
+    for &m in moves {
+        let key = key(&game);
+        let raw_move = encode_move(&game, m);
+        let first = book.entries.partition_point(|entry| entry.key < key);
+        let len = book.entries[first..].partition_point(|entry| entry.key == key);
+        let entries = &mut book.entries[first..first + len];
+        let Some(entry) = entries.iter_mut().find(|entry| entry.raw_move == raw_move) else {
+            return;
+        };
+        if options.color.is_none_or(|color| color == game.to_move) {
+            let step = (entry.weight as u32 * options.rate / 100).max(1) as u16;
+            match outcome {
+                Outcome::DECISIVE { winner } if winner == game.to_move => {
+                    entry.weight = entry.weight.saturating_add(step);
+                    entry.record(2);
+                }
+                Outcome::DECISIVE { .. } => {
+                    entry.weight = entry.weight.saturating_sub(step);
+                    entry.record(0);
+                }
+                Outcome::DRAW => entry.record(1),
+            }
+            // Keep the moves of the position by descending weight
+            entries.sort_by(|a, b| (b.weight, a.raw_move).cmp(&(a.weight, b.raw_move)));
+        }
+        game.make_move(m);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_game_until_it_leaves_the_book() {
+        let [e4, e5, nf3, nc6] =
+            ["e2e4", "e7e5", "g1f3", "b8c6"].map(|uci| Move::from_uci(uci).unwrap());
+        let start = Game::default();
+        let mut after_e4 = start;
+        after_e4.make_move(e4);
+        let entry = |game: &Game, m, weight| Entry {
+            key: key(game),
+            raw_move: encode_move(game, m),
+            weight,
+            learn: 0,
+        };
+        let mut book = Book::new(vec![entry(&start, e4, 100), entry(&after_e4, e5, 5)]);
+        let white_wins = Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        });
+        learn(
+            &mut book,
+            &start,
+            &[e4, e5, nf3, nc6],
+            white_wins,
+            &LearnOptions::default(),
+        );
+        assert_eq!(book.moves(&start), [(e4, 110)]);
+        assert_eq!(book.moves(&after_e4), [(e5, 4)]);
+        assert_eq!(book.probe(&after_e4)[0].learned(), (1, 0));
+
+        // Learning only for Black leaves White's moves alone
+        let options = LearnOptions {
+            color: Some(Color::BLACK),
+            ..LearnOptions::default()
+        };
+        learn(&mut book, &start, &[e4, e5], Some(Outcome::DRAW), &options);
+        assert_eq!(book.probe(&start)[0].learned(), (1, 2));
+        assert_eq!(book.probe(&after_e4)[0].learned(), (2, 1));
+        assert_eq!(Book::from_bytes(&book.to_bytes()).unwrap(), book);
+    }
+}
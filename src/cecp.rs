@@ -0,0 +1,432 @@
+//! A front end for the Chess Engine Communication Protocol version 2, the xboard or WinBoard
+//! protocol. Unlike in [`uci`](crate::uci), the engine keeps track of the game itself and
+//! answers the opponent's moves with its own. Both search on a
+//! [`SearchThread`].
+//!
+//! Supported commands are `xboard`, `protover`, `new`, `force`, `go`, `usermove` (and bare
+//! moves), `setboard`, `level`, `st`, `sd`, `time`, `otim`, `?`, `ping`, `post`, `nopost`,
+//! `result` and `quit`. `undo` and `remove` are answered with an error, since the engine keeps
+//! no history of the game. Moves are in coordinate notation, e.g. `e2e4`.
+//!
+//! A search interrupted by a command that changes the game, like `force` or `setboard`,
+//! still plays its move, like `?` does.
+//!
+//! # Example
+//!
+//! ```
+//! let input = "xboard\nprotover 2\nnew\nsd 3\nusermove e2e4\n";
+//! kritisch::cecp::run(input.as_bytes(), std::io::stdout()).unwrap();
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    game::{Game, Outcome, Termination},
+    search::{
+        mate_in,
+        thread::{SearchMode, SearchThread},
+        time::TimeControl,
+        SearchInfo, SearchLimits, SearchResult, Searcher,
+    },
+    uci::NAME,
+    Color, Move,
+};
+
+/// The score CECP reports mates with, plus or minus the number of moves to mate.
+const MATE_SCORE: i32 = 100_000;
+
+/// Speaks CECP over `input` and `output` until `quit` is received or `input` ends. At the end
+/// of the input, a running search is finished first.
+pub fn run<R, W>(input: R, output: W) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let mut cecp = Cecp::new(output);
+    for line in input.lines() {
+        if !cecp.handle(&line?)? {
+            cecp.search.stop();
+            return Ok(());
+        }
+    }
+    cecp.search.wait();
+    Ok(())
+}
+
+/// The time control set by `level`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Level {
+    /// The number of moves per time control, 0 for the whole game.
+    moves: u32,
+    base: Duration,
+    increment: Duration,
+}
+
+/// The state of the protocol between commands.
+struct Cecp<W> {
+    output: Arc<Mutex<W>>,
+    /// The game, shared with the search thread, which makes the engine's moves.
+    game: Arc<Mutex<Game>>,
+    search: SearchThread,
+    /// The side the engine plays, `None` in force mode.
+    engine: Option<Color>,
+    /// Whether to send thinking output.
+    post: Arc<AtomicBool>,
+    level: Level,
+    /// The time left on the engine's and the opponent's clock, as sent by `time` and `otim`.
+    time: Duration,
+    opponent_time: Duration,
+    /// The fixed time per move set by `st`, which replaces the clock.
+    move_time: Option<Duration>,
+    /// The depth limit set by `sd`.
+    depth: Option<u32>,
+}
+
+impl<W: Write + Send + 'static> Cecp<W> {
+    fn new(output: W) -> Self {
+        let output = Arc::new(Mutex::new(output));
+        let game = Arc::new(Mutex::new(Game::default()));
+        let post = Arc::new(AtomicBool::new(false));
+        let mut searcher = Searcher::default();
+        let (info_output, info_game, info_post) =
+            (Arc::clone(&output), Arc::clone(&game), Arc::clone(&post));
+        searcher.on_info(move |info| {
+            if !info_post.load(Ordering::Relaxed) {
+                return;
+            }
+            // The game only changes once the search is over
+            let game = *info_game.lock().expect("Game lock poisoned");
+            let mut output = info_output.lock().expect("Output lock poisoned");
+            // A failed write shows up again with the next response
+            let _ = write_thinking(&mut *output, &game, info);
+        });
+        let level = Level {
+            moves: 0,
+            base: Duration::from_secs(300),
+            increment: Duration::ZERO,
+        };
+        Self {
+            output,
+            game,
+            search: SearchThread::new(searcher),
+            engine: Some(Color::BLACK),
+            post,
+            level,
+            time: level.base,
+            opponent_time: level.base,
+            move_time: None,
+            depth: None,
+        }
+    }
+
+    /// Handles one line of input, returning `false` on `quit`.
+    fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let line = line.trim();
+        let (command, args) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, args)| (command, args.trim()));
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "computer" | "hard" | "easy"
+            | "name" | "rating" | "ics" | "" => (),
+            "protover" => self.send(&format!(
+                "feature myname=\"{NAME}\" ping=1 setboard=1 usermove=1 san=0 colors=0 \
+                 analyze=0 sigint=0 sigterm=0 done=1"
+            ))?,
+            "new" => {
+                self.search.searcher().clear();
+                *self.game() = Game::default();
+                self.engine = Some(Color::BLACK);
+                self.depth = None;
+                self.move_time = None;
+            }
+            "force" => {
+                self.search.stop();
+                self.engine = None;
+            }
+            "go" => {
+                self.search.stop();
+                let to_move = self.game().to_move;
+                self.engine = Some(to_move);
+                self.think();
+            }
+            "usermove" => self.user_move(args)?,
+            "setboard" => {
+                self.search.stop();
+                match Game::from_fen(args) {
+                    Ok(game) => *self.game() = game,
+                    Err(e) => self.send(&format!("tellusererror Illegal position: {e}"))?,
+                }
+            }
+            "level" => match parse_level(args) {
+                Some(level) => {
+                    self.level = level;
+                    self.time = level.base;
+                    self.opponent_time = level.base;
+                    self.move_time = None;
+                }
+                None => self.send(&format!("Error (invalid time control): {line}"))?,
+            },
+            "st" => match args.parse::<f64>() {
+                Ok(seconds) if seconds > 0.0 => {
+                    self.move_time = Some(Duration::from_secs_f64(seconds));
+                }
+                _ => self.send(&format!("Error (invalid time): {line}"))?,
+            },
+            "sd" => match args.parse::<u32>() {
+                Ok(depth) => self.depth = Some(depth.max(1)),
+                Err(_) => self.send(&format!("Error (invalid depth): {line}"))?,
+            },
+            "time" | "otim" => match args.parse::<i64>() {
+                Ok(centiseconds) => {
+                    // The clock may run below zero when a player is out of time
+                    let time = Duration::from_millis(centiseconds.max(0) as u64 * 10);
+                    if command == "time" {
+                        self.time = time;
+                    } else {
+                        self.opponent_time = time;
+                    }
+                }
+                Err(_) => self.send(&format!("Error (invalid time): {line}"))?,
+            },
+            "?" => {
+                self.search.stop();
+            }
+            "ping" => self.send(&format!("pong {args}"))?,
+            "post" => self.post.store(true, Ordering::Relaxed),
+            "nopost" => self.post.store(false, Ordering::Relaxed),
+            "result" => {
+                self.search.stop();
+                self.engine = None;
+            }
+            "undo" | "remove" => self.send(&format!("Error (command not supported): {command}"))?,
+            "quit" => return Ok(false),
+            // Without usermove, moves are sent on their own
+            _ if Move::from_uci(command).is_ok() && args.is_empty() => self.user_move(command)?,
+            _ => self.send(&format!("Error (unknown command): {command}"))?,
+        }
+        Ok(true)
+    }
+
+    /// Makes the opponent's move and answers it if it is the engine's turn.
+    fn user_move(&mut self, uci: &str) -> io::Result<()> {
+        self.search.stop();
+        let made = Move::from_uci(uci).and_then(|m| self.game().try_make_move(m));
+        if made.is_err() {
+            return self.send(&format!("Illegal move: {uci}"));
+        }
+        if self.engine == Some(self.game().to_move) {
+            self.think();
+        }
+        Ok(())
+    }
+
+    /// Starts searching for the engine's move, which the search thread makes and sends when
+    /// it is done, followed by the result if that ends the game.
+    fn think(&mut self) {
+        let game = *self.game();
+        if game.termination().is_some() {
+            return;
+        }
+        let limits = self.limits(&game);
+        let (output, shared_game) = (Arc::clone(&self.output), Arc::clone(&self.game));
+        self.search
+            .start(&game, limits, SearchMode::LIMITED, move |result| {
+                let mut game = shared_game.lock().expect("Game lock poisoned");
+                let mut output = output.lock().expect("Output lock poisoned");
+                let _ = play(&mut *output, &mut game, result);
+            });
+    }
+
+    /// Returns the limits for searching `game` on the engine's clock.
+    fn limits(&self, game: &Game) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            time: self.move_time,
+            ..SearchLimits::default()
+        };
+        if self.move_time.is_none() {
+            let engine = game.to_move as usize;
+            let mut time = [self.opponent_time; 2];
+            time[engine] = self.time;
+            // The moves left until the next time control, assuming it started from move 1
+            let moves = self.level.moves as usize;
+            let moves_to_go =
+                (moves > 0).then(|| (moves - (game.fullmove_clock - 1) % moves) as u32);
+            limits.clock = Some(TimeControl {
+                time,
+                increment: [self.level.increment; 2],
+                moves_to_go,
+            });
+        }
+        limits
+    }
+
+    fn game(&self) -> std::sync::MutexGuard<'_, Game> {
+        self.game.lock().expect("Game lock poisoned")
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        let mut output = self.output.lock().expect("Output lock poisoned");
+        writeln!(output, "{line}")?;
+        output.flush()
+    }
+}
+
+/// Makes the engine's move on `game` and sends it, followed by the result if the game is over.
+fn play(w: &mut impl Write, game: &mut Game, result: &SearchResult) -> io::Result<()> {
+    let Some(m) = result.best_move else {
+        return Ok(());
+    };
+    game.make_move(m);
+    writeln!(w, "move {m}")?;
+    if let Some(termination) = game.termination() {
+        let reason = match termination {
+            Termination::CHECKMATE if game.to_move == Color::WHITE => "Black mates",
+            Termination::CHECKMATE => "White mates",
+            Termination::STALEMATE => "Stalemate",
+            Termination::FIFTY_MOVE_RULE => "Fifty move rule",
+            Termination::INSUFFICIENT_MATERIAL => "Insufficient material",
+            Termination::VARIANT_WIN | Termination::VARIANT_LOSS => "Variant end",
+            Termination::VARIANT_DRAW => "Variant draw",
+        };
+        let score = match game.outcome() {
+            Some(Outcome::DECISIVE {
+                winner: Color::WHITE,
+            }) => "1-0",
+            Some(Outcome::DECISIVE { .. }) => "0-1",
+            _ => "1/2-1/2",
+        };
+        writeln!(w, "{score} {{{reason}}}")?;
+    }
+    w.flush()
+}
+
+/// Writes a line of thinking output: the depth, the score, the time in centiseconds, the
+/// nodes and the principal variation in SAN.
+fn write_thinking(w: &mut impl Write, game: &Game, info: &SearchInfo) -> io::Result<()> {
+    let score = match mate_in(info.score) {
+        Some(moves) if moves > 0 => MATE_SCORE + moves,
+        Some(moves) => -MATE_SCORE + moves,
+        None => info.score,
+    };
+    let pv = match game.san_line(&info.pv) {
+        Ok(san) => san.join(" "),
+        Err(_) => info
+            .pv
+            .iter()
+            .map(Move::to_string)
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    writeln!(
+        w,
+        "{} {score} {} {} {pv}",
+        info.depth,
+        info.time.as_millis() / 10,
+        info.nodes
+    )?;
+    w.flush()
+}
+
+/// Parses the arguments of `level`: the moves per time control, the base time in minutes or
+/// as `minutes:seconds`, and the increment in seconds.
+fn parse_level(args: &str) -> Option<Level> {
+    let [moves, base, increment] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let base = match base.split_once(':') {
+        Some((minutes, seconds)) => {
+            Duration::from_secs(minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?)
+        }
+        None => Duration::from_secs(base.parse::<u64>().ok()? * 60),
+    };
+    let increment = increment.parse::<f64>().ok().filter(|i| *i >= 0.0)?;
+    Some(Level {
+        moves: moves.parse().ok()?,
+        base,
+        increment: Duration::from_secs_f64(increment),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::all_legal_moves;
+    use crate::search::thread::testing::{self, SharedOutput};
+
+    fn run_script(input: &str) -> String {
+        testing::run_script(|input, output| run(input, output), input)
+    }
+
+    #[test]
+    fn handshake() {
+        let output = run_script("xboard\nprotover 2\nping 7\nfoo\nundo\nquit\nping 8\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("feature ") && lines[0].ends_with(" done=1"));
+        assert_eq!(lines[1], "pong 7");
+        assert_eq!(lines[2], "Error (unknown command): foo");
+        assert_eq!(lines[3], "Error (command not supported): undo");
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn answers_moves() {
+        let output = run_script("new\nsd 2\nusermove e2e4\ne2e5\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "{output}");
+        let reply = Move::from_uci(lines[0].strip_prefix("move ").unwrap()).unwrap();
+        let mut game = Game::default();
+        game.make_move(Move::from_uci("e2e4").unwrap());
+        assert!(all_legal_moves(&game).contains(&reply));
+        // The engine's move was made, so it is white's turn again
+        assert_eq!(lines[1], "Illegal move: e2e5");
+    }
+
+    #[test]
+    fn plays_either_side() {
+        let output =
+            run_script("new\nforce\nsetboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\npost\nsd 3\ngo\n");
+        let lines: Vec<&str> = output.lines().collect();
+        // Mate in one, with the PV in SAN
+        assert!(lines[0].starts_with("1 100001 "), "{output}");
+        assert!(lines[0].ends_with(" Ra8#"), "{output}");
+        assert_eq!(lines[lines.len() - 2], "move a1a8");
+        assert_eq!(lines[lines.len() - 1], "1-0 {White mates}");
+    }
+
+    #[test]
+    fn time_controls() {
+        assert_eq!(
+            parse_level("40 0:30 0.5"),
+            Some(Level {
+                moves: 40,
+                base: Duration::from_secs(30),
+                increment: Duration::from_millis(500),
+            })
+        );
+        assert_eq!(parse_level("0 5 2").unwrap().base, Duration::from_secs(300));
+        assert_eq!(parse_level("0 5"), None);
+
+        let mut cecp = Cecp::new(SharedOutput::default());
+        cecp.handle("level 40 5 0").unwrap();
+        cecp.handle("time 6000").unwrap();
+        cecp.handle("otim 3000").unwrap();
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 12").unwrap();
+        let clock = cecp.limits(&game).clock.unwrap();
+        assert_eq!(
+            clock.time,
+            [Duration::from_secs(30), Duration::from_secs(60)]
+        );
+        assert_eq!(clock.moves_to_go, Some(29));
+
+        cecp.handle("st 2").unwrap();
+        let limits = cecp.limits(&game);
+        assert_eq!(
+            (limits.time, limits.clock),
+            (Some(Duration::from_secs(2)), None)
+        );
+    }
+}
@@ -0,0 +1,487 @@
+//! Compact binary encoding of positions and games.
+//!
+//! A position is stored as its occupancy bitboard followed by one nibble per occupied square,
+//! a flag byte for the side to move, castling rights and en passant, and the two clocks as
+//! variable-length integers. The default position takes 27 bytes. Positions of other
+//! variants add the variant and what it needs besides the pieces: the pockets and promoted
+//! pieces of Crazyhouse and the duck of Duck chess. Chess960 positions add the files of the castling rooks.
+//!
+//! A game is stored as its starting position, the number of moves and then every move as its
+//! index into the list of legal moves in the position it was played in. Since no position of
+//! standard chess has more than 218 legal moves, every move fits into a single byte. Only
+//! where there are more than 256, as in Duck chess, the index takes two. A
+//! [`collection`] stores
+//! many games that way together with their tags.
+
+pub mod collection;
+
+use crate::{
+    bitboard::Bitboard, game::Game, movegen::all_legal_moves, variant::Variant, CastlingRights,
+    CastlingSide, Color, ColoredPiece, Error, File, Move, Piece, Result, Square,
+};
+
+const SIDE_TO_MOVE_FLAG: u8 = 1;
+const CASTLING_SHIFT: u8 = 1;
+const EN_PASSANT_FLAG: u8 = 1 << 5;
+const VARIANT_FLAG: u8 = 1 << 6;
+const CHESS960_FLAG: u8 = 1 << 7;
+/// Stands for the duck's square before the duck has been placed.
+const NO_DUCK: u8 = 64;
+
+/// Encodes `game`'s position into a compact binary representation.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{codec, game::Game};
+/// let game = Game::default();
+/// let bytes = codec::encode_position(&game);
+/// assert_eq!(bytes.len(), 27);
+/// assert_eq!(codec::decode_position(&bytes).unwrap().0, game);
+/// ```
+pub fn encode_position(game: &Game) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_position(game, &mut out);
+    out
+}
+
+/// Decodes a position produced by [`encode_position`], returning it together with the number
+/// of bytes consumed.
+pub fn decode_position(bytes: &[u8]) -> Result<(Game, usize)> {
+    let mut reader = Reader::new(bytes);
+    let game = reader.position()?;
+    Ok((game, reader.pos))
+}
+
+/// Encodes a game, given by its starting position and the moves played from it.
+/// Returns [`Error::IllegalMove`] if any of the moves is not legal where it was played.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{codec, game::Game, Move, Square};
+/// let start = Game::default();
+/// let moves = [
+///     Move::new(Square::E2, Square::E4),
+///     Move::new(Square::E7, Square::E5),
+/// ];
+/// let bytes = codec::encode_game(&start, &moves).unwrap();
+/// let (decoded_start, decoded_moves) = codec::decode_game(&bytes).unwrap();
+/// assert_eq!(decoded_start, start);
+/// assert_eq!(decoded_moves, moves);
+/// ```
+pub fn encode_game(start: &Game, moves: &[Move]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write_position(start, &mut out);
+    write_moves(start, moves, &mut out)?;
+    Ok(out)
+}
+
+/// Decodes a game produced by [`encode_game`] into its starting position and moves.
+pub fn decode_game(bytes: &[u8]) -> Result<(Game, Vec<Move>)> {
+    let mut reader = Reader::new(bytes);
+    let start = reader.position()?;
+    let moves = reader.moves(&start)?;
+    Ok((start, moves))
+}
+
+fn write_position(game: &Game, out: &mut Vec<u8>) {
+    let occupancy = game.all_pieces();
+    out.extend_from_slice(&occupancy.0.to_le_bytes());
+
+    let mut nibbles = occupancy;
+    let mut pending: Option<u8> = None;
+    while !nibbles.is_empty() {
+        let s = Square::from_u8(nibbles.trailing_zeros() as u8);
+        let piece = game.type_at(s);
+        let color = game.color_at(s);
+        let nibble = (color as u8) << 3 | piece as u8;
+        match pending.take() {
+            Some(low) => out.push(low | nibble << 4),
+            None => pending = Some(nibble),
+        }
+        nibbles.clear_lsb();
+    }
+    if let Some(low) = pending {
+        out.push(low);
+    }
+
+    let mut flags = game.castling_rights.bits() << CASTLING_SHIFT;
+    if game.to_move == Color::BLACK {
+        flags |= SIDE_TO_MOVE_FLAG;
+    }
+    if game.en_passant_square.is_some() {
+        flags |= EN_PASSANT_FLAG;
+    }
+    if game.variant != Variant::STANDARD {
+        flags |= VARIANT_FLAG;
+    }
+    if game.chess960 {
+        flags |= CHESS960_FLAG;
+    }
+    out.push(flags);
+    if let Some(ep) = game.en_passant_square {
+        out.push(ep as u8);
+    }
+    if game.variant != Variant::STANDARD {
+        write_variant(game, out);
+    }
+    if game.chess960 {
+        // The kingside rook's file in the low nibble, the queenside rook's in the high one
+        for color in Color::both() {
+            let file = |side| game.castling_rook(color, side).get_file() as u8;
+            out.push(file(CastlingSide::KINGSIDE) | file(CastlingSide::QUEENSIDE) << 4);
+        }
+    }
+
+    write_varint(game.halfmove_clock as u64, out);
+    write_varint(game.fullmove_clock as u64, out);
+}
+
+/// Writes the variant of `game` as its index into [`Variant::ALL`], followed by the pockets
+/// and promoted pieces of a variant with pockets, or the duck's square in Duck chess.
+fn write_variant(game: &Game, out: &mut Vec<u8>) {
+    let index = Variant::ALL
+        .iter()
+        .position(|&variant| variant == game.variant)
+        .expect("every variant is in Variant::ALL");
+    out.push(index as u8);
+    if game.rules().has_pockets() {
+        for color in Color::both() {
+            for piece in &Piece::ALL[..5] {
+                out.push(game.pocket(color, *piece));
+            }
+        }
+        out.extend_from_slice(&game.promoted().0.to_le_bytes());
+    }
+    if game.variant == Variant::DUCK {
+        out.push(game.duck().map_or(NO_DUCK, |s| s as u8));
+    }
+}
+
+/// Writes the number of `moves` played from `start`, then every move as its index into the
+/// legal moves.
+fn write_moves(start: &Game, moves: &[Move], out: &mut Vec<u8>) -> Result<()> {
+    write_varint(moves.len() as u64, out);
+    let mut game = *start;
+    for &m in moves {
+        let legal = all_legal_moves(&game);
+        let index = legal
+            .iter()
+            .position(|legal| *legal == m)
+            .ok_or(Error::IllegalMove(m))?;
+        if legal.len() > 256 {
+            out.extend_from_slice(&(index as u16).to_le_bytes());
+        } else {
+            out.push(index as u8);
+        }
+        game.make_move(m);
+    }
+    Ok(())
+}
+
+/// Writes `v` as an unsigned LEB128 varint.
+fn write_varint(mut v: u64, out: &mut Vec<u8>) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+#[derive(Debug, Clone)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| Error::InvalidEncoding("unexpected end of input".to_string()))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(Error::InvalidEncoding("varint too long".to_string()))
+    }
+
+    /// Reads moves written by [`write_moves`] for a game played from `start`.
+    fn moves(&mut self, start: &Game) -> Result<Vec<Move>> {
+        let count = self.varint()? as usize;
+        let mut game = *start;
+        let mut moves = Vec::with_capacity(count.min(self.bytes.len()));
+        for _ in 0..count {
+            let legal = all_legal_moves(&game);
+            let mut index = self.byte()? as usize;
+            if legal.len() > 256 {
+                index |= (self.byte()? as usize) << 8;
+            }
+            let m = *legal.get(index).ok_or_else(|| {
+                Error::InvalidEncoding(format!("move index {index} out of range"))
+            })?;
+            game.make_move(m);
+            moves.push(m);
+        }
+        Ok(moves)
+    }
+
+    fn bitboard(&mut self) -> Result<Bitboard> {
+        let mut bytes = [0u8; 8];
+        for b in bytes.iter_mut() {
+            *b = self.byte()?;
+        }
+        Ok(Bitboard::from_u64(u64::from_le_bytes(bytes)))
+    }
+
+    fn position(&mut self) -> Result<Game> {
+        let mut squares = self.bitboard()?;
+
+        let mut game = Game::empty();
+        let mut current = 0u8;
+        let mut index = 0;
+        while !squares.is_empty() {
+            let s = Square::from_u8(squares.trailing_zeros() as u8);
+            let nibble = if index % 2 == 0 {
+                current = self.byte()?;
+                current & 0xf
+            } else {
+                current >> 4
+            };
+            let piece = Piece::new(nibble & 0x7)
+                .ok_or_else(|| Error::InvalidEncoding(format!("invalid piece code {nibble}")))?;
+            let color = Color::from_u8(nibble >> 3);
+            game.put_piece(s, ColoredPiece::new(piece, color));
+            squares.clear_lsb();
+            index += 1;
+        }
+
+        let flags = self.byte()?;
+        game.to_move = if flags & SIDE_TO_MOVE_FLAG != 0 {
+            Color::BLACK
+        } else {
+            Color::WHITE
+        };
+        game.castling_rights = CastlingRights::from_bits((flags >> CASTLING_SHIFT) & 0xf)
+            .expect("four castling bits are always valid");
+        game.en_passant_square =
+            if flags & EN_PASSANT_FLAG != 0 {
+                let ep = self.byte()?;
+                Some(Square::new(ep).ok_or_else(|| {
+                    Error::InvalidEncoding(format!("invalid en passant square {ep}"))
+                })?)
+            } else {
+                None
+            };
+        if flags & VARIANT_FLAG != 0 {
+            self.variant(&mut game)?;
+        }
+        if flags & CHESS960_FLAG != 0 {
+            game.chess960 = true;
+            for color in Color::both() {
+                let files = self.byte()?;
+                for (side, file) in [
+                    (CastlingSide::KINGSIDE, files & 0xf),
+                    (CastlingSide::QUEENSIDE, files >> 4),
+                ] {
+                    let file = File::new(file).ok_or_else(|| {
+                        Error::InvalidEncoding(format!("invalid castling file {file}"))
+                    })?;
+                    game.set_castling_file(color, side, file);
+                }
+            }
+        }
+
+        game.halfmove_clock = self.varint()? as usize;
+        game.fullmove_clock = self.varint()? as usize;
+        Ok(game)
+    }
+
+    /// Reads what [`write_variant`] wrote into `game`.
+    fn variant(&mut self, game: &mut Game) -> Result<()> {
+        let index = self.byte()?;
+        game.variant = *Variant::ALL
+            .get(index as usize)
+            .ok_or_else(|| Error::InvalidEncoding(format!("invalid variant {index}")))?;
+        if game.rules().has_pockets() {
+            for color in Color::both() {
+                for piece in &Piece::ALL[..5] {
+                    for _ in 0..self.byte()? {
+                        game.add_to_pocket(ColoredPiece::new(*piece, color));
+                    }
+                }
+            }
+            let promoted = self.bitboard()?;
+            game.set_promoted(promoted);
+        }
+        if game.variant == Variant::DUCK {
+            match self.byte()? {
+                NO_DUCK => {}
+                s => {
+                    let s = Square::new(s).ok_or_else(|| {
+                        Error::InvalidEncoding(format!("invalid duck square {s}"))
+                    })?;
+                    game.put_duck(s);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::{random_playout, seeded_rng};
+
+    #[test]
+    fn position_roundtrip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 300",
+            "8/8/4k3/8/8/4K3/8/8 b - - 99 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let bytes = encode_position(&game);
+            let (decoded, read) = decode_position(&bytes).unwrap();
+            assert_eq!(decoded, game, "{fen}");
+            assert_eq!(read, bytes.len());
+        }
+    }
+
+    #[test]
+    fn game_roundtrip() {
+        let mut game = Game::default();
+        let start = game;
+        let mut moves = Vec::new();
+        // Always play the last legal move to walk through a deterministic game
+        for _ in 0..20 {
+            let legal = all_legal_moves(&game);
+            let Some(&m) = legal.last() else { break };
+            game.make_move(m);
+            moves.push(m);
+        }
+
+        let bytes = encode_game(&start, &moves).unwrap();
+        assert!(bytes.len() <= encode_position(&start).len() + 1 + moves.len());
+        assert_eq!(decode_game(&bytes).unwrap(), (start, moves));
+    }
+
+    #[test]
+    fn crazyhouse_roundtrip() {
+        let start = Variant::CRAZYHOUSE.starting_position();
+        let playout = random_playout(&start, &mut seeded_rng(1193), 80);
+        assert!(Color::both().into_iter().any(|color| Piece::ALL
+            .iter()
+            .any(|&p| playout.game.pocket(color, p) > 0)));
+        let bytes = encode_game(&start, &playout.moves).unwrap();
+        assert_eq!(decode_game(&bytes).unwrap(), (start, playout.moves));
+        let bytes = encode_position(&playout.game);
+        assert_eq!(
+            decode_position(&bytes).unwrap(),
+            (playout.game, bytes.len())
+        );
+
+        let game = Game::from_fen("r3k3/1Q6/8/8/8/8/8/4K3[Nbpp] w - - 0 1").unwrap();
+        let mut promoted = game;
+        promoted.set_promoted(Bitboard::from_square(Square::B7));
+        let (decoded, _) = decode_position(&encode_position(&promoted)).unwrap();
+        assert_eq!(decoded.pocket(Color::BLACK, Piece::PAWN), 2);
+        assert_eq!(decoded.promoted(), promoted.promoted());
+        assert_eq!(decoded, promoted);
+    }
+
+    #[test]
+    fn duck_roundtrip() {
+        let fen = "rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let game = Game::from_fen(fen).unwrap();
+        let (decoded, _) = decode_position(&encode_position(&game)).unwrap();
+        assert_eq!(decoded.duck(), Some(Square::E5));
+        assert_eq!(decoded.to_fen(), fen);
+        assert_eq!(decoded, game);
+
+        // Most positions have more than 256 moves, whose indices take two bytes
+        let start = Variant::DUCK.starting_position();
+        let playout = random_playout(&start, &mut seeded_rng(1200), 40);
+        let bytes = encode_game(&start, &playout.moves).unwrap();
+        assert_eq!(decode_game(&bytes).unwrap(), (start, playout.moves));
+        let bytes = encode_position(&playout.game);
+        assert_eq!(
+            decode_position(&bytes).unwrap(),
+            (playout.game, bytes.len())
+        );
+    }
+
+    #[test]
+    fn minichess_roundtrip() {
+        for variant in [Variant::GARDNER, Variant::FIVE_BY_SIX, Variant::LOS_ALAMOS] {
+            let start = variant.starting_position();
+            let playout = random_playout(&start, &mut seeded_rng(1201), 40);
+            let bytes = encode_game(&start, &playout.moves).unwrap();
+            assert_eq!(
+                decode_game(&bytes).unwrap(),
+                (start, playout.moves),
+                "{variant}"
+            );
+            let (decoded, _) = decode_position(&encode_position(&playout.game)).unwrap();
+            assert_eq!(decoded.to_fen(), playout.game.to_fen());
+            assert_eq!(decoded, playout.game);
+        }
+    }
+
+    #[test]
+    fn chess960_roundtrip() {
+        let start =
+            Game::from_fen("rkrnbbqn/pppppppp/8/8/8/8/PPPPPPPP/RKRNBBQN w CAca - 0 1").unwrap();
+        assert!(start.chess960);
+        let playout = random_playout(&start, &mut seeded_rng(960), 60);
+        let bytes = encode_game(&start, &playout.moves).unwrap();
+        assert_eq!(decode_game(&bytes).unwrap(), (start, playout.moves));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        let (decoded, _) = decode_position(&encode_position(&game)).unwrap();
+        assert_eq!(decoded.to_fen(), game.to_fen());
+        assert_eq!(decoded, game);
+    }
+
+    #[test]
+    fn encode_illegal_move() {
+        let m = Move::new(Square::E2, Square::E5);
+        assert_eq!(
+            encode_game(&Game::default(), &[m]),
+            Err(Error::IllegalMove(m))
+        );
+    }
+
+    #[test]
+    fn decode_truncated() {
+        let bytes = encode_position(&Game::default());
+        for len in 0..bytes.len() {
+            assert!(decode_position(&bytes[..len]).is_err());
+        }
+        let mut bytes = encode_game(&Game::default(), &[]).unwrap();
+        // Claim one move, but give an out-of-range index for it
+        *bytes.last_mut().unwrap() = 1;
+        bytes.push(255);
+        assert!(matches!(
+            decode_game(&bytes),
+            Err(Error::InvalidEncoding(_))
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+//! A binary format for collections of games, for databases of millions of games. It takes a
+//! byte per move where PGN takes four or five, and nothing for the standard starting position.
+//!
+//! A collection starts with the [`MAGIC`] bytes and the [`VERSION`] of the format, followed
+//! by one record per game. Every record starts with its length as a variable-length integer,
+//! so that a reader can skip games without decoding them, and holds:
+//!
+//! - the number of tag pairs, then every name and value as its length and its UTF-8 bytes,
+//! - the result as a byte, 0 for an unfinished game, 1 if White won, 2 if Black won and 3
+//!   for a draw,
+//! - a byte that is 0 if the game starts from the standard position and 1 if that position
+//!   follows in the encoding of [`encode_position`](super::encode_position), which also
+//!   stores the variant and whether the game is Chess960,
+//! - the number of moves, then every move as its index into the legal moves of its position,
+//!   a single byte, or two where there are more than 256 legal moves.
+//!
+//! All lengths and counts are unsigned LEB128 integers.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{codec::collection::{self, CollectionReader}, pgn};
+//! let input = "[White \"Morphy, Paul\"]\n\n1. e4 e5 2. Nf3 d6 3. d4 Bg4 1-0\n";
+//! let games: Vec<_> = pgn::parse(input).into_iter().map(Result::unwrap).collect();
+//! let bytes = collection::encode(&games).unwrap();
+//! assert!(bytes.len() < input.len());
+//! let decoded: Vec<_> = CollectionReader::new(&bytes).unwrap().map(Result::unwrap).collect();
+//! assert_eq!(decoded, games);
+//! ```
+
+use super::{write_moves, write_position, write_varint, Reader};
+use crate::{
+    game::{Game, Outcome},
+    pgn::PgnGame,
+    Color, Error, Result,
+};
+
+/// The bytes every collection starts with.
+pub const MAGIC: [u8; 4] = *b"KRGC";
+/// The version of the format, written after [`MAGIC`]. Version 2 added the games of other
+/// variants and Chess960, so readers still take collections of version 1.
+pub const VERSION: u8 = 2;
+
+/// Encodes `games` into a collection. Returns [`Error::IllegalMove`] if a move of a game is
+/// not legal where it was played.
+pub fn encode(games: &[PgnGame]) -> Result<Vec<u8>> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    for game in games {
+        write_game(game, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Appends the record of `game` to a collection, e.g. to one started by [`encode`] with no
+/// games, so that a collection can be written one game at a time.
+pub fn write_game(game: &PgnGame, out: &mut Vec<u8>) -> Result<()> {
+    let mut record = Vec::new();
+    write_varint(game.tags.len() as u64, &mut record);
+    for (name, value) in &game.tags {
+        for text in [name, value] {
+            write_varint(text.len() as u64, &mut record);
+            record.extend_from_slice(text.as_bytes());
+        }
+    }
+    record.push(match game.outcome {
+        None => 0,
+        Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        }) => 1,
+        Some(Outcome::DECISIVE {
+            winner: Color::BLACK,
+        }) => 2,
+        Some(Outcome::DRAW) => 3,
+    });
+    if game.start == Game::default() {
+        record.push(0);
+    } else {
+        record.push(1);
+        write_position(&game.start, &mut record);
+    }
+    write_moves(&game.start, &game.moves, &mut record)?;
+    write_varint(record.len() as u64, out);
+    out.extend_from_slice(&record);
+    Ok(())
+}
+
+/// Reads the games of a collection one after another.
+#[derive(Debug, Clone)]
+pub struct CollectionReader<'a> {
+    reader: Reader<'a>,
+    failed: bool,
+}
+
+impl<'a> CollectionReader<'a> {
+    /// Starts reading the collection in `bytes`, failing with [`Error::InvalidEncoding`] if it
+    /// doesn't start with the header of this or an earlier version of the format.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.get(..MAGIC.len()) != Some(&MAGIC) {
+            return Err(Error::InvalidEncoding(
+                "not a collection of games".to_string(),
+            ));
+        }
+        let mut reader = Reader::new(bytes);
+        reader.pos = MAGIC.len();
+        let version = reader.byte()?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(Error::InvalidEncoding(format!(
+                "unsupported collection version {version}"
+            )));
+        }
+        Ok(Self {
+            reader,
+            failed: false,
+        })
+    }
+
+    /// Returns the next record undecoded, to skip it or decode it with [`decode_game`] later.
+    pub fn next_record(&mut self) -> Option<Result<&'a [u8]>> {
+        if self.failed || self.reader.pos == self.reader.bytes.len() {
+            return None;
+        }
+        let record = self.reader.varint().and_then(|len| {
+            let start = self.reader.pos;
+            let end = start
+                .checked_add(len as usize)
+                .filter(|&end| end <= self.reader.bytes.len())
+                .ok_or_else(|| Error::InvalidEncoding("unexpected end of input".to_string()))?;
+            self.reader.pos = end;
+            Ok(&self.reader.bytes[start..end])
+        });
+        // Nothing after a broken length can be trusted
+        self.failed = record.is_err();
+        Some(record)
+    }
+}
+
+impl Iterator for CollectionReader<'_> {
+    type Item = Result<PgnGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+            .map(|record| record.and_then(decode_game))
+    }
+}
+
+/// Decodes the record of a game, without its length.
+pub fn decode_game(record: &[u8]) -> Result<PgnGame> {
+    let mut reader = Reader::new(record);
+    let mut tags = Vec::new();
+    for _ in 0..reader.varint()? {
+        let name = text(&mut reader)?;
+        tags.push((name, text(&mut reader)?));
+    }
+    let outcome = match reader.byte()? {
+        0 => None,
+        1 => Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        }),
+        2 => Some(Outcome::DECISIVE {
+            winner: Color::BLACK,
+        }),
+        3 => Some(Outcome::DRAW),
+        b => {
+            return Err(Error::InvalidEncoding(format!("invalid result {b}")));
+        }
+    };
+    let start = match reader.byte()? {
+        0 => Game::default(),
+        1 => reader.position()?,
+        b => {
+            return Err(Error::InvalidEncoding(format!("invalid start flag {b}")));
+        }
+    };
+    let moves = reader.moves(&start)?;
+    if reader.pos != record.len() {
+        return Err(Error::InvalidEncoding(
+            "trailing bytes after a game".to_string(),
+        ));
+    }
+    Ok(PgnGame {
+        tags,
+        start,
+        moves,
+        outcome,
+    })
+}
+
+/// Reads a string given by its length and its UTF-8 bytes.
+fn text(reader: &mut Reader) -> Result<String> {
+    let len = reader.varint()? as usize;
+    let bytes = (0..len)
+        .map(|_| reader.byte())
+        .collect::<Result<Vec<u8>>>()?;
+    String::from_utf8(bytes).map_err(|e| Error::InvalidEncoding(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        movegen::{random_playout, seeded_rng},
+        pgn,
+        variant::Variant,
+    };
+
+    #[test]
+    fn games_round_trip() {
+        let input = r#"
+[Event "Casual game"]
+[Site "Café de la Régence"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+
+1. O-O Kd7 *
+
+1. d4 d5 1/2-1/2
+"#;
+        let games: Vec<PgnGame> = pgn::parse(input).into_iter().map(Result::unwrap).collect();
+        let mut bytes = encode(&[]).unwrap();
+        for game in &games {
+            write_game(game, &mut bytes).unwrap();
+        }
+        assert_eq!(bytes, encode(&games).unwrap());
+        let decoded: Vec<PgnGame> = CollectionReader::new(&bytes)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(decoded, games);
+
+        let mut reader = CollectionReader::new(&bytes).unwrap();
+        reader.next_record().unwrap().unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(decode_game(record).unwrap(), games[1]);
+    }
+
+    #[test]
+    fn variant_games_round_trip() {
+        let chess960 =
+            Game::from_fen("rkrnbbqn/pppppppp/8/8/8/8/PPPPPPPP/RKRNBBQN w CAca - 0 1").unwrap();
+        let starts = Variant::ALL
+            .into_iter()
+            .map(Variant::starting_position)
+            .chain([chess960]);
+        let games: Vec<PgnGame> = starts
+            .enumerate()
+            .map(|(seed, start)| {
+                let playout = random_playout(&start, &mut seeded_rng(seed as u64), 40);
+                PgnGame {
+                    tags: vec![("Variant".to_string(), start.variant.to_string())],
+                    start,
+                    moves: playout.moves,
+                    outcome: playout.outcome,
+                }
+            })
+            .collect();
+        let bytes = encode(&games).unwrap();
+        let decoded: Vec<PgnGame> = CollectionReader::new(&bytes)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(decoded, games);
+        assert_eq!(decoded[games.len() - 1].start.to_fen(), chess960.to_fen());
+    }
+
+    #[test]
+    fn rejects_broken_collections() {
+        assert!(matches!(
+            CollectionReader::new(b"PGN"),
+            Err(Error::InvalidEncoding(_))
+        ));
+        assert!(CollectionReader::new(b"KRGC\x03").is_err());
+        assert!(CollectionReader::new(b"KRGC\x01").is_ok());
+
+        let game = pgn::parse("1. e4 e5 *").remove(0).unwrap();
+        let bytes = encode(&[game.clone(), game]).unwrap();
+        // Cut into the second record
+        let mut reader = CollectionReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+        // An out-of-range move index
+        let mut bytes = bytes;
+        *bytes.last_mut().unwrap() = 255;
+        let results: Vec<_> = CollectionReader::new(&bytes).unwrap().collect();
+        assert!(matches!(results[1], Err(Error::InvalidEncoding(_))));
+    }
+}
@@ -0,0 +1,171 @@
+//! A fixed set of positions for benchmarking, from the opening, the middlegame and the
+//! endgame, plus pathological cases for the move generator.
+//!
+//! The [bench](crate::search::bench) searches the positions of the first three groups, so the
+//! corpus is the one its node count stands for. Engines built on this crate can measure
+//! themselves on the same positions.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{corpus::{self, Category}, movegen::all_legal_moves};
+//! assert!(corpus::ENDGAMES.iter().all(|p| p.category == Category::ENDGAME));
+//! let most_moves = corpus::MOVEGEN[0];
+//! assert_eq!(all_legal_moves(&most_moves.game()).len(), 218);
+//! ```
+
+use crate::game::Game;
+
+/// The group a position belongs to.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Category {
+    OPENING,
+    MIDDLEGAME,
+    ENDGAME,
+    /// Positions that are hard on the move generator rather than the search, e.g. with very
+    /// many moves or every kind of special move.
+    MOVEGEN,
+}
+
+/// A position of the corpus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BenchPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub category: Category,
+}
+
+impl BenchPosition {
+    const fn new(name: &'static str, fen: &'static str, category: Category) -> Self {
+        Self {
+            name,
+            fen,
+            category,
+        }
+    }
+
+    /// Returns the position.
+    pub fn game(&self) -> Game {
+        Game::from_fen(self.fen).expect("Corpus positions are valid")
+    }
+}
+
+/// Positions from the first moves of common openings.
+pub const OPENINGS: [BenchPosition; 4] = [
+    BenchPosition::new(
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        Category::OPENING,
+    ),
+    BenchPosition::new(
+        "open game",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        Category::OPENING,
+    ),
+    BenchPosition::new(
+        "sicilian",
+        "rnbqkb1r/pp2pppp/3p1n2/8/3NP3/8/PPP2PPP/RNBQKB1R w KQkq - 1 5",
+        Category::OPENING,
+    ),
+    BenchPosition::new(
+        "italian",
+        "r1bq1rk1/ppp2ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 w - - 0 7",
+        Category::OPENING,
+    ),
+];
+
+/// Middlegame positions with most of the pieces still on the board.
+pub const MIDDLEGAMES: [BenchPosition; 4] = [
+    BenchPosition::new(
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        Category::MIDDLEGAME,
+    ),
+    BenchPosition::new(
+        "queen's gambit declined",
+        "r2q1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 1 9",
+        Category::MIDDLEGAME,
+    ),
+    BenchPosition::new(
+        "kingside attack",
+        "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+        Category::MIDDLEGAME,
+    ),
+    BenchPosition::new(
+        "opposite castling",
+        "r1b2rk1/2q1b1pp/p2ppn2/1p6/3QP3/1BN1B3/PPP3PP/R4RK1 w - - 0 14",
+        Category::MIDDLEGAME,
+    ),
+];
+
+/// Endgames with few pieces and the pawns deciding.
+pub const ENDGAMES: [BenchPosition; 4] = [
+    BenchPosition::new(
+        "minor pieces and rooks",
+        "2r3k1/pp3ppp/2n1b3/3p4/3P4/2N1B3/PP3PPP/2R3K1 w - - 0 20",
+        Category::ENDGAME,
+    ),
+    BenchPosition::new(
+        "rook and pawns",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        Category::ENDGAME,
+    ),
+    BenchPosition::new(
+        "pawns",
+        "6k1/5p2/6p1/8/7p/8/6PP/6K1 b - - 0 1",
+        Category::ENDGAME,
+    ),
+    BenchPosition::new(
+        "knight against rook",
+        "8/8/1p1r1k2/p1pPN1p1/P3KnP1/1P6/8/3R4 b - - 0 1",
+        Category::ENDGAME,
+    ),
+];
+
+/// Positions that are hard on the move generator.
+pub const MOVEGEN: [BenchPosition; 4] = [
+    BenchPosition::new(
+        "most legal moves",
+        "R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1",
+        Category::MOVEGEN,
+    ),
+    BenchPosition::new(
+        "promotions",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        Category::MOVEGEN,
+    ),
+    BenchPosition::new(
+        "pins and en passant",
+        "8/2p5/3p4/KP5r/1R2Pp1k/8/6P1/8 b - e3 0 1",
+        Category::MOVEGEN,
+    ),
+    BenchPosition::new(
+        "double check",
+        "4k3/8/8/8/1b6/8/8/r3K3 w - - 0 1",
+        Category::MOVEGEN,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{movegen::all_legal_moves, Move};
+
+    #[test]
+    fn positions_are_valid() {
+        for positions in [OPENINGS, MIDDLEGAMES, ENDGAMES, MOVEGEN] {
+            for position in positions {
+                let game = position.game();
+                assert_eq!(game.to_fen(), position.fen);
+                assert!(game.validate().is_ok(), "{}", position.name);
+            }
+        }
+        // The en passant capture would leave the king in check
+        let pinned = MOVEGEN[2].game();
+        let f4e3 = Move::from_uci("f4e3").unwrap();
+        assert!(pinned.is_en_passant(f4e3));
+        assert!(!all_legal_moves(&pinned).contains(&f4e3));
+        assert_eq!(all_legal_moves(&MOVEGEN[3].game()).len(), 2);
+    }
+}
@@ -0,0 +1,299 @@
+//! Parallel processing of large FEN and EPD dumps and game databases, enabled by the
+//! `dataset` feature.
+//!
+//! Every non-empty line is parsed into a [`Game`] and handed to a user-supplied closure on the
+//! rayon thread pool. Results come back in input order, with parse errors tagged by their
+//! line number. [`map_pgn`] and [`map_collection`] do the same for every game of a PGN
+//! database or a [collection](crate::codec::collection), e.g. to filter them or gather
+//! statistics on all cores.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{dataset, pgn};
+//! let input = "[White \"a\"]\n\n1. e4 e5 1-0\n\n[White \"b\"]\n\n1. d4 1/2-1/2\n";
+//! let lengths = dataset::map_pgn(input, |game| game.moves.len());
+//! assert_eq!(lengths, [Ok(2), Ok(1)]);
+//! ```
+
+use std::io::BufRead;
+
+use rayon::prelude::*;
+
+use crate::{
+    codec::collection::{decode_game, CollectionReader},
+    game::Game,
+    pgn::{self, PgnGame},
+    Error, Result,
+};
+
+/// How many lines [`map_reader`] buffers before processing them in parallel.
+const CHUNK_LINES: usize = 1 << 16;
+
+/// A position parsed from one line of a dataset.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    /// The 1-based line number.
+    pub line: usize,
+    pub game: Game,
+    /// Anything following the position, e.g. the EPD operations `bm e4; id "1";`.
+    /// Empty for plain FEN lines.
+    pub operations: &'a str,
+}
+
+/// Parses a FEN or EPD line, returning the position and the remainder of the line.
+///
+/// A line is treated as FEN if the two fields after the en passant square are numbers,
+/// and as EPD with the clocks omitted otherwise.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::dataset::parse_line;
+/// let (game, ops) = parse_line("4k3/8/8/8/8/8/8/4K2R w K - bm O-O; id \"castle\";").unwrap();
+/// assert_eq!(game.fullmove_clock, 1);
+/// assert_eq!(ops, "bm O-O; id \"castle\";");
+///
+/// let (game, ops) = parse_line("4k3/8/8/8/8/8/8/4K2R b - - 3 40").unwrap();
+/// assert_eq!(game.fullmove_clock, 40);
+/// assert!(ops.is_empty());
+/// ```
+pub fn parse_line(line: &str) -> Result<(Game, &str)> {
+    let (position, operations) = match split_fields(line, 6) {
+        Some((fen, rest)) if fen.split_whitespace().skip(4).all(is_number) => (fen, rest),
+        _ => split_fields(line, 4)
+            .ok_or_else(|| Error::InvalidFen(format!("expected at least 4 fields in '{line}'")))?,
+    };
+    Ok((Game::from_fen(position)?, operations))
+}
+
+/// Parses every non-empty line of `input` and applies `f` to the resulting positions in
+/// parallel. The results are returned in input order. Lines that fail to parse produce an
+/// [`Error::Line`] and are not passed to `f`.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{dataset, movegen::all_legal_moves};
+/// let input = "\
+/// rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+///
+/// 4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;
+/// not a fen
+/// ";
+/// let counts = dataset::map(input, |entry| all_legal_moves(&entry.game).len());
+/// assert_eq!(counts.len(), 3);
+/// assert_eq!(counts[0], Ok(20));
+/// assert_eq!(counts[1], Ok(5));
+/// assert!(matches!(counts[2], Err(kritisch::Error::Line { line: 4, .. })));
+/// ```
+pub fn map<T, F>(input: &str, f: F) -> Vec<Result<T>>
+where
+    T: Send,
+    F: Fn(Entry<'_>) -> T + Sync,
+{
+    let lines: Vec<(usize, &str)> = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+    map_lines(&lines, &f)
+}
+
+/// Like [`map`], but reads the input from `reader` in chunks, so dumps larger than memory
+/// can be processed. Only the results are kept.
+pub fn map_reader<R, T, F>(reader: R, f: F) -> std::io::Result<Vec<Result<T>>>
+where
+    R: BufRead,
+    T: Send,
+    F: Fn(Entry<'_>) -> T + Sync,
+{
+    let mut results = Vec::new();
+    let mut chunk = Vec::with_capacity(CHUNK_LINES);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            chunk.push((i + 1, line));
+        }
+        if chunk.len() == CHUNK_LINES {
+            results.extend(map_owned_lines(&chunk, &f));
+            chunk.clear();
+        }
+    }
+    results.extend(map_owned_lines(&chunk, &f));
+    Ok(results)
+}
+
+fn map_owned_lines<T, F>(lines: &[(usize, String)], f: &F) -> Vec<Result<T>>
+where
+    T: Send,
+    F: Fn(Entry<'_>) -> T + Sync,
+{
+    let lines: Vec<(usize, &str)> = lines.iter().map(|(i, l)| (*i, l.as_str())).collect();
+    map_lines(&lines, f)
+}
+
+fn map_lines<T, F>(lines: &[(usize, &str)], f: &F) -> Vec<Result<T>>
+where
+    T: Send,
+    F: Fn(Entry<'_>) -> T + Sync,
+{
+    lines
+        .par_iter()
+        .map(|&(line, text)| {
+            let (game, operations) = parse_line(text).map_err(|e| Error::Line {
+                line,
+                source: Box::new(e),
+            })?;
+            Ok(f(Entry {
+                line,
+                game,
+                operations,
+            }))
+        })
+        .collect()
+}
+
+/// Reads the games of the PGN database `input` in parallel and applies `f` to each of them.
+/// The results are returned in input order. A game that can't be read gives the error
+/// [`pgn::parse`] gives for it, with the line counted from the start of `input`.
+///
+/// The database is split into games at every tag pair that starts a line after the moves of
+/// the game before, so a game must start with its tags on a line of their own if the game
+/// before it ended with a result.
+pub fn map_pgn<T, F>(input: &str, f: F) -> Vec<Result<T>>
+where
+    T: Send,
+    F: Fn(PgnGame) -> T + Sync,
+{
+    split_games(input)
+        .par_iter()
+        .flat_map_iter(|&(offset, text)| {
+            pgn::parse(text).into_iter().map(move |game| match game {
+                Err(Error::Line { line, source }) => Err(Error::Line {
+                    line: offset + line,
+                    source,
+                }),
+                game => game,
+            })
+        })
+        .map(|game| game.map(&f))
+        .collect()
+}
+
+/// Decodes the games of the [collection](crate::codec::collection) in `bytes` in parallel
+/// and applies `f` to each of them. The results are returned in the order of the games,
+/// followed by the error that stopped reading if the collection is cut short. Fails if
+/// `bytes` isn't a collection.
+pub fn map_collection<T, F>(bytes: &[u8], f: F) -> Result<Vec<Result<T>>>
+where
+    T: Send,
+    F: Fn(PgnGame) -> T + Sync,
+{
+    let mut reader = CollectionReader::new(bytes)?;
+    let records: Vec<Result<&[u8]>> = std::iter::from_fn(|| reader.next_record()).collect();
+    Ok(records
+        .into_par_iter()
+        .map(|record| record.and_then(decode_game).map(&f))
+        .collect())
+}
+
+/// Splits a PGN database into pieces of whole games, each with the number of lines before
+/// it.
+fn split_games(input: &str) -> Vec<(usize, &str)> {
+    let mut games = Vec::new();
+    let (mut start, mut start_line) = (0, 0);
+    let mut in_movetext = false;
+    let mut offset = 0;
+    for (i, line) in input.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_movetext {
+                games.push((start_line, &input[start..offset]));
+                (start, start_line) = (offset, i);
+                in_movetext = false;
+            }
+        } else if !trimmed.is_empty() {
+            in_movetext = true;
+        }
+        offset += line.len();
+    }
+    if start < input.len() {
+        games.push((start_line, &input[start..]));
+    }
+    games
+}
+
+/// Splits `line` after its first `n` whitespace-separated fields, returning the fields and
+/// the trimmed remainder, or `None` if the line has fewer than `n` fields.
+fn split_fields(line: &str, n: usize) -> Option<(&str, &str)> {
+    let line = line.trim();
+    let mut end = 0;
+    for _ in 0..n {
+        let rest = &line[end..];
+        let start = end + rest.len() - rest.trim_start().len();
+        if start == line.len() {
+            return None;
+        }
+        end = line[start..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |len| start + len);
+    }
+    Some((&line[..end], line[end..].trim_start()))
+}
+
+fn is_number(field: &str) -> bool {
+    !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_matches_str() {
+        let input = "8/8/4k3/8/8/4K3/8/8 w - - 0 1\n".repeat(1000)
+            + "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c0 \"start\";\n";
+        let f = |entry: Entry<'_>| (entry.line, entry.game.to_fen(), entry.operations.len());
+        let from_str = map(&input, f);
+        let from_reader = map_reader(input.as_bytes(), f).unwrap();
+        assert_eq!(from_str, from_reader);
+        assert_eq!(from_str.len(), 1001);
+        assert_eq!(from_str[1000].as_ref().unwrap().0, 1001);
+    }
+
+    #[test]
+    fn games_come_back_in_order() {
+        let game = |i: usize| format!("[Round \"{i}\"]\n\n1. e4 e5 2. Nf3 *\n\n");
+        let valid: String = (0..200).map(game).collect();
+        // A broken game on line 4 of the last one
+        let input = valid.clone() + "[Round \"200\"]\n\n1. e4\n e4 *\n";
+        let rounds = map_pgn(&input, |game| game.tag("Round").unwrap().to_string());
+        assert_eq!(rounds.len(), 201);
+        assert!(rounds[..200]
+            .iter()
+            .enumerate()
+            .all(|(i, round)| round.as_deref() == Ok(&i.to_string())));
+        assert!(matches!(rounds[200], Err(Error::Line { line: 804, .. })));
+
+        let games: Vec<PgnGame> = pgn::parse(&valid)
+            .into_iter()
+            .collect::<Result<_>>()
+            .unwrap();
+        let bytes = crate::codec::collection::encode(&games).unwrap();
+        let plies = map_collection(&bytes, |game| game.moves.len()).unwrap();
+        assert_eq!(plies, vec![Ok(3); 200]);
+        let cut = map_collection(&bytes[..bytes.len() - 1], |game| game.moves.len()).unwrap();
+        assert_eq!(cut.len(), 200);
+        assert!(cut[199].is_err());
+        assert!(map_collection(b"PGN", |_| ()).is_err());
+    }
+
+    #[test]
+    fn split_fields_keeps_operations() {
+        assert_eq!(split_fields("  a b  c d ", 2), Some(("a b", "c d")));
+        assert_eq!(split_fields("a b", 2), Some(("a b", "")));
+        assert_eq!(split_fields("a b", 3), None);
+    }
+}
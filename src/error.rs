@@ -0,0 +1,63 @@
+use crate::Move;
+
+/// Errors returned by fallible operations in this crate.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    /// A FEN string could not be parsed.
+    #[error("invalid FEN: {0}")]
+    InvalidFen(String),
+
+    /// A move in standard algebraic notation could not be parsed or resolved.
+    #[error("invalid SAN move: {0}")]
+    InvalidSan(String),
+
+    /// A move or command in UCI notation could not be parsed.
+    #[error("invalid UCI: {0}")]
+    InvalidUci(String),
+
+    /// A square could not be parsed from its coordinates.
+    #[error("invalid square: {0}")]
+    InvalidSquare(String),
+
+    /// A move is not legal in the position it was played in.
+    #[error("illegal move: {0:?}")]
+    IllegalMove(Move),
+
+    /// Binary data could not be decoded into a position or game.
+    #[error("invalid encoding: {0}")]
+    InvalidEncoding(String),
+
+    /// A game in PGN could not be read, see [`pgn`](crate::pgn).
+    #[error("invalid PGN: {0}")]
+    InvalidPgn(String),
+
+    /// A JSON document could not be read, see the `lichess` module.
+    #[error("invalid JSON: {0}")]
+    InvalidJson(String),
+
+    /// An NNUE network could not be read, see the `nnue` module.
+    #[error("invalid network: {0}")]
+    InvalidNetwork(String),
+
+    /// A puzzle could not be read, see [`puzzle`](crate::puzzle).
+    #[error("invalid puzzle: {0}")]
+    InvalidPuzzle(String),
+
+    /// A variant name is not known, see [`variant`](crate::variant).
+    #[error("unknown variant: {0}")]
+    InvalidVariant(String),
+
+    /// A position is well-formed but violates the rules of chess.
+    #[error("invalid position: {0}")]
+    InvalidPosition(String),
+
+    /// An error in one line of a multi-line input, such as a FEN or EPD file.
+    #[error("line {line}: {source}")]
+    Line {
+        /// The 1-based line number.
+        line: usize,
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
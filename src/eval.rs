@@ -0,0 +1,465 @@
+//! Static evaluation built from independent [`Term`]s.
+//!
+//! Every term scores a position from white's point of view as a middlegame/endgame
+//! [`Score`] pair. An [`Evaluator`] weights the enabled terms, sums them and blends the
+//! middlegame and endgame parts by how much material is left. Terms can be switched off,
+//! reweighted or replaced, and [`Evaluator::trace`] shows what each one contributed. For
+//! evaluating many positions, an [`EvalCache`](cache::EvalCache) saves evaluating repeated
+//! ones twice.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{eval::Evaluator, game::Game};
+//! let mut evaluator = Evaluator::default();
+//! let game = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").unwrap();
+//! // White has developed a knight, so black to move is a little worse off
+//! assert!(evaluator.evaluate(&game) < 0);
+//!
+//! evaluator.term_mut("mobility").unwrap().enabled = false;
+//! println!("{}", evaluator.trace(&game));
+//! ```
+
+use crate::{bitboard::Bitboard, game::Game, mobility, pawns, pst::Score, Color, Piece, Square};
+
+pub mod cache;
+
+/// The game phase of the starting position, see [`phase`].
+pub const MAX_PHASE: i32 = 24;
+
+/// One aspect of a position, such as material or pawn structure.
+pub trait Term: Send + Sync {
+    /// A short, unique name used to look the term up and to label it in traces.
+    fn name(&self) -> &'static str;
+
+    /// Scores `game` from white's point of view.
+    fn evaluate(&self, game: &Game) -> Score;
+}
+
+/// A term together with its weight in an [`Evaluator`].
+pub struct WeightedTerm {
+    pub term: Box<dyn Term>,
+    /// The weight in percent, so 100 counts the term as is.
+    pub weight: i32,
+    pub enabled: bool,
+}
+
+impl std::fmt::Debug for WeightedTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightedTerm")
+            .field("term", &self.term.name())
+            .field("weight", &self.weight)
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+/// Sums weighted evaluation terms into a score in centipawns.
+///
+/// The default evaluator has [`Material`], [`PieceSquare`], [`Mobility`], [`PawnStructure`]
+/// and [`KingSafety`], all at full weight.
+#[derive(Debug)]
+pub struct Evaluator {
+    pub terms: Vec<WeightedTerm>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        let mut evaluator = Self::empty();
+        evaluator.push(Material);
+        evaluator.push(PieceSquare);
+        evaluator.push(Mobility);
+        evaluator.push(PawnStructure);
+        evaluator.push(KingSafety);
+        evaluator
+    }
+}
+
+impl Evaluator {
+    /// Returns an evaluator without any terms, which scores every position as 0.
+    pub fn empty() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    /// Returns an evaluator that only has the [`Nnue`](crate::nnue::Nnue) term, evaluating
+    /// with the installed network instead of the classical terms.
+    #[cfg(feature = "nnue")]
+    pub fn nnue() -> Self {
+        let mut evaluator = Self::empty();
+        evaluator.push(crate::nnue::Nnue);
+        evaluator
+    }
+
+    /// Adds `term` at full weight.
+    pub fn push(&mut self, term: impl Term + 'static) {
+        self.terms.push(WeightedTerm {
+            term: Box::new(term),
+            weight: 100,
+            enabled: true,
+        });
+    }
+
+    /// Returns the term called `name`, e.g. to change its weight or disable it.
+    pub fn term_mut(&mut self, name: &str) -> Option<&mut WeightedTerm> {
+        self.terms.iter_mut().find(|t| t.term.name() == name)
+    }
+
+    /// Returns the score of `game` in centipawns from the point of view of the side to move.
+    pub fn evaluate(&self, game: &Game) -> i32 {
+        let total = self
+            .terms
+            .iter()
+            .filter(|t| t.enabled)
+            .fold(Score::ZERO, |sum, t| {
+                sum + weigh(t.term.evaluate(game), t.weight)
+            });
+        relative(taper(total, phase(game)), game.to_move)
+    }
+
+    /// Evaluates `game` term by term.
+    pub fn trace(&self, game: &Game) -> Trace {
+        let phase = phase(game);
+        let mut total = Score::ZERO;
+        let entries = self
+            .terms
+            .iter()
+            .filter(|t| t.enabled)
+            .map(|t| {
+                let raw = t.term.evaluate(game);
+                let weighted = weigh(raw, t.weight);
+                total += weighted;
+                TraceEntry {
+                    name: t.term.name(),
+                    raw,
+                    weighted,
+                }
+            })
+            .collect();
+        Trace {
+            entries,
+            phase,
+            total,
+            score: relative(taper(total, phase), game.to_move),
+        }
+    }
+}
+
+/// The contribution of every enabled term to an evaluation, see [`Evaluator::trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+    /// The game phase used to blend middlegame and endgame scores.
+    pub phase: i32,
+    /// The sum of the weighted term scores, from white's point of view.
+    pub total: Score,
+    /// The final score, as returned by [`Evaluator::evaluate`].
+    pub score: i32,
+}
+
+/// One term's score in a [`Trace`], from white's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub name: &'static str,
+    pub raw: Score,
+    pub weighted: Score,
+}
+
+/// Prints a table with one row per term.
+impl std::fmt::Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<16}{:>8}{:>8}", "term", "mg", "eg")?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:<16}{:>8}{:>8}",
+                entry.name, entry.weighted.mg, entry.weighted.eg
+            )?;
+        }
+        writeln!(f, "{:<16}{:>8}{:>8}", "total", self.total.mg, self.total.eg)?;
+        write!(
+            f,
+            "phase {}/{MAX_PHASE}, score {} for the side to move",
+            self.phase, self.score
+        )
+    }
+}
+
+/// Returns how much non-pawn material is left, from [`MAX_PHASE`] in the starting position
+/// down to 0 with only kings and pawns.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{eval::{phase, MAX_PHASE}, game::Game};
+/// assert_eq!(phase(&Game::default()), MAX_PHASE);
+/// assert_eq!(phase(&Game::from_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap()), 0);
+/// ```
+pub fn phase(game: &Game) -> i32 {
+    let phase: u32 = Color::both()
+        .into_iter()
+        .map(|c| {
+            game.piece_count(c, Piece::KNIGHT)
+                + game.piece_count(c, Piece::BISHOP)
+                + 2 * game.piece_count(c, Piece::ROOK)
+                + 4 * game.piece_count(c, Piece::QUEEN)
+        })
+        .sum();
+    // Promotions can push the phase past the starting position
+    (phase as i32).min(MAX_PHASE)
+}
+
+fn weigh(score: Score, weight: i32) -> Score {
+    Score::new(score.mg * weight / 100, score.eg * weight / 100)
+}
+
+fn taper(score: Score, phase: i32) -> i32 {
+    (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+fn relative(score: i32, color: Color) -> i32 {
+    match color {
+        Color::WHITE => score,
+        Color::BLACK => -score,
+    }
+}
+
+/// Returns the pieces of type `piece` and color `color`.
+fn pieces(game: &Game, color: Color, piece: Piece) -> Bitboard {
+    game.piece_bitboards[piece as usize] & game.color_bitboards[color as usize]
+}
+
+/// Sums `f` over both colors, counting black negatively.
+fn white_minus_black(mut f: impl FnMut(Color) -> Score) -> Score {
+    f(Color::WHITE) - f(Color::BLACK)
+}
+
+/// The [`Piece::value`] of all pieces.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Material;
+
+impl Term for Material {
+    fn name(&self) -> &'static str {
+        "material"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        let material = game.material(Color::WHITE) - game.material(Color::BLACK);
+        Score::new(material, material)
+    }
+}
+
+/// The piece-square tables, see [`pst`](crate::pst).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PieceSquare;
+
+impl Term for PieceSquare {
+    fn name(&self) -> &'static str {
+        "piece-square"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        game.pst()
+    }
+}
+
+/// A bonus per safe square the knights, bishops, rooks and queens can move to, see
+/// [`mobility`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Mobility;
+
+/// The mobility bonus per reachable square, indexed by piece discriminant.
+const MOBILITY_WEIGHTS: [Score; 6] = [
+    Score::ZERO,
+    Score::new(4, 4),
+    Score::new(5, 5),
+    Score::new(2, 4),
+    Score::new(1, 2),
+    Score::ZERO,
+];
+
+impl Term for Mobility {
+    fn name(&self) -> &'static str {
+        "mobility"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        white_minus_black(|color| {
+            let mut score = Score::ZERO;
+            for (weight, squares) in MOBILITY_WEIGHTS.iter().zip(mobility::mobility(game, color)) {
+                let squares = squares as i32;
+                score += Score::new(weight.mg * squares, weight.eg * squares);
+            }
+            score
+        })
+    }
+}
+
+/// Penalties for doubled and isolated pawns and a bonus for passed pawns that grows as they
+/// advance, see [`pawns`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PawnStructure;
+
+const DOUBLED_PAWN: Score = Score::new(-10, -20);
+const ISOLATED_PAWN: Score = Score::new(-10, -15);
+/// The passed pawn bonus, indexed by the pawn's rank relative to its color.
+const PASSED_PAWN: [Score; 8] = [
+    Score::ZERO,
+    Score::new(5, 10),
+    Score::new(5, 15),
+    Score::new(10, 25),
+    Score::new(20, 45),
+    Score::new(35, 75),
+    Score::new(60, 120),
+    Score::ZERO,
+];
+
+impl Term for PawnStructure {
+    fn name(&self) -> &'static str {
+        "pawn structure"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        let structure = pawns::PawnStructure::new(game);
+        white_minus_black(|color| {
+            let i = color as usize;
+            let times = |score: Score, pawns: Bitboard| {
+                let count = pawns.count_ones() as i32;
+                Score::new(score.mg * count, score.eg * count)
+            };
+            let mut score = times(DOUBLED_PAWN, structure.doubled[i])
+                + times(ISOLATED_PAWN, structure.isolated[i]);
+            let mut passed = structure.passed[i];
+            while !passed.is_empty() {
+                let s = Square::from_u8(passed.trailing_zeros() as u8);
+                score += PASSED_PAWN[s.get_rank().relative_to(color) as usize];
+                passed.clear_lsb();
+            }
+            score
+        })
+    }
+}
+
+/// Returns the squares on ranks ahead of `s` from `color`'s point of view.
+fn ahead(s: Square, color: Color) -> u64 {
+    let rank = s.get_rank() as u32;
+    match color {
+        Color::WHITE => u64::MAX.checked_shl(8 * (rank + 1)).unwrap_or(0),
+        Color::BLACK => !(u64::MAX << (8 * rank)),
+    }
+}
+
+/// A middlegame bonus for pawns sheltering the king and a penalty for squares next to it
+/// that the opponent attacks.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct KingSafety;
+
+const PAWN_SHIELD: Score = Score::new(10, 0);
+const ATTACKED_KING_ZONE: Score = Score::new(-8, 0);
+
+impl Term for KingSafety {
+    fn name(&self) -> &'static str {
+        "king safety"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        white_minus_black(|color| {
+            let Some(king) = game.king_square(color) else {
+                return Score::ZERO;
+            };
+            let zone = game.attacks_from(king).0;
+            // The squares on the king's and neighboring files, one or two ranks in front of it
+            let front = match color {
+                Color::WHITE => (zone | king.to_u64()) << 8,
+                Color::BLACK => (zone | king.to_u64()) >> 8,
+            } & ahead(king, color);
+            let shield = (pieces(game, color, Piece::PAWN).0 & front).count_ones() as i32;
+            let attacked = (game.attacks(!color).0 & zone).count_ones() as i32;
+            Score::new(
+                PAWN_SHIELD.mg * shield + ATTACKED_KING_ZONE.mg * attacked,
+                PAWN_SHIELD.eg * shield + ATTACKED_KING_ZONE.eg * attacked,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flip(fen: &str) -> Game {
+        // Mirror the placement vertically and swap the colors
+        let mut fields = fen.split(' ');
+        let placement: Vec<String> = fields
+            .next()
+            .unwrap()
+            .split('/')
+            .rev()
+            .map(|rank| {
+                rank.chars()
+                    .map(|c| {
+                        if c.is_ascii_uppercase() {
+                            c.to_ascii_lowercase()
+                        } else {
+                            c.to_ascii_uppercase()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let side = if fields.next() == Some("w") { "b" } else { "w" };
+        Game::from_fen(&format!("{} {side} - - 0 1", placement.join("/"))).unwrap()
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let evaluator = Evaluator::default();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R2QKB1R w - - 0 1",
+            "8/5k2/3p4/1p1P4/1P3K2/8/6P1/8 b - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let flipped = flip(fen);
+            assert_eq!(
+                evaluator.evaluate(&game),
+                evaluator.evaluate(&flipped),
+                "{fen}"
+            );
+            for t in &evaluator.terms {
+                assert_eq!(
+                    t.term.evaluate(&game),
+                    -t.term.evaluate(&flipped),
+                    "{} in {fen}",
+                    t.term.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pawn_structure() {
+        // Doubled and isolated c-pawns against a passed a-pawn on the sixth rank
+        let game = Game::from_fen("4k3/8/P7/8/8/2p5/2p5/4K3 w - - 0 1").unwrap();
+        let score = PawnStructure.evaluate(&game);
+        let expected = PASSED_PAWN[5] + ISOLATED_PAWN
+            - (DOUBLED_PAWN + ISOLATED_PAWN + ISOLATED_PAWN + PASSED_PAWN[6] + PASSED_PAWN[5]);
+        assert_eq!(score, expected);
+    }
+
+    #[test]
+    fn weights_and_trace() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut evaluator = Evaluator::empty();
+        evaluator.push(Material);
+        assert_eq!(evaluator.evaluate(&game), 500);
+        evaluator.term_mut("material").unwrap().weight = 50;
+        assert_eq!(evaluator.evaluate(&game), 250);
+        evaluator.push(PieceSquare);
+        let trace = evaluator.trace(&game);
+        assert_eq!(trace.entries.len(), 2);
+        assert_eq!(trace.entries[0].raw, Score::new(500, 500));
+        assert_eq!(trace.score, evaluator.evaluate(&game));
+        evaluator.term_mut("piece-square").unwrap().enabled = false;
+        assert_eq!(evaluator.evaluate(&game), 250);
+    }
+}
@@ -0,0 +1,134 @@
+//! A cache of static evaluations indexed by the [`Game::key`] of a position, for evaluating
+//! many positions that repeat, e.g. when scoring a dataset or the positions of many games.
+//! Unlike the [transposition table](crate::search::tt) it only holds scores, and it keeps
+//! count of how often it saves an evaluation.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{eval::{cache::EvalCache, Evaluator}, game::Game};
+//! let evaluator = Evaluator::default();
+//! let mut cache = EvalCache::new(1);
+//! let game = Game::default();
+//! let score = cache.evaluate(&evaluator, &game);
+//! assert_eq!(cache.evaluate(&evaluator, &game), score);
+//! assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+//! ```
+
+use super::Evaluator;
+use crate::game::Game;
+
+/// The size of a new [`EvalCache`] in megabytes.
+pub const DEFAULT_SIZE_MB: usize = 4;
+
+/// How often an [`EvalCache`] had the score asked for.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the share of lookups that found a score, 0 if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+/// A fixed-size cache of evaluations in which a new score always replaces the one in its
+/// slot.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    /// The key of the position and its score from the point of view of the side to move.
+    entries: Vec<Option<(u64, i32)>>,
+    stats: CacheStats,
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl EvalCache {
+    /// Creates an empty cache that takes up about `megabytes` of memory, holding at least one
+    /// score.
+    pub fn new(megabytes: usize) -> Self {
+        let len = (megabytes * 1024 * 1024 / std::mem::size_of::<Option<(u64, i32)>>()).max(1);
+        Self {
+            entries: vec![None; len],
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the number of scores the cache can hold.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the score stored for the position with `key`, if it is still there.
+    pub fn get(&mut self, key: u64) -> Option<i32> {
+        let score = self.entries[self.index(key)]
+            .filter(|&(k, _)| k == key)
+            .map(|(_, score)| score);
+        match score {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        score
+    }
+
+    /// Stores `score` for the position with `key`.
+    pub fn insert(&mut self, key: u64, score: i32) {
+        let index = self.index(key);
+        self.entries[index] = Some((key, score));
+    }
+
+    /// Returns `evaluator`'s score of `game`, from the cache if it is there. The cache doesn't
+    /// know which evaluator stored a score, so it must be cleared before switching evaluators.
+    pub fn evaluate(&mut self, evaluator: &Evaluator, game: &Game) -> i32 {
+        let key = game.key();
+        self.get(key).unwrap_or_else(|| {
+            let score = evaluator.evaluate(game);
+            self.insert(key, score);
+            score
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Drops all scores and resets the statistics.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.stats = CacheStats::default();
+    }
+
+    fn index(&self, key: u64) -> usize {
+        // Maps the key onto the cache without a division
+        ((key as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_scores_in_the_same_slot() {
+        let mut cache = EvalCache::new(1);
+        // Keys 1 and 2 share a slot
+        cache.insert(1, 10);
+        assert_eq!(cache.get(1), Some(10));
+        cache.insert(2, 20);
+        assert_eq!((cache.get(1), cache.get(2)), (None, Some(20)));
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1 });
+        assert!((cache.stats().hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+        cache.clear();
+        assert_eq!((cache.get(2), cache.stats().hits), (None, 0));
+    }
+}
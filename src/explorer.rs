@@ -0,0 +1,246 @@
+//! An opening explorer: a tree of the positions of a game database with how often every move
+//! was played and how the games went on, e.g. for browsing a database or checking a
+//! repertoire against it.
+//!
+//! A [`TreeBuilder`] goes through the first [`max_plies`](TreeBuilder::new) of every game and
+//! counts the results of the games in every position and after every move. Positions are known
+//! by their [key](Game::key), so lines that transpose into each other share their statistics.
+//! A game counts once for a position even if it reaches it more than once.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{explorer::TreeBuilder, game::Game, pgn, Color, Move};
+//! let games = pgn::parse("1. e4 e5 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1/2-1/2\n");
+//! let mut builder = TreeBuilder::new(20);
+//! for game in &games {
+//!     builder.add_pgn(game.as_ref().unwrap());
+//! }
+//! let tree = builder.build();
+//! let start = tree.position(&Game::default()).unwrap();
+//! assert_eq!(start.stats.games(), 3);
+//! // 1. e4 was played most and scored one win and one loss
+//! let (e4, stats) = start.moves[0];
+//! assert_eq!(e4, Move::from_uci("e2e4").unwrap());
+//! assert_eq!((stats.white, stats.draws, stats.black), (1, 0, 1));
+//! assert_eq!(stats.score(Color::WHITE), Some(0.5));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    game::{Game, Outcome},
+    pgn::PgnGame,
+    Color, Move,
+};
+
+/// The results of the games that reached a position or played a move.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Stats {
+    /// The games White won.
+    pub white: u32,
+    pub draws: u32,
+    /// The games Black won.
+    pub black: u32,
+    /// The games without a result.
+    pub unfinished: u32,
+}
+
+impl Stats {
+    /// Counts a game that ended with `outcome`, `None` if it is unfinished.
+    pub fn add(&mut self, outcome: Option<Outcome>) {
+        match outcome {
+            Some(Outcome::DECISIVE {
+                winner: Color::WHITE,
+            }) => self.white += 1,
+            Some(Outcome::DECISIVE {
+                winner: Color::BLACK,
+            }) => self.black += 1,
+            Some(Outcome::DRAW) => self.draws += 1,
+            None => self.unfinished += 1,
+        }
+    }
+
+    /// Returns the number of games.
+    pub fn games(&self) -> u32 {
+        self.white + self.draws + self.black + self.unfinished
+    }
+
+    /// Returns the share of the points `color` scored in the finished games, or `None` if
+    /// there are none.
+    pub fn score(&self, color: Color) -> Option<f64> {
+        let finished = self.white + self.draws + self.black;
+        let wins = match color {
+            Color::WHITE => self.white,
+            Color::BLACK => self.black,
+        };
+        (finished > 0).then(|| (wins as f64 + self.draws as f64 / 2.0) / finished as f64)
+    }
+}
+
+/// A position of an [`OpeningTree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+    /// The results of the games that reached the position.
+    pub stats: Stats,
+    /// The moves played in the position with the results of the games that played them, the
+    /// most played first.
+    pub moves: Vec<(Move, Stats)>,
+}
+
+/// The positions of a game database with the moves played in them, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningTree {
+    nodes: HashMap<u64, Node>,
+}
+
+impl OpeningTree {
+    /// Returns the statistics of `game`'s position, or `None` if no game reached it.
+    pub fn position(&self, game: &Game) -> Option<&Node> {
+        self.nodes.get(&game.key())
+    }
+
+    /// Returns the moves played in `game`'s position, the most played first.
+    pub fn moves(&self, game: &Game) -> &[(Move, Stats)] {
+        self.position(game).map_or(&[], |node| &node.moves)
+    }
+
+    /// Returns the number of positions in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// Collects the moves of games to build an [`OpeningTree`] from.
+#[derive(Debug, Clone, Default)]
+pub struct TreeBuilder {
+    max_plies: usize,
+    nodes: HashMap<u64, Node>,
+}
+
+impl TreeBuilder {
+    /// Creates a builder that takes the first `max_plies` of every game into the tree.
+    pub fn new(max_plies: usize) -> Self {
+        Self {
+            max_plies,
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Adds the `moves` of a game played from `start` that ended with `outcome`, `None` if
+    /// it is unfinished. The moves must be legal.
+    pub fn add_game(&mut self, start: &Game, moves: &[Move], outcome: Option<Outcome>) {
+        let mut game = *start;
+        let mut seen = HashSet::new();
+        for &m in moves.iter().take(self.max_plies) {
+            self.visit(&mut seen, &game, Some(m), outcome);
+            game.make_move(m);
+        }
+        // The position the last move leads to is in the tree without moves
+        self.visit(&mut seen, &game, None, outcome);
+    }
+
+    /// Adds a game read from PGN, see [`add_game`](Self::add_game).
+    pub fn add_pgn(&mut self, game: &PgnGame) {
+        self.add_game(&game.start, &game.moves, game.outcome);
+    }
+
+    /// Returns how many positions were added.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Counts the game in `game`'s position and `m` played in it, unless the game was there
+    /// before.
+    fn visit(
+        &mut self,
+        seen: &mut HashSet<u64>,
+        game: &Game,
+        m: Option<Move>,
+        outcome: Option<Outcome>,
+    ) {
+        if !seen.insert(game.key()) {
+            return;
+        }
+        let node = self.nodes.entry(game.key()).or_default();
+        node.stats.add(outcome);
+        let Some(m) = m else {
+            return;
+        };
+        match node.moves.iter_mut().find(|(played, _)| *played == m) {
+            Some((_, stats)) => stats.add(outcome),
+            None => {
+                let mut stats = Stats::default();
+                stats.add(outcome);
+                node.moves.push((m, stats));
+            }
+        }
+    }
+
+    /// Builds the tree from the games added so far.
+    pub fn build(&self) -> OpeningTree {
+        let mut nodes = self.nodes.clone();
+        for node in nodes.values_mut() {
+            node.moves
+                .sort_by_key(|(_, stats)| std::cmp::Reverse(stats.games()));
+        }
+        OpeningTree { nodes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpositions_share_statistics() {
+        let moves = |ucis: [&str; 3]| ucis.map(|uci| Move::from_uci(uci).unwrap());
+        let start = Game::default();
+        let mut builder = TreeBuilder::new(3);
+        builder.add_game(
+            &start,
+            &moves(["e2e4", "e7e5", "g1f3"]),
+            Some(Outcome::DRAW),
+        );
+        builder.add_game(&start, &moves(["g1f3", "e7e5", "e2e4"]), None);
+        let tree = builder.build();
+        assert_eq!(tree.len(), 6);
+
+        let mut game = start;
+        for m in moves(["e2e4", "e7e5", "g1f3"]) {
+            game.make_move(m);
+        }
+        let node = tree.position(&game).unwrap();
+        assert_eq!(node.stats.games(), 2);
+        assert_eq!(node.stats.unfinished, 1);
+        assert!(node.moves.is_empty());
+        assert_eq!(node.stats.score(Color::BLACK), Some(0.5));
+        assert_eq!(tree.moves(&start).len(), 2);
+        assert!(tree.moves(&game).is_empty());
+    }
+
+    #[test]
+    fn counts_repeated_positions_once() {
+        let moves =
+            ["g1f3", "g8f6", "f3g1", "f6g8", "e2e4"].map(|uci| Move::from_uci(uci).unwrap());
+        let white_wins = Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        });
+        let mut builder = TreeBuilder::new(10);
+        builder.add_game(&Game::default(), &moves, white_wins);
+        let tree = builder.build();
+        let start = tree.position(&Game::default()).unwrap();
+        assert_eq!(start.stats.games(), 1);
+        assert_eq!(start.moves, [(moves[0], start.stats)]);
+        assert_eq!(Stats::default().score(Color::WHITE), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Bindings for Kotlin and Swift through UniFFI, enabled by the `ffi` feature, so that the
+//! crate can run chess apps on Android and iOS.
+//!
+//! The interface is defined in `src/kritisch.udl`, from which the build script generates the
+//! scaffolding of the library. The crate builds as a Rust library only, so the shared library
+//! for Android comes from `--crate-type cdylib` and the static one for iOS from
+//! `--crate-type staticlib`. The bindings themselves are generated from the built library
+//! with `uniffi-bindgen` of the same UniFFI version, e.g.
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! uniffi-bindgen generate --library target/release/libkritisch.so \
+//!     --language kotlin --language swift --out-dir bindings
+//! ```
+//!
+//! A [`Game`] wraps a [`game::Game`] behind a lock, since the objects shared with other
+//! languages can't be borrowed mutably. A [`Move`] is a record of square numbers, from 0 for
+//! a1 to 63 for h8. Errors are thrown as a `KritischError` named after the variant of the
+//! [`Error`], carrying its message.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::ffi::{move_from_uci, Game};
+//! let game = Game::new();
+//! game.make_move(move_from_uci("e2e4".to_string()).unwrap()).unwrap();
+//! assert_eq!(game.legal_moves().len(), 20);
+//! assert_eq!(game.san(move_from_uci("g8f6".to_string()).unwrap()).unwrap(), "Nf6");
+//! ```
+
+// The generated scaffolding leaves an empty line after a doc comment
+#![allow(clippy::empty_line_after_doc_comments)]
+
+use std::sync::Mutex;
+
+use crate::{game, movegen, Color, Error, Piece, Square};
+
+pub use crate::game::Outcome;
+
+uniffi::include_scaffolding!("kritisch");
+
+/// The errors thrown to other languages, see [`Error`].
+pub type KritischError = Error;
+
+/// A move as passed to other languages, see [`crate::Move`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub start: u8,
+    pub end: u8,
+    pub promotion: Option<Piece>,
+    pub drop: Option<Piece>,
+    pub duck: Option<u8>,
+}
+
+impl From<crate::Move> for Move {
+    fn from(m: crate::Move) -> Self {
+        Self {
+            start: m.start as u8,
+            end: m.end as u8,
+            promotion: m.promotion,
+            drop: m.drop,
+            duck: m.duck.map(|s| s as u8),
+        }
+    }
+}
+
+impl TryFrom<Move> for crate::Move {
+    type Error = Error;
+
+    fn try_from(m: Move) -> Result<Self, Error> {
+        let square = |s: u8| {
+            Square::new(s).ok_or_else(|| Error::InvalidSquare(format!("square number {s}")))
+        };
+        Ok(Self {
+            start: square(m.start)?,
+            end: square(m.end)?,
+            promotion: m.promotion,
+            drop: m.drop,
+            duck: m.duck.map(square).transpose()?,
+        })
+    }
+}
+
+/// Parses a move in UCI notation, see [`crate::Move::from_uci`].
+pub fn move_from_uci(uci: String) -> Result<Move, Error> {
+    crate::Move::from_uci(&uci).map(Move::from)
+}
+
+/// Returns the move in UCI notation.
+pub fn move_to_uci(m: Move) -> Result<String, Error> {
+    Ok(crate::Move::try_from(m)?.to_string())
+}
+
+/// A game shared with other languages.
+#[derive(Debug, Default)]
+pub struct Game {
+    game: Mutex<game::Game>,
+}
+
+impl Game {
+    /// Returns the starting position.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a position from `fen`.
+    pub fn from_fen(fen: String) -> Result<Self, Error> {
+        Ok(Self {
+            game: Mutex::new(game::Game::from_fen(&fen)?),
+        })
+    }
+
+    pub fn fen(&self) -> String {
+        self.get().to_fen()
+    }
+
+    pub fn turn(&self) -> Color {
+        self.get().to_move
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        movegen::all_legal_moves(&self.get())
+            .iter()
+            .map(|&m| m.into())
+            .collect()
+    }
+
+    /// Plays `m`, returning [`Error::IllegalMove`] if it isn't legal.
+    pub fn make_move(&self, m: Move) -> Result<(), Error> {
+        let m = crate::Move::try_from(m)?;
+        let mut game = self.game.lock().expect("The game lock isn't poisoned");
+        if !movegen::all_legal_moves(&game).contains(&m) {
+            return Err(Error::IllegalMove(m));
+        }
+        game.make_move(m);
+        Ok(())
+    }
+
+    /// Returns the legal move `m` in SAN, see [`game::Game::san`].
+    pub fn san(&self, m: Move) -> Result<String, Error> {
+        self.get().san(m.try_into()?)
+    }
+
+    /// Parses the legal move `san`, see [`game::Game::parse_san`].
+    pub fn parse_san(&self, san: String) -> Result<Move, Error> {
+        self.get().parse_san(&san).map(Move::from)
+    }
+
+    pub fn is_check(&self) -> bool {
+        self.get().is_check()
+    }
+
+    /// Returns the outcome if the game is over.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.get().outcome()
+    }
+
+    /// Counts the leaf nodes of the legal move tree down to `depth`, see [`movegen::perft`].
+    pub fn perft(&self, depth: u32) -> u64 {
+        movegen::perft(&self.get(), depth)
+    }
+
+    /// Returns a copy of the position.
+    fn get(&self) -> game::Game {
+        *self.game.lock().expect("The game lock isn't poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_round_trip() {
+        let game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        let promotion = game.parse_san("b8=N".to_string()).unwrap();
+        assert_eq!(promotion.promotion, Some(Piece::KNIGHT));
+        assert_eq!(move_to_uci(promotion).unwrap(), "b7b8n");
+        assert_eq!(move_from_uci("b7b8n".to_string()).unwrap(), promotion);
+        assert!(game.legal_moves().contains(&promotion));
+
+        let off_board = Move {
+            end: 64,
+            ..promotion
+        };
+        assert!(matches!(
+            move_to_uci(off_board),
+            Err(Error::InvalidSquare(_))
+        ));
+        let illegal = move_from_uci("e1e3".to_string()).unwrap();
+        assert!(matches!(
+            game.make_move(illegal),
+            Err(Error::IllegalMove(_))
+        ));
+    }
+
+    #[test]
+    fn plays_a_game() {
+        let game = Game::new();
+        for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.make_move(move_from_uci(uci.to_string()).unwrap())
+                .unwrap();
+        }
+        assert!(game.is_check());
+        assert_eq!(game.turn(), Color::WHITE);
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome::DECISIVE {
+                winner: Color::BLACK
+            })
+        );
+        assert_eq!(game.perft(1), 0);
+    }
+}
@@ -1,30 +1,157 @@
+use std::fmt::Write;
+
+#[cfg(feature = "nnue")]
+use crate::nnue::Accumulator;
 use crate::{
     bitboard::Bitboard,
-    magics::{BISHOP_MAGICS, BISHOP_MOVES, ROOK_MAGICS, ROOK_MOVES},
-    movegen::{get_blockers_from_position, magic_index, pseudolegal_knight_moves},
-    try_square_offset, CastlingRights, Color, File, Move, Piece, Square, PIECE_REPR_B,
-    PIECE_REPR_W,
+    movegen::{
+        all_legal_moves, bishop_attacks, pseudolegal_knight_moves, rook_attacks, sliding_attacks,
+    },
+    opening::{self, Opening},
+    phase::Phase,
+    pst::{self, Score},
+    render::BoardFormatter,
+    variant::{self, Rules, Variant},
+    zobrist, CastlingRights, CastlingSide, Color, ColoredPiece, Error, File, Move, Piece, Rank,
+    Result, Square,
 };
-use anyhow::Context;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason a game has ended.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Termination {
+    CHECKMATE,
+    STALEMATE,
+    FIFTY_MOVE_RULE,
+    INSUFFICIENT_MATERIAL,
+    /// The side to move has won by a rule of the [`variant`].
+    VARIANT_WIN,
+    /// The side to move has lost by a rule of the [`variant`].
+    VARIANT_LOSS,
+    /// The game is drawn by a rule of the [`variant`].
+    VARIANT_DRAW,
+}
+
+/// The state [`Game::make_move`] can't recover from the position after the move, needed to
+/// take the move back with [`Game::unmake_move`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Undo {
+    captured: Option<(Square, ColoredPiece)>,
+    castling_rights: CastlingRights,
+    en_passant_square: Option<Square>,
+    in_check: Option<Color>,
+    halfmove_clock: usize,
+    castled: bool,
+    promoted: Bitboard,
+    /// The pieces removed by the explosion of a capture, see [`Rules::captures_explode`].
+    exploded: [Option<(Square, ColoredPiece)>; 9],
+    duck: Bitboard,
+}
+
+/// The result of a finished game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    DECISIVE { winner: Color },
+    DRAW,
+}
+
+/// The pieces of either color attacking each square, see [`Game::square_control`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SquareControl {
+    attackers: [[Bitboard; 2]; 64],
+}
+
+impl SquareControl {
+    /// Returns the squares of `color`'s pieces attacking `s`.
+    pub fn attackers(&self, s: Square, color: Color) -> Bitboard {
+        self.attackers[s as usize][color as usize]
+    }
+
+    /// Returns the number of white attackers of `s` minus the number of black ones, so
+    /// positive values mean white controls the square.
+    pub fn net(&self, s: Square) -> i32 {
+        let [white, black] = self.attackers[s as usize].map(|b| b.count_ones() as i32);
+        white - black
+    }
+
+    /// Returns the squares `color` has more attackers on than the opponent.
+    pub fn controlled(&self, color: Color) -> Bitboard {
+        let sign = match color {
+            Color::WHITE => 1,
+            Color::BLACK => -1,
+        };
+        Bitboard::from_squares(Square::all().filter(|&s| self.net(s) * sign > 0))
+    }
+}
+
+/// A chess position.
+///
+/// `Game` owns no heap memory and is `Copy`, so trying a move on a copy of the position is a
+/// plain memory copy. For walking a game tree both copy-make and
+/// [`make_move`](Self::make_move) with [`unmake_move`](Self::unmake_move) are supported,
+/// see [`MakeStrategy`](crate::movegen::MakeStrategy) for which one is faster.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Game {
+    /// The squares occupied by each color. Call [`Game::refresh`] after writing to the
+    /// bitboards directly.
     pub color_bitboards: [Bitboard; 2],
     pub piece_bitboards: [Bitboard; 6],
 
     pub to_move: Color,
-    pub castling_rights: u8,
+    pub castling_rights: CastlingRights,
+    /// Whether the game is Chess960. Its FEN gives the castling rights as the files of the
+    /// rooks, e.g. `HAha`, and its castling moves are the king taking its own rook, e.g.
+    /// `e1h1`, since the king may start next to its destination.
+    pub chess960: bool,
+    /// The starting files of the castling rooks, by color and [`CastlingSide`].
+    castling_files: [[File; 2]; 2],
+    /// The variant whose [`Rules`] the game is played by. FENs don't name the variant, so a
+    /// parsed position is standard chess until this is set, unless its FEN has pockets, a duck
+    /// or a board smaller than 8x8.
+    pub variant: Variant,
+    /// The number of pieces of every kind but the king each color has in its pocket, see
+    /// [`Game::pocket`].
+    pockets: [[u8; 5]; 2],
+    /// The pieces that were pawns once, which go to the pocket as pawns when captured.
+    promoted: Bitboard,
+    /// The square of the duck in Duck chess, see [`Game::duck`]. Empty in all other variants.
+    duck: Bitboard,
 
+    /// The square a pawn passed over with a double step on the last move, where the side to
+    /// move may take it en passant. [`make_move`](Self::make_move) only sets it if a pawn
+    /// stands ready to take, as most FEN writers do. [`from_fen`](Self::from_fen) keeps the
+    /// square of the FEN either way, and [`key`](Self::key) only counts it if it can be taken.
     pub en_passant_square: Option<Square>,
     pub in_check: Option<Color>,
 
     pub halfmove_clock: usize,
     pub fullmove_clock: usize,
+
+    /// The piece on every square, kept in sync with the bitboards so that
+    /// [`Game::piece_at`] doesn't have to scan them.
+    mailbox: [Option<ColoredPiece>; 64],
+    /// The squares attacked by the piece on every square, empty for empty squares.
+    piece_attacks: [Bitboard; 64],
+    /// The squares attacked by each color's pieces.
+    attacks: [Bitboard; 2],
+    /// The number of pieces of each color and type.
+    piece_counts: [[u8; 6]; 2],
+    /// The summed [`Piece::value`] of each color's pieces, pockets included.
+    material: [i32; 2],
+    /// The summed [`pst::value`] of all pieces.
+    pst: Score,
+    /// The XOR of the [`zobrist::piece`] keys of all pieces, the [`zobrist::pocket`] keys of
+    /// the pockets and the [`zobrist::duck`] key of the duck. [`Game::key`] adds the rest of
+    /// the position on top, so the public fields can't get out of sync with it.
+    piece_key: u64,
+    /// The first layer of the NNUE network, see [`Game::accumulator`].
+    #[cfg(feature = "nnue")]
+    accumulator: Accumulator,
 }
 
 impl std::default::Default for Game {
     fn default() -> Self {
-        let white_bb = Bitboard::from_squares(vec![
+        let white_bb = Bitboard::from_squares([
             Square::A1,
             Square::B1,
             Square::C1,
@@ -43,7 +170,7 @@ impl std::default::Default for Game {
             Square::H2,
         ]);
 
-        let black_bb = Bitboard::from_squares(vec![
+        let black_bb = Bitboard::from_squares([
             Square::A8,
             Square::B8,
             Square::C8,
@@ -64,14 +191,12 @@ impl std::default::Default for Game {
 
         let color_bitboards = [white_bb, black_bb];
 
-        let rook_bb = Bitboard::from_squares(vec![Square::A1, Square::H1, Square::A8, Square::H8]);
-        let knight_bb =
-            Bitboard::from_squares(vec![Square::B1, Square::G1, Square::B8, Square::G8]);
-        let bishop_bb =
-            Bitboard::from_squares(vec![Square::C1, Square::F1, Square::C8, Square::F8]);
-        let queen_bb = Bitboard::from_squares(vec![Square::D1, Square::D8]);
-        let king_bb = Bitboard::from_squares(vec![Square::E1, Square::E8]);
-        let pawn_bb = Bitboard::from_squares(vec![
+        let rook_bb = Bitboard::from_squares([Square::A1, Square::H1, Square::A8, Square::H8]);
+        let knight_bb = Bitboard::from_squares([Square::B1, Square::G1, Square::B8, Square::G8]);
+        let bishop_bb = Bitboard::from_squares([Square::C1, Square::F1, Square::C8, Square::F8]);
+        let queen_bb = Bitboard::from_squares([Square::D1, Square::D8]);
+        let king_bb = Bitboard::from_squares([Square::E1, Square::E8]);
+        let pawn_bb = Bitboard::from_squares([
             Square::A2,
             Square::B2,
             Square::C2,
@@ -92,54 +217,56 @@ impl std::default::Default for Game {
 
         let piece_bitboards = [pawn_bb, knight_bb, bishop_bb, rook_bb, queen_bb, king_bb];
 
-        Self {
+        let mut game = Self {
             color_bitboards,
             piece_bitboards,
             to_move: Color::WHITE,
             castling_rights: CastlingRights::ALL_LEGAL,
+            chess960: false,
+            castling_files: [[File::H, File::A]; 2],
+            variant: Variant::STANDARD,
+            pockets: [[0; 5]; 2],
+            promoted: Bitboard::empty(),
+            duck: Bitboard::empty(),
             en_passant_square: None,
             in_check: None,
             halfmove_clock: 0,
             fullmove_clock: 1,
-        }
+            mailbox: [None; 64],
+            piece_attacks: [Bitboard::empty(); 64],
+            attacks: [Bitboard::empty(); 2],
+            piece_counts: [[0; 6]; 2],
+            material: [0; 2],
+            pst: Score::ZERO,
+            piece_key: 0,
+            #[cfg(feature = "nnue")]
+            accumulator: Accumulator::default(),
+        };
+        game.refresh();
+        game
     }
 }
 
+/// Displays a position as a FEN string, see [`Game::fen`].
+#[derive(Debug, Clone, Copy)]
+pub struct Fen<'a>(&'a Game);
+
+impl std::fmt::Display for Fen<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.write_fen(f)
+    }
+}
+
+/// Displays the board from white's perspective in plain ASCII.
+/// Use [`BoardFormatter`] for more rendering options.
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut board = String::new();
-        board.push('\n');
-        for s in 0..64 {
-            let file = s % 8;
-            let rank = s / 8;
-            let square = Square::from_u8(64 - (rank * 8 + 8 - file));
-            if self.color_bitboards[0].contains(square) {
-                for (piece_idx, piece_bb) in self.piece_bitboards.iter().enumerate() {
-                    if piece_bb.contains(square) {
-                        board.push(PIECE_REPR_W[piece_idx]);
-                        board.push(' ');
-                    }
-                }
-            } else if self.color_bitboards[1].contains(square) {
-                for (piece_idx, piece_bb) in self.piece_bitboards.iter().enumerate() {
-                    if piece_bb.contains(square) {
-                        board.push(PIECE_REPR_B[piece_idx]);
-                        board.push(' ');
-                    }
-                }
-            } else {
-                board.push_str(". ");
-            }
-            if File::from_u8(file) == File::H {
-                board.push('\n');
-            }
-        }
-        write!(f, "{}", board)
+        BoardFormatter::default().write(self, f)
     }
 }
 
 impl Game {
-    fn empty() -> Self {
+    pub(crate) fn empty() -> Self {
         let color_bitboards = [Bitboard::empty(); 2];
         let piece_bitboards = [Bitboard::empty(); 6];
 
@@ -148,278 +275,1097 @@ impl Game {
             piece_bitboards,
             to_move: Color::WHITE,
             castling_rights: CastlingRights::ALL_LEGAL,
+            chess960: false,
+            castling_files: [[File::H, File::A]; 2],
+            variant: Variant::STANDARD,
+            pockets: [[0; 5]; 2],
+            promoted: Bitboard::empty(),
+            duck: Bitboard::empty(),
             en_passant_square: None,
             in_check: None,
             halfmove_clock: 0,
             fullmove_clock: 1,
+            mailbox: [None; 64],
+            piece_attacks: [Bitboard::empty(); 64],
+            attacks: [Bitboard::empty(); 2],
+            piece_counts: [[0; 6]; 2],
+            material: [0; 2],
+            pst: Score::ZERO,
+            piece_key: 0,
+            #[cfg(feature = "nnue")]
+            accumulator: Accumulator::default(),
+        }
+    }
+
+    /// Rebuilds what the position caches on top of its bitboards, such as the mailbox behind
+    /// [`Game::piece_at`]. The caches go stale when the bitboards are written to directly
+    /// instead of through methods like [`Game::make_move`]. The other public fields, such as
+    /// the castling rights and the en passant square, are read as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color, Piece, Square};
+    /// let mut game = Game::default();
+    /// game.color_bitboards[Color::WHITE as usize] ^= Square::D1;
+    /// game.piece_bitboards[Piece::QUEEN as usize] ^= Square::D1;
+    /// game.refresh();
+    /// assert_eq!(game.try_type_at(Square::D1), None);
+    /// ```
+    pub fn refresh(&mut self) {
+        self.piece_counts = [[0; 6]; 2];
+        self.material = [0; 2];
+        self.pst = Score::ZERO;
+        self.piece_key = 0;
+        #[cfg(feature = "nnue")]
+        {
+            self.accumulator = Accumulator::default();
+        }
+        for s in Square::all() {
+            let piece = self.piece_from_bitboards(s);
+            self.mailbox[s as usize] = piece;
+            if let Some(piece) = piece {
+                self.piece_counts[piece.color as usize][piece.piece as usize] += 1;
+                self.material[piece.color as usize] += piece.piece.value();
+                self.pst += pst::value(piece, s);
+                self.piece_key ^= zobrist::piece(piece, s);
+                #[cfg(feature = "nnue")]
+                self.accumulator.add(piece, s);
+            }
         }
+        self.refresh_attacks(Bitboard::from_u64(u64::MAX));
     }
-    /// Tries to parse the given FEN string into a position
-    /// TODO: Parse attacks
-    pub fn from_fen(fen: &'static str) -> anyhow::Result<Self> {
+
+    /// Looks up the piece on `s` by scanning the bitboards, bypassing the mailbox.
+    pub(crate) fn piece_from_bitboards(&self, s: Square) -> Option<ColoredPiece> {
+        let piece = (0..=5)
+            .find(|i| self.piece_bitboards[*i as usize].contains(s))
+            .map(|i| Piece::from_u8(i as u8))?;
+        let color = (0..=1)
+            .find(|i| self.color_bitboards[*i as usize].contains(s))
+            .map(|i| Color::from_u8(i as u8))?;
+        Some(ColoredPiece::new(piece, color))
+    }
+
+    /// Tries to parse the given FEN string into a position.
+    /// The halfmove and fullmove clocks may be omitted, in which case they default to 0 and 1.
+    /// Pockets in brackets after the placement, e.g. `RNBQKBNR[Pn]`, or as a ninth rank, e.g.
+    /// `RNBQKBNR/Pn`, make the position
+    /// [Crazyhouse](Variant::CRAZYHOUSE), and a `~` after a piece marks it as promoted. A `*`
+    /// in the placement is the duck of [Duck chess](Variant::DUCK). A placement of fewer ranks
+    /// and files, e.g. `rnbqk/ppppp/5/PPPPP/RNBQK`, is put on the corner from a1 on and makes
+    /// the position the variant played on a board of that [size](variant::Rules::board_size).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Error};
+    /// let game = Game::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1").unwrap();
+    /// assert_eq!(game.all_pieces().count_ones(), 2);
+    ///
+    /// let err = Game::from_fen("8/8/4k3/8/8/4K3/8 w - - 0 1").unwrap_err();
+    /// assert!(matches!(err, Error::InvalidFen(_)));
+    /// ```
+    pub fn from_fen(fen: &str) -> Result<Self> {
         let mut pos = Self::empty();
-        let mut square = Square::A8;
+        let mut fields = fen.split_whitespace();
 
-        let mut index = 0;
+        let placement = fields
+            .next()
+            .ok_or_else(|| Error::InvalidFen("empty FEN string".to_string()))?;
+        let (placement, pockets) = match placement.strip_suffix(']').and_then(|p| p.split_once('['))
+        {
+            Some((placement, pockets)) => (placement, Some(pockets)),
+            // Pockets may also follow the placement as a ninth rank
+            None => match placement.match_indices('/').nth(7) {
+                Some((i, _)) => (&placement[..i], Some(&placement[i + 1..])),
+                None => (placement, None),
+            },
+        };
+        let (files, ranks) = pos.parse_placement(placement)?;
+        if (files, ranks) != (8, 8) {
+            pos.variant = Variant::ALL
+                .into_iter()
+                .find(|variant| variant.rules().board_size() == (files, ranks))
+                .ok_or_else(|| {
+                    Error::InvalidFen(format!(
+                        "no variant is played on {files} by {ranks} squares"
+                    ))
+                })?;
+        }
+        if !pos.duck.is_empty() {
+            pos.variant = Variant::DUCK;
+        }
+        if let Some(pockets) = pockets {
+            pos.parse_pockets(pockets)?;
+            pos.variant = Variant::CRAZYHOUSE;
+        }
 
-        for (i, c) in fen.chars().enumerate() {
-            if c == ' ' {
-                index = i + 1;
-                break;
+        pos.to_move = match fields.next() {
+            Some("w") => Color::WHITE,
+            Some("b") => Color::BLACK,
+            Some(other) => {
+                return Err(Error::InvalidFen(format!(
+                    "expected 'w' or 'b' for the side to move, got '{other}'"
+                )))
             }
-            if c.is_ascii_digit() {
-                let add = (c.to_digit(10).unwrap() as u8).clamp(1, 7);
-                square = square + add;
-                if square.get_file() == File::A {
-                    square = square - 1u8;
-                }
-            } else if c == '/' {
-                square = square - 15u8;
-            } else if PIECE_REPR_B.contains(&c) || PIECE_REPR_W.contains(&c) {
-                let piece = Piece::from_char(&c);
-                let color = if c.is_ascii_lowercase() {
-                    Color::BLACK
-                } else {
-                    Color::WHITE
-                };
-                pos.color_bitboards[color as usize] |= square;
-                pos.piece_bitboards[piece as usize] |= square;
+            None => return Err(Error::InvalidFen("side to move missing".to_string())),
+        };
+
+        match fields.next() {
+            Some(castling) => pos.parse_castling(castling)?,
+            None => return Err(Error::InvalidFen("castling rights missing".to_string())),
+        }
 
-                if square.get_file() != File::H {
-                    square = square + 1u8;
+        pos.en_passant_square = match fields.next() {
+            Some("-") => None,
+            Some(ep) => {
+                let mut chars = ep.chars();
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(file), Some(rank), None) => Some(
+                        Square::from_parts(&file, &rank)
+                            .map_err(|e| Error::InvalidFen(format!("en passant square: {e}")))?,
+                    ),
+                    _ => {
+                        return Err(Error::InvalidFen(format!(
+                            "couldn't parse en passant square '{ep}'"
+                        )))
+                    }
                 }
-            } else {
-                anyhow::bail!("Unexpected character in FEN string");
             }
-            index = i + 1;
+            None => return Err(Error::InvalidFen("en passant square missing".to_string())),
+        };
+
+        if let Some(hmc) = fields.next() {
+            pos.halfmove_clock = hmc
+                .parse()
+                .map_err(|_| Error::InvalidFen(format!("couldn't parse halfmove clock '{hmc}'")))?;
+        }
+        if let Some(fmc) = fields.next() {
+            pos.fullmove_clock = fmc
+                .parse()
+                .map_err(|_| Error::InvalidFen(format!("couldn't parse fullmove clock '{fmc}'")))?;
+        }
+
+        if fields.next().is_some() {
+            return Err(Error::InvalidFen(
+                "unexpected trailing fields after fullmove clock".to_string(),
+            ));
         }
 
-        if let Some(c) = fen.chars().nth(index) {
-            match c {
-                'w' => pos.to_move = Color::WHITE,
-                'b' => pos.to_move = Color::BLACK,
-                _ => anyhow::bail!("Expected color specification for player to move"),
+        Ok(pos)
+    }
+
+    /// Parses the castling rights of a FEN string into `self`, whose pieces must be placed
+    /// already. `KQkq` name the outermost rook on either side of the king as in X-FEN, and the
+    /// rooks' files as in Shredder-FEN, e.g. `HAha`, name the rook directly. A file letter, a
+    /// king off the e-file or a rook off the a- and h-file make the game
+    /// [`chess960`](Self::chess960).
+    fn parse_castling(&mut self, castling: &str) -> Result<()> {
+        self.castling_rights = CastlingRights::empty();
+        if castling == "-" {
+            return Ok(());
+        }
+        for c in castling.chars() {
+            let color = if c.is_ascii_uppercase() {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            };
+            let king = self
+                .king_square(color)
+                .filter(|&s| s.get_rank() == Rank::FIRST.relative_to(color));
+            let (side, file) = match c.to_ascii_lowercase() {
+                'k' => (
+                    CastlingSide::KINGSIDE,
+                    self.outermost_rook(color, king, CastlingSide::KINGSIDE)
+                        .unwrap_or(File::H),
+                ),
+                'q' => (
+                    CastlingSide::QUEENSIDE,
+                    self.outermost_rook(color, king, CastlingSide::QUEENSIDE)
+                        .unwrap_or(File::A),
+                ),
+                lower => {
+                    let file = File::from_char(lower).ok_or_else(|| {
+                        Error::InvalidFen(format!("unexpected character '{c}' in castling rights"))
+                    })?;
+                    let king = king.ok_or_else(|| {
+                        Error::InvalidFen(format!("castling right '{c}' without a king"))
+                    })?;
+                    self.chess960 = true;
+                    if file as u8 > king.get_file() as u8 {
+                        (CastlingSide::KINGSIDE, file)
+                    } else {
+                        (CastlingSide::QUEENSIDE, file)
+                    }
+                }
+            };
+            self.castling_rights |= CastlingRights::new(color, side);
+            self.castling_files[color as usize][side as usize] = file;
+            if king.is_some_and(|king| king.get_file() != File::E)
+                || file != [File::H, File::A][side as usize]
+            {
+                self.chess960 = true;
             }
-            index += 1
         }
+        Ok(())
+    }
+
+    /// Returns the file of `color`'s rook on its first rank that is furthest from `king`
+    /// towards `side`.
+    fn outermost_rook(
+        &self,
+        color: Color,
+        king: Option<Square>,
+        side: CastlingSide,
+    ) -> Option<File> {
+        let rook = ColoredPiece::new(Piece::ROOK, color);
+        let beyond_king = |file: &File| {
+            king.is_none_or(|king| match side {
+                CastlingSide::KINGSIDE => *file as u8 > king.get_file() as u8,
+                CastlingSide::QUEENSIDE => (*file as u8) < king.get_file() as u8,
+            })
+        };
+        let mut files = File::all().filter(beyond_king);
+        let is_rook = |file: &File| {
+            self.piece_at(Square::from_coords(*file, Rank::FIRST.relative_to(color))) == Some(rook)
+        };
+        match side {
+            CastlingSide::KINGSIDE => files.rev().find(is_rook),
+            CastlingSide::QUEENSIDE => files.find(is_rook),
+        }
+    }
 
-        index += 1;
+    /// Returns the Chess960 starting position with the Scharnagl number `index` from 0 to 959,
+    /// or `None` if there is no such position. Number 518 is the standard starting position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// let game = Game::chess960_position(0).unwrap();
+    /// assert_eq!(game.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+    /// assert!(game.chess960);
+    /// let standard = Game::chess960_position(518).unwrap();
+    /// assert_eq!(standard.to_fen().split(' ').next(), Game::default().to_fen().split(' ').next());
+    /// assert!(Game::chess960_position(960).is_none());
+    /// ```
+    pub fn chess960_position(index: u32) -> Option<Self> {
+        if index >= 960 {
+            return None;
+        }
+        let mut back_rank = [None; 8];
+        let mut n = index as usize;
+        // One bishop on a light and one on a dark square
+        back_rank[2 * (n % 4) + 1] = Some(Piece::BISHOP);
+        n /= 4;
+        back_rank[2 * (n % 4)] = Some(Piece::BISHOP);
+        n /= 4;
+        let mut place = |nth: usize, piece: Piece| {
+            let file = (0..8)
+                .filter(|&file| back_rank[file].is_none())
+                .nth(nth)
+                .expect("The back rank has room for every piece");
+            back_rank[file] = Some(piece);
+        };
+        place(n % 6, Piece::QUEEN);
+        n /= 6;
+        // The knights go on two of the five empty squares, the later one first
+        let (first, second) = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ][n];
+        place(second, Piece::KNIGHT);
+        place(first, Piece::KNIGHT);
+        // The king between the rooks on the last three
+        for piece in [Piece::ROOK, Piece::KING, Piece::ROOK] {
+            place(0, piece);
+        }
 
-        pos.castling_rights = CastlingRights::NO_LEGAL;
+        let back_rank = back_rank.map(|piece| piece.expect("Every square is filled"));
+        let rank =
+            |color| -> String { back_rank.iter().map(|piece| piece.to_char(color)).collect() };
+        let rooks: Vec<char> = (b'a'..=b'h')
+            .zip(back_rank)
+            .filter(|&(_, piece)| piece == Piece::ROOK)
+            .map(|(file, _)| file as char)
+            .collect();
+        let (queenside, kingside) = (rooks[0], rooks[1]);
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{}{kingside}{queenside} - 0 1",
+            rank(Color::BLACK),
+            rank(Color::WHITE),
+            kingside.to_ascii_uppercase(),
+            queenside.to_ascii_uppercase(),
+        );
+        Some(Self::from_fen(&fen).expect("Chess960 starting positions are valid"))
+    }
 
-        for c in fen[index..].chars() {
-            if c == ' ' {
-                index += 1;
-                break;
-            } else if c == '-' {
-                index += 2;
-                break;
-            }
-            match c {
-                'K' => pos.castling_rights |= CastlingRights::WHITE_KINGSIDE,
-                'Q' => pos.castling_rights |= CastlingRights::WHITE_QUEENSIDE,
-                'k' => pos.castling_rights |= CastlingRights::BLACK_KINGSIDE,
-                'q' => pos.castling_rights |= CastlingRights::BLACK_QUEENSIDE,
-                _ => anyhow::bail!("Unexpected character in castling rights section of FEN string"),
-            }
-            index += 1;
-        }
-
-        match fen.chars().nth(index) {
-            Some(c) => {
-                if c.is_ascii_lowercase() {
-                    match fen.chars().nth(index + 1) {
-                        Some(d) => {
-                            if d.is_ascii_digit() {
-                                match Square::from_parts(&c, &d) {
-                                    Ok(s) => pos.en_passant_square = Some(s),
-                                    Err(_) => anyhow::bail!(
-                                        "Couldn't parse en passant square in FEN string"
-                                    ),
-                                }
-                            }
+    /// Returns the square `color`'s rook castling towards `side` starts on. It is the a- or
+    /// h-file unless the game is [`chess960`](Self::chess960).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, CastlingSide, Color, Square};
+    /// let game = Game::from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1").unwrap();
+    /// assert_eq!(game.castling_rook(Color::BLACK, CastlingSide::KINGSIDE), Square::G8);
+    /// assert_eq!(game.castling_rook(Color::WHITE, CastlingSide::QUEENSIDE), Square::B1);
+    /// ```
+    pub fn castling_rook(&self, color: Color, side: CastlingSide) -> Square {
+        let file = self.castling_files[color as usize][side as usize];
+        Square::from_coords(file, Rank::FIRST.relative_to(color))
+    }
+
+    /// Sets the file `color`'s rook castles from towards `side`, see [`Game::castling_rook`].
+    pub(crate) fn set_castling_file(&mut self, color: Color, side: CastlingSide, file: File) {
+        self.castling_files[color as usize][side as usize] = file;
+    }
+
+    /// Returns the FEN string describing the position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2";
+    /// assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+    /// ```
+    pub fn to_fen(&self) -> String {
+        // Enough room for any placement and realistic clocks, so the string never has to grow
+        let mut fen = String::with_capacity(96);
+        self.write_fen(&mut fen).unwrap();
+        fen
+    }
+
+    /// Writes the FEN string describing the position to `w` without allocating, e.g. to
+    /// append many positions to one reusable buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// let mut buffer = String::new();
+    /// for _ in 0..2 {
+    ///     Game::default().write_fen(&mut buffer).unwrap();
+    ///     buffer.push('\n');
+    /// }
+    /// assert_eq!(buffer.lines().count(), 2);
+    /// ```
+    pub fn write_fen<W: Write + ?Sized>(&self, w: &mut W) -> std::fmt::Result {
+        let (files, ranks) = self.rules().board_size();
+        for rank in Rank::all().rev().skip(8 - ranks as usize) {
+            let mut empty = 0;
+            for square in rank.squares().take(files as usize) {
+                match self.piece_at(square) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            w.write_char(char::from_digit(empty, 10).unwrap())?;
+                            empty = 0;
+                        }
+                        w.write_char(piece.to_char())?;
+                        if self.promoted.contains(square) {
+                            w.write_char('~')?;
                         }
-                        None => anyhow::bail!(
-                            "Expected file while parsing en-passant square from FEN string"
-                        ),
                     }
-                } else if c == '-' {
-                    index += 1;
+                    None if self.duck.contains(square) => {
+                        if empty > 0 {
+                            w.write_char(char::from_digit(empty, 10).unwrap())?;
+                            empty = 0;
+                        }
+                        w.write_char('*')?;
+                    }
+                    None => empty += 1,
                 }
             }
-            None => anyhow::bail!("Incomplete FEN string - move counts missing"),
-        }
-
-        if fen.chars().nth(index) != Some(' ') {
-            anyhow::bail!(
-                "Error while parsing FEN string - expected whitespace after en passant square"
-            )
-        }
-        index += 1;
-
-        match fen.chars().nth(index) {
-            Some(c) => {
-                if c.is_ascii_digit() {
-                    let mut hmc = String::new();
-                    hmc.push(c);
-                    let mut peek = 1;
-                    loop {
-                        if let Some(n) = fen.chars().nth(index + peek) {
-                            if n == ' ' {
-                                break;
-                            }
-                            hmc.push(n);
-                            peek += 1;
-                        } else {
-                            anyhow::bail!("Incomplete FEN string - fullmove clock missing")
-                        }
+            if empty > 0 {
+                w.write_char(char::from_digit(empty, 10).unwrap())?;
+            }
+            if rank != Rank::FIRST {
+                w.write_char('/')?;
+            }
+        }
+        if self.rules().has_pockets() {
+            w.write_char('[')?;
+            for color in Color::both() {
+                for piece in &Piece::ALL[..5] {
+                    for _ in 0..self.pocket(color, *piece) {
+                        w.write_char(piece.to_char(color))?;
                     }
-                    pos.halfmove_clock = hmc
-                        .parse()
-                        .context("tried to cast FEN halfmove clock to usize")?;
-                    index += peek;
-                } else {
-                    anyhow::bail!("Expected digit in halfmove clock position in FEN string")
                 }
             }
-            None => anyhow::bail!("Incomplete FEN string - halfmove clock missing"),
+            w.write_char(']')?;
         }
 
-        if fen.chars().nth(index) != Some(' ') {
-            anyhow::bail!(
-                "Error while parsing FEN string - expected whitespace after halfmove clock"
-            )
+        let side = match self.to_move {
+            Color::WHITE => 'w',
+            Color::BLACK => 'b',
+        };
+        write!(w, " {side} ")?;
+        if self.chess960 && !self.castling_rights.is_empty() {
+            for (color, side) in [
+                (Color::WHITE, CastlingSide::KINGSIDE),
+                (Color::WHITE, CastlingSide::QUEENSIDE),
+                (Color::BLACK, CastlingSide::KINGSIDE),
+                (Color::BLACK, CastlingSide::QUEENSIDE),
+            ] {
+                if self.castling_rights.allows(color, side) {
+                    let file = self.castling_files[color as usize][side as usize];
+                    let c = (b'a' + file as u8) as char;
+                    w.write_char(match color {
+                        Color::WHITE => c.to_ascii_uppercase(),
+                        Color::BLACK => c,
+                    })?;
+                }
+            }
+            w.write_char(' ')?;
+        } else {
+            write!(w, "{} ", self.castling_rights)?;
+        }
+        match self.en_passant_square {
+            Some(s) => write!(w, "{s}")?,
+            None => w.write_char('-')?,
         }
-        index += 1;
+        write!(w, " {} {}", self.halfmove_clock, self.fullmove_clock)
+    }
 
-        match fen.chars().nth(index) {
-            Some(c) => {
-                if c.is_ascii_digit() {
-                    let mut fmc = String::new();
-                    fmc.push(c);
-                    let mut peek = 1;
-                    while let Some(n) = fen.chars().nth(index + peek) {
-                        if n == ' ' {
-                            break;
-                        }
-                        fmc.push(n);
-                        peek += 1;
+    /// Returns a wrapper that displays the position as a FEN string, for use with `format!`,
+    /// `write!` or any [`std::io::Write`] without building a `String` first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use kritisch::game::Game;
+    /// let mut out = Vec::new();
+    /// writeln!(out, "{}", Game::default().fen()).unwrap();
+    /// assert_eq!(out, b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n");
+    /// ```
+    pub fn fen(&self) -> Fen<'_> {
+        Fen(self)
+    }
+
+    /// Parses the piece placement section of a FEN string into `self`'s bitboards. Returns
+    /// the number of files and ranks it describes, which may be fewer than eight.
+    fn parse_placement(&mut self, placement: &str) -> Result<(u8, u8)> {
+        let rank_count = placement.split('/').count();
+        if rank_count > 8 {
+            return Err(Error::InvalidFen("more than eight ranks".to_string()));
+        }
+        let mut files = None;
+        for (i, rank_str) in placement.split('/').enumerate() {
+            let rank = Rank::new((rank_count - 1 - i) as u8).expect("There are at most 8 ranks");
+
+            let mut file = 0u8;
+            let mut last_piece = None;
+            for c in rank_str.chars() {
+                if c == '~' {
+                    let square = last_piece.take().ok_or_else(|| {
+                        Error::InvalidFen(format!("'~' without a piece in rank {rank_str}"))
+                    })?;
+                    self.promoted |= square;
+                    continue;
+                }
+                last_piece = None;
+                if let Some(d) = c.to_digit(10) {
+                    if d == 0 {
+                        return Err(Error::InvalidFen("zero empty squares in rank".to_string()));
                     }
-                    pos.fullmove_clock = fmc
-                        .parse()
-                        .context("tried to cast FEN fullmove clock to usize")?;
+                    file += d as u8;
+                } else if let Some(piece) = ColoredPiece::from_char(c) {
+                    let square = File::new(file)
+                        .map(|f| Square::from_coords(f, rank))
+                        .ok_or_else(|| Error::InvalidFen(format!("rank {rank_str} too long")))?;
+                    self.put_piece(square, piece);
+                    last_piece = Some(square);
+                    file += 1;
+                } else if c == '*' && self.duck.is_empty() {
+                    let square = File::new(file)
+                        .map(|f| Square::from_coords(f, rank))
+                        .ok_or_else(|| Error::InvalidFen(format!("rank {rank_str} too long")))?;
+                    self.set_duck(Bitboard::from_square(square));
+                    self.refresh_attacks(self.duck);
+                    file += 1;
                 } else {
-                    anyhow::bail!("Expected digit in fullmove clock position in FEN string")
+                    return Err(Error::InvalidFen(format!(
+                        "unexpected character '{c}' in piece placement"
+                    )));
+                }
+                if file > 8 {
+                    return Err(Error::InvalidFen(format!("rank {rank_str} too long")));
                 }
             }
-            None => anyhow::bail!("Incomplete FEN string - fullmove clock missing"),
+            let expected = *files.get_or_insert(file);
+            if file != expected {
+                return Err(Error::InvalidFen(format!(
+                    "rank {rank_str} has {file} squares instead of {expected}"
+                )));
+            }
         }
-        Ok(pos)
+        Ok((files.unwrap_or(0), rank_count as u8))
+    }
+
+    /// Parses the pockets of a FEN string, the pieces between the brackets, into `self`.
+    fn parse_pockets(&mut self, pockets: &str) -> Result<()> {
+        for c in pockets.chars() {
+            match ColoredPiece::from_char(c) {
+                Some(piece) if piece.piece != Piece::KING => self.add_to_pocket(piece),
+                _ => {
+                    return Err(Error::InvalidFen(format!(
+                        "unexpected character '{c}' in pockets"
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Places `piece` on the empty square `s`.
+    pub(crate) fn put_piece(&mut self, s: Square, piece: ColoredPiece) {
+        self.add_piece(s, piece);
+        self.refresh_attacks(Bitboard::from_square(s));
+    }
+
+    /// Removes the piece on `s` and returns it, or returns `None` if `s` is empty. Taking a
+    /// king or rook off its starting square loses the castling rights that depend on it.
+    pub(crate) fn take_piece(&mut self, s: Square) -> Option<ColoredPiece> {
+        let piece = self.piece_at(s)?;
+        self.remove_piece(s, piece.piece);
+        self.refresh_attacks(Bitboard::from_square(s));
+        Some(piece)
     }
-    /// Returns `Some(Piece)` if one of `self`'s piece bitboards
-    /// contains `s` and `None` otherwise.
-    pub fn type_at(&self, s: Square) -> Piece {
-        let mask = Bitboard::from_square(s);
 
-        // Checks if there is a piece bitboard that contains the given square
-        // by bitAnd-ing it with a bitboard of just that square.
-        // Maps the found piece value to the `Piece` enum
-        if let Some(piece) = (0..=5)
-            .find(|i| !(self.piece_bitboards[*i as usize] & mask).is_empty())
-            .map(|piece_idx| Piece::from_u8(piece_idx as u8))
+    /// Places `piece` on the empty square `s` without updating the attack maps.
+    fn add_piece(&mut self, s: Square, piece: ColoredPiece) {
+        self.color_bitboards[piece.color as usize] |= s;
+        self.piece_bitboards[piece.piece as usize] |= s;
+        self.mailbox[s as usize] = Some(piece);
+        self.piece_counts[piece.color as usize][piece.piece as usize] += 1;
+        self.material[piece.color as usize] += piece.piece.value();
+        self.pst += pst::value(piece, s);
+        self.piece_key ^= zobrist::piece(piece, s);
+        #[cfg(feature = "nnue")]
+        self.accumulator.add(piece, s);
+    }
+
+    /// Checks that the position obeys the basic rules of chess: each side has exactly one
+    /// king, there are no pawns on the first or eighth rank and the side that just moved
+    /// is not in check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// assert!(Game::default().validate().is_ok());
+    /// // White to move, but black is in check
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").unwrap();
+    /// assert!(game.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        for color in Color::both() {
+            let kings = (self.piece_bitboards[Piece::KING as usize]
+                & self.color_bitboards[color as usize])
+                .count_ones();
+            if kings != 1 {
+                return Err(Error::InvalidPosition(format!("{color} has {kings} kings")));
+            }
+        }
+
+        let back_ranks = Rank::FIRST.squares().chain(Rank::EIGHTH.squares());
+        if back_ranks
+            .into_iter()
+            .any(|s| self.piece_bitboards[Piece::PAWN as usize].contains(s))
         {
-            return piece;
-        } else {
-            panic!("Tried to get piece type from empty square")
+            return Err(Error::InvalidPosition(
+                "pawn on the first or eighth rank".to_string(),
+            ));
+        }
+
+        let waiting = !self.to_move;
+        let king_square = Square::from_u8(
+            (self.piece_bitboards[Piece::KING as usize] & self.color_bitboards[waiting as usize])
+                .trailing_zeros() as u8,
+        );
+        if self.is_attacked_by(self.to_move, king_square) {
+            return Err(Error::InvalidPosition(format!(
+                "{waiting} is in check but it is {} to move",
+                self.to_move
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the piece and its color on `s`, or `None` if the square is empty.
+    pub fn piece_at(&self, s: Square) -> Option<ColoredPiece> {
+        self.mailbox[s as usize]
+    }
+
+    /// Returns the piece `m` moves or drops, or `None` if `m` starts on an empty square.
+    pub fn moved_piece(&self, m: Move) -> Option<ColoredPiece> {
+        match m.drop {
+            Some(piece) => Some(ColoredPiece::new(piece, self.to_move)),
+            None => self.piece_at(m.start),
+        }
+    }
+
+    /// Returns how many pieces of type `piece` `color` has in its pocket, ready to be dropped
+    /// in Crazyhouse.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color, Piece};
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3[NNp] w - - 0 1").unwrap();
+    /// assert_eq!(game.pocket(Color::WHITE, Piece::KNIGHT), 2);
+    /// assert_eq!(game.pocket(Color::BLACK, Piece::PAWN), 1);
+    /// assert_eq!(game.pocket(Color::BLACK, Piece::KING), 0);
+    /// ```
+    pub fn pocket(&self, color: Color, piece: Piece) -> u8 {
+        self.pockets[color as usize]
+            .get(piece as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the squares of the pieces that were promoted from pawns, which go back to the
+    /// pocket as pawns when they are captured in Crazyhouse.
+    pub fn promoted(&self) -> Bitboard {
+        self.promoted
+    }
+
+    /// Returns the square of the duck in [Duck chess](crate::variant::duck), or `None` if
+    /// there is no duck on the board, as before the first move.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, variant::Variant, Square};
+    /// let game = Game::from_fen("4k3/8/8/3*4/8/8/8/4K3 w - - 0 1").unwrap();
+    /// assert_eq!(game.duck(), Some(Square::D5));
+    /// assert_eq!(game.variant, Variant::DUCK);
+    /// assert!(!game.is_square_empty(Square::D5));
+    /// ```
+    pub fn duck(&self) -> Option<Square> {
+        (!self.duck.is_empty()).then(|| Square::from_u8(self.duck.trailing_zeros() as u8))
+    }
+
+    /// Puts the duck on the empty square `s`.
+    pub(crate) fn put_duck(&mut self, s: Square) {
+        self.set_duck(Bitboard::from_square(s));
+        self.refresh_attacks(self.duck);
+    }
+
+    /// Marks the pieces on `promoted` as promoted from pawns, see [`Game::promoted`].
+    pub(crate) fn set_promoted(&mut self, promoted: Bitboard) {
+        self.promoted = promoted;
+    }
+
+    /// Moves the duck to the square of `duck`, or takes it off the board if `duck` is empty.
+    fn set_duck(&mut self, duck: Bitboard) {
+        for old_or_new in [self.duck, duck] {
+            if !old_or_new.is_empty() {
+                self.piece_key ^= zobrist::duck(Square::from_u8(old_or_new.trailing_zeros() as u8));
+            }
         }
+        self.duck = duck;
+    }
+
+    /// Returns `Some(Piece)` if there is a piece on `s` and `None` otherwise.
+    pub fn try_type_at(&self, s: Square) -> Option<Piece> {
+        self.mailbox[s as usize].map(|p| p.piece)
+    }
+
+    /// Returns the `Piece` on `s`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is empty. Use [`Game::try_type_at`] for a non-panicking version.
+    pub fn type_at(&self, s: Square) -> Piece {
+        self.try_type_at(s)
+            .expect("Tried to get piece type from empty square")
+    }
+
+    /// Returns `Some(Color)` of the piece on `s` and `None` if `s` is empty.
+    pub fn try_color_at(&self, s: Square) -> Option<Color> {
+        self.mailbox[s as usize].map(|p| p.color)
     }
 
     /// Returns the `Color` of the piece on `s`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is empty. Use [`Game::try_color_at`] for a non-panicking version.
     pub fn color_at(&self, s: Square) -> Color {
-        let mask = Bitboard::from_square(s);
-
-        // Checks if there is a color bitboard that contains the given square
-        // by bitAnd-ing it with a bitboard of just that square.
-        // Maps the found piece value to the `Color` enum
-        (0..=1)
-            .find(|i| !(self.color_bitboards[*i as usize] & mask).is_empty())
-            .map(|color_idx| Color::from_u8(color_idx as u8))
-            .unwrap()
+        self.try_color_at(s)
+            .expect("Tried to get piece color from empty square")
     }
 
-    /// Returns a combined `Bitboard` of all pieces on the board
+    /// Returns a combined `Bitboard` of all pieces on the board.
     pub fn all_pieces(&self) -> Bitboard {
         self.color_bitboards[0] | self.color_bitboards[1]
     }
 
-    /// Returns `true` if there is any piece on `s`, `false` otherwise.
+    /// Returns the squares that block the way of a piece: those of [`Game::all_pieces`] and
+    /// the duck's in Duck chess.
+    pub fn blockers(&self) -> Bitboard {
+        self.all_pieces() | self.duck
+    }
+
+    /// Returns `true` if there is neither a piece nor the duck on `s`, `false` otherwise.
     pub fn is_square_empty(&self, s: Square) -> bool {
-        !self.all_pieces().contains(s)
+        !self.blockers().contains(s)
     }
 
-    /// Attempts to make a move on the board. This is the lowest level of doing so and inherently
-    /// only checks for very few error conditions.
-    pub fn make_move(&mut self, m: Move) {
-        let piece = self.type_at(m.start);
-        let color = self.color_at(m.start);
+    /// Makes `m` on the board if it is legal in the current position, returning
+    /// [`Error::IllegalMove`] and leaving the position untouched otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Error, Move, Square};
+    /// let mut game = Game::default();
+    /// let m = Move::new(Square::E2, Square::F2);
+    /// assert_eq!(game.try_make_move(m), Err(Error::IllegalMove(m)));
+    /// ```
+    pub fn try_make_move(&mut self, m: Move) -> Result<()> {
+        if !all_legal_moves(self).contains(&m) {
+            return Err(Error::IllegalMove(m));
+        }
+        self.make_move(m);
+        Ok(())
+    }
 
-        let is_capture = self.is_capture(m);
+    /// Returns `true` if `m`, a move of the side that just moved, becomes legal after at least
+    /// one legal reply of the side to move. Servers accept a premove on these terms while the
+    /// opponent is thinking and play it if it is legal once the reply is made.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let mut game = Game::default();
+    /// game.make_move(Move::from_uci("e2e4").unwrap());
+    /// let plausible = |uci| game.is_plausible_premove(Move::from_uci(uci).unwrap());
+    /// // Black may reply 1... d5
+    /// assert!(plausible("e4d5"));
+    /// assert!(plausible("g1f3"));
+    /// assert!(!plausible("e4e6"));
+    /// assert!(!plausible("e7e5"));
+    /// ```
+    pub fn is_plausible_premove(&self, m: Move) -> bool {
+        all_legal_moves(self).iter().any(|&reply| {
+            let mut game = *self;
+            game.make_move(reply);
+            all_legal_moves(&game).contains(&m)
+        })
+    }
 
-        let is_castle = if piece == Piece::KING {
-            self.is_castle(m, piece, color)
+    /// Returns `m` in standard algebraic notation, e.g. `Nbd2`, `exd5`, `e8=Q` or `O-O+`, or
+    /// [`Error::IllegalMove`] if it isn't legal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+    /// let san = |uci| game.san(Move::from_uci(uci).unwrap()).unwrap();
+    /// assert_eq!(san("a1a8"), "Ra8+");
+    /// assert_eq!(san("h1f1"), "Rhf1");
+    /// ```
+    pub fn san(&self, m: Move) -> Result<String> {
+        let moves = all_legal_moves(self);
+        if !moves.contains(&m) {
+            return Err(Error::IllegalMove(m));
+        }
+        let ColoredPiece { piece, color } =
+            self.moved_piece(m).expect("Legal moves start on a piece");
+        let mut san = String::new();
+        if m.is_drop() {
+            if piece != Piece::PAWN {
+                write!(san, "{piece}").unwrap();
+            }
+            write!(san, "@{}", m.end).unwrap();
+        } else if self.is_castle(m, piece, color) {
+            san.push_str(match castling_side(m) {
+                CastlingSide::KINGSIDE => "O-O",
+                CastlingSide::QUEENSIDE => "O-O-O",
+            });
+        } else if piece == Piece::PAWN {
+            // Pawns only change files when capturing, en passant included
+            if m.start.get_file() != m.end.get_file() {
+                write!(san, "{}x", m.start.get_file()).unwrap();
+            }
+            write!(san, "{}", m.end).unwrap();
+            if let Some(promotion) = m.promotion {
+                write!(san, "={promotion}").unwrap();
+            }
         } else {
-            false
+            write!(san, "{piece}").unwrap();
+            // Name the starting file, rank or square if another such piece can go there too
+            let others: Vec<Square> = moves
+                .iter()
+                .filter(|o| {
+                    o.end == m.end
+                        && o.start != m.start
+                        && !o.is_drop()
+                        && self.type_at(o.start) == piece
+                })
+                .map(|o| o.start)
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|s| s.get_file() != m.start.get_file()) {
+                    write!(san, "{}", m.start.get_file()).unwrap();
+                } else if others.iter().all(|s| s.get_rank() != m.start.get_rank()) {
+                    write!(san, "{}", m.start.get_rank()).unwrap();
+                } else {
+                    write!(san, "{}", m.start).unwrap();
+                }
+            }
+            if self.is_capture(m) {
+                san.push('x');
+            }
+            write!(san, "{}", m.end).unwrap();
+        }
+        // Duck chess knows no check, so the duck's square ends the move
+        if let Some(duck) = m.duck {
+            write!(san, ",{duck}").unwrap();
+            return Ok(san);
+        }
+
+        let mut after = *self;
+        after.make_move(m);
+        if after.is_check() {
+            san.push(if all_legal_moves(&after).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        Ok(san)
+    }
+
+    /// Returns the moves of `line`, played one after another from this position, in standard
+    /// algebraic notation. Fails with [`Error::IllegalMove`] at the first move that isn't
+    /// legal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let line = ["e2e4", "e7e5", "g1f3", "b8c6"].map(|uci| Move::from_uci(uci).unwrap());
+    /// assert_eq!(Game::default().san_line(&line).unwrap(), ["e4", "e5", "Nf3", "Nc6"]);
+    /// ```
+    pub fn san_line(&self, line: &[Move]) -> Result<Vec<String>> {
+        let mut game = *self;
+        line.iter()
+            .map(|&m| {
+                let san = game.san(m)?;
+                game.make_move(m);
+                Ok(san)
+            })
+            .collect()
+    }
+
+    /// Parses `san`, a move in standard algebraic notation such as `Nbd2`, `exd5`, `e8=Q`, `O-O`
+    /// or the drop `N@f3`, into the legal move it names. Check and annotation marks may be left
+    /// out or added, and a pawn reaching the last rank without a promotion piece promotes to a
+    /// queen. In Duck chess the duck's square follows after a comma, e.g. `e4,d5`. Returns
+    /// [`Error::InvalidSan`] if no legal move or more than one fits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+    /// assert_eq!(game.parse_san("Rhf1").unwrap(), Move::from_uci("h1f1").unwrap());
+    /// assert_eq!(game.parse_san("Ra8").unwrap(), Move::from_uci("a1a8").unwrap());
+    /// assert!(game.parse_san("Rf1").is_err());
+    /// ```
+    pub fn parse_san(&self, san: &str) -> Result<Move> {
+        let invalid = |reason: &str| Error::InvalidSan(format!("{san}: {reason}"));
+        let marks = ['+', '#', '!', '?'];
+        let mut text = san.trim().trim_end_matches(marks);
+        let mut duck = None;
+        if let Some((before, square)) = text.split_once(',') {
+            text = before.trim_end_matches(marks);
+            let square: Vec<char> = square.chars().collect();
+            let [c, d] = square.as_slice() else {
+                return Err(invalid("expected the duck's square"));
+            };
+            duck = Some(Square::from_parts(c, d).map_err(|e| invalid(&e.to_string()))?);
+        }
+        let mut promotion = None;
+        if let Some((before, piece)) = text.split_once('=') {
+            text = before;
+            promotion = Some(piece);
+        } else if text.len() > 2 && text.ends_with(['Q', 'R', 'B', 'N', 'K']) {
+            promotion = Some(&text[text.len() - 1..]);
+            text = &text[..text.len() - 1];
+        }
+        let promotion = match promotion {
+            Some(piece) => Some(
+                Piece::try_from_char(piece.chars().next().unwrap_or('-'))
+                    .filter(|&p| piece.len() == 1 && p != Piece::PAWN)
+                    .ok_or_else(|| invalid(&format!("can't promote to '{piece}'")))?,
+            ),
+            None => None,
         };
+        let moves = all_legal_moves(self);
 
-        // If the move castles, dispatch the move handling to `self.castle` instead
-        if is_castle {
-            match m.end {
-                Square::C1 => self.move_piece(
-                    Move {
-                        start: Square::A1,
-                        end: Square::D1,
-                    },
-                    Piece::ROOK,
-                    color,
-                ),
-                Square::G1 => self.move_piece(
-                    Move {
-                        start: Square::H1,
-                        end: Square::F1,
-                    },
-                    Piece::ROOK,
-                    color,
-                ),
-                Square::C8 => self.move_piece(
-                    Move {
-                        start: Square::A8,
-                        end: Square::D8,
-                    },
-                    Piece::ROOK,
-                    color,
-                ),
-                Square::G8 => self.move_piece(
-                    Move {
-                        start: Square::H8,
-                        end: Square::F8,
-                    },
-                    Piece::ROOK,
-                    color,
-                ),
-                _ => panic!(
-                    "Castling to illegal square (move: {:?} {:?} -> {:?})",
-                    piece, m.start, m.end
-                ),
+        let castle = match text {
+            "O-O" | "0-0" => Some(CastlingSide::KINGSIDE),
+            "O-O-O" | "0-0-0" => Some(CastlingSide::QUEENSIDE),
+            _ => None,
+        };
+        if let Some(side) = castle {
+            return moves
+                .iter()
+                .copied()
+                .find(|&m| {
+                    !m.is_drop()
+                        && self.is_castle(m, self.type_at(m.start), self.to_move)
+                        && castling_side(m) == side
+                        && m.duck == duck
+                })
+                .ok_or_else(|| invalid("castling is not legal"));
+        }
+        if let Some((piece, square)) = text.split_once('@') {
+            let piece = match piece {
+                "" => Piece::PAWN,
+                _ => Piece::try_from_char(piece.chars().next().unwrap())
+                    .filter(|_| piece.len() == 1 && piece.starts_with(char::is_uppercase))
+                    .ok_or_else(|| invalid(&format!("can't drop '{piece}'")))?,
+            };
+            let square: Vec<char> = square.chars().collect();
+            let [c, d] = square.as_slice() else {
+                return Err(invalid("expected a target square"));
+            };
+            let end = Square::from_parts(c, d).map_err(|e| invalid(&e.to_string()))?;
+            let m = Move {
+                duck,
+                ..Move::new_drop(piece, end)
+            };
+            return if moves.contains(&m) {
+                Ok(m)
+            } else {
+                Err(invalid("no legal move fits"))
+            };
+        }
+
+        let (piece, rest) = match text.chars().next().and_then(Piece::try_from_char) {
+            Some(piece) if piece != Piece::PAWN && text.starts_with(char::is_uppercase) => {
+                (piece, &text[1..])
+            }
+            _ => (Piece::PAWN, text),
+        };
+        let rest: Vec<char> = rest
+            .chars()
+            .filter(|&c| !matches!(c, 'x' | ':' | '-'))
+            .collect();
+        let [from @ .., c, d] = rest.as_slice() else {
+            return Err(invalid("expected a target square"));
+        };
+        let end = Square::from_parts(c, d).map_err(|e| invalid(&e.to_string()))?;
+        let (mut file, mut rank) = (None, None);
+        for &c in from {
+            match (File::from_char(c), Rank::from_char(c)) {
+                (Some(f), _) if file.is_none() => file = Some(f),
+                (_, Some(r)) if rank.is_none() => rank = Some(r),
+                _ => return Err(invalid(&format!("unexpected '{c}'"))),
             }
         }
 
+        let mut fitting = moves.iter().copied().filter(|&m| {
+            m.end == end
+                && !m.is_drop()
+                && self.type_at(m.start) == piece
+                && !self.is_castle(m, piece, self.to_move)
+                && file.is_none_or(|f| m.start.get_file() == f)
+                && rank.is_none_or(|r| m.start.get_rank() == r)
+                && m.promotion == promotion.or(m.promotion.and(Some(Piece::QUEEN)))
+                && m.duck == duck
+        });
+        match (fitting.next(), fitting.next()) {
+            (Some(m), None) => Ok(m),
+            (None, _) => Err(invalid("no legal move fits")),
+            (Some(_), Some(_)) => Err(invalid("ambiguous")),
+        }
+    }
+
+    /// Attempts to make a move on the board. This is the lowest level of doing so and inherently
+    /// only checks for very few error conditions.
+    ///
+    /// Returns what is needed to take the move back with [`unmake_move`](Self::unmake_move).
+    pub fn make_move(&mut self, m: Move) -> Undo {
+        count!(moves_made);
+        let ColoredPiece { piece, color } = self
+            .moved_piece(m)
+            .expect("Tried to make a move from an empty square");
+        let (colors_before, pieces_before) = (self.color_bitboards, self.piece_bitboards);
+        let mut undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant_square: self.en_passant_square,
+            in_check: self.in_check,
+            halfmove_clock: self.halfmove_clock,
+            castled: !m.is_drop() && self.is_castle(m, piece, color),
+            promoted: self.promoted,
+            exploded: [None; 9],
+            duck: self.duck,
+        };
+
+        let is_capture = self.is_capture(m);
+        let en_passant_square = self.en_passant_square_after(m, piece, color);
+
         if is_capture {
-            self.handle_capture(m, piece, color);
+            undo.captured = Some(self.handle_capture(m, piece, color));
         }
 
-        // TODO: Handle promotions
+        if m.is_drop() {
+            self.take_from_pocket(ColoredPiece::new(piece, color));
+            self.add_piece(m.end, ColoredPiece::new(piece, color));
+        } else {
+            let lost = self.rights_lost_by_moving(piece, color, m.start);
+            self.castling_rights.remove(lost);
+            if self.promoted.contains(m.start) {
+                self.promoted ^= m.start;
+                self.promoted |= m.end;
+            }
+        }
 
-        self.move_piece(m, piece, color);
+        // If the move castles, move the rook along with the king
+        if undo.castled {
+            let (king, rook) = self.castling_moves(m, color);
+            self.castle(king, rook, color);
+        } else if !m.is_drop() {
+            self.move_piece(m, piece, color);
+        }
+        if let Some(promotion) = m.promotion {
+            self.remove_piece(m.end, Piece::PAWN);
+            self.add_piece(m.end, ColoredPiece::new(promotion, color));
+            if self.rules().has_pockets() {
+                self.promoted |= m.end;
+            }
+        }
+        if is_capture && self.rules().captures_explode() {
+            undo.exploded = self.explode(m.end);
+        }
+        if let Some(duck) = m.duck {
+            self.set_duck(Bitboard::from_square(duck));
+        }
+        self.refresh_attacks(
+            self.changed_squares(colors_before, pieces_before) | (self.duck ^ undo.duck),
+        );
 
         // Increment the halfmove clock if the move was not a pawn move or a capture.
         if piece == Piece::PAWN || is_capture {
@@ -432,8 +1378,75 @@ impl Game {
             self.fullmove_clock += 1;
         }
 
+        self.en_passant_square = en_passant_square;
         // Change which player's turn it is
-        self.to_move = self.to_move ^ 1;
+        self.to_move = !self.to_move;
+        undo
+    }
+
+    /// Takes back `m`, which must be the last move made with [`make_move`](Self::make_move),
+    /// restoring the position from before the move.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 20").unwrap();
+    /// let before = game;
+    /// for uci in ["e1g1", "a1a8"] {
+    ///     let m = Move::from_uci(uci).unwrap();
+    ///     let undo = game.make_move(m);
+    ///     assert_ne!(game, before);
+    ///     game.unmake_move(m, undo);
+    ///     assert_eq!(game, before);
+    /// }
+    /// ```
+    pub fn unmake_move(&mut self, m: Move, undo: Undo) {
+        self.to_move = !self.to_move;
+        let color = self.to_move;
+        if color == Color::BLACK {
+            self.fullmove_clock -= 1;
+        }
+
+        let (colors_before, pieces_before) = (self.color_bitboards, self.piece_bitboards);
+        let duck_before = self.duck;
+        if duck_before != undo.duck {
+            self.set_duck(undo.duck);
+        }
+        for (s, piece) in undo.exploded.into_iter().flatten() {
+            self.add_piece(s, piece);
+        }
+        if let Some(promotion) = m.promotion {
+            self.remove_piece(m.end, promotion);
+            self.add_piece(m.end, ColoredPiece::new(Piece::PAWN, color));
+        }
+        if let Some(piece) = m.drop {
+            self.remove_piece(m.end, piece);
+            self.add_to_pocket(ColoredPiece::new(piece, color));
+        } else if undo.castled {
+            let (king, rook) = self.castling_moves(m, color);
+            self.castle(reversed(king), reversed(rook), color);
+        } else {
+            let piece = self
+                .try_type_at(m.end)
+                .expect("Tried to unmake a move to an empty square");
+            self.move_piece(reversed(m), piece, color);
+        }
+        self.promoted = undo.promoted;
+        if let Some((s, captured)) = undo.captured {
+            self.add_piece(s, captured);
+            if let Some(pocketed) = self.pocketed(s, captured) {
+                self.take_from_pocket(pocketed);
+            }
+        }
+        self.refresh_attacks(
+            self.changed_squares(colors_before, pieces_before) | (self.duck ^ duck_before),
+        );
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant_square = undo.en_passant_square;
+        self.in_check = undo.in_check;
+        self.halfmove_clock = undo.halfmove_clock;
     }
 
     /// Actually 'moves' a piece by creating a bitboard mask and XOR/OR-ing it with
@@ -445,51 +1458,133 @@ impl Game {
         self.color_bitboards[c as usize] |= to_mask;
         self.piece_bitboards[p as usize] ^= from_mask;
         self.piece_bitboards[p as usize] |= to_mask;
+        let piece = ColoredPiece::new(p, c);
+        self.mailbox[m.start as usize] = None;
+        self.mailbox[m.end as usize] = Some(piece);
+        self.pst += pst::value(piece, m.end) - pst::value(piece, m.start);
+        self.piece_key ^= zobrist::piece(piece, m.start) ^ zobrist::piece(piece, m.end);
+        #[cfg(feature = "nnue")]
+        {
+            self.accumulator.remove(piece, m.start);
+            self.accumulator.add(piece, m.end);
+        }
     }
 
-    /// Handles a capture move by removing the captured piece from the board
-    fn handle_capture(&mut self, m: Move, p: Piece, c: Color) {
-        let captured_piece = self.type_at(m.end);
+    /// Moves the king and the rook of a castling move. In Chess960 each may land on the
+    /// other's square, so both are taken off the board before either is put back.
+    fn castle(&mut self, king: Move, rook: Move, color: Color) {
+        self.remove_piece(king.start, Piece::KING);
+        self.remove_piece(rook.start, Piece::ROOK);
+        self.add_piece(king.end, ColoredPiece::new(Piece::KING, color));
+        self.add_piece(rook.end, ColoredPiece::new(Piece::ROOK, color));
+    }
 
-        let is_en_passant = if p == Piece::PAWN {
-            self.is_en_passant(m, captured_piece)
-        } else {
-            false
+    /// Returns the king's and the rook's part of `color`'s castling move `m`.
+    fn castling_moves(&self, m: Move, color: Color) -> (Move, Move) {
+        let side = castling_side(m);
+        let (king_end, rook_end) = match side {
+            CastlingSide::KINGSIDE => (Square::G1, Square::F1),
+            CastlingSide::QUEENSIDE => (Square::C1, Square::D1),
         };
+        let king = Move::new(m.start, king_end.relative_to(color));
+        let rook = Move::new(self.castling_rook(color, side), rook_end.relative_to(color));
+        (king, rook)
+    }
 
-        // Remove the captured piece from the board.
-        // If the move is en_passant, remove the piece from the EP square
-        // instead of the move end square.
-        if !is_en_passant {
-            self.remove_piece(m.end, captured_piece);
-        } else {
-            match c {
-                Color::WHITE => {
-                    let target_square = m.end - 8u8;
-                    self.remove_piece(target_square, captured_piece);
-                }
-                Color::BLACK => {
-                    let target_square = m.end + 8u8;
-                    self.remove_piece(target_square, captured_piece);
-                }
+    /// Handles a capture move by removing the captured piece from the board.
+    /// Returns the square and the piece that was removed.
+    fn handle_capture(&mut self, m: Move, p: Piece, c: Color) -> (Square, ColoredPiece) {
+        let (target_square, captured) = self.captured_by(m, p, c);
+        self.remove_piece(target_square, captured.piece);
+        if let Some(pocketed) = self.pocketed(target_square, captured) {
+            self.add_to_pocket(pocketed);
+        }
+        self.promoted &= !Bitboard::from_square(target_square);
+        (target_square, captured)
+    }
+
+    /// Removes the piece that has captured on `s` and every piece but pawns next to it, for
+    /// variants whose captures explode. Returns the removed pieces.
+    fn explode(&mut self, s: Square) -> [Option<(Square, ColoredPiece)>; 9] {
+        let mut exploded = [None; 9];
+        let neighbors = Bitboard::from_u64(king_attack_set(s.to_u64()));
+        let mut squares =
+            (neighbors & self.all_pieces() & !self.piece_bitboards[Piece::PAWN as usize]) | s;
+        for slot in &mut exploded {
+            if squares.is_empty() {
+                break;
             }
+            let square = Square::from_u8(squares.trailing_zeros() as u8);
+            squares.clear_lsb();
+            let piece = self.mailbox[square as usize].expect("exploded squares are occupied");
+            self.remove_piece(square, piece.piece);
+            self.promoted &= !Bitboard::from_square(square);
+            *slot = Some((square, piece));
+        }
+        exploded
+    }
+
+    /// Returns the piece that goes to the capturer's pocket when `captured` is taken on `s`,
+    /// or `None` if the variant has no pockets. Promoted pieces go back as pawns.
+    fn pocketed(&self, s: Square, captured: ColoredPiece) -> Option<ColoredPiece> {
+        if !self.rules().has_pockets() {
+            return None;
+        }
+        let piece = if self.promoted.contains(s) {
+            Piece::PAWN
+        } else {
+            captured.piece
+        };
+        Some(ColoredPiece::new(piece, !captured.color))
+    }
+
+    /// Puts `piece` into the pocket of its color.
+    pub(crate) fn add_to_pocket(&mut self, piece: ColoredPiece) {
+        let count = &mut self.pockets[piece.color as usize][piece.piece as usize];
+        self.piece_key ^= zobrist::pocket(piece, *count) ^ zobrist::pocket(piece, *count + 1);
+        *count += 1;
+        self.material[piece.color as usize] += piece.piece.value();
+    }
+
+    /// Takes `piece` out of the pocket of its color, which must hold one.
+    fn take_from_pocket(&mut self, piece: ColoredPiece) {
+        let count = &mut self.pockets[piece.color as usize][piece.piece as usize];
+        self.piece_key ^= zobrist::pocket(piece, *count) ^ zobrist::pocket(piece, *count - 1);
+        *count -= 1;
+        self.material[piece.color as usize] -= piece.piece.value();
+    }
+
+    /// Returns the square and the piece captured by the capture `m` of `c`'s piece `p`.
+    fn captured_by(&self, m: Move, p: Piece, c: Color) -> (Square, ColoredPiece) {
+        // En passant takes the pawn that has passed the end square instead of a piece on it
+        if p == Piece::PAWN && self.is_en_passant(m) {
+            let target_square = match c {
+                Color::WHITE => m.end - 8u8,
+                Color::BLACK => m.end + 8u8,
+            };
+            return (target_square, ColoredPiece::new(Piece::PAWN, !c));
         }
+        (m.end, ColoredPiece::new(self.type_at(m.end), !c))
     }
 
     /// Returns `true` if there is a piece on `m.end` and if
-    /// it does not have the same color as the piece on `m.start`.
+    /// it does not have the same color as the piece on `m.start`, or if `m` takes en passant.
     pub fn is_capture(&self, m: Move) -> bool {
-        if self.is_square_empty(m.end) {
-            return false;
-        }
-        if self.color_at(m.end) == self.color_at(m.start) {
-            return false;
+        match (self.try_color_at(m.start), self.try_color_at(m.end)) {
+            (Some(moving), Some(captured)) => moving != captured,
+            (Some(_), None) => self.is_en_passant(m),
+            _ => false,
         }
-        true
     }
 
-    /// Returns `true` if `m` is one of eight possible castling moves in check.
+    /// Returns `true` if `m` of `color`'s `piece` castles, in the position before the move.
+    /// Castling moves are one of the four king moves two squares along the first rank, or in
+    /// Chess960 the king taking its own rook.
     pub fn is_castle(&self, m: Move, piece: Piece, color: Color) -> bool {
+        if self.chess960 {
+            return piece == Piece::KING
+                && self.piece_at(m.end) == Some(ColoredPiece::new(Piece::ROOK, color));
+        }
         matches!((piece, color, m.start, m.end), |(
             Piece::KING,
             Color::WHITE,
@@ -513,8 +1608,33 @@ impl Game {
         ))
     }
 
-    pub fn is_en_passant(&self, m: Move, captured_piece: Piece) -> bool {
-        self.en_passant_square == Some(m.end) && captured_piece == Piece::PAWN
+    /// Returns `true` if `m` is a pawn taking en passant, i.e. moving to the empty
+    /// [`en_passant_square`](Self::en_passant_square).
+    pub fn is_en_passant(&self, m: Move) -> bool {
+        self.en_passant_square == Some(m.end)
+            && !m.is_drop()
+            && m.start.get_file() != m.end.get_file()
+            && self.try_type_at(m.start) == Some(Piece::PAWN)
+            && self.is_square_empty(m.end)
+    }
+
+    /// Returns the square `m` of `color`'s `piece` passes over if it is a pawn's double step
+    /// next to a pawn of the opponent, which may then take en passant. Otherwise there is no
+    /// en passant square after the move.
+    fn en_passant_square_after(&self, m: Move, piece: Piece, color: Color) -> Option<Square> {
+        if piece != Piece::PAWN || m.is_drop() || (m.start as u8).abs_diff(m.end as u8) != 16 {
+            return None;
+        }
+        let passed = Square::from_u8((m.start as u8 + m.end as u8) / 2);
+        self.can_take_en_passant(passed, color).then_some(passed)
+    }
+
+    /// Returns `true` if a pawn of `color`'s opponent attacks `passed`, the square a pawn of
+    /// `color` passed over with a double step.
+    fn can_take_en_passant(&self, passed: Square, color: Color) -> bool {
+        let enemy_pawns =
+            self.piece_bitboards[Piece::PAWN as usize] & self.color_bitboards[!color as usize];
+        pawn_attack_set(passed.to_u64(), color) & enemy_pawns.0 != 0
     }
 
     fn remove_piece(&mut self, s: Square, piece: Piece) {
@@ -522,137 +1642,698 @@ impl Game {
 
         let color = self.color_at(s);
 
-        // If a rook was captured on its initial square, update castling rights accordingly
-        if piece == Piece::ROOK {
-            match (s, color) {
-                (Square::A1, Color::WHITE) => {
-                    self.castling_rights &= !CastlingRights::WHITE_QUEENSIDE
-                }
-                (Square::H1, Color::WHITE) => {
-                    self.castling_rights &= !CastlingRights::WHITE_KINGSIDE
-                }
-                (Square::A8, Color::BLACK) => {
-                    self.castling_rights &= !CastlingRights::BLACK_QUEENSIDE
-                }
-                (Square::H8, Color::BLACK) => {
-                    self.castling_rights &= !CastlingRights::BLACK_KINGSIDE
-                }
-                _ => (),
-            }
-        }
+        let lost = self.rights_lost_by_capture(piece, color, s);
+        self.castling_rights.remove(lost);
 
         self.color_bitboards[color as usize] ^= mask;
         self.piece_bitboards[piece as usize] ^= mask;
+        self.mailbox[s as usize] = None;
+        self.piece_counts[color as usize][piece as usize] -= 1;
+        self.material[color as usize] -= piece.value();
+        self.pst -= pst::value(ColoredPiece::new(piece, color), s);
+        self.piece_key ^= zobrist::piece(ColoredPiece::new(piece, color), s);
+        #[cfg(feature = "nnue")]
+        self.accumulator.remove(ColoredPiece::new(piece, color), s);
     }
 
-    pub fn is_attacked_by(&self, color: Color, square: Square) -> bool {
-        match color {
-            Color::WHITE => {
-                if let Some(offset) = try_square_offset(square, -1, -1) {
-                    if (self.piece_bitboards[Piece::PAWN as usize]
-                        & self.color_bitboards[Color::WHITE as usize])
-                        .contains(offset)
-                    {
-                        return true;
-                    }
-                }
-                if let Some(offset) = try_square_offset(square, 1, -1) {
-                    if (self.piece_bitboards[Piece::PAWN as usize]
-                        & self.color_bitboards[Color::WHITE as usize])
-                        .contains(offset)
-                    {
-                        return true;
-                    }
-                }
+    /// Returns the castling rights given up by moving `piece` off `s`: moving the king or a
+    /// castling rook off its starting square gives up castling on that side.
+    fn rights_lost_by_moving(&self, piece: Piece, color: Color, s: Square) -> CastlingRights {
+        match piece {
+            Piece::KING => CastlingRights::for_color(color),
+            Piece::ROOK => self.rook_rights(color, s),
+            _ => CastlingRights::empty(),
+        }
+    }
+
+    /// Returns the castling rights lost when `color`'s `piece` is captured on `s`, which are
+    /// those of a rook captured on its starting square, or all of them if a variant lets the
+    /// king be captured.
+    fn rights_lost_by_capture(&self, piece: Piece, color: Color, s: Square) -> CastlingRights {
+        match piece {
+            Piece::KING => CastlingRights::for_color(color),
+            Piece::ROOK => self.rook_rights(color, s),
+            _ => CastlingRights::empty(),
+        }
+    }
+
+    /// Returns the castling right of `color`'s rook that starts on `s`, if any.
+    fn rook_rights(&self, color: Color, s: Square) -> CastlingRights {
+        [CastlingSide::KINGSIDE, CastlingSide::QUEENSIDE]
+            .into_iter()
+            .find(|&side| self.castling_rook(color, side) == s)
+            .map_or(CastlingRights::empty(), |side| {
+                CastlingRights::new(color, side)
+            })
+    }
+
+    /// Returns the square of `color`'s king, or `None` if it has no king.
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        let king =
+            self.piece_bitboards[Piece::KING as usize] & self.color_bitboards[color as usize];
+        Square::new(king.trailing_zeros() as u8)
+    }
+
+    /// Returns the summed [`Piece::value`] of `color`'s pieces, kept up to date as pieces are
+    /// captured instead of being counted from the bitboards on every call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color};
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/3PP3/R3K3 w - - 0 1").unwrap();
+    /// assert_eq!(game.material(Color::WHITE), 700);
+    /// assert_eq!(game.material(Color::BLACK), 0);
+    /// ```
+    pub fn material(&self, color: Color) -> i32 {
+        self.material[color as usize]
+    }
+
+    /// Returns how many pieces of type `piece` `color` has.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color, Piece};
+    /// let game = Game::default();
+    /// assert_eq!(game.piece_count(Color::BLACK, Piece::PAWN), 8);
+    /// assert_eq!(game.piece_count(Color::WHITE, Piece::QUEEN), 1);
+    /// ```
+    pub fn piece_count(&self, color: Color, piece: Piece) -> u32 {
+        self.piece_counts[color as usize][piece as usize] as u32
+    }
+
+    /// Returns the material signature of the position, the pieces of the side with more
+    /// material followed by those of the other, each from the king down to the pawns, e.g.
+    /// `KRPKR`. See [`material`](crate::material) for more.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// let game = Game::from_fen("4k3/4p3/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    /// assert_eq!(game.material_key(), "KQKP");
+    /// ```
+    pub fn material_key(&self) -> String {
+        let [white, black] = Color::both().map(|color| {
+            let pieces: String = Piece::ALL
+                .into_iter()
+                .rev()
+                .flat_map(|piece| {
+                    let count = self.piece_count(color, piece) as usize;
+                    std::iter::repeat_n(piece.to_char(Color::WHITE), count)
+                })
+                .collect();
+            let worth: i32 = Piece::ALL
+                .map(|piece| piece.value() * self.piece_count(color, piece) as i32)
+                .iter()
+                .sum();
+            (worth, pieces)
+        });
+        // Sides of equal worth are ordered by their pieces, so the colors never matter
+        let (stronger, weaker) = if white >= black {
+            (white, black)
+        } else {
+            (black, white)
+        };
+        stronger.1 + &weaker.1
+    }
+
+    /// Returns the summed piece-square table bonus of all pieces from white's point of view,
+    /// kept up to date as pieces move. See [`pst`] for the tables.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, pst::Score};
+    /// // The starting position is symmetric
+    /// assert_eq!(Game::default().pst(), Score::ZERO);
+    /// let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+    /// assert_eq!(game.pst().mg, 40);
+    /// ```
+    pub fn pst(&self) -> Score {
+        self.pst
+    }
+
+    /// Returns the hidden layer sums of the installed NNUE network, kept up to date as pieces
+    /// move. See [`nnue`](crate::nnue) for the network.
+    #[cfg(feature = "nnue")]
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.accumulator
+    }
+
+    /// Sums up the accumulator from scratch, for a position set up before the network was
+    /// installed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use kritisch::{game::Game, nnue::{self, Network}};
+    /// let mut game = Game::default();
+    /// let bytes = std::fs::read("kritisch.nnue").unwrap();
+    /// nnue::set_network(Network::from_bytes(&bytes).unwrap()).unwrap();
+    /// assert!(!game.accumulator().is_ready());
+    /// game.refresh_accumulator();
+    /// assert!(game.accumulator().is_ready());
+    /// ```
+    #[cfg(feature = "nnue")]
+    pub fn refresh_accumulator(&mut self) {
+        self.accumulator = Accumulator::new(self);
+    }
+
+    /// Returns the Zobrist key of the position, see [`zobrist`]. Positions that only differ in
+    /// their clocks share a key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let mut a = Game::default();
+    /// let mut b = Game::default();
+    /// for uci in ["g1f3", "g8f6", "b1c3"] {
+    ///     a.make_move(Move::from_uci(uci).unwrap());
+    /// }
+    /// for uci in ["b1c3", "g8f6", "g1f3"] {
+    ///     b.make_move(Move::from_uci(uci).unwrap());
+    /// }
+    /// assert_eq!(a.key(), b.key());
+    /// assert_ne!(a.key(), Game::default().key());
+    /// ```
+    pub fn key(&self) -> u64 {
+        let mut key =
+            self.piece_key ^ zobrist::side(self.to_move) ^ zobrist::castling(self.castling_rights);
+        if let Some(s) = self.keyed_en_passant_square() {
+            key ^= zobrist::en_passant(s);
+        }
+        key
+    }
+
+    /// Returns the en passant square if a pawn can take on it. Some FEN writers give the square
+    /// after every double step, which must not give the same position different keys.
+    fn keyed_en_passant_square(&self) -> Option<Square> {
+        self.en_passant_square
+            .filter(|&s| self.can_take_en_passant(s, !self.to_move))
+    }
+
+    /// Returns the name of the opening the position belongs to, if it is one of the named
+    /// lines of [`opening`] or on the way to one, whatever the move order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let mut game = Game::default();
+    /// for uci in ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"] {
+    ///     game.make_move(Move::from_uci(uci).unwrap());
+    /// }
+    /// assert_eq!(game.opening().unwrap().name, "Ruy Lopez");
+    /// ```
+    pub fn opening(&self) -> Option<&'static Opening> {
+        opening::lookup(self)
+    }
+
+    /// Returns how far the game has progressed from the opening to the endgame, judged by the
+    /// material left and the development of the knights and bishops.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, phase::GamePhase};
+    /// let game = Game::from_fen("8/5k2/8/3p4/3P4/8/5K2/8 w - - 0 50").unwrap();
+    /// assert_eq!(game.phase().kind, GamePhase::ENDGAME);
+    /// assert_eq!(game.phase().progress, 1.0);
+    /// ```
+    pub fn phase(&self) -> Phase {
+        Phase::of(self)
+    }
+
+    /// Returns the [`key`](Self::key) the position will have after `m`, without making the
+    /// move, e.g. to [prefetch](crate::search::tt::TranspositionTable::prefetch) its entry in
+    /// the transposition table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    /// let m = Move::from_uci("a1a8").unwrap();
+    /// let key = game.key_after(m);
+    /// game.make_move(m);
+    /// assert_eq!(game.key(), key);
+    /// ```
+    pub fn key_after(&self, m: Move) -> u64 {
+        let ColoredPiece { piece, color } = self
+            .moved_piece(m)
+            .expect("Tried to look ahead of a move from an empty square");
+        // Moving the duck is rare enough not to be worth looking ahead of
+        if m.duck.is_some() {
+            let mut after = *self;
+            after.make_move(m);
+            return after.key();
+        }
+        let mut rights = self.castling_rights;
+        let mut key = self.key()
+            ^ zobrist::side(self.to_move)
+            ^ zobrist::side(!self.to_move)
+            ^ zobrist::castling(rights);
+        let en_passant_squares = [
+            self.keyed_en_passant_square(),
+            self.en_passant_square_after(m, piece, color),
+        ];
+        for s in en_passant_squares.into_iter().flatten() {
+            key ^= zobrist::en_passant(s);
+        }
+        if m.is_drop() {
+            let dropped = ColoredPiece::new(piece, color);
+            let count = self.pocket(color, piece);
+            return key
+                ^ zobrist::piece(dropped, m.end)
+                ^ zobrist::pocket(dropped, count)
+                ^ zobrist::pocket(dropped, count - 1)
+                ^ zobrist::castling(rights);
+        }
+        if self.is_capture(m) {
+            // An explosion removes too many pieces to look ahead of
+            if self.rules().captures_explode() {
+                let mut after = *self;
+                after.make_move(m);
+                return after.key();
             }
-            Color::BLACK => {
-                if let Some(offset) = try_square_offset(square, -1, 1) {
-                    if (self.piece_bitboards[Piece::PAWN as usize]
-                        & self.color_bitboards[Color::BLACK as usize])
-                        .contains(offset)
-                    {
-                        return true;
-                    }
-                }
-                if let Some(offset) = try_square_offset(square, 1, 1) {
-                    if (self.piece_bitboards[Piece::PAWN as usize]
-                        & self.color_bitboards[Color::BLACK as usize])
-                        .contains(offset)
-                    {
-                        return true;
-                    }
-                }
+            let (s, captured) = self.captured_by(m, piece, color);
+            key ^= zobrist::piece(captured, s);
+            rights.remove(self.rights_lost_by_capture(captured.piece, captured.color, s));
+            if let Some(pocketed) = self.pocketed(s, captured) {
+                let count = self.pocket(pocketed.color, pocketed.piece);
+                key ^= zobrist::pocket(pocketed, count) ^ zobrist::pocket(pocketed, count + 1);
             }
         }
+        let mut end = m.end;
+        if self.is_castle(m, piece, color) {
+            let (king, rook) = self.castling_moves(m, color);
+            let rook_piece = ColoredPiece::new(Piece::ROOK, color);
+            key ^= zobrist::piece(rook_piece, rook.start) ^ zobrist::piece(rook_piece, rook.end);
+            end = king.end;
+        }
+        rights.remove(self.rights_lost_by_moving(piece, color, m.start));
+        let moved = ColoredPiece::new(piece, color);
+        let landed = ColoredPiece::new(m.promotion.unwrap_or(piece), color);
+        key ^ zobrist::piece(moved, m.start)
+            ^ zobrist::piece(landed, end)
+            ^ zobrist::castling(rights)
+    }
 
-        if self.is_attacked_by_knight(color, square) {
-            return true;
+    /// Returns `true` if the king of the side to move is attacked.
+    pub fn is_check(&self) -> bool {
+        match self.king_square(self.to_move) {
+            Some(s) => self.is_attacked_by(!self.to_move, s),
+            None => false,
+        }
+    }
+
+    /// Returns `true` if neither side has enough material left to ever deliver checkmate:
+    /// bare kings, a single minor piece, or only bishops on squares of one color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let count =
+            |piece| self.piece_count(Color::WHITE, piece) + self.piece_count(Color::BLACK, piece);
+        if count(Piece::PAWN) + count(Piece::ROOK) + count(Piece::QUEEN) > 0 {
+            return false;
         }
-        if self.is_attacked_by_king(color, square) {
+
+        let knights = count(Piece::KNIGHT);
+        if knights + count(Piece::BISHOP) <= 1 {
             return true;
         }
-        self.is_attacked_by_slider(color, square)
+
+        // Any number of bishops can't mate if they all stand on squares of the same color
+        const DARK_SQUARES: u64 = 0xaa55aa55aa55aa55;
+        let bishops = self.piece_bitboards[Piece::BISHOP as usize];
+        knights == 0
+            && ((bishops & DARK_SQUARES).is_empty() || (bishops & !DARK_SQUARES).is_empty())
+    }
+
+    /// Returns the rules of the game's [`variant`](Self::variant).
+    pub fn rules(&self) -> &'static dyn Rules {
+        self.variant.rules()
+    }
+
+    /// Returns why the game has ended by the rules of its variant, or `None` if it is still
+    /// ongoing. Repetitions are not detected, since `Game` doesn't keep a history of positions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::{Game, Termination};
+    /// // Fool's mate
+    /// let game = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    /// assert_eq!(game.termination(), Some(Termination::CHECKMATE));
+    /// ```
+    pub fn termination(&self) -> Option<Termination> {
+        variant::termination(self)
+    }
+
+    /// Returns the result of the game, or `None` if it is still ongoing.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.termination()? {
+            Termination::CHECKMATE | Termination::VARIANT_LOSS => Some(Outcome::DECISIVE {
+                winner: !self.to_move,
+            }),
+            Termination::VARIANT_WIN => Some(Outcome::DECISIVE {
+                winner: self.to_move,
+            }),
+            _ => Some(Outcome::DRAW),
+        }
+    }
+
+    /// Returns `true` if any piece of `color` attacks `square`.
+    /// This is a lookup in the cached attack maps, see
+    /// [`is_attacked_by_with`](Self::is_attacked_by_with) for other occupancies.
+    pub fn is_attacked_by(&self, color: Color, square: Square) -> bool {
+        self.attacks[color as usize].contains(square)
+    }
+
+    /// Returns `true` if any piece of `color` attacks `square` when exactly the squares in
+    /// `occupied` block sliders. Pieces of `color` outside of `occupied` don't attack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{bitboard::Bitboard, game::Game, Color, Square};
+    /// let game = Game::from_fen("4k3/4r3/8/8/8/4B3/8/4K3 w - - 0 1").unwrap();
+    /// assert!(!game.is_attacked_by_with(Color::BLACK, Square::E1, game.all_pieces()));
+    /// // Without the bishop in the way the rook reaches the king
+    /// let occupied = game.all_pieces() ^ Square::E3;
+    /// assert!(game.is_attacked_by_with(Color::BLACK, Square::E1, occupied));
+    /// ```
+    pub fn is_attacked_by_with(&self, color: Color, square: Square, occupied: Bitboard) -> bool {
+        !(self.attackers_to(square, occupied) & self.color_bitboards[color as usize]).is_empty()
+    }
+
+    /// Returns the pieces of either color that attack `square` when exactly the squares in
+    /// `occupied` block sliders, leaving out pieces that aren't in `occupied`.
+    ///
+    /// Works outward from `square` like a super-piece: the squares a knight, king, pawn or
+    /// slider on `square` would attack are exactly the squares such a piece attacks `square`
+    /// from, so one lookup per piece type replaces going through the attackers.
+    pub fn attackers_to(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        let pieces = |piece: Piece| self.piece_bitboards[piece as usize].0;
+        let target = square.to_u64();
+        let queens = pieces(Piece::QUEEN);
+        let rooks = pieces(Piece::ROOK) | queens;
+        let bishops = pieces(Piece::BISHOP) | queens;
+
+        let pawns = pieces(Piece::PAWN);
+        let mut attackers = pawn_attack_set(target, Color::BLACK)
+            & pawns
+            & self.color_bitboards[Color::WHITE as usize].0;
+        attackers |= pawn_attack_set(target, Color::WHITE)
+            & pawns
+            & self.color_bitboards[Color::BLACK as usize].0;
+        attackers |= pseudolegal_knight_moves(square).0 & pieces(Piece::KNIGHT);
+        attackers |= king_attack_set(target) & pieces(Piece::KING);
+        attackers |= rook_attacks(square, occupied).0 & rooks;
+        attackers |= bishop_attacks(square, occupied).0 & bishops;
+        Bitboard::from_u64(attackers & occupied.0)
     }
 
-    // Returns `true` if `square` can be reached by a knight of `color`.
-    fn is_attacked_by_knight(&self, color: Color, square: Square) -> bool {
-        // Since knight moves are fully symmetrical, get knight moves from `square`
-        let mut origins = pseudolegal_knight_moves(square);
-        while !origins.is_empty() {
-            let s = Square::from_u8(origins.trailing_zeros() as u8);
-            if (self.color_bitboards[color as usize] & self.piece_bitboards[Piece::KNIGHT as usize])
-                .contains(s)
+    /// Static exchange evaluation: returns the material `m` wins for the side making it if
+    /// both sides keep recapturing on `m.end` with their least valuable piece for as long as
+    /// that pays off. Quiet moves score what they lose by moving to an attacked square.
+    /// Pins are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move, Piece};
+    /// // The knight on d4 is defended by the pawn on e5
+    /// let game = Game::from_fen("4k3/8/8/4p3/3n4/8/3R4/3QK3 w - - 0 1").unwrap();
+    /// let rxd4 = Move::from_uci("d2d4").unwrap();
+    /// assert_eq!(game.see(rxd4), Piece::KNIGHT_VALUE - Piece::ROOK_VALUE + Piece::PAWN_VALUE);
+    /// // Without the pawn the knight is free
+    /// let game = Game::from_fen("4k3/8/8/8/3n4/8/3R4/3QK3 w - - 0 1").unwrap();
+    /// assert_eq!(game.see(rxd4), Piece::KNIGHT_VALUE);
+    /// ```
+    pub fn see(&self, m: Move) -> i32 {
+        let target = m.end;
+        // gains[d] is the material won by the side making the d-th capture, if the exchange
+        // stops right after it
+        let mut gains = [0; 32];
+        gains[0] = self.try_type_at(target).map_or(0, Piece::value);
+        let ColoredPiece {
+            piece: mut attacker,
+            color: mut side,
+        } = self
+            .moved_piece(m)
+            .expect("Tried to exchange from an empty square");
+        // A dropped piece comes from the pocket and stays on the board
+        let mut occupied = (self.blockers() ^ m.start) | m.end;
+        let mut d = 0;
+        loop {
+            d += 1;
+            side = !side;
+            gains[d] = attacker.value() - gains[d - 1];
+            let attackers = self.attackers_to(target, occupied);
+            let ours = attackers & self.color_bitboards[side as usize];
+            let Some(piece) = Piece::ALL
+                .into_iter()
+                .find(|&p| !(ours & self.piece_bitboards[p as usize]).is_empty())
+            else {
+                break;
+            };
+            // The king may only capture last
+            if piece == Piece::KING
+                && !(attackers & self.color_bitboards[!side as usize]).is_empty()
             {
-                return true;
+                break;
             }
-            origins.clear_lsb();
+            let from = (ours & self.piece_bitboards[piece as usize]).trailing_zeros();
+            occupied ^= Square::from_u8(from as u8);
+            attacker = piece;
+        }
+        while d > 1 {
+            d -= 1;
+            gains[d - 1] = -(-gains[d - 1]).max(gains[d]);
         }
-        false
+        gains[0]
     }
 
-    // Returns `true` if `square` can be reached by the king of `color`.
-    fn is_attacked_by_king(&self, color: Color, square: Square) -> bool {
-        // Since king moves are fully symmetrical, get knight moves from `square`
-        for (dx, dy) in [
-            (-1, -1),
-            (-1, 1),
-            (1, -1),
-            (1, -1),
-            (0, -1),
-            (0, 1),
-            (-1, 0),
-            (1, 0),
-        ] {
-            if let Some(s) = try_square_offset(square, dx, dy) {
-                if (self.piece_bitboards[Piece::KING as usize]
-                    & self.color_bitboards[color as usize])
-                    .contains(s)
-                {
-                    return true;
+    /// Returns the pieces of `color` the opponent wins material by capturing, i.e. those with
+    /// an attacker for which the capture comes out ahead by [`see`](Self::see), such as
+    /// undefended pieces or pieces attacked by something worth less. The king never hangs,
+    /// and like [`see`](Self::see) this ignores pins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{bitboard::Bitboard, game::Game, Color, Square};
+    /// let game = Game::from_fen("4k3/8/2n5/1B6/8/8/8/4K3 w - - 0 1").unwrap();
+    /// assert_eq!(game.hanging_pieces(Color::BLACK), Bitboard::from_square(Square::C6));
+    /// // Taking the knight defended by a pawn would cost the bishop
+    /// let game = Game::from_fen("4k3/1p6/2n5/1B6/8/8/8/4K3 w - - 0 1").unwrap();
+    /// assert!(game.hanging_pieces(Color::BLACK).is_empty());
+    /// ```
+    pub fn hanging_pieces(&self, color: Color) -> Bitboard {
+        let occupied = self.blockers();
+        let mut hanging = Bitboard::empty();
+        let mut pieces =
+            self.color_bitboards[color as usize] & !self.piece_bitboards[Piece::KING as usize];
+        while !pieces.is_empty() {
+            let target = Square::from_u8(pieces.trailing_zeros() as u8);
+            let attackers = self.attackers_to(target, occupied);
+            let defended = !(attackers & self.color_bitboards[color as usize]).is_empty();
+            let mut captures = attackers & self.color_bitboards[!color as usize];
+            while !captures.is_empty() {
+                let from = Square::from_u8(captures.trailing_zeros() as u8);
+                // The king can't take a defended piece at all, which the exchange doesn't know
+                let legal = !(defended && self.type_at(from) == Piece::KING);
+                if legal && self.see(Move::new(from, target)) > 0 {
+                    hanging |= target;
+                    break;
                 }
+                captures.clear_lsb();
             }
+            pieces.clear_lsb();
         }
-        false
+        hanging
     }
 
-    fn is_attacked_by_slider(&self, color: Color, square: Square) -> bool {
-        let blockers = get_blockers_from_position(&self, Piece::QUEEN, square);
-        let mut moves = Bitboard::from_u64(
-            ROOK_MOVES[magic_index(&ROOK_MAGICS[square as usize], blockers)]
-                | BISHOP_MOVES[magic_index(&BISHOP_MAGICS[square as usize], blockers)],
-        );
-        while !moves.is_empty() {
-            let s = Square::from_u8(moves.trailing_zeros() as u8);
-            if self.color_bitboards[color as usize].contains(s) {
-                if self.piece_bitboards[Piece::ROOK as usize].contains(s)
-                    || self.piece_bitboards[Piece::BISHOP as usize].contains(s)
-                    || self.piece_bitboards[Piece::QUEEN as usize].contains(s)
-                {
-                    return true;
+    /// Returns all squares attacked by `color`, including squares occupied by its own pieces.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color, Square};
+    /// let game = Game::default();
+    /// assert!(game.attacks(Color::WHITE).contains(Square::F3));
+    /// assert!(!game.attacks(Color::WHITE).contains(Square::E4));
+    /// ```
+    pub fn attacks(&self, color: Color) -> Bitboard {
+        self.attacks[color as usize]
+    }
+
+    /// Returns the squares attacked by the piece on `s`, or an empty bitboard if `s` is empty.
+    pub fn attacks_from(&self, s: Square) -> Bitboard {
+        self.piece_attacks[s as usize]
+    }
+
+    /// Returns the attackers of every square for both colors, e.g. for drawing a heatmap of
+    /// which side controls which part of the board. Like [`attacks`](Self::attacks), this
+    /// counts attacks on squares occupied by pieces of the same color, which defend them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color, Square};
+    /// let control = Game::default().square_control();
+    /// // The pawns on b2 and d2 and the knight on b1
+    /// assert_eq!(control.attackers(Square::C3, Color::WHITE).count_ones(), 3);
+    /// assert_eq!(control.net(Square::C3), 3);
+    /// assert_eq!(control.net(Square::D4), 0);
+    /// ```
+    pub fn square_control(&self) -> SquareControl {
+        let mut control = SquareControl {
+            attackers: [[Bitboard::empty(); 2]; 64],
+        };
+        for color in Color::both() {
+            let mut pieces = self.color_bitboards[color as usize];
+            while !pieces.is_empty() {
+                let from = Square::from_u8(pieces.trailing_zeros() as u8);
+                let mut attacked = self.attacks_from(from);
+                while !attacked.is_empty() {
+                    let s = attacked.trailing_zeros() as usize;
+                    control.attackers[s][color as usize] |= from;
+                    attacked.clear_lsb();
                 }
+                pieces.clear_lsb();
+            }
+        }
+        control
+    }
+
+    /// Computes all squares attacked by `color` from the bitboards alone, treating the pieces
+    /// of each type as one set instead of going through them one square at a time. This
+    /// matches [`attacks`](Self::attacks), but doesn't need the cached per-piece attacks and
+    /// is cheaper than building them when only the union is needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Color};
+    /// let game = Game::default();
+    /// assert_eq!(game.attacked_squares(Color::BLACK), game.attacks(Color::BLACK));
+    /// ```
+    pub fn attacked_squares(&self, color: Color) -> Bitboard {
+        let own = self.color_bitboards[color as usize];
+        let pieces = |piece: Piece| (own & self.piece_bitboards[piece as usize]).0;
+        let queens = pieces(Piece::QUEEN);
+
+        let mut attacks = pawn_attack_set(pieces(Piece::PAWN), color);
+
+        let knights = pieces(Piece::KNIGHT);
+        attacks |= ((knights << 17) | (knights >> 15)) & !FILE_A;
+        attacks |= ((knights << 15) | (knights >> 17)) & !FILE_H;
+        attacks |= ((knights << 10) | (knights >> 6)) & !(FILE_A | FILE_B);
+        attacks |= ((knights << 6) | (knights >> 10)) & !(FILE_G | FILE_H);
+
+        attacks |= king_attack_set(pieces(Piece::KING));
+
+        attacks |= sliding_attacks(
+            Bitboard::from_u64(pieces(Piece::ROOK) | queens),
+            Bitboard::from_u64(pieces(Piece::BISHOP) | queens),
+            self.blockers(),
+        )
+        .0;
+        Bitboard::from_u64(attacks)
+    }
+
+    /// Returns all squares attacked by `piece` standing on `s` in the current position.
+    pub(crate) fn compute_attacks(&self, s: Square, piece: ColoredPiece) -> Bitboard {
+        let occupied = self.blockers();
+        match piece.piece {
+            Piece::PAWN => Bitboard::from_u64(pawn_attack_set(s.to_u64(), piece.color)),
+            Piece::KNIGHT => pseudolegal_knight_moves(s),
+            Piece::BISHOP => bishop_attacks(s, occupied),
+            Piece::ROOK => rook_attacks(s, occupied),
+            Piece::QUEEN => rook_attacks(s, occupied) | bishop_attacks(s, occupied),
+            Piece::KING => Bitboard::from_u64(king_attack_set(s.to_u64())),
+        }
+    }
+
+    /// Returns the squares whose piece differs from the position given by `colors` and `pieces`.
+    fn changed_squares(&self, colors: [Bitboard; 2], pieces: [Bitboard; 6]) -> Bitboard {
+        let mut changed = Bitboard::empty();
+        for (old, new) in colors.iter().zip(self.color_bitboards) {
+            changed |= *old ^ new;
+        }
+        for (old, new) in pieces.iter().zip(self.piece_bitboards) {
+            changed |= *old ^ new;
+        }
+        changed
+    }
+
+    /// Updates the cached attack maps after the pieces on `changed` were added, removed or
+    /// replaced. Only the attacks of the changed pieces and of sliders whose rays pass through
+    /// a changed square are recomputed.
+    fn refresh_attacks(&mut self, changed: Bitboard) {
+        let mut squares = changed | self.blockers();
+        while !squares.is_empty() {
+            let s = Square::from_u8(squares.trailing_zeros() as u8);
+            squares.clear_lsb();
+
+            let piece = self.mailbox[s as usize];
+            let stale = changed.contains(s)
+                || (piece.is_some_and(|p| p.piece.is_slider())
+                    && !(self.piece_attacks[s as usize] & changed).is_empty());
+            if stale {
+                self.piece_attacks[s as usize] = match piece {
+                    Some(piece) => self.compute_attacks(s, piece),
+                    None => Bitboard::empty(),
+                };
+            }
+        }
+
+        for color in Color::both() {
+            let mut attacks = Bitboard::empty();
+            let mut pieces = self.color_bitboards[color as usize];
+            while !pieces.is_empty() {
+                attacks |= self.piece_attacks[pieces.trailing_zeros() as usize];
+                pieces.clear_lsb();
             }
-            moves.clear_lsb();
+            self.attacks[color as usize] = attacks;
         }
-        false
     }
 }
+
+const FILE_A: u64 = 0x0101010101010101;
+const FILE_B: u64 = FILE_A << 1;
+const FILE_G: u64 = FILE_A << 6;
+const FILE_H: u64 = FILE_A << 7;
+
+/// Returns the squares attacked by `color`'s pawns on `pawns`.
+fn pawn_attack_set(pawns: u64, color: Color) -> u64 {
+    let (east, west) = match color {
+        Color::WHITE => (pawns << 9, pawns << 7),
+        Color::BLACK => (pawns >> 7, pawns >> 9),
+    };
+    (east & !FILE_A) | (west & !FILE_H)
+}
+
+/// Returns the squares attacked by the kings on `kings`.
+fn king_attack_set(kings: u64) -> u64 {
+    let row = kings | ((kings << 1) & !FILE_A) | ((kings >> 1) & !FILE_H);
+    (row | (row << 8) | (row >> 8)) ^ kings
+}
+
+/// Returns the side the castling move `m` castles towards, the one its target lies on as seen
+/// from the king.
+fn castling_side(m: Move) -> CastlingSide {
+    if m.end.get_file() as u8 > m.start.get_file() as u8 {
+        CastlingSide::KINGSIDE
+    } else {
+        CastlingSide::QUEENSIDE
+    }
+}
+
+/// Returns `m` played backwards.
+fn reversed(m: Move) -> Move {
+    Move::new(m.end, m.start)
+}
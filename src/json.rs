@@ -0,0 +1,253 @@
+//! JSON export of positions for web frontends and of search output for scripts, enabled by
+//! the `json` feature.
+//!
+//! The format of positions is designed to be consumed directly by JavaScript chessboard
+//! widgets: `pieces` uses the chessboard.js position object format (`{"e1": "wK"}`) and
+//! `dests` matches chessground's map from origin square to destination squares. Drops only
+//! show up in `legalMoves`, e.g. `N@f3`, and `pockets` counts the pieces ready to be dropped.
+//! In Duck chess every move also names the duck's new square, e.g. `e2e4,e4d5`, which `dests`
+//! leaves out, and `duck` is the duck's square.
+//!
+//! Search output is one object per event, told apart by its `type`: `info` for a completed
+//! iteration and `bestmove` for the result. The [`uci`](crate::uci) front end writes them as
+//! JSON lines when its `JSON` option is set.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::{
+    game::{Game, Outcome, Termination},
+    movegen::all_legal_moves,
+    search::{mate_in, SearchInfo, SearchResult},
+    Color, Move, Piece, Square,
+};
+
+impl Game {
+    /// Returns a JSON representation of the position, including the piece map, legal moves
+    /// in UCI notation, check and termination status, and the clocks. In variants with
+    /// pockets `pockets` maps each color to its pieces in hand, e.g. `{"white": {"N": 1}}`,
+    /// and is `null` otherwise. `duck` is the square of the duck, or `null` without one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::game::Game;
+    /// let json = Game::default().to_json();
+    /// assert_eq!(json["pieces"]["e1"], "wK");
+    /// assert_eq!(json["turn"], "white");
+    /// assert_eq!(json["legalMoves"].as_array().unwrap().len(), 20);
+    /// assert_eq!(json["status"], "ongoing");
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut pieces = BTreeMap::new();
+        for s in Square::all() {
+            if let Some(piece) = self.piece_at(s) {
+                let color = match piece.color {
+                    Color::WHITE => 'w',
+                    Color::BLACK => 'b',
+                };
+                pieces.insert(s.to_string(), format!("{color}{}", piece.piece));
+            }
+        }
+
+        let moves = all_legal_moves(self);
+        let mut dests: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for m in moves.iter().filter(|m| !m.is_drop()) {
+            let ends = dests.entry(m.start.to_string()).or_default();
+            // Duck chess has the same move with the duck on every empty square
+            let end = m.end.to_string();
+            if !ends.contains(&end) {
+                ends.push(end);
+            }
+        }
+
+        let pockets = self.rules().has_pockets().then(|| {
+            let pocket = |color| {
+                Piece::ALL
+                    .into_iter()
+                    .filter(|&piece| self.pocket(color, piece) > 0)
+                    .map(|piece| (piece.to_string(), self.pocket(color, piece)))
+                    .collect::<BTreeMap<_, _>>()
+            };
+            json!({
+                "white": pocket(Color::WHITE),
+                "black": pocket(Color::BLACK),
+            })
+        });
+
+        let status = match self.termination() {
+            None => "ongoing",
+            Some(Termination::CHECKMATE) => "checkmate",
+            Some(Termination::STALEMATE) => "stalemate",
+            Some(Termination::FIFTY_MOVE_RULE) => "fiftyMoveRule",
+            Some(Termination::INSUFFICIENT_MATERIAL) => "insufficientMaterial",
+            Some(Termination::VARIANT_WIN | Termination::VARIANT_LOSS) => "variantEnd",
+            Some(Termination::VARIANT_DRAW) => "variantDraw",
+        };
+        let winner = match self.outcome() {
+            Some(Outcome::DECISIVE { winner }) => Value::from(winner.to_string()),
+            _ => Value::Null,
+        };
+
+        json!({
+            "pieces": pieces,
+            "turn": self.to_move.to_string(),
+            "legalMoves": moves.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+            "dests": dests,
+            "check": self.is_check(),
+            "status": status,
+            "winner": winner,
+            "castling": self.castling_rights.to_string(),
+            "enPassant": self.en_passant_square.map(|s| s.to_string()),
+            "pockets": pockets,
+            "duck": self.duck().map(|s| s.to_string()),
+            "halfmoveClock": self.halfmove_clock,
+            "fullmoveNumber": self.fullmove_clock,
+        })
+    }
+
+    /// Returns [`Game::to_json`] serialized to a compact string.
+    pub fn to_json_string(&self) -> String {
+        self.to_json().to_string()
+    }
+}
+
+impl SearchInfo {
+    /// Returns a JSON representation of the iteration, with the score as `{"cp": n}` or
+    /// `{"mate": n}`, the time in milliseconds and `wdl` as `[win, draw, loss]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use kritisch::{game::Game, search::{Searcher, SearchLimits}};
+    /// let (sender, receiver) = mpsc::channel();
+    /// let mut searcher = Searcher::default();
+    /// searcher.on_info(move |info| sender.send(info.to_json()).unwrap());
+    /// searcher.search(&Game::default(), SearchLimits::depth(2));
+    /// let json = receiver.recv().unwrap();
+    /// assert_eq!(json["type"], "info");
+    /// assert_eq!(json["depth"], 1);
+    /// ```
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": "info",
+            "depth": self.depth,
+            "seldepth": self.seldepth,
+            "score": score_json(self.score),
+            "nodes": self.nodes,
+            "nps": self.nps,
+            "timeMs": self.time.as_millis() as u64,
+            "hashfull": self.hashfull,
+            "wdl": [self.wdl.win, self.wdl.draw, self.wdl.loss],
+            "pv": moves_json(&self.pv),
+        })
+    }
+}
+
+impl SearchResult {
+    /// Returns a JSON representation of the result, with `bestMove` and the expected reply
+    /// `ponder` in UCI notation, or `null` if there is none.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": "bestmove",
+            "bestMove": self.best_move.map(|m| m.to_string()),
+            "ponder": self.pv.get(1).map(|m| m.to_string()),
+            "score": score_json(self.score),
+            "depth": self.depth,
+            "nodes": self.nodes,
+            "pv": moves_json(&self.pv),
+        })
+    }
+}
+
+fn score_json(score: i32) -> Value {
+    match mate_in(score) {
+        Some(moves) => json!({ "mate": moves }),
+        None => json!({ "cp": score }),
+    }
+}
+
+fn moves_json(moves: &[Move]) -> Value {
+    moves.iter().map(|m| m.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+
+    #[test]
+    fn json_default_position() {
+        let json = Game::default().to_json();
+        assert_eq!(json["pieces"].as_object().unwrap().len(), 32);
+        assert_eq!(json["pieces"]["d8"], "bQ");
+        assert_eq!(json["dests"]["g1"], serde_json::json!(["f3", "h3"]));
+        assert_eq!(json["check"], false);
+        assert_eq!(json["winner"], serde_json::Value::Null);
+        assert_eq!(json["castling"], "KQkq");
+        assert_eq!(json["enPassant"], serde_json::Value::Null);
+        assert_eq!(json["fullmoveNumber"], 1);
+    }
+
+    #[test]
+    fn json_checkmate() {
+        let game = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        let json = game.to_json();
+        assert_eq!(json["check"], true);
+        assert_eq!(json["status"], "checkmate");
+        assert_eq!(json["winner"], "black");
+        assert!(json["legalMoves"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_crazyhouse_pockets() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3[NNp] w - - 0 1").unwrap();
+        let json = game.to_json();
+        assert_eq!(
+            json["pockets"],
+            serde_json::json!({ "white": { "N": 2 }, "black": { "P": 1 } })
+        );
+        // Drops are no moves from a square
+        assert_eq!(json["dests"].as_object().unwrap().len(), 1);
+        assert!(json["dests"].get("a3").is_none());
+        let legal = json["legalMoves"].as_array().unwrap();
+        assert!(legal.contains(&serde_json::json!("N@a3")));
+        assert_eq!(
+            Game::default().to_json()["pockets"],
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn json_duck() {
+        let game =
+            Game::from_fen("rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let json = game.to_json();
+        assert_eq!(json["duck"], "e5");
+        assert_eq!(json["dests"]["e7"], serde_json::json!(["e6"]));
+        assert_eq!(json["dests"]["g8"], serde_json::json!(["f6", "h6"]));
+        assert!(json["legalMoves"].as_array().unwrap().len() > 19 * 30);
+        assert_eq!(Game::default().to_json()["duck"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn json_search_result() {
+        use crate::search::{search, SearchLimits};
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let json = search(&game, SearchLimits::depth(2)).to_json();
+        assert_eq!(json["type"], "bestmove");
+        assert_eq!(json["bestMove"], "a1a8");
+        assert_eq!(json["ponder"], serde_json::Value::Null);
+        assert_eq!(json["score"], serde_json::json!({ "mate": 1 }));
+        assert_eq!(json["pv"], serde_json::json!(["a1a8"]));
+    }
+
+    #[test]
+    fn json_string_parses() {
+        let s = Game::default().to_json_string();
+        let value: serde_json::Value = serde_json::from_str(&s).unwrap();
+        assert_eq!(value, Game::default().to_json());
+    }
+}
@@ -0,0 +1,176 @@
+//! Slider attacks with kindergarten bitboards: every line through a square is projected onto
+//! six index bits by one multiplication and looked up in two small tables of 4 KiB each, so
+//! no large magic tables are needed.
+
+use crate::{bitboard::Bitboard, Square};
+
+const FILE_A: u64 = 0x0101010101010101;
+const FILE_B: u64 = FILE_A << 1;
+const MAIN_DIAGONAL: u64 = 0x8040201008040201;
+const ANTI_DIAGONAL: u64 = 0x0102040810204080;
+/// Maps the inner squares of the a-file onto the six highest bits.
+const C2_H7_DIAGONAL: u64 = 0x0080402010080400;
+
+/// For every file and inner occupancy of a line, the attacks along the first rank, copied
+/// onto all eight ranks so the result can be masked with any rank or diagonal.
+static FILL_UP_ATTACKS: [[u64; 64]; 8] = fill_up_attacks();
+/// For every rank and inner occupancy of the a-file, the attacks along the a-file.
+static A_FILE_ATTACKS: [[u64; 64]; 8] = a_file_attacks();
+
+/// Returns the squares a rook on `square` attacks when the squares in `occupied` are blocked.
+#[inline]
+pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let s = square as u32;
+    let rank_mask = 0xff << (s & 56);
+    Bitboard::from_u64(line_attacks(s, occupied.0, rank_mask) | file_attacks(s, occupied.0))
+}
+
+/// Returns the squares a bishop on `square` attacks when the squares in `occupied` are
+/// blocked.
+#[inline]
+pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let s = square as u32;
+    Bitboard::from_u64(
+        line_attacks(s, occupied.0, diagonal_mask(s))
+            | line_attacks(s, occupied.0, anti_diagonal_mask(s)),
+    )
+}
+
+/// Returns the attacks along a rank or diagonal given by `mask`, which has at most one square
+/// per file.
+#[inline]
+fn line_attacks(s: u32, occupied: u64, mask: u64) -> u64 {
+    let index = ((mask & occupied).wrapping_mul(FILE_B) >> 58) as usize;
+    mask & FILL_UP_ATTACKS[(s & 7) as usize][index]
+}
+
+#[inline]
+fn file_attacks(s: u32, occupied: u64) -> u64 {
+    let file = s & 7;
+    let index = ((FILE_A & (occupied >> file)).wrapping_mul(C2_H7_DIAGONAL) >> 58) as usize;
+    A_FILE_ATTACKS[(s >> 3) as usize][index] << file
+}
+
+#[inline]
+fn diagonal_mask(s: u32) -> u64 {
+    let offset = (s & 7) as i32 - (s >> 3) as i32;
+    if offset >= 0 {
+        MAIN_DIAGONAL >> (offset * 8)
+    } else {
+        MAIN_DIAGONAL << (-offset * 8)
+    }
+}
+
+#[inline]
+fn anti_diagonal_mask(s: u32) -> u64 {
+    let offset = 7 - (s & 7) as i32 - (s >> 3) as i32;
+    if offset >= 0 {
+        ANTI_DIAGONAL >> (offset * 8)
+    } else {
+        ANTI_DIAGONAL << (-offset * 8)
+    }
+}
+
+/// Returns the attacks of a slider on `position` along a line of eight squares, where bit `i`
+/// of `occupied` marks square `i` of the line as blocked.
+const fn line_walk(position: u32, occupied: u32) -> u32 {
+    let mut attacks = 0;
+    let mut i = position + 1;
+    while i < 8 {
+        attacks |= 1 << i;
+        if occupied & (1 << i) != 0 {
+            break;
+        }
+        i += 1;
+    }
+    let mut i = position;
+    while i > 0 {
+        i -= 1;
+        attacks |= 1 << i;
+        if occupied & (1 << i) != 0 {
+            break;
+        }
+    }
+    attacks
+}
+
+const fn fill_up_attacks() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut file = 0;
+    while file < 8 {
+        let mut inner = 0;
+        while inner < 64 {
+            let occupied = (inner << 1) as u64;
+            let index = (occupied.wrapping_mul(FILE_B) >> 58) as usize;
+            table[file][index] = line_walk(file as u32, occupied as u32) as u64 * FILE_A;
+            inner += 1;
+        }
+        file += 1;
+    }
+    table
+}
+
+const fn a_file_attacks() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut rank = 0;
+    while rank < 8 {
+        let mut inner = 0;
+        while inner < 64 {
+            // Spread the six inner bits over a2 to a7
+            let mut occupied = 0u64;
+            let mut bit = 0;
+            while bit < 6 {
+                if inner & (1 << bit) != 0 {
+                    occupied |= 1 << ((bit + 1) * 8);
+                }
+                bit += 1;
+            }
+            let index = (occupied.wrapping_mul(C2_H7_DIAGONAL) >> 58) as usize;
+            let walked = line_walk(rank as u32, (inner << 1) as u32);
+            // Turn the walked ranks into squares on the a-file
+            let mut attacks = 0u64;
+            let mut r = 0;
+            while r < 8 {
+                if walked & (1 << r) != 0 {
+                    attacks |= 1 << (r * 8);
+                }
+                r += 1;
+            }
+            table[rank][index] = attacks;
+            inner += 1;
+        }
+        rank += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{magics::generate::slow_attacks, Piece};
+
+    #[test]
+    fn matches_ray_walk() {
+        // A simple xorshift generator for reproducible occupancies
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..1000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let occupied = state & state.rotate_left(23);
+            for s in Square::all() {
+                let bb = Bitboard::from_u64(occupied);
+                assert_eq!(
+                    rook_attacks(s, bb).0,
+                    slow_attacks(Piece::ROOK, s, occupied),
+                    "{s}"
+                );
+                assert_eq!(
+                    bishop_attacks(s, bb).0,
+                    slow_attacks(Piece::BISHOP, s, occupied),
+                    "{s}"
+                );
+            }
+        }
+    }
+}
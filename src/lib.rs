@@ -1,9 +1,72 @@
-#![feature(test)]
+/// Adds `n` (default 1) to a [`stats`] counter if the `stats` feature is enabled, and
+/// compiles to nothing otherwise.
+macro_rules! count {
+    ($counter:ident) => {
+        count!($counter, 1)
+    };
+    ($counter:ident, $n:expr) => {
+        #[cfg(feature = "stats")]
+        $crate::stats::COUNTERS
+            .$counter
+            .fetch_add($n as u64, std::sync::atomic::Ordering::Relaxed);
+    };
+}
 
 pub mod bitboard;
+pub mod book;
+pub mod cecp;
+pub mod codec;
+pub mod corpus;
+#[cfg(feature = "dataset")]
+pub mod dataset;
+pub mod error;
+pub mod eval;
+pub mod explorer;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod game;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "kindergarten")]
+mod kindergarten;
+#[cfg(feature = "json")]
+pub mod lichess;
 pub mod magics;
+pub mod matches;
+pub mod material;
+pub mod mobility;
 pub mod movegen;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod opening;
+pub mod pawns;
+pub mod perft;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+mod pext;
+pub mod pgn;
+pub mod phase;
+pub mod pst;
+pub mod puzzle;
+pub mod render;
+pub mod review;
+pub mod search;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+pub mod solve;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod tactics;
+pub mod uci;
+pub mod variant;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wdl;
+pub mod zobrist;
+
+pub use error::{Error, Result};
+// The UniFFI scaffolding looks for its tag at the crate root
+#[cfg(feature = "ffi")]
+use ffi::UniFfiTag;
 
 const PIECE_REPR_W: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
 const PIECE_REPR_B: [char; 6] = ['p', 'n', 'b', 'r', 'q', 'k'];
@@ -14,22 +77,62 @@ pub enum Color {
     BLACK = 1,
 }
 impl Color {
-    pub fn from_u8(v: u8) -> Self {
+    /// Returns the color with discriminant `v`, or `None` if `v` is out of range.
+    pub const fn new(v: u8) -> Option<Self> {
         match v {
-            0 => Self::WHITE,
-            1 => Self::BLACK,
-            _ => panic!(),
+            0 => Some(Self::WHITE),
+            1 => Some(Self::BLACK),
+            _ => None,
+        }
+    }
+
+    pub const fn from_u8(v: u8) -> Self {
+        match Self::new(v) {
+            Some(color) => color,
+            None => panic!("Color index out of range"),
+        }
+    }
+
+    /// Returns the other color.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Color;
+    /// assert_eq!(Color::WHITE.opposite(), Color::BLACK);
+    /// assert_eq!(!Color::BLACK, Color::WHITE);
+    /// ```
+    pub const fn opposite(self) -> Self {
+        match self {
+            Self::WHITE => Self::BLACK,
+            Self::BLACK => Self::WHITE,
         }
     }
+
+    /// Returns both colors, white first.
+    pub const fn both() -> [Self; 2] {
+        [Self::WHITE, Self::BLACK]
+    }
 }
-impl std::ops::BitXor<u8> for Color {
+impl std::ops::Not for Color {
     type Output = Self;
 
-    fn bitxor(self, rhs: u8) -> Self::Output {
-        Color::from_u8(self as u8 ^ rhs)
+    fn not(self) -> Self::Output {
+        self.opposite()
+    }
+}
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Color::WHITE => write!(f, "white"),
+            Color::BLACK => write!(f, "black"),
+        }
     }
 }
 
+/// The magic multiplier and table location for one square's slider attacks, see
+/// [`movegen::magic_index`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct MagicTableEntry {
     pub mask: u64,
     pub magic: u64,
@@ -47,50 +150,650 @@ pub enum Piece {
     KING = 5,
 }
 impl Piece {
-    pub fn from_char(c: &char) -> Self {
+    /// All piece types, in the order of their discriminants.
+    pub const ALL: [Self; 6] = [
+        Self::PAWN,
+        Self::KNIGHT,
+        Self::BISHOP,
+        Self::ROOK,
+        Self::QUEEN,
+        Self::KING,
+    ];
+
+    pub const PAWN_VALUE: i32 = 100;
+    pub const KNIGHT_VALUE: i32 = 320;
+    pub const BISHOP_VALUE: i32 = 330;
+    pub const ROOK_VALUE: i32 = 500;
+    pub const QUEEN_VALUE: i32 = 900;
+    /// The king is never traded, so it does not count towards material.
+    pub const KING_VALUE: i32 = 0;
+
+    /// Centipawn values indexed by piece discriminant.
+    pub const VALUES: [i32; 6] = [
+        Self::PAWN_VALUE,
+        Self::KNIGHT_VALUE,
+        Self::BISHOP_VALUE,
+        Self::ROOK_VALUE,
+        Self::QUEEN_VALUE,
+        Self::KING_VALUE,
+    ];
+
+    /// Returns the canonical centipawn value of the piece.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Piece;
+    /// assert_eq!(Piece::ROOK.value(), 500);
+    /// ```
+    pub const fn value(self) -> i32 {
+        Self::VALUES[self as usize]
+    }
+
+    /// Returns `true` for bishops, rooks and queens.
+    pub const fn is_slider(self) -> bool {
+        matches!(self, Self::BISHOP | Self::ROOK | Self::QUEEN)
+    }
+
+    /// Returns the FEN character for the piece, uppercase for white and lowercase for black.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Color, Piece};
+    /// assert_eq!(Piece::KNIGHT.to_char(Color::WHITE), 'N');
+    /// assert_eq!(Piece::KNIGHT.to_char(Color::BLACK), 'n');
+    /// ```
+    pub const fn to_char(self, color: Color) -> char {
+        match color {
+            Color::WHITE => PIECE_REPR_W[self as usize],
+            Color::BLACK => PIECE_REPR_B[self as usize],
+        }
+    }
+
+    /// Parses a piece letter in either case, returning `None` if `c` does not denote a piece.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Piece;
+    /// assert_eq!(Piece::try_from_char('N'), Some(Piece::KNIGHT));
+    /// assert_eq!(Piece::try_from_char('x'), None);
+    /// ```
+    pub fn try_from_char(c: char) -> Option<Self> {
         match c.to_ascii_lowercase() {
-            'p' => Self::PAWN,
-            'n' => Self::KNIGHT,
-            'b' => Self::BISHOP,
-            'r' => Self::ROOK,
-            'q' => Self::QUEEN,
-            'k' => Self::KING,
-            _ => panic!(),
-        }
-    }
-    pub fn from_u8(i: u8) -> Self {
-        match i {
-            0 => Self::PAWN,
-            1 => Self::KNIGHT,
-            2 => Self::BISHOP,
-            3 => Self::ROOK,
-            4 => Self::QUEEN,
-            5 => Self::KING,
-            _ => panic!(),
+            'p' => Some(Self::PAWN),
+            'n' => Some(Self::KNIGHT),
+            'b' => Some(Self::BISHOP),
+            'r' => Some(Self::ROOK),
+            'q' => Some(Self::QUEEN),
+            'k' => Some(Self::KING),
+            _ => None,
+        }
+    }
+
+    pub fn from_char(c: &char) -> Self {
+        Self::try_from_char(*c).unwrap_or_else(|| panic!("Unable to parse {c} to piece"))
+    }
+
+    /// Returns the piece with discriminant `i`, or `None` if `i` is out of range.
+    pub const fn new(i: u8) -> Option<Self> {
+        if (i as usize) < Self::ALL.len() {
+            Some(Self::ALL[i as usize])
+        } else {
+            None
+        }
+    }
+
+    pub const fn from_u8(i: u8) -> Self {
+        match Self::new(i) {
+            Some(piece) => piece,
+            None => panic!("Piece index out of range"),
+        }
+    }
+}
+
+/// Displays the piece as its uppercase letter, as used in SAN.
+impl std::fmt::Display for Piece {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_char(Color::WHITE))
+    }
+}
+
+/// A piece of a specific color, e.g. a black knight.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ColoredPiece {
+    pub piece: Piece,
+    pub color: Color,
+}
+impl ColoredPiece {
+    pub const fn new(piece: Piece, color: Color) -> Self {
+        Self { piece, color }
+    }
+
+    /// Parses a FEN piece character, uppercase for white and lowercase for black.
+    /// Returns `None` if `c` does not denote a piece.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Color, ColoredPiece, Piece};
+    /// let piece = ColoredPiece::from_char('q').unwrap();
+    /// assert_eq!(piece, ColoredPiece::new(Piece::QUEEN, Color::BLACK));
+    /// assert!(ColoredPiece::from_char('x').is_none());
+    /// ```
+    pub fn from_char(c: char) -> Option<Self> {
+        let color = if PIECE_REPR_W.contains(&c) {
+            Color::WHITE
+        } else if PIECE_REPR_B.contains(&c) {
+            Color::BLACK
+        } else {
+            return None;
+        };
+        Some(Self::new(Piece::try_from_char(c)?, color))
+    }
+
+    /// Returns the FEN character for the piece.
+    pub const fn to_char(self) -> char {
+        self.piece.to_char(self.color)
+    }
+
+    /// Returns the Unicode chess symbol for the piece, outlined for white and filled for black.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Color, ColoredPiece, Piece};
+    /// assert_eq!(ColoredPiece::new(Piece::KING, Color::WHITE).to_unicode(), '♔');
+    /// assert_eq!(ColoredPiece::new(Piece::PAWN, Color::BLACK).to_unicode(), '♟');
+    /// ```
+    pub const fn to_unicode(self) -> char {
+        match (self.color, self.piece) {
+            (Color::WHITE, Piece::KING) => '♔',
+            (Color::WHITE, Piece::QUEEN) => '♕',
+            (Color::WHITE, Piece::ROOK) => '♖',
+            (Color::WHITE, Piece::BISHOP) => '♗',
+            (Color::WHITE, Piece::KNIGHT) => '♘',
+            (Color::WHITE, Piece::PAWN) => '♙',
+            (Color::BLACK, Piece::KING) => '♚',
+            (Color::BLACK, Piece::QUEEN) => '♛',
+            (Color::BLACK, Piece::ROOK) => '♜',
+            (Color::BLACK, Piece::BISHOP) => '♝',
+            (Color::BLACK, Piece::KNIGHT) => '♞',
+            (Color::BLACK, Piece::PAWN) => '♟',
         }
     }
 }
+impl std::fmt::Display for ColoredPiece {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// The side of the board a king castles towards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CastlingSide {
+    KINGSIDE,
+    QUEENSIDE,
+}
 
-#[derive(Debug)]
-pub struct CastlingRights;
+bitflags::bitflags! {
+    /// The castling rights of both players, stored as one flag per color and side.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    pub struct CastlingRights: u8 {
+        const WHITE_KINGSIDE = 1;
+        const WHITE_QUEENSIDE = 2;
+        const BLACK_QUEENSIDE = 4;
+        const BLACK_KINGSIDE = 8;
+
+        const BOTH_KINGSIDES = Self::WHITE_KINGSIDE.bits() | Self::BLACK_KINGSIDE.bits();
+        const BOTH_QUEENSIDES = Self::WHITE_QUEENSIDE.bits() | Self::BLACK_QUEENSIDE.bits();
+        const WHITE_CASTLING = Self::WHITE_KINGSIDE.bits() | Self::WHITE_QUEENSIDE.bits();
+        const BLACK_CASTLING = Self::BLACK_KINGSIDE.bits() | Self::BLACK_QUEENSIDE.bits();
+        const ALL_LEGAL = Self::WHITE_CASTLING.bits() | Self::BLACK_CASTLING.bits();
+    }
+}
 impl CastlingRights {
-    pub const NO_LEGAL: u8 = 0;
-    pub const WHITE_KINGSIDE: u8 = 1;
-    pub const WHITE_QUEENSIDE: u8 = 2;
-    pub const BLACK_QUEENSIDE: u8 = 4;
-    pub const BLACK_KINGSIDE: u8 = 8;
-
-    pub const BOTH_KINGSIDES: u8 = Self::WHITE_KINGSIDE | Self::BLACK_KINGSIDE;
-    pub const BOTH_QUEENSIDES: u8 = Self::WHITE_QUEENSIDE | Self::BLACK_QUEENSIDE;
-    pub const WHITE_CASTLING: u8 = Self::WHITE_KINGSIDE | Self::WHITE_QUEENSIDE;
-    pub const BLACK_CASTLING: u8 = Self::BLACK_KINGSIDE | Self::BLACK_QUEENSIDE;
-    pub const ALL_LEGAL: u8 = Self::WHITE_CASTLING | Self::BLACK_CASTLING;
+    /// Returns the single right for `color` to castle towards `side`.
+    pub const fn new(color: Color, side: CastlingSide) -> Self {
+        match (color, side) {
+            (Color::WHITE, CastlingSide::KINGSIDE) => Self::WHITE_KINGSIDE,
+            (Color::WHITE, CastlingSide::QUEENSIDE) => Self::WHITE_QUEENSIDE,
+            (Color::BLACK, CastlingSide::KINGSIDE) => Self::BLACK_KINGSIDE,
+            (Color::BLACK, CastlingSide::QUEENSIDE) => Self::BLACK_QUEENSIDE,
+        }
+    }
+
+    /// Returns both castling rights of `color`.
+    pub const fn for_color(color: Color) -> Self {
+        match color {
+            Color::WHITE => Self::WHITE_CASTLING,
+            Color::BLACK => Self::BLACK_CASTLING,
+        }
+    }
+
+    /// Returns `true` if `color` still has the right to castle towards `side`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{CastlingRights, CastlingSide, Color};
+    /// let rights = CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE;
+    /// assert!(rights.allows(Color::WHITE, CastlingSide::KINGSIDE));
+    /// assert!(!rights.allows(Color::BLACK, CastlingSide::KINGSIDE));
+    /// ```
+    pub const fn allows(self, color: Color, side: CastlingSide) -> bool {
+        self.contains(Self::new(color, side))
+    }
+
+    /// Parses the castling rights section of a FEN string, e.g. `KQkq` or `-`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::CastlingRights;
+    /// assert_eq!(CastlingRights::from_fen("Kq").unwrap(), CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE);
+    /// assert_eq!(CastlingRights::from_fen("-").unwrap(), CastlingRights::empty());
+    /// assert!(CastlingRights::from_fen("KX").is_err());
+    /// ```
+    pub fn from_fen(s: &str) -> Result<Self> {
+        if s == "-" {
+            return Ok(Self::empty());
+        }
+        if s.is_empty() {
+            return Err(Error::InvalidFen("castling rights missing".to_string()));
+        }
+
+        let mut rights = Self::empty();
+        for c in s.chars() {
+            rights |= match c {
+                'K' => Self::WHITE_KINGSIDE,
+                'Q' => Self::WHITE_QUEENSIDE,
+                'k' => Self::BLACK_KINGSIDE,
+                'q' => Self::BLACK_QUEENSIDE,
+                _ => {
+                    return Err(Error::InvalidFen(format!(
+                        "unexpected character '{c}' in castling rights"
+                    )))
+                }
+            };
+        }
+        Ok(rights)
+    }
+}
+/// Displays the castling rights in FEN notation, e.g. `KQkq` or `-` if no rights are left.
+impl std::fmt::Display for CastlingRights {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+        for (flag, c) in [
+            (Self::WHITE_KINGSIDE, 'K'),
+            (Self::WHITE_QUEENSIDE, 'Q'),
+            (Self::BLACK_KINGSIDE, 'k'),
+            (Self::BLACK_QUEENSIDE, 'q'),
+        ] {
+            if self.contains(flag) {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Move {
     pub start: Square,
     pub end: Square,
+    /// The piece a pawn turns into on reaching the last rank, see [`Move::new_promotion`].
+    /// `None` for all other moves.
+    pub promotion: Option<Piece>,
+    /// The piece put on `end` from the pocket in Crazyhouse, see [`Move::new_drop`]. `None`
+    /// for a move of a piece on the board.
+    pub drop: Option<Piece>,
+    /// The square the duck is put on after the piece has moved in Duck chess, see
+    /// [`Move::with_duck`]. `None` in all other variants.
+    pub duck: Option<Square>,
+}
+impl Move {
+    /// Creates a move of the piece on `start` to `end`.
+    pub const fn new(start: Square, end: Square) -> Self {
+        Self {
+            start,
+            end,
+            promotion: None,
+            drop: None,
+            duck: None,
+        }
+    }
+
+    /// Creates a move of the pawn on `start` to `end` on the last rank, where it turns into
+    /// `piece`.
+    pub const fn new_promotion(start: Square, end: Square, piece: Piece) -> Self {
+        Self {
+            start,
+            end,
+            promotion: Some(piece),
+            drop: None,
+            duck: None,
+        }
+    }
+
+    /// Creates a drop of `piece` from the pocket on `square`, where both `start` and `end` are
+    /// `square`.
+    pub const fn new_drop(piece: Piece, square: Square) -> Self {
+        Self {
+            start: square,
+            end: square,
+            promotion: None,
+            drop: Some(piece),
+            duck: None,
+        }
+    }
+
+    /// Returns the move followed by putting the duck on `square`, as every move of
+    /// [Duck chess](crate::variant::duck) is.
+    pub const fn with_duck(self, square: Square) -> Self {
+        Self {
+            duck: Some(square),
+            ..self
+        }
+    }
+
+    /// Returns `true` if the move drops a piece from the pocket.
+    pub const fn is_drop(&self) -> bool {
+        self.drop.is_some()
+    }
+
+    /// Parses a move in UCI long algebraic notation, e.g. `e2e4` or the promotion `e7e8q`, or
+    /// a drop, e.g. `N@f3`. A move of Duck chess is followed by a comma and the duck's move from
+    /// the end of the move to its new square, e.g. `e2e4,e4d5`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Move, Piece, Square};
+    /// let m = Move::from_uci("g1f3").unwrap();
+    /// assert_eq!(m, Move::new(Square::G1, Square::F3));
+    /// let promotion = Move::new_promotion(Square::E7, Square::E8, Piece::QUEEN);
+    /// assert_eq!(Move::from_uci("e7e8q").unwrap(), promotion);
+    /// assert_eq!(Move::from_uci("N@f3").unwrap(), Move::new_drop(Piece::KNIGHT, Square::F3));
+    /// assert!(Move::from_uci("g1").is_err());
+    /// assert!(Move::from_uci("K@f3").is_err());
+    /// assert!(Move::from_uci("e7e8p").is_err());
+    /// let duck = Move::new(Square::E2, Square::E4).with_duck(Square::D5);
+    /// assert_eq!(Move::from_uci("e2e4,e4d5").unwrap(), duck);
+    /// assert!(Move::from_uci("e2e4,e5d5").is_err());
+    /// ```
+    pub fn from_uci(s: &str) -> Result<Self> {
+        if let Some((m, duck)) = s.split_once(',') {
+            let m = Self::from_uci(m)?;
+            let duck = Self::from_uci(duck)
+                .ok()
+                .filter(|duck| duck.start == m.end && duck.promotion.is_none() && !duck.is_drop())
+                .ok_or_else(|| {
+                    Error::InvalidUci(format!("expected the duck to move like 'e4d5' in '{s}'"))
+                })?;
+            return Ok(m.with_duck(duck.end));
+        }
+        let chars: Vec<char> = s.chars().collect();
+        let (a, b, c, d, promotion) = match *chars.as_slice() {
+            [a, b, c, d] => (a, b, c, d, None),
+            [a, b, c, d, promotion] if b != '@' => (a, b, c, d, Some(promotion)),
+            _ => {
+                return Err(Error::InvalidUci(format!(
+                    "expected a move like 'e2e4', got '{s}'"
+                )))
+            }
+        };
+        let square =
+            |c, d| Square::from_parts(c, d).map_err(|e| Error::InvalidUci(format!("{s}: {e}")));
+        if b == '@' {
+            let piece = Piece::try_from_char(a)
+                .filter(|&piece| a.is_ascii_uppercase() && piece != Piece::KING)
+                .ok_or_else(|| Error::InvalidUci(format!("can't drop '{a}' in '{s}'")))?;
+            return Ok(Self::new_drop(piece, square(&c, &d)?));
+        }
+        let (start, end) = (square(&a, &b)?, square(&c, &d)?);
+        match promotion {
+            None => Ok(Self::new(start, end)),
+            Some(p) => {
+                let piece = Piece::try_from_char(p)
+                    .filter(|&piece| piece != Piece::PAWN)
+                    .ok_or_else(|| Error::InvalidUci(format!("can't promote to '{p}' in '{s}'")))?;
+                Ok(Self::new_promotion(start, end, piece))
+            }
+        }
+    }
+
+    /// Returns the legal move in `before` that leads to `after`, e.g. to recover the moves of
+    /// a game that comes as a sequence of FENs, or `None` if there is none. Only the pieces on
+    /// the board, the duck and the side to move are compared, so the castling rights, the en
+    /// passant square and the clocks of `after` needn't match what the move leaves.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, Move};
+    /// let before = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    /// let after = Game::from_fen("r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1").unwrap();
+    /// assert_eq!(Move::infer(&before, &after), Some(Move::from_uci("e1g1").unwrap()));
+    /// assert_eq!(Move::infer(&after, &before), None);
+    /// ```
+    pub fn infer(before: &game::Game, after: &game::Game) -> Option<Self> {
+        movegen::all_legal_moves(before).into_iter().find(|&m| {
+            let mut position = *before;
+            position.make_move(m);
+            position.color_bitboards == after.color_bitboards
+                && position.piece_bitboards == after.piece_bitboards
+                && position.duck() == after.duck()
+                && position.to_move == after.to_move
+        })
+    }
+}
+/// Displays the move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`, or a drop as e.g.
+/// `N@f3`, followed by the duck's move in Duck chess, e.g. `e2e4,e4d5`.
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(piece) = self.drop {
+            write!(f, "{piece}@{}", self.end)?;
+        } else {
+            write!(f, "{}{}", self.start, self.end)?;
+        }
+        if let Some(piece) = self.promotion {
+            write!(f, "{}", piece.to_char(Color::BLACK))?;
+        }
+        if let Some(duck) = self.duck {
+            write!(f, ",{}{duck}", self.end)?;
+        }
+        Ok(())
+    }
+}
+
+/// No legal chess position has more than 218 moves, and Crazyhouse positions, where every
+/// piece in the pocket can be dropped on every empty square, stay well below 512, so a
+/// [`MoveList`] of this capacity doesn't allocate during move generation. Only Duck chess,
+/// where every move is combined with every square the duck can go to, needs more.
+pub const MAX_MOVES: usize = 512;
+
+/// A list of moves stored inline with a capacity of [`MAX_MOVES`], so generating moves doesn't
+/// allocate. Past that it moves its moves to the heap. Dereferences to a slice of the moves it
+/// holds.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{Move, MoveList, Square};
+/// let mut moves = MoveList::new();
+/// moves.push(Move::new(Square::E2, Square::E4));
+/// moves.push(Move::new(Square::D2, Square::D4));
+/// moves.retain(|m| m.start == Square::D2);
+/// assert_eq!(moves.len(), 1);
+/// assert_eq!(moves[0].end, Square::D4);
+/// ```
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+    /// All the moves once there are more than [`MAX_MOVES`], otherwise unused.
+    spilled: Vec<Move>,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            // A pawn drop on a1 that promotes to a pawn is all zero bytes, which makes filling
+            // the array a memset
+            moves: [Move {
+                promotion: Some(Piece::PAWN),
+                duck: Some(Square::A1),
+                ..Move::new_drop(Piece::PAWN, Square::A1)
+            }; MAX_MOVES],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    /// Appends `m` to the end of the list.
+    #[inline]
+    pub fn push(&mut self, m: Move) {
+        if self.len < MAX_MOVES {
+            self.moves[self.len] = m;
+        } else {
+            self.spill(m);
+        }
+        self.len += 1;
+    }
+
+    /// Appends `m` to a list that holds [`MAX_MOVES`] moves or more, moving them to the heap
+    /// first if they are still inline.
+    #[cold]
+    fn spill(&mut self, m: Move) {
+        if self.len == MAX_MOVES {
+            self.spilled.clear();
+            self.spilled.extend_from_slice(&self.moves);
+        }
+        self.spilled.push(m);
+    }
+
+    /// Keeps only the moves for which `f` returns `true`, preserving their order.
+    pub fn retain(&mut self, mut f: impl FnMut(&Move) -> bool) {
+        if self.len > MAX_MOVES {
+            self.spilled.retain(f);
+            self.len = self.spilled.len();
+            if self.len <= MAX_MOVES {
+                self.moves[..self.len].copy_from_slice(&self.spilled);
+            }
+            return;
+        }
+        let mut kept = 0;
+        for i in 0..self.len {
+            let m = self.moves[i];
+            if f(&m) {
+                self.moves[kept] = m;
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn as_slice(&self) -> &[Move] {
+        if self.len <= MAX_MOVES {
+            &self.moves[..self.len]
+        } else {
+            &self.spilled
+        }
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        self.as_slice()
+    }
+}
+
+impl std::ops::DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        if self.len <= MAX_MOVES {
+            &mut self.moves[..self.len]
+        } else {
+            &mut self.spilled
+        }
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for MoveList {}
+
+impl PartialEq<Vec<Move>> for MoveList {
+    fn eq(&self, other: &Vec<Move>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<const N: usize> PartialEq<[Move; N]> for MoveList {
+    fn eq(&self, other: &[Move; N]) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        let mut moves = Self::new();
+        for m in iter {
+            moves.push(m);
+        }
+        moves
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Chain<
+        std::iter::Take<std::array::IntoIter<Move, MAX_MOVES>>,
+        std::vec::IntoIter<Move>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // Only one of the two is non-empty
+        if self.len <= MAX_MOVES {
+            self.moves.into_iter().take(self.len).chain(Vec::new())
+        } else {
+            self.moves.into_iter().take(0).chain(self.spilled)
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -105,19 +808,93 @@ pub enum Rank {
     EIGHTH = 7,
 }
 impl Rank {
-    pub fn from_u8(r: u8) -> Self {
+    /// Returns the rank with index `r` (0 for the first rank), or `None` if `r` is out of range.
+    pub const fn new(r: u8) -> Option<Self> {
         match r {
-            0 => Self::FIRST,
-            1 => Self::SECOND,
-            2 => Self::THIRD,
-            3 => Self::FOURTH,
-            4 => Self::FIFTH,
-            5 => Self::SIXTH,
-            6 => Self::SEVENTH,
-            7 => Self::EIGHTH,
-            _ => panic!(),
+            0 => Some(Self::FIRST),
+            1 => Some(Self::SECOND),
+            2 => Some(Self::THIRD),
+            3 => Some(Self::FOURTH),
+            4 => Some(Self::FIFTH),
+            5 => Some(Self::SIXTH),
+            6 => Some(Self::SEVENTH),
+            7 => Some(Self::EIGHTH),
+            _ => None,
+        }
+    }
+
+    pub const fn from_u8(r: u8) -> Self {
+        match Self::new(r) {
+            Some(rank) => rank,
+            None => panic!("Rank index out of range"),
         }
     }
+
+    /// Parses a rank digit ('1' to '8').
+    pub const fn from_char(c: char) -> Option<Self> {
+        if !c.is_ascii() {
+            return None;
+        }
+        Self::new((c as u8).wrapping_sub(b'1'))
+    }
+
+    /// Returns an iterator over all ranks, from the first to the eighth.
+    pub fn all() -> impl DoubleEndedIterator<Item = Rank> {
+        (0..8).map(Self::from_u8)
+    }
+
+    /// Returns the next rank towards the eighth rank, or `None` on the eighth rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Rank;
+    /// assert_eq!(Rank::FIRST.up(), Some(Rank::SECOND));
+    /// assert_eq!(Rank::EIGHTH.up(), None);
+    /// ```
+    pub const fn up(self) -> Option<Self> {
+        Self::new(self as u8 + 1)
+    }
+
+    /// Returns the next rank towards the first rank, or `None` on the first rank.
+    pub const fn down(self) -> Option<Self> {
+        match self {
+            Self::FIRST => None,
+            _ => Self::new(self as u8 - 1),
+        }
+    }
+
+    /// Returns the rank as seen from `color`'s side of the board, so that e.g. the
+    /// second rank relative to black is the seventh rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Color, Rank};
+    /// assert_eq!(Rank::SECOND.relative_to(Color::WHITE), Rank::SECOND);
+    /// assert_eq!(Rank::SECOND.relative_to(Color::BLACK), Rank::SEVENTH);
+    /// ```
+    pub const fn relative_to(self, color: Color) -> Self {
+        match color {
+            Color::WHITE => self,
+            Color::BLACK => Self::from_u8(7 - self as u8),
+        }
+    }
+
+    /// Returns an iterator over the squares on this rank, from the a-file to the h-file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Rank, Square};
+    /// let squares: Vec<Square> = Rank::EIGHTH.squares().collect();
+    /// assert_eq!(squares.first(), Some(&Square::A8));
+    /// assert_eq!(squares.last(), Some(&Square::H8));
+    /// ```
+    pub fn squares(self) -> impl DoubleEndedIterator<Item = Square> {
+        let first = self as u8 * 8;
+        (first..first + 8).map(Square::from_u8)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -132,19 +909,86 @@ pub enum File {
     H = 7,
 }
 impl File {
-    pub fn from_u8(f: u8) -> Self {
+    /// Returns the file with index `f` (0 for the a-file), or `None` if `f` is out of range.
+    pub const fn new(f: u8) -> Option<Self> {
         match f {
-            0 => Self::A,
-            1 => Self::B,
-            2 => Self::C,
-            3 => Self::D,
-            4 => Self::E,
-            5 => Self::F,
-            6 => Self::G,
-            7 => Self::H,
-            _ => panic!(),
+            0 => Some(Self::A),
+            1 => Some(Self::B),
+            2 => Some(Self::C),
+            3 => Some(Self::D),
+            4 => Some(Self::E),
+            5 => Some(Self::F),
+            6 => Some(Self::G),
+            7 => Some(Self::H),
+            _ => None,
         }
     }
+
+    pub const fn from_u8(f: u8) -> Self {
+        match Self::new(f) {
+            Some(file) => file,
+            None => panic!("File index out of range"),
+        }
+    }
+
+    /// Parses a lowercase file letter ('a' to 'h').
+    pub const fn from_char(c: char) -> Option<Self> {
+        if !c.is_ascii() {
+            return None;
+        }
+        Self::new((c as u8).wrapping_sub(b'a'))
+    }
+
+    /// Returns an iterator over all files, from the a-file to the h-file.
+    pub fn all() -> impl DoubleEndedIterator<Item = File> {
+        (0..8).map(Self::from_u8)
+    }
+
+    /// Returns the neighboring file towards the h-file, or `None` on the h-file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::File;
+    /// assert_eq!(File::A.east(), Some(File::B));
+    /// assert_eq!(File::H.east(), None);
+    /// ```
+    pub const fn east(self) -> Option<Self> {
+        Self::new(self as u8 + 1)
+    }
+
+    /// Returns the neighboring file towards the a-file, or `None` on the a-file.
+    pub const fn west(self) -> Option<Self> {
+        match self {
+            Self::A => None,
+            _ => Self::new(self as u8 - 1),
+        }
+    }
+
+    /// Returns an iterator over the squares on this file, from the first to the eighth rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{File, Square};
+    /// let squares: Vec<Square> = File::E.squares().collect();
+    /// assert_eq!(squares.first(), Some(&Square::E1));
+    /// assert_eq!(squares.last(), Some(&Square::E8));
+    /// ```
+    pub fn squares(self) -> impl DoubleEndedIterator<Item = Square> {
+        (self as u8..64).step_by(8).map(Square::from_u8)
+    }
+}
+
+impl std::fmt::Display for Rank {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", (b'1' + *self as u8) as char)
+    }
+}
+impl std::fmt::Display for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", (b'a' + *self as u8) as char)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -215,114 +1059,241 @@ pub enum Square {
     H8 = 63,
 }
 impl Square {
-    pub fn from_parts(c: &char, d: &char) -> anyhow::Result<Self> {
-        let file = match c {
-            'a' => 0,
-            'b' => 1,
-            'c' => 2,
-            'd' => 3,
-            'e' => 4,
-            'f' => 5,
-            'g' => 6,
-            'h' => 7,
-            _ => anyhow::bail!("File for square out of bounds while parsing square from parts"),
-        };
+    pub fn from_parts(c: &char, d: &char) -> Result<Self> {
+        let file = File::from_char(*c)
+            .ok_or_else(|| Error::InvalidSquare(format!("file '{c}' out of bounds")))?;
+        let rank = Rank::from_char(*d)
+            .ok_or_else(|| Error::InvalidSquare(format!("rank '{d}' out of bounds")))?;
 
-        let rank = match d {
-            '1' => 0,
-            '2' => 1,
-            '3' => 2,
-            '4' => 3,
-            '5' => 4,
-            '6' => 5,
-            '7' => 6,
-            '8' => 7,
-            _ => anyhow::bail!("Rank for square out of bounds while parsing square from parts"),
-        };
+        Ok(Self::from_coords(file, rank))
+    }
 
-        Ok(Self::from_u8(file + rank * 8))
+    /// Returns the square at the intersection of `file` and `rank`.
+    pub const fn from_coords(file: File, rank: Rank) -> Self {
+        Self::from_u8(rank as u8 * 8 + file as u8)
     }
-    pub fn from_u8(v: u8) -> Self {
+    /// Returns the square with index `v` (0 for a1, 63 for h8), or `None` if `v` is out of range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// assert_eq!(Square::new(12), Some(Square::E2));
+    /// assert_eq!(Square::new(64), None);
+    /// ```
+    pub const fn new(v: u8) -> Option<Self> {
         match v {
-            0 => Self::A1,
-            1 => Self::B1,
-            2 => Self::C1,
-            3 => Self::D1,
-            4 => Self::E1,
-            5 => Self::F1,
-            6 => Self::G1,
-            7 => Self::H1,
-            8 => Self::A2,
-            9 => Self::B2,
-            10 => Self::C2,
-            11 => Self::D2,
-            12 => Self::E2,
-            13 => Self::F2,
-            14 => Self::G2,
-            15 => Self::H2,
-            16 => Self::A3,
-            17 => Self::B3,
-            18 => Self::C3,
-            19 => Self::D3,
-            20 => Self::E3,
-            21 => Self::F3,
-            22 => Self::G3,
-            23 => Self::H3,
-            24 => Self::A4,
-            25 => Self::B4,
-            26 => Self::C4,
-            27 => Self::D4,
-            28 => Self::E4,
-            29 => Self::F4,
-            30 => Self::G4,
-            31 => Self::H4,
-            32 => Self::A5,
-            33 => Self::B5,
-            34 => Self::C5,
-            35 => Self::D5,
-            36 => Self::E5,
-            37 => Self::F5,
-            38 => Self::G5,
-            39 => Self::H5,
-            40 => Self::A6,
-            41 => Self::B6,
-            42 => Self::C6,
-            43 => Self::D6,
-            44 => Self::E6,
-            45 => Self::F6,
-            46 => Self::G6,
-            47 => Self::H6,
-            48 => Self::A7,
-            49 => Self::B7,
-            50 => Self::C7,
-            51 => Self::D7,
-            52 => Self::E7,
-            53 => Self::F7,
-            54 => Self::G7,
-            55 => Self::H7,
-            56 => Self::A8,
-            57 => Self::B8,
-            58 => Self::C8,
-            59 => Self::D8,
-            60 => Self::E8,
-            61 => Self::F8,
-            62 => Self::G8,
-            63 => Self::H8,
-            _ => panic!("Unable to parse {v} to square"),
-        }
-    }
-
-    pub fn to_u64(self) -> u64 {
+            0 => Some(Self::A1),
+            1 => Some(Self::B1),
+            2 => Some(Self::C1),
+            3 => Some(Self::D1),
+            4 => Some(Self::E1),
+            5 => Some(Self::F1),
+            6 => Some(Self::G1),
+            7 => Some(Self::H1),
+            8 => Some(Self::A2),
+            9 => Some(Self::B2),
+            10 => Some(Self::C2),
+            11 => Some(Self::D2),
+            12 => Some(Self::E2),
+            13 => Some(Self::F2),
+            14 => Some(Self::G2),
+            15 => Some(Self::H2),
+            16 => Some(Self::A3),
+            17 => Some(Self::B3),
+            18 => Some(Self::C3),
+            19 => Some(Self::D3),
+            20 => Some(Self::E3),
+            21 => Some(Self::F3),
+            22 => Some(Self::G3),
+            23 => Some(Self::H3),
+            24 => Some(Self::A4),
+            25 => Some(Self::B4),
+            26 => Some(Self::C4),
+            27 => Some(Self::D4),
+            28 => Some(Self::E4),
+            29 => Some(Self::F4),
+            30 => Some(Self::G4),
+            31 => Some(Self::H4),
+            32 => Some(Self::A5),
+            33 => Some(Self::B5),
+            34 => Some(Self::C5),
+            35 => Some(Self::D5),
+            36 => Some(Self::E5),
+            37 => Some(Self::F5),
+            38 => Some(Self::G5),
+            39 => Some(Self::H5),
+            40 => Some(Self::A6),
+            41 => Some(Self::B6),
+            42 => Some(Self::C6),
+            43 => Some(Self::D6),
+            44 => Some(Self::E6),
+            45 => Some(Self::F6),
+            46 => Some(Self::G6),
+            47 => Some(Self::H6),
+            48 => Some(Self::A7),
+            49 => Some(Self::B7),
+            50 => Some(Self::C7),
+            51 => Some(Self::D7),
+            52 => Some(Self::E7),
+            53 => Some(Self::F7),
+            54 => Some(Self::G7),
+            55 => Some(Self::H7),
+            56 => Some(Self::A8),
+            57 => Some(Self::B8),
+            58 => Some(Self::C8),
+            59 => Some(Self::D8),
+            60 => Some(Self::E8),
+            61 => Some(Self::F8),
+            62 => Some(Self::G8),
+            63 => Some(Self::H8),
+            _ => None,
+        }
+    }
+
+    pub const fn from_u8(v: u8) -> Self {
+        match Self::new(v) {
+            Some(square) => square,
+            None => panic!("Square index out of range"),
+        }
+    }
+
+    /// Returns an iterator over all 64 squares, from a1 to h8.
+    pub fn all() -> impl DoubleEndedIterator<Item = Square> {
+        (0..64).map(Self::from_u8)
+    }
+
+    /// Returns an iterator over the squares on the diagonal (a1-h8 direction) through `self`,
+    /// including `self`, from the lowest to the highest rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// let diagonal: Vec<Square> = Square::C1.diagonal().collect();
+    /// assert_eq!(diagonal, vec![Square::C1, Square::D2, Square::E3, Square::F4, Square::G5, Square::H6]);
+    /// ```
+    pub fn diagonal(self) -> impl Iterator<Item = Square> {
+        let back = (self.get_file() as u8).min(self.get_rank() as u8);
+        let start = self as u8 - back * 9;
+        let len = 8 - (start % 8).max(start / 8);
+        (0..len).map(move |i| Self::from_u8(start + i * 9))
+    }
+
+    /// Returns an iterator over the squares on the anti-diagonal (h1-a8 direction) through
+    /// `self`, including `self`, from the lowest to the highest rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// let anti_diagonal: Vec<Square> = Square::C1.anti_diagonal().collect();
+    /// assert_eq!(anti_diagonal, vec![Square::C1, Square::B2, Square::A3]);
+    /// ```
+    pub fn anti_diagonal(self) -> impl Iterator<Item = Square> {
+        let back = (7 - self.get_file() as u8).min(self.get_rank() as u8);
+        let start = self as u8 - back * 7;
+        let len = (start % 8).min(7 - start / 8) + 1;
+        (0..len).map(move |i| Self::from_u8(start + i * 7))
+    }
+
+    /// Mirrors `self` across the horizontal center line of the board, e.g. E2 becomes E7.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// assert_eq!(Square::E2.flip_vertical(), Square::E7);
+    /// assert_eq!(Square::H8.flip_vertical(), Square::H1);
+    /// ```
+    pub const fn flip_vertical(self) -> Self {
+        Self::from_u8(self as u8 ^ 56)
+    }
+
+    /// Returns `self` as seen from `color`'s side of the board.
+    /// Squares are unchanged for white and flipped vertically for black, so that
+    /// color-agnostic logic can be written from white's point of view.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{Color, Square};
+    /// assert_eq!(Square::E2.relative_to(Color::WHITE), Square::E2);
+    /// assert_eq!(Square::E2.relative_to(Color::BLACK), Square::E7);
+    /// ```
+    pub const fn relative_to(self, color: Color) -> Self {
+        match color {
+            Color::WHITE => self,
+            Color::BLACK => self.flip_vertical(),
+        }
+    }
+
+    pub const fn to_u64(self) -> u64 {
         1 << self as u8
     }
 
-    pub fn get_rank(self) -> Rank {
+    pub const fn get_rank(self) -> Rank {
         Rank::from_u8(self as u8 / 8)
     }
 
-    pub fn get_file(self) -> File {
+    pub const fn get_file(self) -> File {
         File::from_u8(self as u8 % 8)
     }
+
+    /// Returns the Chebyshev distance between `self` and `other`, i.e. the larger of
+    /// the rank and file distances.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// assert_eq!(Square::A1.distance(Square::H8), 7);
+    /// assert_eq!(Square::E4.distance(Square::F6), 2);
+    /// ```
+    pub const fn distance(self, other: Square) -> u8 {
+        let (file_dist, rank_dist) = self.file_rank_distance(other);
+        if file_dist > rank_dist {
+            file_dist
+        } else {
+            rank_dist
+        }
+    }
+
+    /// Returns the Manhattan (taxicab) distance between `self` and `other`, i.e. the sum of
+    /// the rank and file distances.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// assert_eq!(Square::A1.manhattan_distance(Square::H8), 14);
+    /// assert_eq!(Square::E4.manhattan_distance(Square::F6), 3);
+    /// ```
+    pub const fn manhattan_distance(self, other: Square) -> u8 {
+        let (file_dist, rank_dist) = self.file_rank_distance(other);
+        file_dist + rank_dist
+    }
+
+    /// Returns the number of moves a king on `self` needs to reach `other` on an otherwise
+    /// empty board. This is the same as the Chebyshev distance, but reads better in
+    /// endgame code (e.g. the rule of the square).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::Square;
+    /// assert_eq!(Square::E1.king_steps_between(Square::E8), 7);
+    /// assert_eq!(Square::E4.king_steps_between(Square::E4), 0);
+    /// ```
+    pub const fn king_steps_between(self, other: Square) -> u8 {
+        self.distance(other)
+    }
+
+    const fn file_rank_distance(self, other: Square) -> (u8, u8) {
+        let file_dist = (self.get_file() as u8).abs_diff(other.get_file() as u8);
+        let rank_dist = (self.get_rank() as u8).abs_diff(other.get_rank() as u8);
+        (file_dist, rank_dist)
+    }
 }
 impl std::ops::Add<u8> for Square {
     type Output = Self;
@@ -354,72 +1325,7 @@ impl std::ops::Sub<i8> for Square {
 }
 impl std::fmt::Display for Square {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Square::A1 => write!(f, "a1"),
-            Square::B1 => write!(f, "b1"),
-            Square::C1 => write!(f, "c1"),
-            Square::D1 => write!(f, "d1"),
-            Square::E1 => write!(f, "e1"),
-            Square::F1 => write!(f, "f1"),
-            Square::G1 => write!(f, "g1"),
-            Square::H1 => write!(f, "h1"),
-            Square::A2 => write!(f, "a2"),
-            Square::B2 => write!(f, "b2"),
-            Square::C2 => write!(f, "c2"),
-            Square::D2 => write!(f, "d2"),
-            Square::E2 => write!(f, "e2"),
-            Square::F2 => write!(f, "f2"),
-            Square::G2 => write!(f, "g2"),
-            Square::H2 => write!(f, "h2"),
-            Square::A3 => write!(f, "a3"),
-            Square::B3 => write!(f, "b3"),
-            Square::C3 => write!(f, "c3"),
-            Square::D3 => write!(f, "d3"),
-            Square::E3 => write!(f, "e3"),
-            Square::F3 => write!(f, "f3"),
-            Square::G3 => write!(f, "g3"),
-            Square::H3 => write!(f, "h3"),
-            Square::A4 => write!(f, "a4"),
-            Square::B4 => write!(f, "b4"),
-            Square::C4 => write!(f, "c4"),
-            Square::D4 => write!(f, "d4"),
-            Square::E4 => write!(f, "e4"),
-            Square::F4 => write!(f, "f4"),
-            Square::G4 => write!(f, "g4"),
-            Square::H4 => write!(f, "h4"),
-            Square::A5 => write!(f, "a5"),
-            Square::B5 => write!(f, "b5"),
-            Square::C5 => write!(f, "c5"),
-            Square::D5 => write!(f, "d5"),
-            Square::E5 => write!(f, "e5"),
-            Square::F5 => write!(f, "f5"),
-            Square::G5 => write!(f, "g5"),
-            Square::H5 => write!(f, "h5"),
-            Square::A6 => write!(f, "a6"),
-            Square::B6 => write!(f, "b6"),
-            Square::C6 => write!(f, "c6"),
-            Square::D6 => write!(f, "d6"),
-            Square::E6 => write!(f, "e6"),
-            Square::F6 => write!(f, "f6"),
-            Square::G6 => write!(f, "g6"),
-            Square::H6 => write!(f, "h6"),
-            Square::A7 => write!(f, "a7"),
-            Square::B7 => write!(f, "b7"),
-            Square::C7 => write!(f, "c7"),
-            Square::D7 => write!(f, "d7"),
-            Square::E7 => write!(f, "e7"),
-            Square::F7 => write!(f, "f7"),
-            Square::G7 => write!(f, "g7"),
-            Square::H7 => write!(f, "h7"),
-            Square::A8 => write!(f, "a8"),
-            Square::B8 => write!(f, "b8"),
-            Square::C8 => write!(f, "c8"),
-            Square::D8 => write!(f, "d8"),
-            Square::E8 => write!(f, "e8"),
-            Square::F8 => write!(f, "f8"),
-            Square::G8 => write!(f, "g8"),
-            Square::H8 => write!(f, "h8"),
-        }
+        write!(f, "{}{}", self.get_file(), self.get_rank())
     }
 }
 
@@ -435,14 +1341,14 @@ impl std::fmt::Display for Square {
 /// assert!(try_square_offset(square, 1, 0).is_none());
 /// assert_eq!(try_square_offset(square, 0, 1).unwrap(), Square::H8);
 /// ```
-pub fn try_square_offset(square: Square, dx: i8, dy: i8) -> Option<Square> {
+pub const fn try_square_offset(square: Square, dx: i8, dy: i8) -> Option<Square> {
     let square_idx = square as i8;
     let file = square_idx % 8;
     let rank = square_idx / 8;
     let new_file = file + dx;
     let new_rank = rank + dy;
 
-    if new_file >= 0 && new_file < 8 && new_rank >= 0 && new_rank < 8 {
+    if 0 <= new_file && new_file < 8 && 0 <= new_rank && new_rank < 8 {
         Some(Square::from_u8((new_rank * 8 + new_file) as u8))
     } else {
         None
@@ -597,7 +1503,12 @@ mod tests {
     }
 
     mod game {
-        use crate::{bitboard::Bitboard, game::Game, Color, Move, Piece, Square};
+        use crate::{
+            bitboard::Bitboard,
+            game::{Game, Outcome, Termination},
+            movegen::all_legal_moves,
+            Color, ColoredPiece, Error, Move, Piece, Square,
+        };
 
         #[test]
         fn game_from_fen() {
@@ -618,19 +1529,183 @@ mod tests {
         }
 
         #[test]
-        fn piece_type() {
-            let game = Game::default();
-            let black_king = game.type_at(Square::E8);
-            assert_eq!(black_king, Piece::KING);
-        }
-
-        #[test]
-        #[should_panic]
+        fn mailbox_matches_bitboards() {
+            let mut game = Game::from_fen(
+                "r3k2r/pp1n1ppp/2pbpn2/q2p4/2PP4/2NBPN2/PPQ2PPP/R3K2R w KQkq - 0 10",
+            )
+            .unwrap();
+            let assert_in_sync = |game: &Game| {
+                for s in Square::all() {
+                    assert_eq!(game.piece_at(s), game.piece_from_bitboards(s), "{s}");
+                }
+                for color in Color::both() {
+                    let mut material = 0;
+                    for piece in Piece::ALL {
+                        let count = (game.piece_bitboards[piece as usize]
+                            & game.color_bitboards[color as usize])
+                            .count_ones();
+                        assert_eq!(game.piece_count(color, piece), count, "{color:?} {piece:?}");
+                        material += count as i32 * piece.value();
+                    }
+                    assert_eq!(game.material(color), material);
+                }
+                let parsed = Game::from_fen(&game.to_fen()).unwrap();
+                assert_eq!(game.pst(), parsed.pst());
+                assert_eq!(game.key(), parsed.key());
+            };
+            // Castle on both sides, then prefer captures
+            for uci in ["e1g1", "e8c8"] {
+                game.make_move(Move::from_uci(uci).unwrap());
+                assert_in_sync(&game);
+            }
+            for _ in 0..30 {
+                let moves = all_legal_moves(&game);
+                let Some(&m) = moves
+                    .iter()
+                    .find(|m| game.is_capture(**m))
+                    .or(moves.first())
+                else {
+                    break;
+                };
+                game.make_move(m);
+                assert_in_sync(&game);
+            }
+        }
+
+        #[test]
+        fn unmake_restores_position() {
+            let mut game = Game::from_fen(
+                "r3k2r/pp1n1ppp/2pbpn2/q2p4/2PP4/2NBPN2/PPQ2PPP/R3K2R w KQkq - 0 10",
+            )
+            .unwrap();
+            for _ in 0..40 {
+                let moves = all_legal_moves(&game);
+                let Some(&m) = moves.iter().find(|m| game.is_capture(**m)).or(moves.last()) else {
+                    break;
+                };
+                let before = game;
+                let undo = game.make_move(m);
+                let after = game;
+                game.unmake_move(m, undo);
+                assert_eq!(game, before, "{m}");
+                game = after;
+            }
+        }
+
+        #[test]
+        fn key_after_matches_make_move() {
+            for fen in [
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                "1r3kr1/8/8/8/8/8/8/1R3KR1 w GBgb - 0 1",
+            ] {
+                let game = Game::from_fen(fen).unwrap();
+                for m in all_legal_moves(&game) {
+                    let mut child = game;
+                    child.make_move(m);
+                    assert_eq!(game.key_after(m), child.key(), "{m} in {fen}");
+                }
+            }
+        }
+
+        #[test]
+        fn attack_maps_stay_in_sync() {
+            let mut game = Game::from_fen(
+                "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
+            )
+            .unwrap();
+            for _ in 0..40 {
+                let moves = all_legal_moves(&game);
+                let Some(&m) = moves.iter().find(|m| game.is_capture(**m)).or(moves.last()) else {
+                    break;
+                };
+                game.make_move(m);
+                let fresh = Game::from_fen(&game.to_fen()).unwrap();
+                for color in Color::both() {
+                    assert_eq!(game.attacks(color), fresh.attacks(color), "{m}");
+                    assert_eq!(game.attacked_squares(color), game.attacks(color), "{m}");
+                }
+                for s in Square::all() {
+                    assert_eq!(game.attacks_from(s), fresh.attacks_from(s), "{m} {s}");
+                }
+            }
+        }
+
+        #[test]
+        fn attackers_to_matches_attack_maps() {
+            let game = Game::from_fen(
+                "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
+            )
+            .unwrap();
+            for target in Square::all() {
+                let mut expected = Bitboard::empty();
+                for s in Square::all() {
+                    if game.attacks_from(s).contains(target) {
+                        expected |= s;
+                    }
+                }
+                let attackers = game.attackers_to(target, game.all_pieces());
+                assert_eq!(attackers, expected, "{target}");
+            }
+        }
+
+        #[test]
+        fn slider_attacks_follow_their_own_lines() {
+            let game = Game::from_fen("4k3/8/8/8/3R4/8/8/4K2B w - - 0 1").unwrap();
+            assert!(game.is_attacked_by(Color::WHITE, Square::D8));
+            assert!(game.is_attacked_by(Color::WHITE, Square::A8));
+            // Neither the rook attacks diagonally nor the bishop along ranks
+            assert!(!game.is_attacked_by(Color::WHITE, Square::E5));
+            assert!(!game.is_attacked_by(Color::WHITE, Square::A1));
+            assert!(!game.is_attacked_by(Color::BLACK, Square::E6));
+        }
+
+        #[test]
+        fn fen_roundtrip() {
+            for fen in [
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
+                "8/8/4k3/8/8/4K3/8/8 b - - 99 140",
+                "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 3 20",
+            ] {
+                assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+            }
+        }
+
+        #[test]
+        fn piece_type() {
+            let game = Game::default();
+            let black_king = game.type_at(Square::E8);
+            assert_eq!(black_king, Piece::KING);
+        }
+
+        #[test]
+        #[should_panic]
         fn piece_type_empty_square() {
             let game = Game::default();
             let _ = game.type_at(Square::E6);
         }
 
+        #[test]
+        fn piece_at() {
+            let game = Game::default();
+            assert_eq!(
+                game.piece_at(Square::D1),
+                Some(ColoredPiece::new(Piece::QUEEN, Color::WHITE))
+            );
+            assert_eq!(game.piece_at(Square::D4), None);
+        }
+
+        #[test]
+        fn try_accessors() {
+            let game = Game::default();
+            assert_eq!(game.try_type_at(Square::G8), Some(Piece::KNIGHT));
+            assert_eq!(game.try_color_at(Square::G8), Some(Color::BLACK));
+            assert_eq!(game.try_type_at(Square::G6), None);
+            assert_eq!(game.try_color_at(Square::G6), None);
+        }
+
         #[test]
         fn all_pieces() {
             let game = Game::default();
@@ -668,10 +1743,7 @@ mod tests {
         #[test]
         fn make_move_legal() {
             let mut game = Game::default();
-            let m = Move {
-                start: Square::E2,
-                end: Square::E3,
-            };
+            let m = Move::new(Square::E2, Square::E3);
             game.make_move(m);
             assert_eq!(game.all_pieces().0, 0xffff00000010efff);
             assert_eq!(game.to_move, Color::BLACK);
@@ -680,24 +1752,61 @@ mod tests {
             assert_eq!(game.fullmove_clock, 1);
         }
 
-        /* #[test]
+        #[test]
         fn make_move_illegal() {
             let mut game = Game::default();
-            let m = Move {
-                start: Square::E2,
-                end: Square::F2,
-            };
-            let res = game.make_move(m);
+            let m = Move::new(Square::E2, Square::F2);
+            let res = game.try_make_move(m);
             assert!(res.is_err());
-        } */
+            assert_eq!(game, Game::default());
+        }
+
+        #[test]
+        fn premoves() {
+            // The rook on a8 may stay, and Black can leave c1 and d1 unattacked
+            let game = Game::from_fen("r3k3/8/8/8/8/8/8/R3K3 b Qq - 0 1").unwrap();
+            let plausible = |uci| game.is_plausible_premove(Move::from_uci(uci).unwrap());
+            assert!(plausible("a1a8"));
+            assert!(plausible("e1c1"));
+            assert!(!plausible("e1e3"));
+            assert!(!plausible("a8a1"));
+            // No premove is plausible once the game is over
+            let mated = Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+            assert!(!mated.is_plausible_premove(Move::from_uci("a8b8").unwrap()));
+        }
+
+        #[test]
+        fn fen_errors() {
+            for fen in [
+                "",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+                "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1",
+            ] {
+                assert!(
+                    matches!(Game::from_fen(fen), Err(Error::InvalidFen(_))),
+                    "{fen}"
+                );
+            }
+        }
+
+        #[test]
+        fn validate_position() {
+            assert!(Game::default().validate().is_ok());
+            let no_king = Game::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+            assert!(matches!(no_king.validate(), Err(Error::InvalidPosition(_))));
+            let pawn_on_back_rank = Game::from_fen("P3k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+            assert!(pawn_on_back_rank.validate().is_err());
+        }
 
         #[test]
         fn make_move_capture() {
             let mut game = Game::default();
-            let m = Move {
-                start: Square::E2,
-                end: Square::E7,
-            };
+            let m = Move::new(Square::E2, Square::E7);
             game.make_move(m);
             assert_eq!(game.all_pieces().0, 18446462598732902399);
             assert_eq!(game.to_move, Color::BLACK);
@@ -706,6 +1815,54 @@ mod tests {
             assert_eq!(game.fullmove_clock, 1);
         }
 
+        #[test]
+        fn checkmate_status() {
+            // Fool's mate
+            let game =
+                Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                    .unwrap();
+            assert!(game.is_check());
+            assert_eq!(game.termination(), Some(Termination::CHECKMATE));
+            assert_eq!(
+                game.outcome(),
+                Some(Outcome::DECISIVE {
+                    winner: Color::BLACK
+                })
+            );
+        }
+
+        #[test]
+        fn draw_status() {
+            let stalemate = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+            assert!(!stalemate.is_check());
+            assert_eq!(stalemate.termination(), Some(Termination::STALEMATE));
+            assert_eq!(stalemate.outcome(), Some(Outcome::DRAW));
+
+            let fifty_moves = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+            assert_eq!(
+                fifty_moves.termination(),
+                Some(Termination::FIFTY_MOVE_RULE)
+            );
+
+            for fen in [
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+                "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+                "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            ] {
+                let game = Game::from_fen(fen).unwrap();
+                assert_eq!(
+                    game.termination(),
+                    Some(Termination::INSUFFICIENT_MATERIAL),
+                    "{fen}"
+                );
+            }
+
+            // Opposite-colored bishops can still mate
+            let game = Game::from_fen("4k3/8/8/8/8/8/8/2B1Kb2 w - - 0 1").unwrap();
+            assert_eq!(game.termination(), None);
+            assert_eq!(Game::default().outcome(), None);
+        }
+
         #[test]
         fn attackers_from_fen() {
             let game =
@@ -713,13 +1870,201 @@ mod tests {
                     .unwrap();
             assert!(game.is_attacked_by(Color::WHITE, Square::B5));
         }
+
+        #[test]
+        fn square_control_matches_attackers_to() {
+            let game = Game::from_fen(
+                "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
+            )
+            .unwrap();
+            let control = game.square_control();
+            for s in Square::all() {
+                let attackers = game.attackers_to(s, game.all_pieces());
+                for color in Color::both() {
+                    let expected = attackers & game.color_bitboards[color as usize];
+                    assert_eq!(control.attackers(s, color), expected, "{s}");
+                }
+            }
+            // The pawn on e4 and the knight on c3 against the pawn on e6 and the queen
+            assert_eq!(control.net(Square::D5), 0);
+            assert!(!control.controlled(Color::BLACK).contains(Square::D5));
+            // The knight on f3 against the knight on c6 and the bishop on g7
+            assert_eq!(control.net(Square::E5), -1);
+            assert!(control.controlled(Color::BLACK).contains(Square::E5));
+            assert!(!control.controlled(Color::WHITE).contains(Square::E5));
+        }
+
+        #[test]
+        fn hanging_pieces() {
+            let hanging =
+                |fen: &str, color: Color| Game::from_fen(fen).unwrap().hanging_pieces(color);
+            assert!(Game::default().hanging_pieces(Color::WHITE).is_empty());
+            // A defended bishop still hangs to a pawn
+            assert_eq!(
+                hanging("4k3/8/3p4/4B2R/8/8/8/4K3 w - - 0 1", Color::WHITE),
+                Bitboard::from_square(Square::E5)
+            );
+            // The king may only take undefended pieces, and never hangs itself
+            assert_eq!(
+                hanging("8/8/8/8/8/3k4/3P4/6K1 w - - 0 1", Color::WHITE),
+                Bitboard::from_square(Square::D2)
+            );
+            assert!(hanging("8/8/8/8/8/3k4/3P4/3RK3 w - - 0 1", Color::WHITE).is_empty());
+            assert!(hanging("8/8/8/8/8/3k4/3P4/3RK3 w - - 0 1", Color::BLACK).is_empty());
+        }
+
+        #[test]
+        fn static_exchange() {
+            let see = |fen: &str, uci: &str| {
+                Game::from_fen(fen)
+                    .unwrap()
+                    .see(Move::from_uci(uci).unwrap())
+            };
+            // The rook on d1 backs up the one on d2 through it
+            assert_eq!(see("3r2k1/8/8/3n4/8/8/3R4/3R2K1 w - - 0 1", "d2d5"), 320);
+            // The king may only recapture pieces that aren't defended
+            assert_eq!(see("4k3/8/8/8/8/8/3q4/3RK3 b - - 0 1", "d2d1"), 500 - 900);
+            assert_eq!(see("3rk3/8/8/8/8/8/3q4/3RK3 b - - 0 1", "d2d1"), 500);
+            assert_eq!(
+                see("3rk3/8/8/8/8/4N3/3q4/3RK3 b - - 0 1", "d2d1"),
+                500 - 900
+            );
+            // A quiet move into a pawn's attack
+            assert_eq!(see("4k3/8/8/8/4p3/8/8/4K1N1 w - - 0 1", "g1f3"), -320);
+            // Capturing a pawn with the queen stops when the queen would be lost
+            assert_eq!(see("4k3/8/8/2p5/3p4/8/8/3QK3 w - - 0 1", "d1d4"), 100 - 900);
+            assert_eq!(see("4k3/8/8/8/3p4/8/8/3QK3 w - - 0 1", "d1d4"), 100);
+        }
+
+        #[test]
+        fn standard_algebraic_notation() {
+            let san = |fen: &str, uci: &str| {
+                Game::from_fen(fen)
+                    .unwrap()
+                    .san(Move::from_uci(uci).unwrap())
+            };
+            let knights = "4k3/8/8/8/8/1N3N2/8/1N2K3 w - - 0 1";
+            for (fen, uci, expected) in [
+                (knights, "f3d2", "Nfd2"),
+                (knights, "b1d2", "N1d2"),
+                (knights, "b3d2", "Nb3d2"),
+                (knights, "f3g5", "Ng5"),
+                ("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1", "e4e5", "e5"),
+                ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", "exd5"),
+                ("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1", "O-O-O"),
+                ("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1d8", "Rxd8+"),
+                ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", "a8=Q+"),
+                ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8n", "axb8=N"),
+            ] {
+                assert_eq!(san(fen, uci).unwrap(), expected, "{uci} in {fen}");
+            }
+            assert!(matches!(san(knights, "e1e3"), Err(Error::IllegalMove(_))));
+        }
+
+        #[test]
+        fn parses_standard_algebraic_notation() {
+            let parse = |fen: &str, san: &str| Game::from_fen(fen).unwrap().parse_san(san);
+            let knights = "4k3/8/8/8/8/1N3N2/8/1N2K3 w - - 0 1";
+            for (fen, san, uci) in [
+                (knights, "Nfd2", "f3d2"),
+                (knights, "N1d2", "b1d2"),
+                (knights, "Nb3d2", "b3d2"),
+                (knights, "Nfg5!?", "f3g5"),
+                ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "exd5", "e4d5"),
+                ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e5", "e4e5"),
+                ("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "0-0-0", "e1c1"),
+                ("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1", "Rxd8+", "d1d8"),
+                ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=Q", "a7a8q"),
+                ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8N", "a7a8n"),
+                ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8", "a7a8q"),
+            ] {
+                assert_eq!(
+                    parse(fen, san).unwrap(),
+                    Move::from_uci(uci).unwrap(),
+                    "{san}"
+                );
+            }
+            for san in ["Nd2", "Nd4", "Nh8", "Kg1", "O-O", "N", "Nz9", "Nf3=Q"] {
+                assert!(
+                    matches!(parse(knights, san), Err(Error::InvalidSan(_))),
+                    "{san}"
+                );
+            }
+        }
+    }
+
+    mod moves {
+        use crate::{Error, Move, MoveList, Piece, Rank, Square, MAX_MOVES};
+
+        #[test]
+        fn move_uci_roundtrip() {
+            let m = Move::new(Square::A7, Square::H1);
+            assert_eq!(m.to_string(), "a7h1");
+            assert_eq!(Move::from_uci("a7h1").unwrap(), m);
+            let m = Move::new_promotion(Square::E2, Square::E1, Piece::KNIGHT);
+            assert_eq!(m.to_string(), "e2e1n");
+            assert_eq!(Move::from_uci("e2e1n").unwrap(), m);
+            let m = Move::new_drop(Piece::KNIGHT, Square::F3).with_duck(Square::A1);
+            assert_eq!(m.to_string(), "N@f3,f3a1");
+            assert_eq!(Move::from_uci("N@f3,f3a1").unwrap(), m);
+        }
+
+        #[test]
+        fn move_uci_errors() {
+            for uci in ["", "e2", "e2e9", "i2e4", "e2e4e5", "e7e8p", "N@f3q"] {
+                assert!(matches!(Move::from_uci(uci), Err(Error::InvalidUci(_))));
+            }
+            for uci in ["e2e4,", "e2e4,e4", "e2e4,e2d5", "e2e4,e4d5q", "e2e4,P@d5"] {
+                assert!(matches!(Move::from_uci(uci), Err(Error::InvalidUci(_))));
+            }
+        }
+
+        #[test]
+        fn move_list() {
+            let mut moves: MoveList = ["e2e4", "d2d4", "g1f3", "b1c3"]
+                .into_iter()
+                .map(|uci| Move::from_uci(uci).unwrap())
+                .collect();
+            assert_eq!(moves.len(), 4);
+            moves.retain(|m| m.start.get_rank() == Rank::SECOND);
+            assert_eq!(
+                moves
+                    .clone()
+                    .into_iter()
+                    .map(|m| m.to_string())
+                    .collect::<Vec<_>>(),
+                ["e2e4", "d2d4"]
+            );
+            moves.clear();
+            assert!(moves.is_empty());
+            assert_eq!(moves.into_iter().count(), 0);
+        }
+
+        #[test]
+        fn move_list_spills_past_capacity() {
+            let m = |i: usize| Move::new(Square::from_u8((i % 64) as u8), Square::A1);
+            let mut moves: MoveList = (0..MAX_MOVES + 10).map(m).collect();
+            assert_eq!(moves.len(), MAX_MOVES + 10);
+            assert_eq!(moves[MAX_MOVES + 9], m(MAX_MOVES + 9));
+            assert_eq!(moves.clone().into_iter().count(), MAX_MOVES + 10);
+            moves.retain(|m| m.start != Square::A1);
+            assert_eq!(moves.len(), MAX_MOVES + 10 - 9);
+            moves.retain(|m| m.start.get_rank() == Rank::FIRST);
+            // Back inline: seven first-rank squares of nine rounds
+            assert_eq!(moves.len(), 7 * 9);
+            assert!(moves.iter().all(|m| m.start.get_rank() == Rank::FIRST));
+            moves.push(m(1));
+            assert_eq!(moves.into_iter().last(), Some(m(1)));
+        }
     }
 
     mod movegen {
         use crate::{
+            bitboard::Bitboard,
             game::Game,
-            movegen::{self, all_legal_moves},
-            Color, Move, Square,
+            magics,
+            movegen::{self, all_legal_moves, MakeStrategy},
+            try_square_offset, Color, Move, Square,
         };
 
         #[test]
@@ -728,6 +2073,182 @@ mod tests {
             assert_eq!(moves.0, 43234889994);
         }
 
+        #[test]
+        fn pawn_attacks_on_edge_ranks() {
+            assert_eq!(
+                movegen::pawn_attacks(Square::E1, Color::WHITE),
+                Bitboard::empty().with(Square::D2).with(Square::F2)
+            );
+            assert!(movegen::pawn_attacks(Square::E1, Color::BLACK).is_empty());
+            assert!(movegen::pawn_attacks(Square::E8, Color::WHITE).is_empty());
+            assert_eq!(
+                movegen::pawn_attacks(Square::A8, Color::BLACK),
+                Bitboard::from_square(Square::B7)
+            );
+        }
+
+        #[test]
+        fn const_tables() {
+            // Everything needed to build attack tables at compile time
+            const fn king_zone(square: Square) -> Bitboard {
+                let mut zone = Bitboard::from_square(square);
+                let mut dx = -1;
+                while dx <= 1 {
+                    let mut dy = -1;
+                    while dy <= 1 {
+                        if let Some(s) = try_square_offset(square, dx, dy) {
+                            zone = zone.with(s);
+                        }
+                        dy += 1;
+                    }
+                    dx += 1;
+                }
+                zone
+            }
+            const ZONES: [Bitboard; 64] = {
+                let mut zones = [Bitboard::empty(); 64];
+                let mut s = 0;
+                while s < 64 {
+                    zones[s] = king_zone(Square::from_u8(s as u8));
+                    s += 1;
+                }
+                zones
+            };
+            const E4_ROOK: Bitboard = magics::rook_attacks(Square::E4, Bitboard::empty());
+            const WHITE_KNIGHT_F3: Bitboard = movegen::pseudolegal_knight_moves(Square::F3)
+                .difference(movegen::pawn_attacks(Square::E2, Color::WHITE));
+
+            assert_eq!(ZONES[Square::A1 as usize].count_ones(), 4);
+            assert_eq!(ZONES[Square::E4 as usize].count_ones(), 9);
+            assert_eq!(
+                E4_ROOK,
+                movegen::rook_attacks(Square::E4, Bitboard::empty())
+            );
+            assert!(!WHITE_KNIGHT_F3.contains(Square::D3));
+            assert!(WHITE_KNIGHT_F3.contains(Square::E5));
+        }
+
+        #[test]
+        fn perft_strategies_agree() {
+            let game = Game::default();
+            for (depth, nodes) in [(1, 20), (2, 400), (3, 8902)] {
+                for strategy in [MakeStrategy::COPY_MAKE, MakeStrategy::MAKE_UNMAKE] {
+                    assert_eq!(movegen::perft_with(&game, depth, strategy), nodes);
+                }
+            }
+        }
+
+        #[test]
+        fn infer_moves() {
+            let infer = |before: &str, after: &str| {
+                let before = Game::from_fen(before).unwrap();
+                Move::infer(&before, &Game::from_fen(after).unwrap()).map(|m| m.to_string())
+            };
+            assert_eq!(
+                infer(
+                    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+                ),
+                Some("e2e4".to_string())
+            );
+            assert_eq!(
+                infer(
+                    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                    "rnbqkbnr/ppp1p1pp/5P2/3p4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3"
+                ),
+                Some("e5f6".to_string())
+            );
+            assert_eq!(
+                infer(
+                    "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+                    "2kr3r/8/8/8/8/8/8/R3K2R w KQ - 1 2"
+                ),
+                Some("e8c8".to_string())
+            );
+            // The promoted piece tells the promotions apart
+            assert_eq!(
+                infer(
+                    "8/1P6/8/8/8/8/8/k1K5 w - - 0 1",
+                    "1N6/8/8/8/8/8/8/k1K5 b - - 0 1"
+                ),
+                Some("b7b8n".to_string())
+            );
+            assert_eq!(
+                infer(
+                    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR b KQkq - 0 1"
+                ),
+                None
+            );
+        }
+
+        #[test]
+        fn en_passant() {
+            let mut game = Game::default();
+            for uci in ["e2e4", "a7a6", "e4e5", "d7d5"] {
+                let m = Move::from_uci(uci).unwrap();
+                let key = game.key_after(m);
+                game.make_move(m);
+                assert_eq!(game.key(), key);
+            }
+            // Only a double step next to a pawn that can take sets the square
+            assert_eq!(game.en_passant_square, Some(Square::D6));
+            let exd6 = Move::from_uci("e5d6").unwrap();
+            assert!(game.is_en_passant(exd6) && game.is_capture(exd6));
+            assert!(movegen::all_legal_moves(&game).contains(&exd6));
+            let before = game;
+            let undo = game.make_move(exd6);
+            assert_eq!(
+                game.to_fen(),
+                "rnbqkbnr/1pp1pppp/p2P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3"
+            );
+            game.unmake_move(exd6, undo);
+            assert_eq!(game, before);
+            game.make_move(Move::from_uci("g1f3").unwrap());
+            assert_eq!(game.en_passant_square, None);
+
+            // A FEN with the square but no pawn to take keeps it, but has the key of the
+            // position make_move leaves
+            let mut e4 = Game::default();
+            e4.make_move(Move::from_uci("e2e4").unwrap());
+            let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.en_passant_square, Some(Square::E3));
+            assert_eq!(game.to_fen(), fen);
+            assert_eq!(game.key(), e4.key());
+            assert_eq!(
+                movegen::all_legal_moves(&game),
+                movegen::all_legal_moves(&e4)
+            );
+            let e5 = Move::from_uci("e7e5").unwrap();
+            let mut after = game;
+            after.make_move(e5);
+            assert_eq!(game.key_after(e5), after.key());
+            assert!(game.opening().is_some());
+
+            let kiwipete = Game::from_fen(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            )
+            .unwrap();
+            assert_eq!(movegen::perft(&kiwipete, 3), 97862);
+        }
+
+        #[test]
+        fn promotions() {
+            let game = Game::from_fen("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1").unwrap();
+            for (depth, nodes) in [(1, 24), (2, 496), (3, 9483)] {
+                for strategy in [MakeStrategy::COPY_MAKE, MakeStrategy::MAKE_UNMAKE] {
+                    assert_eq!(movegen::perft_with(&game, depth, strategy), nodes);
+                }
+            }
+            let mut game = game;
+            let m = Move::from_uci("g2h1r").unwrap();
+            let key = game.key_after(m);
+            game.make_move(m);
+            assert_eq!(game.key(), key);
+            assert_eq!(game.to_fen(), "n1n5/PPPk4/8/8/8/8/4Kp1p/5N1r w - - 0 2");
+        }
+
         #[test]
         fn slider_moves() {
             // Position after 1. e2 e4
@@ -790,86 +2311,26 @@ mod tests {
             assert_eq!(
                 moves,
                 [
-                    Move {
-                        start: Square::B1,
-                        end: Square::A3
-                    },
-                    Move {
-                        start: Square::B1,
-                        end: Square::C3
-                    },
-                    Move {
-                        start: Square::G1,
-                        end: Square::F3
-                    },
-                    Move {
-                        start: Square::G1,
-                        end: Square::H3
-                    },
-                    Move {
-                        start: Square::A2,
-                        end: Square::A3
-                    },
-                    Move {
-                        start: Square::A2,
-                        end: Square::A4
-                    },
-                    Move {
-                        start: Square::B2,
-                        end: Square::B3
-                    },
-                    Move {
-                        start: Square::B2,
-                        end: Square::B4
-                    },
-                    Move {
-                        start: Square::C2,
-                        end: Square::C3
-                    },
-                    Move {
-                        start: Square::C2,
-                        end: Square::C4
-                    },
-                    Move {
-                        start: Square::D2,
-                        end: Square::D3
-                    },
-                    Move {
-                        start: Square::D2,
-                        end: Square::D4
-                    },
-                    Move {
-                        start: Square::E2,
-                        end: Square::E3
-                    },
-                    Move {
-                        start: Square::E2,
-                        end: Square::E4
-                    },
-                    Move {
-                        start: Square::F2,
-                        end: Square::F3
-                    },
-                    Move {
-                        start: Square::F2,
-                        end: Square::F4
-                    },
-                    Move {
-                        start: Square::G2,
-                        end: Square::G3
-                    },
-                    Move {
-                        start: Square::G2,
-                        end: Square::G4
-                    },
-                    Move {
-                        start: Square::H2,
-                        end: Square::H3
-                    },
-                    Move {
-                        start: Square::H2,
-                        end: Square::H4
-                    }
+                    Move::new(Square::B1, Square::A3),
+                    Move::new(Square::B1, Square::C3),
+                    Move::new(Square::G1, Square::F3),
+                    Move::new(Square::G1, Square::H3),
+                    Move::new(Square::A2, Square::A3),
+                    Move::new(Square::A2, Square::A4),
+                    Move::new(Square::B2, Square::B3),
+                    Move::new(Square::B2, Square::B4),
+                    Move::new(Square::C2, Square::C3),
+                    Move::new(Square::C2, Square::C4),
+                    Move::new(Square::D2, Square::D3),
+                    Move::new(Square::D2, Square::D4),
+                    Move::new(Square::E2, Square::E3),
+                    Move::new(Square::E2, Square::E4),
+                    Move::new(Square::F2, Square::F3),
+                    Move::new(Square::F2, Square::F4),
+                    Move::new(Square::G2, Square::G3),
+                    Move::new(Square::G2, Square::G4),
+                    Move::new(Square::H2, Square::H3),
+                    Move::new(Square::H2, Square::H4)
                 ]
             );
         }
@@ -892,19 +2353,15 @@ mod tests {
                 Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/1P3P1q/8/P1PPP1PP/RNBQKBNR w KQkq - 1 3")
                     .unwrap();
             let moves = all_legal_moves(&game);
-            assert_eq!(
-                moves,
-                vec![Move {
-                    start: Square::G2,
-                    end: Square::G3
-                }]
-            );
+            assert_eq!(moves, vec![Move::new(Square::G2, Square::G3)]);
         }
 
         #[test]
         fn all_legal_with_castling() {
-            let game =
-                Game::from_fen("r2qk2r/1ppn1ppp/p2bbn2/3p2B1/3P4/2NBPN1P/PP3PP1/R2QK2R b KQkq - 2 9").unwrap();
+            let game = Game::from_fen(
+                "r2qk2r/1ppn1ppp/p2bbn2/3p2B1/3P4/2NBPN1P/PP3PP1/R2QK2R b KQkq - 2 9",
+            )
+            .unwrap();
             let moves = all_legal_moves(&game);
             for m in &moves {
                 println!("{:?}", m);
@@ -913,8 +2370,215 @@ mod tests {
         }
     }
 
+    mod castling {
+        use crate::{
+            game::Game, movegen::all_legal_moves, CastlingRights, CastlingSide, Color, Move, Square,
+        };
+
+        #[test]
+        fn castling_rights_display() {
+            assert_eq!(CastlingRights::ALL_LEGAL.to_string(), "KQkq");
+            assert_eq!(CastlingRights::BLACK_CASTLING.to_string(), "kq");
+            assert_eq!(CastlingRights::empty().to_string(), "-");
+        }
+
+        #[test]
+        fn castling_rights_fen_roundtrip() {
+            for fen in ["KQkq", "Kk", "Q", "q", "-"] {
+                let rights = CastlingRights::from_fen(fen).unwrap();
+                assert_eq!(rights.to_string(), fen);
+            }
+            assert!(CastlingRights::from_fen("").is_err());
+        }
+
+        #[test]
+        fn castling_rights_remove() {
+            let mut rights = CastlingRights::ALL_LEGAL;
+            rights.remove(CastlingRights::new(Color::WHITE, CastlingSide::QUEENSIDE));
+            assert!(!rights.allows(Color::WHITE, CastlingSide::QUEENSIDE));
+            assert!(rights.allows(Color::WHITE, CastlingSide::KINGSIDE));
+
+            rights.remove(CastlingRights::for_color(Color::BLACK));
+            assert_eq!(rights, CastlingRights::WHITE_KINGSIDE);
+        }
+
+        #[test]
+        fn no_castling_through_attacked_squares() {
+            let game = Game::from_fen("5r1k/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+            assert!(!all_legal_moves(&game).contains(&Move::from_uci("e1g1").unwrap()));
+            // The rook leaving b1 opens the a-file rook's way to the king on c1
+            let game = Game::from_fen("4k3/8/8/8/8/8/8/rR2K3 w B - 0 1").unwrap();
+            assert!(!all_legal_moves(&game).contains(&Move::from_uci("e1b1").unwrap()));
+        }
+
+        #[test]
+        fn chess960_fen_rights() {
+            let fen = "1r3kr1/8/8/8/8/8/8/1R3KR1 w GBgb - 0 1";
+            let game = Game::from_fen(fen).unwrap();
+            assert!(game.chess960);
+            assert_eq!(game.to_fen(), fen);
+            // X-FEN names the outermost rooks
+            let x_fen = Game::from_fen("1r3kr1/8/8/8/8/8/8/1R3KR1 w KQkq - 0 1").unwrap();
+            assert_eq!(x_fen, game);
+            assert_eq!(
+                game.castling_rook(Color::WHITE, CastlingSide::KINGSIDE),
+                Square::G1
+            );
+            assert!(!Game::default().chess960);
+            assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K2R w X - 0 1").is_err());
+        }
+
+        #[test]
+        fn chess960_castling_swaps_king_and_rook() {
+            let before = Game::from_fen("1r3kr1/5pp1/8/8/8/8/5PP1/1R3KR1 w GBgb - 0 1").unwrap();
+            for (uci, san, fen) in [
+                ("f1g1", "O-O", "1r3kr1/5pp1/8/8/8/8/5PP1/1R3RK1 b gb - 1 1"),
+                (
+                    "f1b1",
+                    "O-O-O",
+                    "1r3kr1/5pp1/8/8/8/8/5PP1/2KR2R1 b gb - 1 1",
+                ),
+            ] {
+                let m = Move::from_uci(uci).unwrap();
+                assert!(all_legal_moves(&before).contains(&m), "{uci}");
+                assert_eq!(before.san(m).unwrap(), san);
+                assert_eq!(before.parse_san(san).unwrap(), m);
+                let mut game = before;
+                let undo = game.make_move(m);
+                assert_eq!(game.to_fen(), fen);
+                game.unmake_move(m, undo);
+                assert_eq!(game, before, "{uci}");
+            }
+        }
+    }
+
+    mod color {
+        use crate::Color;
+
+        #[test]
+        fn color_opposite() {
+            assert_eq!(Color::WHITE.opposite(), Color::BLACK);
+            assert_eq!(!Color::WHITE, Color::BLACK);
+            assert_eq!(!!Color::BLACK, Color::BLACK);
+        }
+
+        #[test]
+        fn color_display() {
+            assert_eq!(Color::WHITE.to_string(), "white");
+            assert_eq!(Color::BLACK.to_string(), "black");
+        }
+
+        #[test]
+        fn color_both() {
+            let colors: Vec<Color> = Color::both().into_iter().collect();
+            assert_eq!(colors, vec![Color::WHITE, Color::BLACK]);
+        }
+    }
+
+    mod piece {
+        use crate::{Color, File, Piece, Rank};
+
+        #[test]
+        fn piece_char_roundtrip() {
+            for piece in Piece::ALL {
+                for color in Color::both() {
+                    assert_eq!(Piece::from_char(&piece.to_char(color)), piece);
+                }
+            }
+        }
+
+        #[test]
+        fn piece_non_panicking_constructors() {
+            assert_eq!(Piece::new(4), Some(Piece::QUEEN));
+            assert_eq!(Piece::new(6), None);
+            assert_eq!(Piece::try_from_char('?'), None);
+            assert_eq!(Color::new(2), None);
+            assert_eq!(Rank::new(7), Some(Rank::EIGHTH));
+            assert_eq!(File::new(8), None);
+        }
+
+        #[test]
+        fn piece_display() {
+            assert_eq!(Piece::QUEEN.to_string(), "Q");
+            assert_eq!(Piece::PAWN.to_string(), "P");
+        }
+
+        #[test]
+        fn piece_values() {
+            assert_eq!(Piece::PAWN.value(), Piece::PAWN_VALUE);
+            assert!(Piece::QUEEN.value() > Piece::ROOK.value());
+            assert_eq!(Piece::KING.value(), 0);
+        }
+    }
+
+    mod colored_piece {
+        use crate::{Color, ColoredPiece, Piece};
+
+        #[test]
+        fn colored_piece_from_char() {
+            assert_eq!(
+                ColoredPiece::from_char('K'),
+                Some(ColoredPiece::new(Piece::KING, Color::WHITE))
+            );
+            assert_eq!(
+                ColoredPiece::from_char('p'),
+                Some(ColoredPiece::new(Piece::PAWN, Color::BLACK))
+            );
+            assert_eq!(ColoredPiece::from_char('1'), None);
+        }
+
+        #[test]
+        fn colored_piece_display() {
+            let piece = ColoredPiece::new(Piece::BISHOP, Color::BLACK);
+            assert_eq!(piece.to_string(), "b");
+        }
+    }
+
+    mod rank_file {
+        use crate::{Color, File, Rank, Square};
+
+        #[test]
+        fn rank_file_display() {
+            let ranks: String = Rank::all().map(|r| r.to_string()).collect();
+            assert_eq!(ranks, "12345678");
+            let files: String = File::all().map(|f| f.to_string()).collect();
+            assert_eq!(files, "abcdefgh");
+        }
+
+        #[test]
+        fn rank_file_from_char() {
+            for (rank, c) in Rank::all().zip("12345678".chars()) {
+                assert_eq!(Rank::from_char(c), Some(rank));
+            }
+            for (file, c) in File::all().zip("abcdefgh".chars()) {
+                assert_eq!(File::from_char(c), Some(file));
+            }
+            assert_eq!(Rank::from_char('9'), None);
+            assert_eq!(File::from_char('A'), None);
+            assert_eq!(File::from_char('ť'), None);
+            assert_eq!(Rank::from_char('ı'), None);
+            assert!(Square::from_parts(&'š', &'ı').is_err());
+        }
+
+        #[test]
+        fn rank_file_arithmetic() {
+            assert_eq!(Rank::FOURTH.up(), Some(Rank::FIFTH));
+            assert_eq!(Rank::FOURTH.down(), Some(Rank::THIRD));
+            assert_eq!(Rank::FIRST.down(), None);
+            assert_eq!(File::D.west(), Some(File::C));
+            assert_eq!(File::A.west(), None);
+            assert_eq!(File::H.east(), None);
+        }
+
+        #[test]
+        fn rank_relative_to() {
+            assert_eq!(Rank::EIGHTH.relative_to(Color::BLACK), Rank::FIRST);
+            assert!(Rank::all().all(|r| r.relative_to(Color::WHITE) == r));
+        }
+    }
+
     mod square {
-        use crate::Square;
+        use crate::{Color, File, Rank, Square};
 
         #[test]
         fn square_display() {
@@ -929,6 +2593,19 @@ mod tests {
             assert_eq!(square as u8, 15);
         }
 
+        #[test]
+        fn square_new() {
+            assert!((0..64).all(|i| Square::new(i) == Some(Square::from_u8(i))));
+            assert_eq!(Square::new(64), None);
+            assert_eq!(Square::new(u8::MAX), None);
+        }
+
+        #[test]
+        #[should_panic]
+        fn square_from_u8_oob() {
+            let _ = Square::from_u8(64);
+        }
+
         #[test]
         fn square_from_parts() {
             let file = 'e';
@@ -957,102 +2634,65 @@ mod tests {
             let rank = '9';
             let _ = Square::from_parts(&file, &rank).unwrap();
         }
-    }
 
-    mod bench {
-        extern crate test;
-
-        use crate::{
-            game::Game,
-            movegen::{all_legal_moves, king_moves, pawn_moves, pseudolegal_slider_moves, slider_moves},
-            try_square_offset, Color, Square,
-        };
-        use test::Bencher;
-
-        #[bench]
-        fn bench_square_offset(b: &mut Bencher) {
-            let s = Square::E4;
-            b.iter(|| try_square_offset(s, 1, -1));
-        }
-
-        #[bench]
-        fn bench_color_at(b: &mut Bencher) {
-            let g = Game::default();
-            b.iter(|| g.color_at(Square::E2));
-        }
-
-        #[bench]
-        fn bench_type_at(b: &mut Bencher) {
-            let g = Game::default();
-            b.iter(|| g.type_at(Square::E2));
-        }
-
-        #[bench]
-        fn bench_pawn_moves(b: &mut Bencher) {
-            let g = Game::default();
-            b.iter(|| pawn_moves(&g, Square::E2));
-        }
-
-        #[bench]
-        fn bench_square_get_file(b: &mut Bencher) {
-            let s = Square::E2;
-            b.iter(|| s.get_file());
-        }
-
-        #[bench]
-        fn bench_square_get_rank(b: &mut Bencher) {
-            let s = Square::E2;
-            b.iter(|| s.get_rank());
-        }
-
-        #[bench]
-        fn bench_slider_moves(b: &mut Bencher) {
-            let g = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
-                .unwrap();
-            b.iter(|| slider_moves(&g, Square::F1));
-        }
+        #[test]
+        fn square_iterators() {
+            assert_eq!(Square::all().count(), 64);
+            assert!(Square::all().enumerate().all(|(i, s)| s as usize == i));
+
+            let rank: Vec<Square> = Rank::THIRD.squares().collect();
+            assert_eq!(rank.len(), 8);
+            assert!(rank.iter().all(|s| s.get_rank() == Rank::THIRD));
+
+            let file: Vec<Square> = File::B.squares().collect();
+            assert_eq!(file.len(), 8);
+            assert!(file.iter().all(|s| s.get_file() == File::B));
+
+            let diagonal: Vec<Square> = Square::D4.diagonal().collect();
+            assert_eq!(diagonal.len(), 8);
+            assert_eq!(diagonal.first(), Some(&Square::A1));
+            assert_eq!(diagonal.last(), Some(&Square::H8));
+            assert_eq!(Square::H1.diagonal().collect::<Vec<_>>(), vec![Square::H1]);
+            assert_eq!(
+                Square::B7.diagonal().collect::<Vec<_>>(),
+                vec![Square::A6, Square::B7, Square::C8]
+            );
 
-        #[bench]
-        fn bench_pseudo_slider_moves(b: &mut Bencher) {
-            let g = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
-                .unwrap();
-            b.iter(|| pseudolegal_slider_moves(&g, Square::F1));
+            let anti_diagonal: Vec<Square> = Square::E4.anti_diagonal().collect();
+            assert_eq!(anti_diagonal.len(), 8);
+            assert_eq!(anti_diagonal.first(), Some(&Square::H1));
+            assert_eq!(anti_diagonal.last(), Some(&Square::A8));
+            assert_eq!(
+                Square::A1.anti_diagonal().collect::<Vec<_>>(),
+                vec![Square::A1]
+            );
+            assert_eq!(
+                Square::G7.anti_diagonal().collect::<Vec<_>>(),
+                vec![Square::H6, Square::G7, Square::F8]
+            );
         }
 
-        #[bench]
-        fn bench_king_moves(b: &mut Bencher) {
-            let g = Game::from_fen("rnbq1bnr/pppp1ppp/6k1/4p3/4P3/1K6/PPPP1PPP/RNBQ1BNR b - - 7 5")
-                .unwrap();
-            b.iter(|| king_moves(&g, Color::WHITE));
-        }
+        #[test]
+        fn square_flip_and_relative() {
+            assert_eq!(Square::A1.flip_vertical(), Square::A8);
+            assert_eq!(Square::D5.flip_vertical(), Square::D4);
+            assert!(Square::all().all(|s| s.flip_vertical().flip_vertical() == s));
 
-        #[bench]
-        fn bench_attackers_simple(b: &mut Bencher) {
-            let game =
-                Game::from_fen("rnbqkbnr/p1pppppp/8/1p6/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
-                    .unwrap();
-            b.iter(|| game.is_attacked_by(Color::WHITE, Square::B5));
+            assert_eq!(Square::G1.relative_to(Color::WHITE), Square::G1);
+            assert_eq!(Square::G1.relative_to(Color::BLACK), Square::G8);
         }
 
-        #[bench]
-        fn bench_attackers_complex(b: &mut Bencher) {
-            let game = Game::from_fen(
-                "r1bqk1nr/pp3pbp/2n1p1p1/2p5/3pP3/2NP1NP1/PPP2PBP/R1BQ1RK1 w kq - 0 8",
-            )
-            .unwrap();
-            b.iter(|| game.is_attacked_by(Color::WHITE, Square::H5));
-        }
+        #[test]
+        fn square_distances() {
+            assert_eq!(Square::A1.distance(Square::H8), 7);
+            assert_eq!(Square::B2.distance(Square::C4), 2);
+            assert_eq!(Square::D4.distance(Square::D4), 0);
 
-        #[bench]
-        fn bench_all_legal_from_default(b: &mut Bencher) {
-            let game = Game::default();
-            b.iter(|| all_legal_moves(&game));
-        }
+            assert_eq!(Square::A1.manhattan_distance(Square::H8), 14);
+            assert_eq!(Square::B2.manhattan_distance(Square::C4), 3);
+            assert_eq!(Square::H1.manhattan_distance(Square::A1), 7);
 
-        #[bench]
-        fn bench_all_legal_from_complex(b: &mut Bencher) {
-            let game = Game::from_fen("r2qkb1r/1ppn1ppp/p3bn2/3p2B1/3P4/2N1PN1P/PP3PP1/R2QKB1R b KQkq - 0 8").unwrap();
-            b.iter(|| all_legal_moves(&game));
+            assert_eq!(Square::G1.king_steps_between(Square::A7), 6);
         }
     }
 }
@@ -0,0 +1,334 @@
+//! Reading games exported from lichess as NDJSON, one game per line, enabled by the `json`
+//! feature, so that a player's history can be replayed and analyzed with this crate.
+//!
+//! Besides the moves, which may be given in SAN as lichess writes them or in UCI notation,
+//! every [`LichessGame`] keeps the metadata of the export: the players with their ratings,
+//! the variant, the time control, the opening, the clock after every move if the export
+//! asked for `clocks` and the server analysis if it asked for `evals`. Fields missing from
+//! the export are left empty.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Outcome, lichess, Color};
+//! let line = r#"{"id":"q7ZvsdUF","rated":true,"variant":"standard","speed":"blitz",
+//!     "status":"mate","players":{"white":{"user":{"name":"Lance5500"},"rating":2389},
+//!     "black":{"aiLevel":3}},"winner":"white","moves":"e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7#",
+//!     "clock":{"initial":300,"increment":3}}"#;
+//! let games = lichess::parse(&line.replace('\n', ""));
+//! let game = games[0].as_ref().unwrap();
+//! assert_eq!(game.white.name.as_deref(), Some("Lance5500"));
+//! assert_eq!(game.black.ai_level, Some(3));
+//! assert_eq!(game.moves.len(), 7);
+//! assert_eq!(game.outcome, Some(Outcome::DECISIVE { winner: Color::WHITE }));
+//! assert!(game.positions().last().unwrap().is_check());
+//! ```
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::{
+    game::{Game, Outcome},
+    movegen::all_legal_moves,
+    pgn::{self, PgnGame},
+    variant::Variant,
+    Color, Error, Move, Result,
+};
+
+/// A player of a [`LichessGame`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Player {
+    /// The user name, or `None` for anonymous players and the computer.
+    pub name: Option<String>,
+    /// The title, e.g. `GM`.
+    pub title: Option<String>,
+    pub rating: Option<u32>,
+    /// How much the game changed the rating.
+    pub rating_diff: Option<i32>,
+    /// The level of the computer, if it played.
+    pub ai_level: Option<u32>,
+}
+
+/// An evaluation of the server analysis, from White's point of view.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Eval {
+    CENTIPAWNS(i32),
+    /// Mate in the given number of moves, negative if Black mates.
+    MATE(i32),
+}
+
+/// A game exported from lichess.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LichessGame {
+    /// The id the game is found under, e.g. `https://lichess.org/q7ZvsdUF`.
+    pub id: String,
+    pub rated: bool,
+    pub variant: Variant,
+    /// The speed, e.g. `blitz` or `correspondence`.
+    pub speed: String,
+    /// How the game ended, e.g. `mate`, `resign` or `started` for a game still going on.
+    pub status: String,
+    /// When the game was created, in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub white: Player,
+    pub black: Player,
+    /// The initial time and the increment, or `None` for games without a clock.
+    pub clock: Option<(Duration, Duration)>,
+    /// The ECO code and the name of the opening.
+    pub opening: Option<(String, String)>,
+    /// The position the game starts from.
+    pub start: Game,
+    pub moves: Vec<Move>,
+    /// How the game ended, or `None` if it is unfinished or was aborted.
+    pub outcome: Option<Outcome>,
+    /// The time left on the clock of the player who moved, after every move.
+    pub clocks: Vec<Duration>,
+    /// The evaluation after every move, `None` where the analysis has none, e.g. after mate.
+    pub evals: Vec<Option<Eval>>,
+}
+
+impl LichessGame {
+    /// Parses a game from a line of the export.
+    pub fn parse(line: &str) -> Result<Self> {
+        let json: Value =
+            serde_json::from_str(line).map_err(|e| Error::InvalidJson(e.to_string()))?;
+        let id = string(&json["id"]).unwrap_or_default();
+        let variant = match json["variant"].as_str().unwrap_or("standard") {
+            "standard" | "chess960" | "fromPosition" => Variant::STANDARD,
+            "crazyhouse" => Variant::CRAZYHOUSE,
+            "atomic" => Variant::ATOMIC,
+            "kingOfTheHill" => Variant::KING_OF_THE_HILL,
+            "horde" => Variant::HORDE,
+            "antichess" => Variant::ANTICHESS,
+            other => return Err(Error::InvalidVariant(other.to_string())),
+        };
+        let mut start = match json["initialFen"].as_str() {
+            Some(fen) => Game::from_fen(fen)?,
+            None => variant.starting_position(),
+        };
+        start.variant = variant;
+
+        let mut game = start;
+        let mut moves = Vec::new();
+        for text in json["moves"].as_str().unwrap_or("").split_whitespace() {
+            let m = parse_move(&game, text)?;
+            game.make_move(m);
+            moves.push(m);
+        }
+
+        let status = string(&json["status"]).unwrap_or_default();
+        let outcome = match json["winner"].as_str() {
+            Some("white") => Some(Outcome::DECISIVE {
+                winner: Color::WHITE,
+            }),
+            Some("black") => Some(Outcome::DECISIVE {
+                winner: Color::BLACK,
+            }),
+            _ => match status.as_str() {
+                "created" | "started" | "aborted" | "noStart" | "unknownFinish" | "" => None,
+                _ => Some(Outcome::DRAW),
+            },
+        };
+
+        let clock = &json["clock"];
+        let clock = clock["initial"]
+            .as_u64()
+            .zip(clock["increment"].as_u64())
+            .map(|(initial, increment)| {
+                (Duration::from_secs(initial), Duration::from_secs(increment))
+            });
+        let opening = &json["opening"];
+        let opening = string(&opening["eco"]).zip(string(&opening["name"]));
+        // Clocks are given in centiseconds
+        let clocks = values(&json["clocks"])
+            .filter_map(Value::as_u64)
+            .map(|centis| Duration::from_millis(centis * 10))
+            .collect();
+        let evals = values(&json["analysis"])
+            .map(|entry| {
+                let eval = |key| entry[key].as_i64().map(|n| n as i32);
+                eval("eval")
+                    .map(Eval::CENTIPAWNS)
+                    .or_else(|| eval("mate").map(Eval::MATE))
+            })
+            .collect();
+
+        Ok(Self {
+            id,
+            rated: json["rated"].as_bool().unwrap_or(false),
+            variant,
+            speed: string(&json["speed"]).unwrap_or_default(),
+            status,
+            created_at: json["createdAt"].as_u64().unwrap_or(0),
+            white: player(&json["players"]["white"]),
+            black: player(&json["players"]["black"]),
+            clock,
+            opening,
+            start,
+            moves,
+            outcome,
+            clocks,
+            evals,
+        })
+    }
+
+    /// Returns the start and the position after every move.
+    pub fn positions(&self) -> Vec<Game> {
+        let mut game = self.start;
+        let mut positions = vec![game];
+        for &m in &self.moves {
+            game.make_move(m);
+            positions.push(game);
+        }
+        positions
+    }
+
+    /// Returns the game as PGN with the tags lichess gives its own exports, so that it can be
+    /// written or [reviewed](crate::review) like any other.
+    pub fn to_pgn(&self) -> PgnGame {
+        let name = |player: &Player| player.name.clone().unwrap_or_else(|| "?".to_string());
+        let mut tags = vec![
+            ("Event".to_string(), format!("{} game", self.speed)),
+            (
+                "Site".to_string(),
+                format!("https://lichess.org/{}", self.id),
+            ),
+            ("White".to_string(), name(&self.white)),
+            ("Black".to_string(), name(&self.black)),
+            (
+                "Result".to_string(),
+                pgn::format_result(self.outcome).to_string(),
+            ),
+        ];
+        for (tag, player) in [("WhiteElo", &self.white), ("BlackElo", &self.black)] {
+            if let Some(rating) = player.rating {
+                tags.push((tag.to_string(), rating.to_string()));
+            }
+        }
+        if let Some((initial, increment)) = self.clock {
+            let control = format!("{}+{}", initial.as_secs(), increment.as_secs());
+            tags.push(("TimeControl".to_string(), control));
+        }
+        if let Some((eco, name)) = &self.opening {
+            tags.push(("ECO".to_string(), eco.clone()));
+            tags.push(("Opening".to_string(), name.clone()));
+        }
+        if self.start != self.variant.starting_position() {
+            tags.push(("FEN".to_string(), self.start.to_fen()));
+            tags.push(("SetUp".to_string(), "1".to_string()));
+        }
+        PgnGame {
+            tags,
+            start: self.start,
+            moves: self.moves.clone(),
+            outcome: self.outcome,
+        }
+    }
+}
+
+/// Reads every non-empty line of `input` as a game. A game that can't be read gives an
+/// [`Error::Line`] with its 1-based line number, and reading goes on with the next line.
+pub fn parse(input: &str) -> Vec<Result<LichessGame>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            LichessGame::parse(line).map_err(|e| Error::Line {
+                line: i + 1,
+                source: Box::new(e),
+            })
+        })
+        .collect()
+}
+
+/// Parses a legal move given in UCI notation or SAN.
+fn parse_move(game: &Game, text: &str) -> Result<Move> {
+    match Move::from_uci(text) {
+        Ok(m) if all_legal_moves(game).contains(&m) => Ok(m),
+        Ok(m) => Err(Error::IllegalMove(m)),
+        Err(_) => game.parse_san(text),
+    }
+}
+
+fn player(json: &Value) -> Player {
+    Player {
+        name: string(&json["user"]["name"]),
+        title: string(&json["user"]["title"]),
+        rating: json["rating"].as_u64().map(|rating| rating as u32),
+        rating_diff: json["ratingDiff"].as_i64().map(|diff| diff as i32),
+        ai_level: json["aiLevel"].as_u64().map(|level| level as u32),
+    }
+}
+
+fn string(json: &Value) -> Option<String> {
+    json.as_str().map(str::to_string)
+}
+
+/// Returns the elements of `json` if it is an array, none otherwise.
+fn values(json: &Value) -> impl Iterator<Item = &Value> {
+    json.as_array().into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_clocks_and_analysis() {
+        let line = r#"{"id":"abcdefgh","rated":false,"variant":"fromPosition",
+            "initialFen":"4k3/8/8/8/8/8/8/4K2R w K - 0 1","speed":"rapid","status":"resign",
+            "createdAt":1700000000000,"players":{"white":{"user":{"name":"a","title":"GM"},
+            "rating":2500,"ratingDiff":3},"black":{"user":{"name":"b"},"rating":1500}},
+            "winner":"white","moves":"e1g1 Kd7 Rf7+","clocks":[60000,60000,59850,59000,59700],
+            "analysis":[{"eval":15},{"eval":900},{"mate":12,"best":"h1h8"}],
+            "opening":{"eco":"A00","name":"Custom"},"clock":{"initial":600,"increment":0}}"#;
+        let game = LichessGame::parse(&line.replace('\n', "")).unwrap();
+        assert_eq!(game.white.title.as_deref(), Some("GM"));
+        assert_eq!(game.white.rating_diff, Some(3));
+        assert_eq!(game.moves[0], Move::from_uci("e1g1").unwrap());
+        assert_eq!(game.clocks[2], Duration::from_millis(598_500));
+        assert_eq!(
+            game.evals,
+            [
+                Some(Eval::CENTIPAWNS(15)),
+                Some(Eval::CENTIPAWNS(900)),
+                Some(Eval::MATE(12))
+            ]
+        );
+        assert_eq!(game.positions().len(), 4);
+
+        let pgn = game.to_pgn();
+        assert_eq!(pgn.tag("Site"), Some("https://lichess.org/abcdefgh"));
+        assert_eq!(pgn.tag("TimeControl"), Some("600+0"));
+        assert_eq!(pgn.tag("FEN"), Some("4k3/8/8/8/8/8/8/4K2R w K - 0 1"));
+        assert_eq!(pgn.tag("Result"), Some("1-0"));
+        let written = pgn.to_string();
+        assert!(written.contains("1. O-O Kd7 2. Rf7+ 1-0"));
+    }
+
+    #[test]
+    fn parse_reports_bad_lines() {
+        let input = concat!(
+            r#"{"id":"a","status":"started","moves":"e4 e5"}"#,
+            "\n\n",
+            r#"{"id":"b","moves":"e4 e4"}"#,
+            "\n",
+            r#"{"id":"c","variant":"racingKings"}"#,
+            "\n{\n",
+        );
+        let games = parse(input);
+        assert_eq!(games.len(), 4);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.outcome, None);
+        assert_eq!(game.to_pgn().tag("Result"), Some("*"));
+        assert!(matches!(&games[1], Err(Error::Line { line: 3, source })
+            if matches!(**source, Error::InvalidSan(_))));
+        assert!(matches!(&games[2], Err(Error::Line { source, .. })
+            if matches!(**source, Error::InvalidVariant(_))));
+        assert!(matches!(&games[3], Err(Error::Line { source, .. })
+            if matches!(**source, Error::InvalidJson(_))));
+    }
+}
@@ -0,0 +1,202 @@
+//! Compact slider attack tables for targets where table memory matters.
+//!
+//! A slider's attacks only depend on the first blocker along each of its rays, so every
+//! square has few distinct attack sets, while its magic table holds one slot per blocker
+//! configuration. Storing a `u16` reference to the distinct set in every slot instead of the
+//! set itself shrinks the rook tables from 800 KiB to about 240 KiB and the bishop tables
+//! from 41 KiB to about 22 KiB. The same [`magic_index`](crate::movegen::magic_index)
+//! selects the slot.
+
+use super::{
+    on_board, slider_attacks, BISHOP_DIRECTIONS, BISHOP_MAGICS, ROOK_DIRECTIONS, ROOK_MAGICS,
+};
+use crate::MagicTableEntry;
+
+const ROOK_SET_COUNT: usize = attack_set_count(&ROOK_DIRECTIONS);
+const BISHOP_SET_COUNT: usize = attack_set_count(&BISHOP_DIRECTIONS);
+const _: () = assert!(ROOK_SET_COUNT <= 1 << 16 && BISHOP_SET_COUNT <= 1 << 16);
+
+/// The distinct rook attack sets of all squares, indexed by [`ROOK_SET_INDICES`].
+pub static ROOK_ATTACK_SETS: [u64; ROOK_SET_COUNT] = attack_sets(&ROOK_DIRECTIONS);
+/// The distinct bishop attack sets of all squares, indexed by [`BISHOP_SET_INDICES`].
+pub static BISHOP_ATTACK_SETS: [u64; BISHOP_SET_COUNT] = attack_sets(&BISHOP_DIRECTIONS);
+
+/// For every magic index into [`ROOK_MOVES`](super::ROOK_MOVES), the position of that
+/// attack set in [`ROOK_ATTACK_SETS`].
+#[allow(long_running_const_eval)]
+pub static ROOK_SET_INDICES: [u16; 102400] = set_indices(&ROOK_DIRECTIONS, ROOK_MAGICS);
+/// For every magic index into [`BISHOP_MOVES`](super::BISHOP_MOVES), the position of that
+/// attack set in [`BISHOP_ATTACK_SETS`].
+pub static BISHOP_SET_INDICES: [u16; 5248] = set_indices(&BISHOP_DIRECTIONS, BISHOP_MAGICS);
+
+/// The rays of a slider on one square.
+#[derive(Copy, Clone)]
+struct Rays {
+    /// All squares along each ray up to the edge of the board.
+    masks: [u64; 4],
+    /// The number of squares along each ray.
+    lengths: [u32; 4],
+    /// The change in square index per step along each ray.
+    steps: [i8; 4],
+}
+
+impl Rays {
+    const fn new(directions: &[(i8, i8); 4], square: u8) -> Self {
+        let mut rays = Rays {
+            masks: [0; 4],
+            lengths: [0; 4],
+            steps: [0; 4],
+        };
+        let mut d = 0;
+        while d < 4 {
+            let (dx, dy) = directions[d];
+            rays.steps[d] = dy * 8 + dx;
+            let mut file = (square % 8) as i8 + dx;
+            let mut rank = (square / 8) as i8 + dy;
+            while on_board(file, rank) {
+                rays.masks[d] |= 1 << (rank * 8 + file);
+                rays.lengths[d] += 1;
+                file += dx;
+                rank += dy;
+            }
+            d += 1;
+        }
+        rays
+    }
+
+    /// Returns the number of distinct attack sets, one for every combination of first blockers.
+    const fn set_count(&self) -> usize {
+        let mut count = 1;
+        let mut d = 0;
+        while d < 4 {
+            if self.lengths[d] > 0 {
+                count *= self.lengths[d] as usize;
+            }
+            d += 1;
+        }
+        count
+    }
+
+    /// Returns the position of the attack set for `occupied` among the distinct attack sets,
+    /// reading the distance to the first blocker on every ray as one digit.
+    const fn set_rank(&self, square: u8, occupied: u64) -> usize {
+        let mut rank = 0;
+        let mut stride = 1;
+        let mut d = 0;
+        while d < 4 {
+            let length = self.lengths[d];
+            if length > 0 {
+                let blockers = occupied & self.masks[d];
+                let distance = if blockers == 0 {
+                    length
+                } else {
+                    // The first blocker is the one closest to `square`
+                    let first = if self.steps[d] > 0 {
+                        blockers.trailing_zeros()
+                    } else {
+                        63 - blockers.leading_zeros()
+                    };
+                    first.abs_diff(square as u32) / self.steps[d].unsigned_abs() as u32
+                };
+                rank += (distance - 1) as usize * stride;
+                stride *= length as usize;
+            }
+            d += 1;
+        }
+        rank
+    }
+}
+
+const fn attack_set_count(directions: &[(i8, i8); 4]) -> usize {
+    let mut count = 0;
+    let mut square = 0;
+    while square < 64 {
+        count += Rays::new(directions, square).set_count();
+        square += 1;
+    }
+    count
+}
+
+/// Lists every square's distinct attack sets, each computed from one blocker configuration
+/// that produces it.
+const fn attack_sets<const N: usize>(directions: &[(i8, i8); 4]) -> [u64; N] {
+    let mut sets = [0; N];
+    let mut base = 0;
+    let mut square = 0;
+    while square < 64 {
+        let rays = Rays::new(directions, square);
+        let count = rays.set_count();
+        let mut rank = 0;
+        while rank < count {
+            // Decode the distance to the first blocker on every ray and place a blocker there
+            let mut occupied = 0u64;
+            let mut remaining = rank;
+            let mut d = 0;
+            while d < 4 {
+                let length = rays.lengths[d] as usize;
+                if length > 0 {
+                    let distance = (remaining % length) as i8 + 1;
+                    remaining /= length;
+                    occupied |= 1 << (square as i8 + distance * rays.steps[d]);
+                }
+                d += 1;
+            }
+            sets[base + rank] = slider_attacks(directions, square, occupied);
+            rank += 1;
+        }
+        base += count;
+        square += 1;
+    }
+    sets
+}
+
+const fn set_indices<const N: usize>(
+    directions: &[(i8, i8); 4],
+    entries: &[MagicTableEntry; 64],
+) -> [u16; N] {
+    let mut table = [0; N];
+    let mut base = 0;
+    let mut square = 0;
+    while square < 64 {
+        let entry = &entries[square];
+        let rays = Rays::new(directions, square as u8);
+        // Walk through all subsets of the mask with the carry-rippler trick
+        let mut occupied = 0u64;
+        loop {
+            let index = (occupied.wrapping_mul(entry.magic) >> entry.shift) as usize;
+            table[entry.offset as usize + index] =
+                (base + rays.set_rank(square as u8, occupied)) as u16;
+            occupied = occupied.wrapping_sub(entry.mask) & entry.mask;
+            if occupied == 0 {
+                break;
+            }
+        }
+        base += rays.set_count();
+        square += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{magics::generate, Piece};
+
+    #[test]
+    fn compact_tables_match_full_tables() {
+        let rook_moves: Vec<u64> = ROOK_SET_INDICES
+            .iter()
+            .map(|&i| ROOK_ATTACK_SETS[i as usize])
+            .collect();
+        assert!(generate::verify(Piece::ROOK, ROOK_MAGICS, &rook_moves));
+        let bishop_moves: Vec<u64> = BISHOP_SET_INDICES
+            .iter()
+            .map(|&i| BISHOP_ATTACK_SETS[i as usize])
+            .collect();
+        assert!(generate::verify(
+            Piece::BISHOP,
+            BISHOP_MAGICS,
+            &bishop_moves
+        ));
+    }
+}
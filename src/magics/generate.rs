@@ -0,0 +1,227 @@
+//! Runtime generation and verification of magic bitboard tables.
+//!
+//! The magics shipped in [`crate::magics`] were produced with this kind of search. Use
+//! [`generate`] to build fresh tables, [`generate_with_bits`] to experiment with denser
+//! layouts and [`verify`] to check any set of tables against a plain ray walk.
+
+use super::{BISHOP_DIRECTIONS, ROOK_DIRECTIONS};
+use crate::{bitboard::Bitboard, movegen::magic_index, MagicTableEntry, Piece, Square};
+
+/// How many candidate magics are tried per square before giving up.
+const MAX_ATTEMPTS: usize = 10_000_000;
+
+/// Magic entries for all 64 squares together with the attack table they index into.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MagicTables {
+    pub entries: Vec<MagicTableEntry>,
+    pub moves: Vec<u64>,
+}
+
+impl MagicTables {
+    /// Looks up the attacks from `square` with the squares in `occupied` blocked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{bitboard::Bitboard, magics::generate, Piece, Square};
+    /// let tables = generate::generate(Piece::BISHOP, 1);
+    /// let attacks = tables.attacks(Square::C1, Bitboard::from_square(Square::E3));
+    /// assert_eq!(attacks.0, 0x110a00);
+    /// ```
+    pub fn attacks(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        Bitboard::from_u64(self.moves[magic_index(&self.entries[square as usize], occupied)])
+    }
+}
+
+/// Returns the squares whose occupancy can block a rook or bishop on `square`.
+/// Edge squares are left out, since a slider reaches them whether they are occupied or not.
+///
+/// # Panics
+///
+/// Panics if `piece` is not a rook or a bishop.
+pub fn relevant_occupancy(piece: Piece, square: Square) -> u64 {
+    super::relevant_occupancy(directions(piece), square as u8)
+}
+
+/// Returns the squares a rook or bishop on `square` attacks, walking each ray until the edge
+/// of the board or the first square in `occupied`.
+///
+/// # Panics
+///
+/// Panics if `piece` is not a rook or a bishop.
+pub fn slow_attacks(piece: Piece, square: Square, occupied: u64) -> u64 {
+    super::slider_attacks(directions(piece), square as u8, occupied)
+}
+
+/// Finds magics for all squares, using as many index bits as each square has relevant
+/// occupancy squares. The search is deterministic for a given `seed`.
+///
+/// # Panics
+///
+/// Panics if `piece` is not a rook or a bishop.
+pub fn generate(piece: Piece, seed: u64) -> MagicTables {
+    generate_with_bits(piece, seed, |square| {
+        relevant_occupancy(piece, square).count_ones() as u8
+    })
+    .expect("a magic with one index bit per relevant square always exists")
+}
+
+/// Finds magics for all squares, using `bits(square)` index bits for each square.
+/// Returns `None` if no magic is found for some square, which becomes likely once fewer bits
+/// than relevant occupancy squares are requested.
+///
+/// # Panics
+///
+/// Panics if `piece` is not a rook or a bishop.
+pub fn generate_with_bits(
+    piece: Piece,
+    seed: u64,
+    bits: impl Fn(Square) -> u8,
+) -> Option<MagicTables> {
+    let mut rng = XorShift(seed.max(1));
+    let mut entries = Vec::with_capacity(64);
+    let mut moves = Vec::new();
+    for square in Square::all() {
+        let mask = relevant_occupancy(piece, square);
+        let bits = bits(square);
+        let (magic, table) = find_magic(piece, square, mask, bits, &mut rng)?;
+        entries.push(MagicTableEntry {
+            mask,
+            magic,
+            shift: 64 - bits,
+            offset: moves.len() as u32,
+        });
+        moves.extend(table);
+    }
+    Some(MagicTables { entries, moves })
+}
+
+/// Checks that `entries` and `moves` give the correct attacks for every square and every
+/// blocker configuration.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{magics::{generate, ROOK_MAGICS, ROOK_MOVES}, Piece};
+/// assert!(generate::verify(Piece::ROOK, ROOK_MAGICS, ROOK_MOVES));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `piece` is not a rook or a bishop.
+pub fn verify(piece: Piece, entries: &[MagicTableEntry], moves: &[u64]) -> bool {
+    entries.len() == 64
+        && Square::all().all(|square| {
+            let entry = &entries[square as usize];
+            entry.mask == relevant_occupancy(piece, square)
+                && subsets(entry.mask).all(|occupied| {
+                    moves.get(magic_index(entry, Bitboard::from_u64(occupied)))
+                        == Some(&slow_attacks(piece, square, occupied))
+                })
+        })
+}
+
+fn directions(piece: Piece) -> &'static [(i8, i8); 4] {
+    match piece {
+        Piece::ROOK => &ROOK_DIRECTIONS,
+        Piece::BISHOP => &BISHOP_DIRECTIONS,
+        _ => panic!("Non-slider piece passed to magic generation"),
+    }
+}
+
+/// Iterates over all subsets of `mask`, starting with the empty set.
+fn subsets(mask: u64) -> impl Iterator<Item = u64> {
+    let mut next = Some(0u64);
+    std::iter::from_fn(move || {
+        let current = next?;
+        // Carry-rippler trick to step to the next subset
+        let following = current.wrapping_sub(mask) & mask;
+        next = (following != 0).then_some(following);
+        Some(current)
+    })
+}
+
+fn find_magic(
+    piece: Piece,
+    square: Square,
+    mask: u64,
+    bits: u8,
+    rng: &mut XorShift,
+) -> Option<(u64, Vec<u64>)> {
+    let occupancies: Vec<u64> = subsets(mask).collect();
+    let attacks: Vec<u64> = occupancies
+        .iter()
+        .map(|&occupied| slow_attacks(piece, square, occupied))
+        .collect();
+
+    let shift = 64 - bits;
+    let mut table = vec![0u64; 1 << bits];
+    // The attempt in which each table slot was last written, so the table never needs clearing
+    let mut used = vec![0usize; 1 << bits];
+    'candidates: for attempt in 1..=MAX_ATTEMPTS {
+        // Sparse candidates make much better magics
+        let magic = rng.next() & rng.next() & rng.next();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+
+        for (&occupied, &attack) in occupancies.iter().zip(&attacks) {
+            let index = (occupied.wrapping_mul(magic) >> shift) as usize;
+            if used[index] != attempt {
+                used[index] = attempt;
+                table[index] = attack;
+            } else if table[index] != attack {
+                continue 'candidates;
+            }
+        }
+        return Some((magic, table));
+    }
+    None
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::magics::{BISHOP_MAGICS, BISHOP_MOVES, ROOK_MAGICS};
+
+    #[test]
+    fn shipped_tables_are_valid() {
+        assert!(verify(Piece::BISHOP, BISHOP_MAGICS, BISHOP_MOVES));
+        for square in Square::all() {
+            assert_eq!(
+                ROOK_MAGICS[square as usize].mask,
+                relevant_occupancy(Piece::ROOK, square)
+            );
+        }
+    }
+
+    #[test]
+    fn generated_tables_are_valid() {
+        let bishops = generate(Piece::BISHOP, 42);
+        assert_eq!(bishops.moves.len(), BISHOP_MOVES.len());
+        assert!(verify(Piece::BISHOP, &bishops.entries, &bishops.moves));
+        assert_eq!(bishops, generate(Piece::BISHOP, 42));
+
+        let rooks = generate(Piece::ROOK, 42);
+        assert!(verify(Piece::ROOK, &rooks.entries, &rooks.moves));
+    }
+
+    #[test]
+    fn corrupted_tables_fail_verification() {
+        let mut bishops = generate(Piece::BISHOP, 7);
+        let index = magic_index(&bishops.entries[0], Bitboard::empty());
+        bishops.moves[index] ^= 1;
+        assert!(!verify(Piece::BISHOP, &bishops.entries, &bishops.moves));
+    }
+}
@@ -0,0 +1,151 @@
+//! Runs the engine on stdin and stdout. It speaks CECP if the first command is `xboard` and
+//! UCI otherwise. With `bench [depth]` it runs the [bench](kritisch::search::bench) instead,
+//! with `datagen [games]` it writes [self-play data](kritisch::search::datagen), with
+//! `book <games.pgn> <book.bin>` it [builds](kritisch::book::builder) a Polyglot book, with
+//! `suite <suite.epd> [milliseconds]` it runs a [test suite](kritisch::search::suite), with
+//! `index <games.pgn> <index.bin>` it [indexes](kritisch::pgn::index) the positions of a
+//! database and with `find <index.bin> <fen>` it prints the numbers of the games, counting
+//! from 0, that reached a position.
+
+use std::io::{self, BufRead, Read};
+use std::time::Duration;
+
+use kritisch::{
+    book::builder::{BookBuilder, BuildOptions},
+    game::Game,
+    pgn::{self, index::PositionIndex},
+    search::{
+        bench::{bench, DEFAULT_DEPTH},
+        datagen::{generate, DatagenOptions, Format},
+        suite, SearchLimits,
+    },
+};
+
+/// The number of games `datagen` plays by default.
+const DEFAULT_GAMES: usize = 100;
+/// The milliseconds `suite` searches every position for by default.
+const DEFAULT_SUITE_MILLIS: u64 = 1000;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "bench") {
+        let depth = match args.get(1).map(|depth| depth.parse()) {
+            Some(Ok(depth)) => depth,
+            Some(Err(e)) => {
+                eprintln!("invalid depth: {e}");
+                std::process::exit(2);
+            }
+            None => DEFAULT_DEPTH,
+        };
+        println!("{}", bench(depth));
+        return Ok(());
+    }
+    if args.first().is_some_and(|arg| arg == "datagen") {
+        let games = match args.get(1).map(|games| games.parse()) {
+            Some(Ok(games)) => games,
+            Some(Err(e)) => {
+                eprintln!("invalid number of games: {e}");
+                std::process::exit(2);
+            }
+            None => DEFAULT_GAMES,
+        };
+        let options = DatagenOptions::default();
+        let written = generate(&mut io::stdout().lock(), &options, games, Format::TEXT)?;
+        eprintln!("{written} positions from {games} games");
+        return Ok(());
+    }
+    if args.first().is_some_and(|arg| arg == "book") {
+        let (Some(pgn_path), Some(book_path)) = (args.get(1), args.get(2)) else {
+            eprintln!("usage: kritisch book <games.pgn> <book.bin>");
+            std::process::exit(2);
+        };
+        let mut builder = BookBuilder::new(BuildOptions::default());
+        for game in pgn::parse(&std::fs::read_to_string(pgn_path)?) {
+            match game {
+                Ok(game) => builder.add_pgn(&game),
+                Err(e) => eprintln!("skipping a game: {e}"),
+            }
+        }
+        let book = builder.build();
+        std::fs::write(book_path, book.to_bytes())?;
+        eprintln!("{} entries written to {book_path}", book.len());
+        return Ok(());
+    }
+    if args.first().is_some_and(|arg| arg == "index") {
+        let (Some(pgn_path), Some(index_path)) = (args.get(1), args.get(2)) else {
+            eprintln!("usage: kritisch index <games.pgn> <index.bin>");
+            std::process::exit(2);
+        };
+        let games = pgn::parse(&std::fs::read_to_string(pgn_path)?);
+        for e in games.iter().filter_map(|game| game.as_ref().err()) {
+            eprintln!("skipping a game: {e}");
+        }
+        let index = PositionIndex::from_games(&games);
+        std::fs::write(index_path, index.to_bytes())?;
+        eprintln!(
+            "{} entries of {} games written to {index_path}",
+            index.len(),
+            games.len()
+        );
+        return Ok(());
+    }
+    if args.first().is_some_and(|arg| arg == "find") {
+        let (Some(index_path), Some(fen)) = (args.get(1), args.get(2)) else {
+            eprintln!("usage: kritisch find <index.bin> <fen>");
+            std::process::exit(2);
+        };
+        let bytes = std::fs::read(index_path)?;
+        let found =
+            Game::from_fen(fen).and_then(|game| Ok(PositionIndex::from_bytes(&bytes)?.find(&game)));
+        match found {
+            Ok(games) => games.iter().for_each(|game| println!("{game}")),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if args.first().is_some_and(|arg| arg == "suite") {
+        let Some(path) = args.get(1) else {
+            eprintln!("usage: kritisch suite <suite.epd> [milliseconds]");
+            std::process::exit(2);
+        };
+        let millis = match args.get(2).map(|millis| millis.parse()) {
+            Some(Ok(millis)) => millis,
+            Some(Err(e)) => {
+                eprintln!("invalid time: {e}");
+                std::process::exit(2);
+            }
+            None => DEFAULT_SUITE_MILLIS,
+        };
+        let positions = match suite::parse(&std::fs::read_to_string(path)?) {
+            Ok(positions) => positions,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
+        let limits = SearchLimits::time(Duration::from_millis(millis));
+        let result = suite::run(&positions, &limits);
+        for position in result.results.iter().filter(|position| !position.solved) {
+            let best_move = position
+                .best_move
+                .map_or("none".to_string(), |m| m.to_string());
+            println!("{} failed with {best_move}", position.id);
+        }
+        println!("{result}");
+        return Ok(());
+    }
+
+    let mut stdin = io::stdin().lock();
+    let mut first = String::new();
+    stdin.read_line(&mut first)?;
+    // Hand the first command on to the protocol as well
+    let input = first.as_bytes().chain(stdin);
+    if first.trim() == "xboard" {
+        kritisch::cecp::run(input, io::stdout())
+    } else {
+        kritisch::uci::run(input, io::stdout())
+    }
+}
@@ -0,0 +1,353 @@
+//! Engine-versus-engine matches between two configurations of the engine, e.g. to find out
+//! whether a change to the search or the evaluation makes it stronger.
+//!
+//! Every opening is played twice, once with each [`Player`] as White, so that neither profits
+//! from the better side of an opening. A game ends by the rules, threefold repetition
+//! included, when a player runs out of time, when it fails to answer with a legal move, or as
+//! a draw after [`max_plies`](MatchOptions::max_plies). A player can be any [`Engine`],
+//! including an external one through the [UCI client](crate::uci::client), and learns the
+//! positions of the game so far through [`Engine::set_history`]. The games come back as
+//! [`PgnGame`]s, and the [`Score`] sums them up from the first player's point of view.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{matches::{play_match, MatchOptions, Player}, search::{SearchLimits, Searcher}};
+//! let mut players = [
+//!     Player::new("depth 2", Searcher::default()),
+//!     Player::new("depth 1", Searcher::default()),
+//! ];
+//! players[0].limits = SearchLimits::depth(2);
+//! players[1].limits = SearchLimits::depth(1);
+//! let options = MatchOptions { clock: None, max_plies: 20, ..MatchOptions::default() };
+//! let result = play_match(&mut players, &options);
+//! assert_eq!(result.score.games(), 2);
+//! println!("{}\n{}", result.pgn(), result.score);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    game::{Game, Outcome},
+    movegen::all_legal_moves,
+    pgn::{format_result, PgnGame},
+    search::{time::TimeControl, Engine, SearchLimits},
+    Color,
+};
+
+/// One side of a match.
+pub struct Player {
+    /// The name in the `White` and `Black` tags of the games.
+    pub name: String,
+    pub engine: Box<dyn Engine>,
+    /// The limits of every search, on top of the clock if the match has one.
+    pub limits: SearchLimits,
+}
+
+impl Player {
+    /// Creates a player that searches without limits other than the clock.
+    pub fn new(name: impl Into<String>, engine: impl Engine + 'static) -> Self {
+        Self {
+            name: name.into(),
+            engine: Box::new(engine),
+            limits: SearchLimits::default(),
+        }
+    }
+}
+
+/// How the games of a match are played.
+#[derive(Debug, Clone)]
+pub struct MatchOptions {
+    /// The positions the games start from, each played twice.
+    pub openings: Vec<Game>,
+    /// The clocks at the start of every game, or `None` to play without a clock. Then every
+    /// player needs [`limits`](Player::limits).
+    pub clock: Option<TimeControl>,
+    /// The length in plies after which a game that hasn't ended is adjudicated a draw.
+    pub max_plies: u32,
+    /// The name in the `Event` tag of the games.
+    pub event: String,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            openings: vec![Game::default()],
+            clock: Some(TimeControl {
+                time: [Duration::from_secs(10); 2],
+                increment: [Duration::from_millis(100); 2],
+                moves_to_go: None,
+            }),
+            max_plies: 400,
+            event: "kritisch match".to_string(),
+        }
+    }
+}
+
+/// The results of a match from one player's point of view.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the points scored, one for a win and half for a draw.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// Returns the difference in Elo between the player and the opponent that the score
+    /// suggests, or `None` if the player won or lost every game.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::matches::Score;
+    /// let score = Score { wins: 3, draws: 4, losses: 1 };
+    /// assert_eq!(score.elo_difference().unwrap().round(), 89.0);
+    /// assert_eq!(Score { wins: 2, draws: 0, losses: 0 }.elo_difference(), None);
+    /// ```
+    pub fn elo_difference(&self) -> Option<f64> {
+        let share = self.points() / self.games() as f64;
+        (share > 0.0 && share < 1.0).then(|| -400.0 * (1.0 / share - 1.0).log10())
+    }
+
+    fn add(&mut self, outcome: Option<Outcome>, player_is_white: bool) {
+        match outcome {
+            Some(Outcome::DECISIVE { winner }) if (winner == Color::WHITE) == player_is_white => {
+                self.wins += 1
+            }
+            Some(Outcome::DECISIVE { .. }) => self.losses += 1,
+            Some(Outcome::DRAW) | None => self.draws += 1,
+        }
+    }
+}
+
+/// Displays the score as wins, draws and losses with the points and the Elo difference, e.g.
+/// `+3 =4 -1, 5.0/8, Elo +89`.
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} ={} -{}, {:.1}/{}",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.points(),
+            self.games()
+        )?;
+        if let Some(elo) = self.elo_difference() {
+            write!(f, ", Elo {elo:+.0}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The games of a match and their score for the first player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchResult {
+    pub games: Vec<PgnGame>,
+    pub score: Score,
+}
+
+impl MatchResult {
+    /// Returns all games in PGN, separated by empty lines.
+    pub fn pgn(&self) -> String {
+        let games: Vec<String> = self.games.iter().map(PgnGame::to_string).collect();
+        games.join("\n")
+    }
+}
+
+/// Plays every opening of `options` twice between the two `players`, the first player taking
+/// White in the first game of each opening.
+pub fn play_match(players: &mut [Player; 2], options: &MatchOptions) -> MatchResult {
+    let [first, second] = players;
+    let mut games = Vec::new();
+    let mut score = Score::default();
+    for opening in &options.openings {
+        for first_is_white in [true, false] {
+            let round = games.len() + 1;
+            let game = if first_is_white {
+                play_game(first, second, opening, options, round)
+            } else {
+                play_game(second, first, opening, options, round)
+            };
+            score.add(game.outcome, first_is_white);
+            games.push(game);
+        }
+    }
+    MatchResult { games, score }
+}
+
+/// Plays one game of a match from `opening`, the `round` of the match.
+pub fn play_game(
+    white: &mut Player,
+    black: &mut Player,
+    opening: &Game,
+    options: &MatchOptions,
+    round: usize,
+) -> PgnGame {
+    white.engine.new_game();
+    black.engine.new_game();
+    let mut game = *opening;
+    let mut moves = Vec::new();
+    // The keys of the positions before the current one
+    let mut history = Vec::new();
+    let mut clock = options.clock;
+    let mut termination = None;
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+        let key = game.key();
+        if history.iter().filter(|&&k| k == key).count() >= 2 {
+            break Outcome::DRAW;
+        }
+        if moves.len() >= options.max_plies as usize {
+            termination = Some("adjudication");
+            break Outcome::DRAW;
+        }
+        let player = match game.to_move {
+            Color::WHITE => &mut *white,
+            Color::BLACK => &mut *black,
+        };
+        let mut limits = player.limits.clone();
+        limits.clock = clock;
+        player.engine.set_history(&history);
+        let started = Instant::now();
+        let result = player.engine.search(&game, limits);
+        if let Some(clock) = &mut clock {
+            let side = game.to_move as usize;
+            let Some(left) = clock.time[side].checked_sub(started.elapsed()) else {
+                termination = Some("time forfeit");
+                break Outcome::DECISIVE {
+                    winner: !game.to_move,
+                };
+            };
+            clock.time[side] = left + clock.increment[side];
+        }
+        // External engines may fail to answer with a legal move
+        let Some(m) = result
+            .best_move
+            .filter(|m| all_legal_moves(&game).contains(m))
+        else {
+            termination = Some("rules infraction");
+            break Outcome::DECISIVE {
+                winner: !game.to_move,
+            };
+        };
+        history.push(key);
+        game.make_move(m);
+        moves.push(m);
+    };
+
+    let mut tags = vec![
+        ("Event", options.event.clone()),
+        ("Round", round.to_string()),
+        ("White", white.name.clone()),
+        ("Black", black.name.clone()),
+        ("Result", format_result(Some(outcome)).to_string()),
+    ];
+    if *opening != Game::default() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", opening.to_fen()));
+    }
+    if let Some(termination) = termination {
+        tags.push(("Termination", termination.to_string()));
+    }
+    PgnGame {
+        tags: tags
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+        start: *opening,
+        moves,
+        outcome: Some(outcome),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pgn, search::Searcher};
+
+    #[test]
+    fn plays_every_opening_with_both_colors() {
+        let mut players = [
+            Player::new("strong", Searcher::default()),
+            Player::new("weak", Searcher::default()),
+        ];
+        players[0].limits = SearchLimits::depth(3);
+        players[1].limits = SearchLimits::depth(1);
+        let options = MatchOptions {
+            openings: vec![
+                Game::default(),
+                Game::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").unwrap(),
+            ],
+            clock: None,
+            max_plies: 40,
+            ..MatchOptions::default()
+        };
+        let result = play_match(&mut players, &options);
+        assert_eq!(result.score.games(), 4);
+        let names: Vec<_> = result.games.iter().map(|game| game.tag("White")).collect();
+        assert_eq!(
+            names,
+            [Some("strong"), Some("weak"), Some("strong"), Some("weak")]
+        );
+        assert_eq!(result.games[2].tag("SetUp"), Some("1"));
+
+        let read: Vec<_> = pgn::parse(&result.pgn())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, result.games);
+    }
+
+    #[test]
+    fn forfeits_on_time() {
+        let mut players = [
+            Player::new("a", Searcher::default()),
+            Player::new("b", Searcher::default()),
+        ];
+        players[0].limits = SearchLimits::depth(1);
+        players[1].limits = SearchLimits::depth(1);
+        let options = MatchOptions {
+            clock: Some(TimeControl::default()),
+            ..MatchOptions::default()
+        };
+        let [white, black] = &mut players;
+        let game = play_game(white, black, &Game::default(), &options, 1);
+        assert_eq!(game.tag("Termination"), Some("time forfeit"));
+        assert_eq!(game.tag("Result"), Some("0-1"));
+    }
+
+    #[test]
+    fn draws_by_threefold_repetition() {
+        // Both players would rather draw than play on
+        let player = |name| {
+            let mut searcher = Searcher::default();
+            searcher.contempt = -100;
+            let mut player = Player::new(name, searcher);
+            player.limits = SearchLimits::depth(2);
+            player
+        };
+        let (mut white, mut black) = (player("a"), player("b"));
+        let options = MatchOptions {
+            clock: None,
+            max_plies: 100,
+            ..MatchOptions::default()
+        };
+        let opening = Game::from_fen("7k/7n/8/8/8/8/N7/K7 w - - 0 1").unwrap();
+        let game = play_game(&mut white, &mut black, &opening, &options, 1);
+        assert_eq!(game.outcome, Some(Outcome::DRAW));
+        // Not adjudicated after the last ply
+        assert_eq!(game.tag("Termination"), None);
+    }
+}
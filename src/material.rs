@@ -0,0 +1,168 @@
+//! Material signatures and endgame classification, e.g. for picking the tablebase or the
+//! specialized evaluation of an endgame, or for finding the endgames in a database.
+//!
+//! The signature of a position, see [`Game::material_key`], lists the pieces of the side with
+//! more material and then those of the other side, each from the king down to the pawns, e.g.
+//! `KRPKR`. It doesn't say which side is which, so a position and its mirror image with the
+//! colors swapped share it. [`classify`] sorts endgames into the usual kinds by the pieces
+//! left, and [`men`] counts them the way tablebases do.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, material::{classify, men, Endgame}};
+//! // The Lucena position
+//! let game = Game::from_fen("1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1").unwrap();
+//! assert_eq!(game.material_key(), "KRPKR");
+//! assert_eq!(classify(&game), Some(Endgame::ROOK));
+//! assert_eq!(men(&game), 5);
+//! assert_eq!(classify(&Game::default()), None);
+//! ```
+
+use crate::{game::Game, phase::ENDGAME_PIECES, Color, Piece};
+
+/// The kind of an endgame, by the pieces other than kings and pawns left on the board.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Endgame {
+    /// Only kings, with or without pawns.
+    PAWN,
+    /// Knights and bishops, unless they are opposite colored bishops.
+    MINOR_PIECE,
+    /// A single bishop each, on squares of different colors.
+    OPPOSITE_BISHOPS,
+    /// Only rooks.
+    ROOK,
+    /// Rooks and minor pieces.
+    ROOK_AND_MINOR,
+    /// Only queens.
+    QUEEN,
+    /// Any other mix of pieces, i.e. queens with other pieces.
+    MIXED,
+}
+
+/// Returns the kind of endgame `game` is, or `None` if more than [`ENDGAME_PIECES`] knights,
+/// bishops, rooks and queens are left, as in a
+/// [`GamePhase::ENDGAME`](crate::phase::GamePhase::ENDGAME).
+pub fn classify(game: &Game) -> Option<Endgame> {
+    let count =
+        |piece| game.piece_count(Color::WHITE, piece) + game.piece_count(Color::BLACK, piece);
+    let [knights, bishops, rooks, queens] =
+        [Piece::KNIGHT, Piece::BISHOP, Piece::ROOK, Piece::QUEEN].map(count);
+    if knights + bishops + rooks + queens > ENDGAME_PIECES {
+        return None;
+    }
+    let minors = knights + bishops;
+    Some(match (minors, rooks, queens) {
+        (0, 0, 0) => Endgame::PAWN,
+        (_, 0, 0) if is_opposite_bishops(game) => Endgame::OPPOSITE_BISHOPS,
+        (_, 0, 0) => Endgame::MINOR_PIECE,
+        (0, _, 0) => Endgame::ROOK,
+        (_, _, 0) => Endgame::ROOK_AND_MINOR,
+        (0, 0, _) => Endgame::QUEEN,
+        _ => Endgame::MIXED,
+    })
+}
+
+/// Returns `true` if each side has a single bishop and no knight, and the bishops stand on
+/// squares of different colors.
+fn is_opposite_bishops(game: &Game) -> bool {
+    const DARK_SQUARES: u64 = 0xaa55aa55aa55aa55;
+    let on_dark_squares = |color: Color| {
+        let bishops =
+            game.piece_bitboards[Piece::BISHOP as usize] & game.color_bitboards[color as usize];
+        let alone = game.piece_count(color, Piece::BISHOP) == 1
+            && game.piece_count(color, Piece::KNIGHT) == 0;
+        alone.then(|| !(bishops & DARK_SQUARES).is_empty())
+    };
+    matches!(
+        (on_dark_squares(Color::WHITE), on_dark_squares(Color::BLACK)),
+        (Some(white), Some(black)) if white != black
+    )
+}
+
+/// Returns the number of men on the board, i.e. all pieces including kings and pawns, by which
+/// tablebases are sorted.
+pub fn men(game: &Game) -> u32 {
+    Color::both()
+        .into_iter()
+        .flat_map(|color| Piece::ALL.map(|piece| game.piece_count(color, piece)))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classified(fen: &str) -> Option<Endgame> {
+        classify(&Game::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn classifies_by_the_pieces_left() {
+        assert_eq!(
+            classified("4k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Some(Endgame::PAWN)
+        );
+        assert_eq!(
+            classified("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1"),
+            Some(Endgame::PAWN)
+        );
+        assert_eq!(
+            classified("4k3/5b2/8/8/8/8/2B5/4K3 w - - 0 1"),
+            Some(Endgame::MINOR_PIECE)
+        );
+        assert_eq!(
+            classified("4k3/4b3/8/8/8/8/2B5/4K3 w - - 0 1"),
+            Some(Endgame::OPPOSITE_BISHOPS)
+        );
+        assert_eq!(
+            classified("4k3/4n3/8/8/8/8/2B5/4K3 w - - 0 1"),
+            Some(Endgame::MINOR_PIECE)
+        );
+        assert_eq!(
+            classified("4k3/4b3/8/8/8/8/2BN4/4K3 w - - 0 1"),
+            Some(Endgame::MINOR_PIECE)
+        );
+        assert_eq!(
+            classified("r3k3/8/8/8/8/8/8/R3K3 w - - 0 1"),
+            Some(Endgame::ROOK)
+        );
+        assert_eq!(
+            classified("r3k3/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+            Some(Endgame::ROOK_AND_MINOR)
+        );
+        assert_eq!(
+            classified("q3k3/8/8/8/8/8/8/3QK3 w - - 0 1"),
+            Some(Endgame::QUEEN)
+        );
+        assert_eq!(
+            classified("q3k3/8/8/8/8/8/8/R3K3 w - - 0 1"),
+            Some(Endgame::MIXED)
+        );
+        assert_eq!(classified("r1b1kb1r/8/8/8/8/8/8/R1B1KB1R w - - 0 1"), None);
+    }
+
+    #[test]
+    fn material_key_puts_the_stronger_side_first() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert_eq!(game.material_key(), "KRK");
+        let mirrored = Game::from_fen("4k2r/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(mirrored.material_key(), "KRK");
+        assert_eq!(
+            Game::default().material_key(),
+            "KQRRBBNNPPPPPPPPKQRRBBNNPPPPPPPP"
+        );
+
+        // A bishop and a knight are worth different amounts, so the order doesn't depend on
+        // the colors, and neither does it for sides of equal worth
+        let game = Game::from_fen("4k3/4n3/8/8/8/8/2B5/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.material_key(), "KBKN");
+        let game = Game::from_fen("4k3/4b3/8/8/8/8/2N5/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.material_key(), "KBKN");
+        let game = Game::from_fen("4k3/4b3/8/8/8/8/2BP4/4K3 b - - 0 1").unwrap();
+        assert_eq!(game.material_key(), "KBPKB");
+        assert_eq!(men(&game), 5);
+        assert_eq!(men(&Game::default()), 32);
+    }
+}
@@ -0,0 +1,83 @@
+//! Mobility, the number of squares the pieces can move to, e.g. for evaluation or for showing a
+//! learner which pieces are short of room.
+//!
+//! Only safe squares count, those neither occupied by a piece of the same color nor attacked by
+//! an opposing pawn, since a piece that goes there can be taken by a pawn for less than it is
+//! worth. Pawns have no mobility of their own, because they don't move to the squares they
+//! attack. The [`Mobility`](crate::eval::Mobility) evaluation term scores the result.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, mobility, Color, Piece, Square};
+//! // The pawn on e7 takes d6 and f6 away from the knight
+//! let game = Game::from_fen("4k3/4p3/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+//! assert_eq!(mobility::piece_mobility(&game, Square::E4), 6);
+//! let white = mobility::mobility(&game, Color::WHITE);
+//! assert_eq!(white[Piece::KNIGHT as usize], 6);
+//! assert_eq!(white[Piece::KING as usize], 5);
+//! ```
+
+use crate::{bitboard::Bitboard, game::Game, pawns, Color, Piece, Square};
+
+/// Returns the squares `color`'s pieces can safely move to, i.e. those without a piece of
+/// `color` on them that no opposing pawn attacks.
+pub fn safe_squares(game: &Game, color: Color) -> Bitboard {
+    !(game.color_bitboards[color as usize] | pawns::attacks(game, !color))
+}
+
+/// Returns the number of safe squares the piece on `s` attacks, or 0 if `s` is empty or holds
+/// a pawn.
+pub fn piece_mobility(game: &Game, s: Square) -> u32 {
+    match game.piece_at(s) {
+        Some(p) if p.piece != Piece::PAWN => {
+            (game.attacks_from(s) & safe_squares(game, p.color)).count_ones()
+        }
+        _ => 0,
+    }
+}
+
+/// Returns the mobility of `color`'s pieces summed per piece type, indexed by [`Piece`].
+pub fn mobility(game: &Game, color: Color) -> [u32; 6] {
+    let safe = safe_squares(game, color);
+    let mut mobility = [0; 6];
+    let mut pieces =
+        game.color_bitboards[color as usize] & !game.piece_bitboards[Piece::PAWN as usize];
+    while !pieces.is_empty() {
+        let s = Square::from_u8(pieces.trailing_zeros() as u8);
+        mobility[game.type_at(s) as usize] += (game.attacks_from(s) & safe).count_ones();
+        pieces.clear_lsb();
+    }
+    mobility
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_squares_of_each_piece() {
+        let game = Game::default();
+        assert_eq!(mobility(&game, Color::WHITE), [0, 4, 0, 0, 0, 0]);
+        assert_eq!(mobility(&game, Color::BLACK), [0, 4, 0, 0, 0, 0]);
+        assert_eq!(piece_mobility(&game, Square::G1), 2);
+        assert_eq!(piece_mobility(&game, Square::E2), 0);
+        assert_eq!(piece_mobility(&game, Square::E4), 0);
+    }
+
+    #[test]
+    fn leaves_out_squares_attacked_by_pawns() {
+        // The rook attacks 14 squares, but the pawn on c6 covers d5
+        let game = Game::from_fen("4k3/8/2p5/8/3R4/8/8/K7 b - - 0 1").unwrap();
+        assert_eq!(piece_mobility(&game, Square::D4), 13);
+        assert!(!safe_squares(&game, Color::WHITE).contains(Square::D5));
+
+        // Squares attacked by other pieces still count
+        let game = Game::from_fen("4k3/8/8/3r4/8/8/8/Q3K3 w - - 0 1").unwrap();
+        assert_eq!(piece_mobility(&game, Square::A1), 17);
+        assert_eq!(
+            mobility(&game, Color::WHITE)[Piece::QUEEN as usize],
+            piece_mobility(&game, Square::A1)
+        );
+    }
+}
@@ -1,10 +1,31 @@
+#[cfg(all(feature = "compact-magics", not(feature = "kindergarten")))]
+use crate::magics::compact::{
+    BISHOP_ATTACK_SETS, BISHOP_SET_INDICES, ROOK_ATTACK_SETS, ROOK_SET_INDICES,
+};
+#[cfg(all(
+    feature = "lazy-tables",
+    not(any(feature = "compact-magics", feature = "kindergarten"))
+))]
+use crate::magics::{lazy_bishop_moves, lazy_rook_moves};
+#[cfg(not(any(
+    feature = "compact-magics",
+    feature = "kindergarten",
+    feature = "lazy-tables"
+)))]
+use crate::magics::{BISHOP_MOVES, ROOK_MOVES};
 use crate::{
     bitboard::Bitboard,
-    game::Game,
-    magics::{BISHOP_MAGICS, BISHOP_MOVES, ROOK_MAGICS, ROOK_MOVES},
-    try_square_offset, CastlingRights, Color, MagicTableEntry, Move, Piece, Rank, Square,
+    game::{Game, Outcome},
+    magics::{BISHOP_MAGICS, ROOK_MAGICS},
+    try_square_offset,
+    zobrist::splitmix64,
+    CastlingSide, Color, ColoredPiece, MagicTableEntry, Move, MoveList, Piece, Rank, Square,
 };
 
+mod retro;
+
+pub use retro::{retro_moves, RetroMove};
+
 /// Pawn attack patterns are known at compile time and
 /// can be masked to get them from the correct rank
 const PAWN_ATTACKS: [[u64; 8]; 2] = [
@@ -33,72 +54,35 @@ const PAWN_ATTACKS: [[u64; 8]; 2] = [
 ];
 
 /// All knight moves are known at compile time
-const KNIGHT_MOVES: [u64; 64] = [
-    132096,
-    329728,
-    659712,
-    1319424,
-    2638848,
-    5277696,
-    10489856,
-    4202496,
-    33816580,
-    84410376,
-    168886289,
-    337772578,
-    675545156,
-    1351090312,
-    2685403152,
-    1075839008,
-    8657044482,
-    21609056261,
-    43234889994,
-    86469779988,
-    172939559976,
-    345879119952,
-    687463207072,
-    275414786112,
-    2216203387392,
-    5531918402816,
-    11068131838464,
-    22136263676928,
-    44272527353856,
-    88545054707712,
-    175990581010432,
-    70506185244672,
-    567348067172352,
-    1416171111120896,
-    2833441750646784,
-    5666883501293568,
-    11333767002587136,
-    22667534005174272,
-    45053588738670592,
-    18049583422636032,
-    145241105196122112,
-    362539804446949376,
-    725361088165576704,
-    1450722176331153408,
-    2901444352662306816,
-    5802888705324613632,
-    11533718717099671552,
-    4620693356194824192,
-    288234782788157440,
-    576469569871282176,
-    1224997833292120064,
-    2449995666584240128,
-    4899991333168480256,
-    9799982666336960512,
-    1152939783987658752,
-    2305878468463689728,
-    1128098930098176,
-    2257297371824128,
-    4796069720358912,
-    9592139440717824,
-    19184278881435648,
-    38368557762871296,
-    4679521487814656,
-    9077567998918656,
-];
+const KNIGHT_MOVES: [u64; 64] = knight_table();
+
+const fn knight_table() -> [u64; 64] {
+    const JUMPS: [(i8, i8); 8] = [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut j = 0;
+        while j < JUMPS.len() {
+            let file = (square % 8) as i8 + JUMPS[j].0;
+            let rank = (square / 8) as i8 + JUMPS[j].1;
+            if 0 <= file && file < 8 && 0 <= rank && rank < 8 {
+                table[square] |= 1 << (rank * 8 + file);
+            }
+            j += 1;
+        }
+        square += 1;
+    }
+    table
+}
 
 /// Retrieves the pseudo-legal knight moves for `square` from the lookup table.
 /// Does NOT check for positional legality.
@@ -110,7 +94,7 @@ const KNIGHT_MOVES: [u64; 64] = [
 /// let moves = pseudolegal_knight_moves(Square::C3);
 /// assert_eq!(moves.0, 43234889994);
 /// ```
-pub fn pseudolegal_knight_moves(square: Square) -> Bitboard {
+pub const fn pseudolegal_knight_moves(square: Square) -> Bitboard {
     Bitboard::from_u64(KNIGHT_MOVES[square as usize])
 }
 
@@ -141,10 +125,13 @@ pub fn knight_moves(game: &Game, square: Square) -> Bitboard {
 /// let attacks = pawn_attacks(Square::E2, Color::WHITE);
 /// assert_eq!(attacks.0, 2621440);
 /// ```
-pub fn pawn_attacks(square: Square, color: Color) -> Bitboard {
-    let file_idx = square.get_file() as usize;
-    let rank_idx = (square.get_rank() as usize) - 1.clamp(0, 5);
-    let attacks = PAWN_ATTACKS[color as usize][file_idx] << (8 * rank_idx);
+pub const fn pawn_attacks(square: Square, color: Color) -> Bitboard {
+    let pattern = PAWN_ATTACKS[color as usize][square.get_file() as usize];
+    // The patterns are for pawns on the second rank
+    let attacks = match square.get_rank() {
+        Rank::FIRST => pattern >> 8,
+        rank => pattern << (8 * (rank as u32 - 1)),
+    };
     Bitboard::from_u64(attacks)
 }
 
@@ -176,16 +163,16 @@ pub fn pawn_moves(game: &Game, square: Square) -> Bitboard {
         if game.is_square_empty(offset) {
             moves |= offset;
 
-            // If the pawn is on its initial rank, check if the square two ahead is empty
-            let r = square.get_rank();
-            match (r, color) {
-                (Rank::SECOND, Color::WHITE) | (Rank::SEVENTH, Color::BLACK) => {
-                    let two_ahead = square + 16 * direction;
-                    if game.is_square_empty(two_ahead) {
-                        moves |= two_ahead;
-                    }
+            // If the pawn is on its initial rank, check if the square two ahead is empty. Only
+            // Horde has pawns on the first rank, which may move two squares as well
+            if matches!(
+                square.relative_to(color).get_rank(),
+                Rank::FIRST | Rank::SECOND
+            ) {
+                let two_ahead = square + 16 * direction;
+                if game.is_square_empty(two_ahead) {
+                    moves |= two_ahead;
                 }
-                _ => (),
             }
         }
     }
@@ -217,14 +204,19 @@ pub fn pawn_moves(game: &Game, square: Square) -> Bitboard {
 /// assert_eq!(moves.0, 117768192);
 /// ```
 pub fn king_moves(game: &Game, color: Color) -> Bitboard {
-    let mut moves = Bitboard::empty();
-
     let king_mask =
         game.color_bitboards[color as usize] & game.piece_bitboards[Piece::KING as usize];
     if king_mask.is_empty() {
         panic!("No king found");
     }
     let square = Square::from_u8(king_mask.trailing_zeros() as u8);
+    king_moves_from(game, square, color)
+}
+
+/// Returns the squares `color`'s king on `square` can move to, like [`king_moves`], for
+/// variants where a color may have more than one king.
+fn king_moves_from(game: &Game, square: Square, color: Color) -> Bitboard {
+    let mut moves = Bitboard::empty();
 
     for (dx, dy) in [
         (1, 1),
@@ -243,42 +235,53 @@ pub fn king_moves(game: &Game, color: Color) -> Bitboard {
         }
     }
 
+    // Remove moves that would capture a piece of the same color, then add castling, which
+    // takes the own rook in Chess960
+    let moves = moves & !game.color_bitboards[color as usize];
     // If there currently is no check given, check for castling moves
     if game.in_check.is_none() {
-        match color {
-            Color::WHITE => {
-                if game.castling_rights & CastlingRights::WHITE_KINGSIDE != 0
-                    && game.is_square_empty(Square::F1)
-                    && game.is_square_empty(Square::G1)
-                {
-                    moves |= Square::G1;
-                } else if game.castling_rights & CastlingRights::WHITE_QUEENSIDE != 0
-                    && game.is_square_empty(Square::B1)
-                    && game.is_square_empty(Square::C1)
-                    && game.is_square_empty(Square::D1)
-                {
-                    moves |= Square::C1;
-                }
-            }
-            Color::BLACK => {
-                if game.castling_rights & CastlingRights::BLACK_KINGSIDE != 0
-                    && game.is_square_empty(Square::F8)
-                    && game.is_square_empty(Square::G8)
-                {
-                    moves |= Square::G8;
-                } else if game.castling_rights & CastlingRights::BLACK_QUEENSIDE != 0
-                    && game.is_square_empty(Square::B8)
-                    && game.is_square_empty(Square::C8)
-                    && game.is_square_empty(Square::D8)
-                {
-                    moves |= Square::C8;
-                }
-            }
+        moves | castling_targets(game, color, square)
+    } else {
+        moves
+    }
+}
+
+/// Returns the targets of `color`'s castling moves with the king on `king`: where the king
+/// lands, or the rook's square in Chess960. The squares the king and the rook cross must be
+/// empty but for the two of them, and the king must not cross an attacked square. Whether it
+/// lands on one is left to the legality check, since the rook may still block an attack there.
+fn castling_targets(game: &Game, color: Color, king: Square) -> Bitboard {
+    let mut targets = Bitboard::empty();
+    // Outside of Chess960 the king castles from the e-file only
+    if !game.chess960 && king != Square::E1.relative_to(color) {
+        return targets;
+    }
+    for (side, king_end, rook_end) in [
+        (CastlingSide::KINGSIDE, Square::G1, Square::F1),
+        (CastlingSide::QUEENSIDE, Square::C1, Square::D1),
+    ] {
+        let rook = game.castling_rook(color, side);
+        if !game.castling_rights.allows(color, side)
+            || game.piece_at(rook) != Some(ColoredPiece::new(Piece::ROOK, color))
+        {
+            continue;
+        }
+        let (king_end, rook_end) = (king_end.relative_to(color), rook_end.relative_to(color));
+        let king_path = rank_span(king, king_end);
+        let occupied = game.blockers() ^ king ^ rook;
+        if ((king_path | rank_span(rook, rook_end)) & occupied).is_empty()
+            && ((king_path ^ king_end) & game.rules().checked_squares(game, color)).is_empty()
+        {
+            targets |= if game.chess960 { rook } else { king_end };
         }
     }
+    targets
+}
 
-    // Remove moves that would capture a piece of the same color before returning
-    moves & !game.color_bitboards[color as usize]
+/// Returns the squares from `a` to `b` on their rank, both included.
+fn rank_span(a: Square, b: Square) -> Bitboard {
+    let (low, high) = ((a as u8).min(b as u8), (a as u8).max(b as u8));
+    Bitboard::from_u64(((1u64 << (high - low + 1)) - 1) << low)
 }
 
 /// Calculates the pseudo-legal slider moves for `square` by using the pre-calculated slider
@@ -298,22 +301,139 @@ pub fn pseudolegal_slider_moves(game: &Game, square: Square) -> Bitboard {
     // Get the blockers for the slider type and square
     let blockers = get_blockers_from_position(game, piece, square);
 
-    // Retrieve the moves from the magic table
+    // Retrieve the moves from the lookup tables
     match piece {
-        Piece::ROOK => {
-            Bitboard::from_u64(ROOK_MOVES[magic_index(&ROOK_MAGICS[square as usize], blockers)])
-        }
-        Piece::BISHOP => {
-            Bitboard::from_u64(BISHOP_MOVES[magic_index(&BISHOP_MAGICS[square as usize], blockers)])
-        }
-        Piece::QUEEN => Bitboard::from_u64(
-            ROOK_MOVES[magic_index(&ROOK_MAGICS[square as usize], blockers)]
-                | BISHOP_MOVES[magic_index(&BISHOP_MAGICS[square as usize], blockers)],
-        ),
+        Piece::ROOK => rook_attacks(square, blockers),
+        Piece::BISHOP => bishop_attacks(square, blockers),
+        Piece::QUEEN => rook_attacks(square, blockers) | bishop_attacks(square, blockers),
         _ => panic!("Non-slider piece passed to `pseudolegal_slider_moves`"),
     }
 }
 
+/// Returns the squares a rook on `square` attacks when the squares in `occupied` are blocked.
+/// Uses `pext` indexing on CPUs with BMI2 if the `pext` feature is enabled. Otherwise uses
+/// kindergarten bitboards if the `kindergarten` feature is enabled, and the magic tables if
+/// not. With the `lazy-tables` feature, the magic tables are filled on first use.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{bitboard::Bitboard, movegen::rook_attacks, Square};
+/// let attacks = rook_attacks(Square::A1, Bitboard::from_square(Square::A3));
+/// assert_eq!(attacks.0, 0x101fe);
+/// ```
+#[inline]
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    count!(slider_lookups);
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if let Some(tables) = crate::pext::tables() {
+        return tables.rook(square, occupied);
+    }
+    #[cfg(feature = "kindergarten")]
+    return crate::kindergarten::rook_attacks(square, occupied);
+    #[cfg(not(feature = "kindergarten"))]
+    {
+        let index = magic_index(&ROOK_MAGICS[square as usize], occupied);
+        #[cfg(feature = "compact-magics")]
+        let attacks = ROOK_ATTACK_SETS[ROOK_SET_INDICES[index] as usize];
+        #[cfg(all(feature = "lazy-tables", not(feature = "compact-magics")))]
+        let attacks = lazy_rook_moves()[index];
+        #[cfg(not(any(feature = "compact-magics", feature = "lazy-tables")))]
+        let attacks = ROOK_MOVES[index];
+        Bitboard::from_u64(attacks)
+    }
+}
+
+/// Returns the squares a bishop on `square` attacks when the squares in `occupied` are
+/// blocked. See [`rook_attacks`] for how the lookup is done.
+#[inline]
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    count!(slider_lookups);
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if let Some(tables) = crate::pext::tables() {
+        return tables.bishop(square, occupied);
+    }
+    #[cfg(feature = "kindergarten")]
+    return crate::kindergarten::bishop_attacks(square, occupied);
+    #[cfg(not(feature = "kindergarten"))]
+    {
+        let index = magic_index(&BISHOP_MAGICS[square as usize], occupied);
+        #[cfg(feature = "compact-magics")]
+        let attacks = BISHOP_ATTACK_SETS[BISHOP_SET_INDICES[index] as usize];
+        #[cfg(all(feature = "lazy-tables", not(feature = "compact-magics")))]
+        let attacks = lazy_bishop_moves()[index];
+        #[cfg(not(any(feature = "compact-magics", feature = "lazy-tables")))]
+        let attacks = BISHOP_MOVES[index];
+        Bitboard::from_u64(attacks)
+    }
+}
+
+/// The squares a slider can step to from each file without wrapping around the board edge.
+const NOT_FILE_A: u64 = !0x0101010101010101;
+const NOT_FILE_H: u64 = !0x8080808080808080;
+
+/// The sliding directions as a shift and the squares a step in that direction may land on:
+/// north, east, north-east and north-west shift left, south, west, south-west and south-east
+/// shift right. The first two of each group are rook directions.
+pub(crate) const SLIDING_DIRECTIONS: [(u32, u64, u64); 4] = [
+    (8, u64::MAX, u64::MAX),
+    (1, NOT_FILE_A, NOT_FILE_H),
+    (9, NOT_FILE_A, NOT_FILE_H),
+    (7, NOT_FILE_H, NOT_FILE_A),
+];
+
+/// Returns all squares attacked by the rook-like sliders on `rooks` and the bishop-like
+/// sliders on `bishops` together, when the squares in `occupied` are blocked. Queens belong in
+/// both sets. The sliders are filled along all directions at once instead of being looked up
+/// one square at a time, using AVX2 for four directions in parallel if the `simd` feature is
+/// enabled and the CPU supports it.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{bitboard::Bitboard, movegen::sliding_attacks, Square};
+/// let rooks = Bitboard::from_square(Square::A1) | Square::H8;
+/// let attacks = sliding_attacks(rooks, Bitboard::empty(), rooks);
+/// assert_eq!(attacks.0, 0x7f818181818181fe);
+/// ```
+pub fn sliding_attacks(rooks: Bitboard, bishops: Bitboard, occupied: Bitboard) -> Bitboard {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::has_avx2() {
+        // SAFETY: AVX2 support was just detected
+        return Bitboard::from_u64(unsafe {
+            crate::simd::sliding_attacks(rooks.0, bishops.0, occupied.0)
+        });
+    }
+    Bitboard::from_u64(scalar_sliding_attacks(rooks.0, bishops.0, occupied.0))
+}
+
+/// Kogge-Stone occluded fill along each sliding direction, one direction at a time.
+pub(crate) fn scalar_sliding_attacks(rooks: u64, bishops: u64, occupied: u64) -> u64 {
+    let mut attacks = 0;
+    for (i, &(shift, left_mask, right_mask)) in SLIDING_DIRECTIONS.iter().enumerate() {
+        let sliders = if i < 2 { rooks } else { bishops };
+
+        let mut generator = sliders;
+        let mut empty = !occupied & left_mask;
+        generator |= empty & (generator << shift);
+        empty &= empty << shift;
+        generator |= empty & (generator << (2 * shift));
+        empty &= empty << (2 * shift);
+        generator |= empty & (generator << (4 * shift));
+        attacks |= (generator << shift) & left_mask;
+
+        let mut generator = sliders;
+        let mut empty = !occupied & right_mask;
+        generator |= empty & (generator >> shift);
+        empty &= empty >> shift;
+        generator |= empty & (generator >> (2 * shift));
+        empty &= empty >> (2 * shift);
+        generator |= empty & (generator >> (4 * shift));
+        attacks |= (generator >> shift) & right_mask;
+    }
+    attacks
+}
+
 /// Returns a bitboard of squares a slider piece on `square` can move to.
 /// This checks for positional legality, but not whether or not it leaves the king in check.
 ///
@@ -336,9 +456,8 @@ pub fn slider_moves(game: &Game, square: Square) -> Bitboard {
 
 // Gets the index in the magic table for the given blocker mask
 #[inline]
-pub fn magic_index(entry: &MagicTableEntry, mut blockers: Bitboard) -> usize {
-    blockers &= entry.mask;
-    let hash = blockers.0.wrapping_mul(entry.magic);
+pub const fn magic_index(entry: &MagicTableEntry, blockers: Bitboard) -> usize {
+    let hash = (blockers.0 & entry.mask).wrapping_mul(entry.magic);
     let index = (hash >> entry.shift) as usize;
     entry.offset as usize + index
 }
@@ -355,11 +474,15 @@ pub fn get_blockers_from_position(game: &Game, piece: Piece, square: Square) ->
     };
 
     // Only return the pieces that are actually on the board
-    blockers & game.all_pieces()
+    blockers & game.blockers()
 }
 
+/// The first and the eighth rank.
+const BACK_RANKS: u64 = 0xff00_0000_0000_00ff;
+
 /// Returns all legal moves for the color to move in `game`
-/// as a `Vec<Move>`.
+/// as a [`MoveList`], without allocating outside of Duck chess. Which moves are legal is up
+/// to the [`Rules`](crate::variant::Rules) of the game's variant.
 /// 
 /// # Example
 /// 
@@ -369,58 +492,319 @@ pub fn get_blockers_from_position(game: &Game, piece: Piece, square: Square) ->
 /// let moves = all_legal_moves(&game);
 /// assert_eq!(
 ///     moves,
-///     vec![Move {
-///         start: Square::G2,
-///         end: Square::G3
-///     }]
+///     vec![Move::new(Square::G2, Square::G3)]
 /// );
 /// ```
-pub fn all_legal_moves(game: &Game) -> Vec<Move> {
+pub fn all_legal_moves(game: &Game) -> MoveList {
+    count!(move_generations);
+    let rules = game.rules();
+    let mut moves = rules.pseudo_legal_moves(game);
+    count!(moves_generated, moves.len());
+    #[cfg(feature = "stats")]
+    let pseudo_legal = moves.len();
+    rules.retain_legal(game, &mut moves);
+    #[cfg(feature = "stats")]
+    count!(legality_rejections, pseudo_legal - moves.len());
+    moves
+}
+
+/// A source of pseudo-random `u64`s for [`random_legal_move`] and [`random_playout`].
+///
+/// This is a small stand-in for the `Rng` trait of the `rand` crate, which this crate doesn't
+/// depend on. It is implemented by [`SeededRng`] and by any closure returning `u64`s, so a
+/// generator from another crate can be passed as `&mut || rng.next_u64()`.
+pub trait Rng {
+    /// Returns the next random number.
+    fn next_u64(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Rng for F {
+    fn next_u64(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// The splitmix64 generator returned by [`seeded_rng`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng(u64);
+
+impl Rng for SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        splitmix64(&mut self.0)
+    }
+}
+
+/// Returns a generator of pseudo-random numbers for [`random_legal_move`] and
+/// [`random_playout`]. Generators made from the same `seed` give the same numbers, so that
+/// the moves they pick can be reproduced, e.g. for fuzzing.
+pub const fn seeded_rng(seed: u64) -> SeededRng {
+    SeededRng(seed)
+}
+
+/// Picks one of the legal moves in `game` with the random numbers of `rng`, or returns `None`
+/// if there are none.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::{all_legal_moves, random_legal_move, seeded_rng}};
+/// let game = Game::default();
+/// let m = random_legal_move(&game, &mut seeded_rng(42)).unwrap();
+/// assert!(all_legal_moves(&game).contains(&m));
+/// assert_eq!(random_legal_move(&game, &mut seeded_rng(42)), Some(m));
+/// // Always the first move
+/// assert_eq!(random_legal_move(&game, &mut || 0), Some(all_legal_moves(&game)[0]));
+/// ```
+pub fn random_legal_move(game: &Game, rng: &mut impl Rng) -> Option<Move> {
+    let moves = all_legal_moves(game);
+    if moves.is_empty() {
+        return None;
+    }
+    Some(moves[(rng.next_u64() % moves.len() as u64) as usize])
+}
+
+/// A game played on with random moves by [`random_playout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playout {
+    /// The position the playout ended in.
+    pub game: Game,
+    /// The moves played.
+    pub moves: Vec<Move>,
+    /// The result, or `None` if the playout was cut off before the game ended.
+    pub outcome: Option<Outcome>,
+}
+
+/// Plays random moves from `game` as [`random_legal_move`] picks them with `rng` until the
+/// game ends by the rules of its variant or by threefold repetition, or `max_plies` were
+/// played.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::{random_playout, seeded_rng}};
+/// let playout = random_playout(&Game::default(), &mut seeded_rng(7), 10_000);
+/// assert_eq!(playout, random_playout(&Game::default(), &mut seeded_rng(7), 10_000));
+/// assert!(playout.outcome.is_some());
+/// ```
+pub fn random_playout(game: &Game, rng: &mut impl Rng, max_plies: usize) -> Playout {
+    let mut game = *game;
+    let mut moves = Vec::new();
+    let mut keys = vec![game.key()];
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break Some(outcome);
+        }
+        if keys.iter().filter(|&&key| key == game.key()).count() >= 3 {
+            break Some(Outcome::DRAW);
+        }
+        if moves.len() >= max_plies {
+            break None;
+        }
+        let Some(m) = random_legal_move(&game, rng) else {
+            break None;
+        };
+        game.make_move(m);
+        moves.push(m);
+        keys.push(game.key());
+    };
+    Playout {
+        game,
+        moves,
+        outcome,
+    }
+}
+
+/// Returns the moves of the color to move in `game` that follow the movement rules of the
+/// pieces, including those that leave the own king in check.
+pub fn pseudo_legal_moves(game: &Game) -> MoveList {
     let color = game.to_move;
     let mut pieces = game.all_pieces() & game.color_bitboards[color as usize];
 
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
 
     while !pieces.is_empty() {
         let s = Square::from_u8(pieces.trailing_zeros() as u8);
-        let mut move_bb = match game.type_at(s) {
+        let piece = game.type_at(s);
+        let mut move_bb = match piece {
             Piece::ROOK | Piece::BISHOP | Piece::QUEEN => slider_moves(game, s),
             Piece::PAWN => pawn_moves(game, s),
             Piece::KNIGHT => knight_moves(game, s),
-            Piece::KING => king_moves(game, color),
+            Piece::KING => king_moves_from(game, s, color),
         };
+        // Pawns reaching the last rank promote to each of the pieces the rules allow
+        let mut promotions = if piece == Piece::PAWN {
+            move_bb & Bitboard::from_u64(BACK_RANKS)
+        } else {
+            Bitboard::empty()
+        };
+        move_bb ^= promotions;
 
         while !move_bb.is_empty() {
             let sq = Square::from_u8(move_bb.trailing_zeros() as u8);
-            moves.push(Move { start: s, end: sq });
+            moves.push(Move::new(s, sq));
             move_bb.clear_lsb();
         }
+        while !promotions.is_empty() {
+            let sq = Square::from_u8(promotions.trailing_zeros() as u8);
+            for &promotion in game.rules().promotion_pieces() {
+                moves.push(Move::new_promotion(s, sq, promotion));
+            }
+            promotions.clear_lsb();
+        }
 
         pieces.clear_lsb();
     }
+    moves
+}
+
+/// Adds the drops of the pieces in the pocket of the color to move in `game` to `moves`: any
+/// piece on any empty square, except pawns on the first and last rank.
+pub fn drop_moves(game: &Game, moves: &mut MoveList) {
+    let color = game.to_move;
+    let empty = !game.blockers();
+    for piece in Piece::ALL {
+        if piece == Piece::KING || game.pocket(color, piece) == 0 {
+            continue;
+        }
+        let mut targets = if piece == Piece::PAWN {
+            empty & !BACK_RANKS
+        } else {
+            empty
+        };
+        while !targets.is_empty() {
+            let square = Square::from_u8(targets.trailing_zeros() as u8);
+            moves.push(Move::new_drop(piece, square));
+            targets.clear_lsb();
+        }
+    }
+}
+
+/// Keeps only those of the pseudo-legal `moves` that don't leave the king of the color to
+/// move in check, the legality rule of standard chess.
+pub fn retain_king_safe(game: &Game, moves: &mut MoveList) {
+    let color = game.to_move;
+    // Without a king on the board, no move can leave it in check
+    let Some(king) = game.king_square(color) else {
+        return;
+    };
+    let in_check = game.is_attacked_by(!color, king);
+    let mut enemy_sliders = game.color_bitboards[!color as usize]
+        & (game.piece_bitboards[Piece::BISHOP as usize]
+            | game.piece_bitboards[Piece::ROOK as usize]
+            | game.piece_bitboards[Piece::QUEEN as usize]);
+    let mut slider_attacks = Bitboard::empty();
+    while !enemy_sliders.is_empty() {
+        slider_attacks |= game.attacks_from(Square::from_u8(enemy_sliders.trailing_zeros() as u8));
+        enemy_sliders.clear_lsb();
+    }
 
     moves.retain(|mv| {
-        let delete = {
-            let mut game_copy = game.clone();
+        // When not in check, the cached attack maps decide most moves without making them:
+        // the king may go wherever the opponent doesn't attack, and any other piece that no
+        // enemy slider attacks can't be pinned
+        let is_castle = mv.start == king && game.is_castle(*mv, Piece::KING, color);
+        if !in_check && !is_castle && game.en_passant_square != Some(mv.end) {
+            if mv.start == king {
+                return !game.is_attacked_by(!color, mv.end);
+            }
+            if !slider_attacks.contains(mv.start) {
+                return true;
+            }
+        }
+
+        // Castling and en passant move a second piece, so they are played out on a copy
+        if is_castle || game.en_passant_square == Some(mv.end) {
+            let mut game_copy = *game;
             game_copy.make_move(*mv);
-            let king_square = Square::from_u8(
-                (game_copy.color_bitboards[color as usize]
-                    & game_copy.piece_bitboards[Piece::KING as usize])
-                    .trailing_zeros() as u8,
-            );
-            game_copy.is_attacked_by(color ^ 1, king_square)
-        };
-        !delete
+            return !game_copy.is_attacked_by(!color, game_copy.king_square(color).unwrap());
+        }
+
+        // Otherwise look for attackers of the king's square in the position after the move,
+        // leaving out a piece captured by it
+        let occupied = (game.blockers() ^ mv.start) | mv.end;
+        let king_after = if mv.start == king { mv.end } else { king };
+        let attackers = game.attackers_to(king_after, occupied)
+            & game.color_bitboards[!color as usize]
+            & !Bitboard::from_square(mv.end);
+        attackers.is_empty()
     });
+}
 
+/// How a tree walk such as [`perft`] tries the moves of a position.
+///
+/// Making a move mostly means updating the attack maps, which both strategies do once per
+/// move. Unmaking also updates them, while copying a [`Game`] is a plain memory copy, so
+/// copy-make is the faster default.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum MakeStrategy {
+    /// Make every move on a copy of the position.
+    #[default]
+    COPY_MAKE,
+    /// Make every move on the position itself and take it back with [`Game::unmake_move`].
+    MAKE_UNMAKE,
+}
+
+/// Counts the leaf nodes of the legal move tree of `game` down to `depth`, using the
+/// default [`MakeStrategy`].
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::perft};
+/// assert_eq!(perft(&Game::default(), 2), 400);
+/// ```
+pub fn perft(game: &Game, depth: u32) -> u64 {
+    perft_with(game, depth, MakeStrategy::default())
+}
+
+/// Counts the leaf nodes of the legal move tree of `game` down to `depth`, trying moves with
+/// `strategy`.
+pub fn perft_with(game: &Game, depth: u32, strategy: MakeStrategy) -> u64 {
+    match strategy {
+        MakeStrategy::COPY_MAKE => perft_copy_make(game, depth),
+        MakeStrategy::MAKE_UNMAKE => perft_make_unmake(&mut { *game }, depth),
+    }
+}
+
+fn perft_copy_make(game: &Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = all_legal_moves(game);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
     moves
+        .iter()
+        .map(|&m| {
+            let mut child = *game;
+            child.make_move(m);
+            perft_copy_make(&child, depth - 1)
+        })
+        .sum()
+}
+
+fn perft_make_unmake(game: &mut Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = all_legal_moves(game);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for &m in &moves {
+        let undo = game.make_move(m);
+        nodes += perft_make_unmake(game, depth - 1);
+        game.unmake_move(m, undo);
+    }
+    nodes
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    extern crate test;
-    use test::Bencher;
 
     #[test]
     fn slider_blockers() {
@@ -429,9 +813,40 @@ mod tests {
         assert_eq!(blockers.0, 20480);
     }
 
-    #[bench]
-    fn bench_blockers_from_pos(b: &mut Bencher) {
-        let game = Game::default();
-        b.iter(|| get_blockers_from_position(&game, Piece::BISHOP, Square::F1));
+    #[test]
+    fn random_playouts_are_reproducible() {
+        let start = Game::default();
+        let playouts: Vec<Playout> = (0..8)
+            .map(|seed| random_playout(&start, &mut seeded_rng(seed), 10_000))
+            .collect();
+        assert!(playouts
+            .windows(2)
+            .any(|pair| pair[0].moves != pair[1].moves));
+        for (seed, playout) in playouts.iter().enumerate() {
+            let mut rng = seeded_rng(seed as u64);
+            assert_eq!(*playout, random_playout(&start, &mut rng, 10_000));
+            let mut game = start;
+            for &m in &playout.moves {
+                assert!(all_legal_moves(&game).contains(&m));
+                game.make_move(m);
+            }
+            assert_eq!(game, playout.game);
+            assert!(playout.outcome.is_some());
+        }
+    }
+
+    #[test]
+    fn random_playouts_stop() {
+        let bare_kings = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let playout = random_playout(&bare_kings, &mut seeded_rng(0), 100);
+        assert!(playout.moves.is_empty());
+        assert_eq!(playout.outcome, Some(Outcome::DRAW));
+
+        let playout = random_playout(&Game::default(), &mut seeded_rng(0), 5);
+        assert_eq!(playout.moves.len(), 5);
+        assert_eq!(playout.outcome, None);
+
+        let mated = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert_eq!(random_legal_move(&mated.unwrap(), &mut || 0), None);
     }
 }
@@ -0,0 +1,330 @@
+//! Retrograde move generation, finding the moves that may have led to a position.
+
+use super::all_legal_moves;
+use crate::{
+    bitboard::Bitboard, game::Game, try_square_offset, variant::Variant, CastlingRights,
+    CastlingSide, Color, ColoredPiece, Move, Piece, Rank, Square,
+};
+
+/// A move that may have been played last, as found by [`retro_moves`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetroMove {
+    /// The move as it was played, e.g. `e7e8q` for a promotion or `e1g1` for castling.
+    pub m: Move,
+    /// The piece `m` captured, which taking the move back puts back on the board.
+    pub captured: Option<Piece>,
+    /// The position before `m`, from which playing it leads to the current position.
+    pub before: Game,
+}
+
+/// Returns the moves the side that isn't to move in `game` may have played last, each with the
+/// position it was played in, e.g. for retro-analysis problems or for checking that a
+/// position can be reached at all.
+///
+/// An un-move is plausible if it is a legal move in a legal position, uncaptures no more
+/// pieces than a side can have, and agrees with what `game` records: the castling rights
+/// left, the en passant square and a halfmove clock above zero, which rules out pawn moves
+/// and captures. The positions before may have had more castling rights, which can't be
+/// told from `game`, so they only have those of `game` and the one a castling move used.
+/// Only standard chess is supported, without castling in Chess960, so other variants have
+/// no retro moves.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::retro_moves, Move};
+/// // A pawn double step leaves an en passant square, so it was the last move
+/// let game = Game::from_fen("4k3/8/8/8/2Pp4/8/8/4K3 b - c3 0 1").unwrap();
+/// let retro = retro_moves(&game);
+/// assert_eq!(retro.len(), 1);
+/// assert_eq!(retro[0].m, Move::from_uci("c2c4").unwrap());
+/// assert_eq!(retro[0].before.to_fen(), "4k3/8/8/8/3p4/8/2P5/4K3 w - - 0 1");
+/// ```
+pub fn retro_moves(game: &Game) -> Vec<RetroMove> {
+    let mut retro = Vec::new();
+    if game.variant != Variant::STANDARD {
+        return retro;
+    }
+    let mover = !game.to_move;
+    let empty = !game.all_pieces();
+    let forward = match mover {
+        Color::WHITE => 1,
+        Color::BLACK => -1,
+    };
+    let behind = |s: Square, dx: i8| try_square_offset(s, dx, -forward);
+
+    if let Some(passed) = game.en_passant_square {
+        let start = behind(passed, 0);
+        let end = try_square_offset(passed, 0, forward);
+        if let (Some(start), Some(end)) = (start, end) {
+            retro.extend(unmake(game, Move::new(start, end), None));
+        }
+        return retro;
+    }
+
+    // Pawn moves and captures reset the halfmove clock
+    let quiet = game.halfmove_clock > 0;
+    let uncaptured = |s: Square| uncaptures(game, s).filter(move |_| !quiet);
+    for end in game.color_bitboards[mover as usize].squares() {
+        let piece = game.type_at(end);
+        if has_castling_right(game, mover, end) {
+            continue;
+        }
+        let rank = end.relative_to(mover).get_rank();
+        if piece == Piece::PAWN {
+            if quiet {
+                continue;
+            }
+            if let Some(start) = behind(end, 0).filter(|&s| empty.contains(s)) {
+                if start.relative_to(mover).get_rank() != Rank::FIRST {
+                    retro.extend(unmake(game, Move::new(start, end), None));
+                }
+                let double = behind(start, 0).filter(|&s| empty.contains(s));
+                if let Some(double) = double.filter(|_| rank == Rank::FOURTH) {
+                    // Next to a pawn that could take it, a double step would have left an en
+                    // passant square
+                    let enemy_pawn = Some(ColoredPiece::new(Piece::PAWN, !mover));
+                    let can_be_taken = [-1, 1]
+                        .into_iter()
+                        .filter_map(|dx| try_square_offset(end, dx, 0))
+                        .any(|s| game.piece_at(s) == enemy_pawn);
+                    if !can_be_taken {
+                        retro.extend(unmake(game, Move::new(double, end), None));
+                    }
+                }
+            }
+            for start in [-1, 1].into_iter().filter_map(|dx| behind(end, dx)) {
+                if !empty.contains(start) || start.relative_to(mover).get_rank() == Rank::FIRST {
+                    continue;
+                }
+                let m = Move::new(start, end);
+                for captured in uncaptured(end) {
+                    retro.extend(unmake(game, m, Some((end, captured))));
+                }
+                // En passant took the pawn that had just passed the end square
+                let passed = behind(end, 0).filter(|&s| empty.contains(s));
+                let came_from = try_square_offset(end, 0, forward).filter(|&s| empty.contains(s));
+                if let (Some(passed), Some(_)) = (passed, came_from) {
+                    if rank == Rank::SIXTH {
+                        retro.extend(unmake(game, m, Some((passed, Piece::PAWN))));
+                    }
+                }
+            }
+            continue;
+        }
+
+        for start in (game.attacks_from(end) & empty).squares() {
+            let m = Move::new(start, end);
+            retro.extend(unmake(game, m, None));
+            for captured in uncaptured(end) {
+                retro.extend(unmake(game, m, Some((end, captured))));
+            }
+        }
+        if piece != Piece::KING && rank == Rank::EIGHTH && !quiet {
+            if let Some(start) = behind(end, 0).filter(|&s| empty.contains(s)) {
+                retro.extend(unmake(game, Move::new_promotion(start, end, piece), None));
+            }
+            for start in [-1, 1].into_iter().filter_map(|dx| behind(end, dx)) {
+                if empty.contains(start) {
+                    let m = Move::new_promotion(start, end, piece);
+                    for captured in uncaptured(end) {
+                        retro.extend(unmake(game, m, Some((end, captured))));
+                    }
+                }
+            }
+        }
+    }
+
+    // Castling gives up all castling rights, and the king and rook land next to each other
+    let rights = CastlingRights::for_color(mover);
+    if !game.chess960 && !game.castling_rights.intersects(rights) {
+        let [e, a, b, c, d, f, g, h] = [
+            Square::E1,
+            Square::A1,
+            Square::B1,
+            Square::C1,
+            Square::D1,
+            Square::F1,
+            Square::G1,
+            Square::H1,
+        ]
+        .map(|s| s.relative_to(mover));
+        let king = ColoredPiece::new(Piece::KING, mover);
+        let rook = ColoredPiece::new(Piece::ROOK, mover);
+        for (king_end, rook_end, must_be_empty) in [
+            (g, f, Bitboard::from_squares([e, h])),
+            (c, d, Bitboard::from_squares([a, b, e])),
+        ] {
+            if game.piece_at(king_end) == Some(king)
+                && game.piece_at(rook_end) == Some(rook)
+                && (must_be_empty & !empty).is_empty()
+            {
+                retro.extend(unmake(game, Move::new(e, king_end), None));
+            }
+        }
+    }
+    retro
+}
+
+/// Returns the pieces of the side to move in `game` that the other side may have captured on
+/// `s`: any but the king, as long as the side to move had fewer than 16 pieces, and pawns only
+/// if it had fewer than 8 and `s` isn't on the first or last rank.
+fn uncaptures(game: &Game, s: Square) -> impl Iterator<Item = Piece> + '_ {
+    let color = game.to_move;
+    let pieces: u32 = Piece::ALL.iter().map(|&p| game.piece_count(color, p)).sum();
+    let pawn_fits = game.piece_count(color, Piece::PAWN) < 8
+        && !matches!(s.get_rank(), Rank::FIRST | Rank::EIGHTH);
+    [
+        Piece::PAWN,
+        Piece::KNIGHT,
+        Piece::BISHOP,
+        Piece::ROOK,
+        Piece::QUEEN,
+    ]
+    .into_iter()
+    .filter(move |&p| pieces < 16 && (p != Piece::PAWN || pawn_fits))
+}
+
+/// Returns `true` if `color`'s piece on `s` is its king or a rook it may still castle with,
+/// neither of which can have moved.
+fn has_castling_right(game: &Game, color: Color, s: Square) -> bool {
+    [CastlingSide::KINGSIDE, CastlingSide::QUEENSIDE]
+        .into_iter()
+        .filter(|&side| game.castling_rights.allows(color, side))
+        .any(|side| game.king_square(color) == Some(s) || game.castling_rook(color, side) == s)
+}
+
+/// Takes back `m` of the side that isn't to move in `game`, putting `captured` back on the
+/// square it was taken on. Returns `None` if the position before isn't legal or `m` isn't a
+/// legal move in it.
+fn unmake(game: &Game, m: Move, captured: Option<(Square, Piece)>) -> Option<RetroMove> {
+    let mover = !game.to_move;
+    let mut before = *game;
+    let piece = before.take_piece(m.end)?;
+    let piece = ColoredPiece::new(m.promotion.map_or(piece.piece, |_| Piece::PAWN), mover);
+    before.put_piece(m.start, piece);
+
+    let mut castling_rights = game.castling_rights;
+    let [start_file, end_file] = [m.start, m.end].map(|s| s.get_file() as u8);
+    if piece.piece == Piece::KING && start_file.abs_diff(end_file) == 2 {
+        let side = if end_file > start_file {
+            CastlingSide::KINGSIDE
+        } else {
+            CastlingSide::QUEENSIDE
+        };
+        let (rook_start, rook_end) = match side {
+            CastlingSide::KINGSIDE => (Square::H1, Square::F1),
+            CastlingSide::QUEENSIDE => (Square::A1, Square::D1),
+        };
+        let rook = before.take_piece(rook_end.relative_to(mover))?;
+        before.put_piece(rook_start.relative_to(mover), rook);
+        castling_rights |= CastlingRights::new(mover, side);
+    }
+    if let Some((s, captured)) = captured {
+        before.put_piece(s, ColoredPiece::new(captured, game.to_move));
+    }
+
+    before.castling_rights = castling_rights;
+    before.en_passant_square = captured.filter(|&(s, _)| s != m.end).map(|_| m.end);
+    before.to_move = mover;
+    before.halfmove_clock = game.halfmove_clock.saturating_sub(1);
+    if mover == Color::BLACK {
+        before.fullmove_clock = game.fullmove_clock.saturating_sub(1).max(1);
+    }
+    let legal = before.validate().is_ok() && all_legal_moves(&before).contains(&m);
+    legal.then_some(RetroMove {
+        m,
+        captured: captured.map(|(_, piece)| piece),
+        before,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retro(fen: &str) -> Vec<RetroMove> {
+        let game = Game::from_fen(fen).unwrap();
+        let retro = retro_moves(&game);
+        // Every move taken back leads to the position again
+        for r in &retro {
+            assert_eq!(
+                Move::infer(&r.before, &game),
+                Some(r.m),
+                "{}",
+                r.before.to_fen()
+            );
+        }
+        retro
+    }
+
+    fn moves(retro: &[RetroMove]) -> Vec<String> {
+        retro.iter().map(|r| r.m.to_string()).collect()
+    }
+
+    #[test]
+    fn takes_back_quiet_moves_and_captures() {
+        // Only the knights can have moved, since no white piece can have been captured
+        assert_eq!(
+            moves(&retro(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            )),
+            ["a6b8", "c6b8", "f6g8", "h6g8"]
+        );
+
+        // The pawn stepped from e3 or e2, or took on e4
+        let found = retro("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        let pawn: Vec<&RetroMove> = found.iter().filter(|r| r.m.end == Square::E4).collect();
+        assert!(pawn.iter().any(|r| r.m.to_string() == "e3e4"));
+        assert!(pawn.iter().any(|r| r.m.to_string() == "e2e4"));
+        assert!(pawn
+            .iter()
+            .any(|r| r.m.to_string() == "d3e4" && r.captured == Some(Piece::KNIGHT)));
+        assert!(pawn
+            .iter()
+            .all(|r| r.m.start.get_file() == Square::E1.get_file() || r.captured.is_some()));
+
+        // After a quiet move there's no pawn move or capture to take back
+        assert!(retro("4k3/8/8/8/4P3/8/8/4K3 b - - 3 40")
+            .iter()
+            .all(|r| r.m.end == Square::E1 && r.captured.is_none()));
+    }
+
+    #[test]
+    fn the_last_move_gave_the_check() {
+        // The rook moved to e1 along the first rank, and the king didn't move
+        let found = retro("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1");
+        assert_eq!(found.len(), 5 * 5);
+        assert!(found
+            .iter()
+            .all(|r| r.m.end == Square::E1 && r.m.start.get_rank() == Rank::FIRST));
+    }
+
+    #[test]
+    fn takes_back_special_moves() {
+        let found = retro("1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(found
+            .iter()
+            .any(|r| r.m.to_string() == "b7b8n" && r.captured.is_none()));
+        assert!(found
+            .iter()
+            .any(|r| r.m.to_string() == "a7b8n" && r.captured == Some(Piece::ROOK)));
+
+        let found = retro("4k3/8/3P4/8/8/8/8/4K3 b - - 0 1");
+        let en_passant = found
+            .iter()
+            .find(|r| r.before.en_passant_square.is_some())
+            .unwrap();
+        assert_eq!(en_passant.m.to_string(), "c5d6");
+        assert_eq!(
+            en_passant.before.to_fen(),
+            "4k3/8/8/2Pp4/8/8/8/4K3 w - d6 0 1"
+        );
+
+        let found = retro("4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        let castling = found.iter().find(|r| r.m.to_string() == "e1g1").unwrap();
+        assert_eq!(castling.before.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        // With the right to castle left the king and rook haven't moved
+        assert!(retro("4k3/8/8/8/8/8/8/4K2R b K - 1 1").is_empty());
+    }
+}
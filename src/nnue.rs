@@ -0,0 +1,373 @@
+//! Neural network evaluation with an efficiently updatable first layer, enabled by the `nnue`
+//! feature.
+//!
+//! The network sees the board as 768 inputs, one for every piece of every color on every
+//! square, once from white's and once from black's point of view. Both views go through the
+//! same hidden layer of [`HIDDEN`] neurons, whose sums, the [`Accumulator`], only change in the
+//! few inputs a move touches. [`Game`] keeps them up to date as pieces move, like its
+//! piece-square score, so an evaluation only has to run the output layer: the side to move's
+//! half of the accumulator and the other side's, clipped to `0..=QA`, weighted and summed.
+//!
+//! A process has at most one network, installed with [`set_network`]. Positions set up before
+//! that are evaluated from scratch, see [`Game::refresh_accumulator`]. [`Evaluator::nnue`]
+//! evaluates with it instead of the classical terms.
+//!
+//! # Network format
+//!
+//! All numbers are little endian. The input index of a piece on square `s` seen by
+//! `perspective` is `(side * 6 + piece) * 64 + square`, where `side` is 0 for the
+//! perspective's own pieces and 1 for the opponent's, `piece` is the [`Piece`](crate::Piece)
+//! discriminant and `square` is `s` for white and `s ^ 56` for black, so both see the board
+//! from their side.
+//!
+//! | Bytes | Content |
+//! |-------|---------|
+//! | 4 | The magic `KNUE` |
+//! | 4 | The format version as `u32`, currently [`VERSION`] |
+//! | 4 | The hidden layer size as `u32`, which must be [`HIDDEN`] |
+//! | 768 × [`HIDDEN`] × 2 | The input weights as `i16`, input by input, scaled by [`QA`] |
+//! | [`HIDDEN`] × 2 | The hidden layer biases as `i16`, scaled by [`QA`] |
+//! | 2 × [`HIDDEN`] × 2 | The output weights as `i16`, side to move first, scaled by [`QB`] |
+//! | 4 | The output bias as `i32`, scaled by [`QA`] × [`QB`] |
+//!
+//! The evaluation in centipawns is the output layer's sum times [`SCALE`] / ([`QA`] × [`QB`]).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kritisch::{eval::Evaluator, game::Game, nnue::{self, Network}};
+//! let bytes = std::fs::read("kritisch.nnue").unwrap();
+//! nnue::set_network(Network::from_bytes(&bytes).unwrap()).unwrap();
+//! println!("{}", Evaluator::nnue().evaluate(&Game::default()));
+//! ```
+//!
+//! [`Evaluator::nnue`]: crate::eval::Evaluator::nnue
+
+use std::sync::OnceLock;
+
+use crate::{eval::Term, game::Game, pst::Score, Color, ColoredPiece, Error, Result, Square};
+
+/// The number of neurons in the hidden layer.
+pub const HIDDEN: usize = 128;
+/// The number of inputs of each perspective: 2 sides × 6 pieces × 64 squares.
+pub const INPUTS: usize = 768;
+/// The value a hidden neuron's activation is clipped to, and the scale of the input weights.
+pub const QA: i32 = 255;
+/// The scale of the output weights.
+pub const QB: i32 = 64;
+/// Converts the network's output to centipawns.
+pub const SCALE: i32 = 400;
+/// The version of the [network format](self#network-format) written and read.
+pub const VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"KNUE";
+const HEADER_LEN: usize = 12;
+const LEN: usize = HEADER_LEN + (INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN) * 2 + 4;
+
+static NETWORK: OnceLock<Network> = OnceLock::new();
+
+/// Installs `network` for every position from now on. There is only one network per process,
+/// so this fails if one is installed already.
+pub fn set_network(network: Network) -> Result<()> {
+    NETWORK
+        .set(network)
+        .map_err(|_| Error::InvalidNetwork("a network is installed already".to_string()))
+}
+
+/// Returns the network installed with [`set_network`], if any.
+pub fn network() -> Option<&'static Network> {
+    NETWORK.get()
+}
+
+/// The weights of a network, see the [network format](self#network-format).
+#[derive(Clone, PartialEq, Eq)]
+pub struct Network {
+    input_weights: Box<[[i16; HIDDEN]; INPUTS]>,
+    hidden_biases: [i16; HIDDEN],
+    output_weights: [[i16; HIDDEN]; 2],
+    output_bias: i32,
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Network")
+            .field("output_bias", &self.output_bias)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Network {
+    /// Reads a network in the [network format](self#network-format).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidNetwork(reason);
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(invalid("missing the KNUE magic".to_string()));
+        }
+        let header = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        if header(4) != VERSION {
+            return Err(invalid(format!("unsupported version {}", header(4))));
+        }
+        if header(8) != HIDDEN as u32 {
+            return Err(invalid(format!(
+                "hidden layer of {} neurons, expected {HIDDEN}",
+                header(8)
+            )));
+        }
+        if bytes.len() != LEN {
+            return Err(invalid(format!("{} bytes, expected {LEN}", bytes.len())));
+        }
+
+        let mut values = bytes[HEADER_LEN..LEN - 4]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let mut read = |layer: &mut [i16; HIDDEN]| {
+            layer.fill_with(|| values.next().expect("The length was checked"));
+        };
+        let mut network = Self::zeroed();
+        network.input_weights.iter_mut().for_each(&mut read);
+        read(&mut network.hidden_biases);
+        network.output_weights.iter_mut().for_each(&mut read);
+        network.output_bias = i32::from_le_bytes(bytes[LEN - 4..].try_into().unwrap());
+        Ok(network)
+    }
+
+    /// Writes the network in the [network format](self#network-format).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(HIDDEN as u32).to_le_bytes());
+        let layers = self
+            .input_weights
+            .iter()
+            .chain([&self.hidden_biases])
+            .chain(&self.output_weights);
+        for value in layers.flatten() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    fn zeroed() -> Self {
+        Self {
+            input_weights: vec![[0; HIDDEN]; INPUTS]
+                .into_boxed_slice()
+                .try_into()
+                .expect("The vector has INPUTS layers"),
+            hidden_biases: [0; HIDDEN],
+            output_weights: [[0; HIDDEN]; 2],
+            output_bias: 0,
+        }
+    }
+
+    /// Returns the score in centipawns for the side whose half of the accumulator is `us`.
+    fn output(&self, us: &[i16; HIDDEN], them: &[i16; HIDDEN]) -> i32 {
+        let crelu = |v: i16| (v as i32).clamp(0, QA);
+        let mut sum = self.output_bias;
+        for (half, weights) in [us, them].into_iter().zip(&self.output_weights) {
+            for (&v, &w) in half.iter().zip(weights) {
+                sum += crelu(v) * w as i32;
+            }
+        }
+        sum * SCALE / (QA * QB)
+    }
+}
+
+/// Returns the input index of `piece` on `s` seen by `perspective`, see the
+/// [network format](self#network-format).
+fn input(perspective: Color, piece: ColoredPiece, s: Square) -> usize {
+    let side = (piece.color != perspective) as usize;
+    let square = match perspective {
+        Color::WHITE => s as usize,
+        Color::BLACK => s as usize ^ 56,
+    };
+    (side * 6 + piece.piece as usize) * 64 + square
+}
+
+/// The hidden layer sums of a position from both perspectives, before the activation.
+///
+/// An accumulator is only maintained once a network is installed. The accumulator of a
+/// position set up before that stays empty, and [`Nnue`] evaluates it from scratch instead.
+#[derive(Debug, Copy, Clone)]
+pub struct Accumulator {
+    /// The sums from white's and black's point of view.
+    values: [[i16; HIDDEN]; 2],
+    /// Whether the values belong to the installed network.
+    ready: bool,
+}
+
+impl Default for Accumulator {
+    /// Returns the accumulator of an empty board.
+    fn default() -> Self {
+        match network() {
+            Some(network) => Self {
+                values: [network.hidden_biases; 2],
+                ready: true,
+            },
+            None => Self {
+                values: [[0; HIDDEN]; 2],
+                ready: false,
+            },
+        }
+    }
+}
+
+/// The accumulator follows from the pieces on the board, so it doesn't tell positions apart.
+impl PartialEq for Accumulator {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Accumulator {}
+
+impl Accumulator {
+    /// Returns the accumulator of `game`, summed up piece by piece.
+    pub fn new(game: &Game) -> Self {
+        let mut accumulator = Self::default();
+        for s in Square::all() {
+            if let Some(piece) = game.piece_at(s) {
+                accumulator.add(piece, s);
+            }
+        }
+        accumulator
+    }
+
+    /// Returns `true` if the accumulator belongs to the installed network.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Returns the sums from `perspective`'s point of view.
+    pub fn values(&self, perspective: Color) -> &[i16; HIDDEN] {
+        &self.values[perspective as usize]
+    }
+
+    /// Adds the inputs of `piece` on `s`.
+    pub(crate) fn add(&mut self, piece: ColoredPiece, s: Square) {
+        self.update(piece, s, i16::wrapping_add);
+    }
+
+    /// Removes the inputs of `piece` on `s`.
+    pub(crate) fn remove(&mut self, piece: ColoredPiece, s: Square) {
+        self.update(piece, s, i16::wrapping_sub);
+    }
+
+    fn update(&mut self, piece: ColoredPiece, s: Square, op: fn(i16, i16) -> i16) {
+        let Some(network) = network().filter(|_| self.ready) else {
+            return;
+        };
+        for perspective in [Color::WHITE, Color::BLACK] {
+            let weights = &network.input_weights[input(perspective, piece, s)];
+            for (v, &w) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *v = op(*v, w);
+            }
+        }
+    }
+}
+
+/// The installed network as an evaluation [`Term`], scoring every position as 0 until one is
+/// installed. It scores the middlegame and the endgame alike, so it isn't tapered.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Nnue;
+
+impl Term for Nnue {
+    fn name(&self) -> &'static str {
+        "nnue"
+    }
+
+    fn evaluate(&self, game: &Game) -> Score {
+        let Some(network) = network() else {
+            return Score::ZERO;
+        };
+        let fresh;
+        let accumulator = match game.accumulator() {
+            accumulator if accumulator.is_ready() => accumulator,
+            _ => {
+                fresh = Accumulator::new(game);
+                &fresh
+            }
+        };
+        let us = game.to_move;
+        let score = network.output(accumulator.values(us), accumulator.values(!us));
+        let score = match us {
+            Color::WHITE => score,
+            Color::BLACK => -score,
+        };
+        Score::new(score, score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval::Evaluator, zobrist::splitmix64, Move};
+
+    /// Installs a network with small pseudo-random weights. Every test installs the same one,
+    /// so it doesn't matter which test gets there first.
+    fn install() -> &'static Network {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = || (splitmix64(&mut state) % 33) as i16 - 16;
+        let mut network = Network::zeroed();
+        for layer in network.input_weights.iter_mut() {
+            layer.fill_with(&mut next);
+        }
+        network.hidden_biases.fill_with(|| next() + 64);
+        for layer in network.output_weights.iter_mut() {
+            layer.fill_with(&mut next);
+        }
+        network.output_bias = 1000;
+        let _ = set_network(network);
+        super::network().unwrap()
+    }
+
+    #[test]
+    fn round_trips_the_network_format() {
+        let network = install();
+        let bytes = network.to_bytes();
+        assert_eq!(bytes.len(), LEN);
+        assert_eq!(&Network::from_bytes(&bytes).unwrap(), network);
+
+        let mut wrong_size = bytes.clone();
+        wrong_size[8] = 64;
+        for bytes in [&bytes[..LEN - 1], &bytes[1..], &wrong_size] {
+            assert!(matches!(
+                Network::from_bytes(bytes),
+                Err(Error::InvalidNetwork(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn updates_the_accumulator_incrementally() {
+        install();
+        let mut game =
+            Game::from_fen("r3k2r/pppq1ppp/2n5/3pp3/4P3/2N2N2/PPPP1PPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        for uci in ["e4d5", "d7d5", "c3d5", "e8c8", "e1g1", "d8d5"] {
+            let before = game;
+            let m = Move::from_uci(uci).unwrap();
+            let undo = game.make_move(m);
+            assert_eq!(
+                game.accumulator().values,
+                Accumulator::new(&game).values,
+                "{uci}"
+            );
+            let mut unmade = game;
+            unmade.unmake_move(m, undo);
+            assert_eq!(unmade.accumulator().values, before.accumulator().values);
+        }
+    }
+
+    #[test]
+    fn evaluates_from_the_side_to_move() {
+        install();
+        let evaluator = Evaluator::nnue();
+        let white = Game::from_fen("4k3/8/8/3q4/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Game::from_fen("4k3/8/8/8/3Q4/8/8/4K3 b - - 0 1").unwrap();
+        // The positions mirror each other, so both sides to move see the same thing
+        assert_eq!(evaluator.evaluate(&white), evaluator.evaluate(&black));
+        assert_ne!(evaluator.evaluate(&white), 0);
+    }
+}
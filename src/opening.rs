@@ -0,0 +1,440 @@
+//! Names of chess openings, e.g. for showing the opening of a game while it is played or for
+//! sorting a database by it.
+//!
+//! The named lines of [`OPENINGS`] are put together into a tree of moves, in which every
+//! position carries the name of the deepest named line leading to it. Because the tree also
+//! knows every position by its [`key`](Game::key), a line that reaches a known position by
+//! another move order still finds its name, e.g. 1. c4 e6 2. d4 d5 is the Queen's Gambit
+//! Declined. [`Game::opening`] looks a position up, and an [`OpeningTracker`] follows the moves
+//! of a game and keeps the last name found once the game has left the tree.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, opening::OpeningTracker, Move};
+//! let mut game = Game::default();
+//! let mut tracker = OpeningTracker::new(&game);
+//! for san in ["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "Be3"] {
+//!     let m = game.parse_san(san).unwrap();
+//!     game.make_move(m);
+//!     tracker.push(m, &game);
+//! }
+//! // 6. Be3 is past the end of the named lines
+//! assert_eq!(game.opening(), None);
+//! let opening = tracker.opening().unwrap();
+//! assert_eq!(opening.eco, "B90");
+//! assert_eq!(opening.name, "Sicilian Defense: Najdorf Variation");
+//! assert_eq!(opening.family(), "Sicilian Defense");
+//! ```
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{game::Game, variant::Variant, Move};
+
+/// A named opening line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Opening {
+    /// The code of the opening in the Encyclopaedia of Chess Openings, e.g. `C60`.
+    pub eco: &'static str,
+    /// The name of the opening, followed by that of the variation after a colon if there is
+    /// one, e.g. `Ruy Lopez: Berlin Defense`.
+    pub name: &'static str,
+    /// The moves of the line in SAN from the starting position, separated by spaces.
+    pub moves: &'static str,
+}
+
+impl Opening {
+    /// Returns the name of the opening without the variation, e.g. `Ruy Lopez`.
+    pub fn family(&self) -> &'static str {
+        self.name
+            .split_once(": ")
+            .map_or(self.name, |(family, _)| family)
+    }
+
+    /// Returns the name of the variation, if the opening has one, e.g. `Berlin Defense`.
+    pub fn variation(&self) -> Option<&'static str> {
+        self.name.split_once(": ").map(|(_, variation)| variation)
+    }
+}
+
+const fn opening(eco: &'static str, name: &'static str, moves: &'static str) -> Opening {
+    Opening { eco, name, moves }
+}
+
+/// The named opening lines, each from the starting position.
+pub const OPENINGS: [Opening; 85] = [
+    opening("A00", "Polish Opening", "b4"),
+    opening("A00", "Grob Opening", "g4"),
+    opening("A00", "Hungarian Opening", "g3"),
+    opening("A01", "Nimzo-Larsen Attack", "b3"),
+    opening("A02", "Bird Opening", "f4"),
+    opening("A04", "Zukertort Opening", "Nf3"),
+    opening("A09", "Réti Opening", "Nf3 d5 c4"),
+    opening("A10", "English Opening", "c4"),
+    opening("A20", "English Opening: King's English Variation", "c4 e5"),
+    opening("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+    opening("A40", "Queen's Pawn Game", "d4"),
+    opening("A45", "Indian Defense", "d4 Nf6"),
+    opening("A45", "Trompowsky Attack", "d4 Nf6 Bg5"),
+    opening("A51", "Budapest Defense", "d4 Nf6 c4 e5"),
+    opening("A56", "Benoni Defense", "d4 Nf6 c4 c5"),
+    opening("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    opening("A60", "Modern Benoni", "d4 Nf6 c4 c5 d5 e6"),
+    opening("A80", "Dutch Defense", "d4 f5"),
+    opening("B00", "King's Pawn Game", "e4"),
+    opening("B00", "Nimzowitsch Defense", "e4 Nc6"),
+    opening("B01", "Scandinavian Defense", "e4 d5"),
+    opening("B02", "Alekhine Defense", "e4 Nf6"),
+    opening("B06", "Modern Defense", "e4 g6"),
+    opening("B07", "Pirc Defense", "e4 d6 d4 Nf6 Nc3 g6"),
+    opening("B10", "Caro-Kann Defense", "e4 c6"),
+    opening(
+        "B12",
+        "Caro-Kann Defense: Advance Variation",
+        "e4 c6 d4 d5 e5",
+    ),
+    opening(
+        "B13",
+        "Caro-Kann Defense: Exchange Variation",
+        "e4 c6 d4 d5 exd5",
+    ),
+    opening(
+        "B18",
+        "Caro-Kann Defense: Classical Variation",
+        "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5",
+    ),
+    opening("B20", "Sicilian Defense", "e4 c5"),
+    opening(
+        "B21",
+        "Sicilian Defense: Smith-Morra Gambit",
+        "e4 c5 d4 cxd4 c3",
+    ),
+    opening("B22", "Sicilian Defense: Alapin Variation", "e4 c5 c3"),
+    opening("B23", "Sicilian Defense: Closed", "e4 c5 Nc3"),
+    opening(
+        "B33",
+        "Sicilian Defense: Sveshnikov Variation",
+        "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5",
+    ),
+    opening(
+        "B41",
+        "Sicilian Defense: Kan Variation",
+        "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6",
+    ),
+    opening(
+        "B56",
+        "Sicilian Defense: Classical Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6",
+    ),
+    opening(
+        "B70",
+        "Sicilian Defense: Dragon Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6",
+    ),
+    opening(
+        "B80",
+        "Sicilian Defense: Scheveningen Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6",
+    ),
+    opening(
+        "B90",
+        "Sicilian Defense: Najdorf Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6",
+    ),
+    opening("C00", "French Defense", "e4 e6"),
+    opening(
+        "C01",
+        "French Defense: Exchange Variation",
+        "e4 e6 d4 d5 exd5",
+    ),
+    opening("C02", "French Defense: Advance Variation", "e4 e6 d4 d5 e5"),
+    opening(
+        "C03",
+        "French Defense: Tarrasch Variation",
+        "e4 e6 d4 d5 Nd2",
+    ),
+    opening(
+        "C11",
+        "French Defense: Classical Variation",
+        "e4 e6 d4 d5 Nc3 Nf6",
+    ),
+    opening(
+        "C15",
+        "French Defense: Winawer Variation",
+        "e4 e6 d4 d5 Nc3 Bb4",
+    ),
+    opening("C21", "Center Game", "e4 e5 d4"),
+    opening("C21", "Danish Gambit", "e4 e5 d4 exd4 c3"),
+    opening("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    opening("C25", "Vienna Game", "e4 e5 Nc3"),
+    opening("C30", "King's Gambit", "e4 e5 f4"),
+    opening("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    opening("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    opening("C41", "Philidor Defense", "e4 e5 Nf3 d6"),
+    opening("C42", "Petrov's Defense", "e4 e5 Nf3 Nf6"),
+    opening("C44", "Ponziani Opening", "e4 e5 Nf3 Nc6 c3"),
+    opening("C45", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    opening("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    opening("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    opening("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    opening("C50", "Italian Game: Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    opening(
+        "C51",
+        "Italian Game: Evans Gambit",
+        "e4 e5 Nf3 Nc6 Bc4 Bc5 b4",
+    ),
+    opening(
+        "C55",
+        "Italian Game: Two Knights Defense",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6",
+    ),
+    opening(
+        "C57",
+        "Italian Game: Two Knights Defense, Fried Liver Attack",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7",
+    ),
+    opening("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    opening("C65", "Ruy Lopez: Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    opening("C70", "Ruy Lopez: Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6"),
+    opening(
+        "C68",
+        "Ruy Lopez: Exchange Variation",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6",
+    ),
+    opening(
+        "C80",
+        "Ruy Lopez: Open Variation",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4",
+    ),
+    opening(
+        "C84",
+        "Ruy Lopez: Closed Variation",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7",
+    ),
+    opening("D02", "London System", "d4 d5 Nf3 Nf6 Bf4"),
+    opening("D06", "Queen's Gambit", "d4 d5 c4"),
+    opening(
+        "D07",
+        "Queen's Gambit Declined: Chigorin Defense",
+        "d4 d5 c4 Nc6",
+    ),
+    opening(
+        "D08",
+        "Queen's Gambit Declined: Albin Countergambit",
+        "d4 d5 c4 e5",
+    ),
+    opening("D10", "Slav Defense", "d4 d5 c4 c6"),
+    opening("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    opening("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    opening("D32", "Tarrasch Defense", "d4 d5 c4 e6 Nc3 c5"),
+    opening(
+        "D35",
+        "Queen's Gambit Declined: Exchange Variation",
+        "d4 d5 c4 e6 Nc3 Nf6 cxd5",
+    ),
+    opening("D43", "Semi-Slav Defense", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6"),
+    opening("D80", "Grünfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+    opening("E00", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    opening("E11", "Bogo-Indian Defense", "d4 Nf6 c4 e6 Nf3 Bb4"),
+    opening("E12", "Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+    opening("E20", "Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    opening("E60", "King's Indian Defense", "d4 Nf6 c4 g6"),
+    opening(
+        "E70",
+        "King's Indian Defense: Normal Variation",
+        "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6",
+    ),
+];
+
+/// A position in the tree of named lines.
+#[derive(Debug)]
+struct Node {
+    /// The deepest named line leading to the position.
+    opening: Option<&'static Opening>,
+    /// The moves that lead on to other positions in the tree, with the index of their node.
+    children: Vec<(Move, usize)>,
+}
+
+/// The named lines of [`OPENINGS`] as a tree of moves, with the node of every position by its
+/// key. The root is the starting position.
+#[derive(Debug)]
+struct OpeningTree {
+    nodes: Vec<Node>,
+    positions: HashMap<u64, usize>,
+}
+
+impl OpeningTree {
+    fn get() -> &'static Self {
+        static TREE: OnceLock<OpeningTree> = OnceLock::new();
+        TREE.get_or_init(Self::new)
+    }
+
+    fn new() -> Self {
+        let root = Game::default();
+        let mut tree = Self {
+            nodes: vec![Node {
+                opening: None,
+                children: Vec::new(),
+            }],
+            positions: HashMap::from([(root.key(), 0)]),
+        };
+        for opening in &OPENINGS {
+            let mut game = root;
+            let mut node = 0;
+            for san in opening.moves.split(' ') {
+                let m = game
+                    .parse_san(san)
+                    .unwrap_or_else(|_| panic!("Illegal move {san} in {}", opening.name));
+                game.make_move(m);
+                node = match tree.child(node, m) {
+                    Some(child) => child,
+                    None => {
+                        let child = tree.nodes.len();
+                        tree.nodes.push(Node {
+                            opening: None,
+                            children: Vec::new(),
+                        });
+                        tree.nodes[node].children.push((m, child));
+                        tree.positions.entry(game.key()).or_insert(child);
+                        child
+                    }
+                };
+            }
+            tree.nodes[node].opening = Some(opening);
+        }
+        // Children always come after their parent, so their parent already has its name
+        for parent in 0..tree.nodes.len() {
+            let opening = tree.nodes[parent].opening;
+            for i in 0..tree.nodes[parent].children.len() {
+                let child = tree.nodes[parent].children[i].1;
+                tree.nodes[child].opening = tree.nodes[child].opening.or(opening);
+            }
+        }
+        tree
+    }
+
+    fn child(&self, node: usize, m: Move) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|&&(child_move, _)| child_move == m)
+            .map(|&(_, child)| child)
+    }
+
+    /// Returns the node of `game`'s position, if it is in the tree.
+    fn find(&self, game: &Game) -> Option<usize> {
+        if game.variant != Variant::STANDARD || game.chess960 {
+            return None;
+        }
+        self.positions.get(&game.key()).copied()
+    }
+}
+
+/// Returns the opening of `game`'s position if it is one of the named lines or on the way to
+/// one, see [`Game::opening`].
+pub fn lookup(game: &Game) -> Option<&'static Opening> {
+    let tree = OpeningTree::get();
+    tree.find(game).and_then(|node| tree.nodes[node].opening)
+}
+
+/// Follows the moves of a game through the tree of named lines, remembering the last opening
+/// found after the game has left it.
+#[derive(Debug, Clone)]
+pub struct OpeningTracker {
+    /// The node of the current position, `None` once the game has left the tree.
+    node: Option<usize>,
+    opening: Option<&'static Opening>,
+}
+
+impl OpeningTracker {
+    /// Starts tracking a game from `game`'s position.
+    pub fn new(game: &Game) -> Self {
+        let node = OpeningTree::get().find(game);
+        Self {
+            node,
+            opening: lookup(game),
+        }
+    }
+
+    /// Follows `m`, which led to the position of `game`. The move is looked up among the
+    /// moves of the tree first, and if it isn't there, the position it led to, which finds
+    /// transpositions and brings a game back into the tree.
+    pub fn push(&mut self, m: Move, game: &Game) {
+        let tree = OpeningTree::get();
+        self.node = self
+            .node
+            .and_then(|node| tree.child(node, m))
+            .or_else(|| tree.find(game));
+        if let Some(node) = self.node {
+            self.opening = tree.nodes[node].opening.or(self.opening);
+        }
+    }
+
+    /// Returns the opening of the deepest named line the game has followed or transposed to.
+    pub fn opening(&self) -> Option<&'static Opening> {
+        self.opening
+    }
+
+    /// Returns `true` if the current position is in the tree of named lines.
+    pub fn in_book(&self) -> bool {
+        self.node.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &str) -> Game {
+        let mut game = Game::default();
+        for san in moves.split(' ') {
+            game.make_move(game.parse_san(san).unwrap());
+        }
+        game
+    }
+
+    #[test]
+    fn every_line_is_named() {
+        for opening in &OPENINGS {
+            assert_eq!(lookup(&play(opening.moves)), Some(opening));
+        }
+        assert_eq!(lookup(&Game::default()), None);
+    }
+
+    #[test]
+    fn positions_on_the_way_take_the_name_before() {
+        let opening = lookup(&play("e4 e5 Nf3 Nc6 Bb5 a6 Ba4")).unwrap();
+        assert_eq!(opening.name, "Ruy Lopez: Morphy Defense");
+        assert_eq!(opening.family(), "Ruy Lopez");
+        assert_eq!(opening.variation(), Some("Morphy Defense"));
+        assert_eq!(lookup(&play("e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nh5")), None);
+    }
+
+    #[test]
+    fn transpositions_find_the_name() {
+        let game = play("c4 e6 d4 d5");
+        assert_eq!(lookup(&game).unwrap().name, "Queen's Gambit Declined");
+
+        let mut game = Game::default();
+        let mut tracker = OpeningTracker::new(&game);
+        for san in ["Nf3", "Nf6", "c4", "e6", "d4", "b6", "a3"] {
+            let m = game.parse_san(san).unwrap();
+            game.make_move(m);
+            tracker.push(m, &game);
+            if san == "c4" {
+                assert_eq!(tracker.opening().unwrap().name, "Zukertort Opening");
+            }
+        }
+        assert!(!tracker.in_book());
+        assert_eq!(tracker.opening().unwrap().name, "Queen's Indian Defense");
+    }
+
+    #[test]
+    fn only_standard_chess_has_names() {
+        let mut game = play("e4 c5");
+        assert!(game.opening().is_some());
+        game.variant = Variant::ATOMIC;
+        assert_eq!(game.opening(), None);
+    }
+}
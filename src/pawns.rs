@@ -0,0 +1,188 @@
+//! Pawn structure analysis, e.g. for evaluation or for pointing out weak pawns to a learner.
+//!
+//! [`PawnStructure::new`] finds, for each color at once with bitboard fills, which pawns are
+//! doubled, isolated, backward, passed or connected, and which files are open or half-open.
+//! The [`PawnStructure`](crate::eval::PawnStructure) evaluation term scores some of them.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{bitboard::Bitboard, game::Game, pawns::PawnStructure, Color, Square};
+//! let game = Game::from_fen("4k3/p4p2/8/8/1P6/2P3P1/2P5/4K3 w - - 0 1").unwrap();
+//! let pawns = PawnStructure::new(&game);
+//! let white = Color::WHITE as usize;
+//! // The c2 pawn stands behind the one on c3
+//! assert_eq!(pawns.doubled[white], Bitboard::from_square(Square::C2));
+//! assert!(pawns.isolated[white].contains(Square::G3));
+//! assert!(pawns.connected[white].contains(Square::B4));
+//! assert!(pawns.passed[white].contains(Square::C3));
+//! assert!(pawns.open_files.contains(Square::D4));
+//! ```
+
+use crate::{bitboard::Bitboard, game::Game, Color, Piece};
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+/// The pawn structure of a position. The fields with one bitboard per color are indexed by
+/// [`Color`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PawnStructure {
+    /// The pawns with a pawn of their own color in front of them on the same file, so of two
+    /// pawns on a file only the rear one counts.
+    pub doubled: [Bitboard; 2],
+    /// The pawns without pawns of their own color on the neighboring files.
+    pub isolated: [Bitboard; 2],
+    /// The pawns that aren't isolated but have no pawns of their own color level with or
+    /// behind them on the neighboring files, and whose square in front is attacked by an
+    /// opposing pawn.
+    pub backward: [Bitboard; 2],
+    /// The pawns without opposing pawns in front of them on their own or the neighboring
+    /// files.
+    pub passed: [Bitboard; 2],
+    /// The pawns that are defended by a pawn of their own color or stand next to one.
+    pub connected: [Bitboard; 2],
+    /// Every square of the files without any pawns.
+    pub open_files: Bitboard,
+    /// Every square of the files with pawns of the opponent only.
+    pub half_open_files: [Bitboard; 2],
+}
+
+impl PawnStructure {
+    /// Analyzes the pawns of `game`.
+    pub fn new(game: &Game) -> Self {
+        let pawns = Color::both().map(|color| {
+            (game.piece_bitboards[Piece::PAWN as usize] & game.color_bitboards[color as usize]).0
+        });
+        let mut structure = Self {
+            doubled: [Bitboard::empty(); 2],
+            isolated: [Bitboard::empty(); 2],
+            backward: [Bitboard::empty(); 2],
+            passed: [Bitboard::empty(); 2],
+            connected: [Bitboard::empty(); 2],
+            open_files: Bitboard::from_u64(!file_fill(pawns[0] | pawns[1])),
+            half_open_files: [Bitboard::empty(); 2],
+        };
+        for color in Color::both() {
+            let own = pawns[color as usize];
+            let enemy = pawns[!color as usize];
+            let neighbors = sideways(file_fill(own));
+            let isolated = own & !neighbors;
+            // Squares level with or in front of an own pawn on a neighboring file
+            let supported = sideways(fill(own, color));
+            let enemy_span = front_span(enemy, !color);
+            let i = color as usize;
+            structure.doubled[i] = Bitboard::from_u64(own & front_span(own, !color));
+            structure.isolated[i] = Bitboard::from_u64(isolated);
+            structure.backward[i] = Bitboard::from_u64(
+                own & !isolated & !supported & push(pawn_attacks(enemy, !color), !color),
+            );
+            structure.passed[i] = Bitboard::from_u64(own & !(enemy_span | sideways(enemy_span)));
+            structure.connected[i] =
+                Bitboard::from_u64(own & (pawn_attacks(own, color) | sideways(own)));
+            structure.half_open_files[i] = Bitboard::from_u64(!file_fill(own) & file_fill(enemy));
+        }
+        structure
+    }
+}
+
+/// Returns the squares attacked by `color`'s pawns in `game`.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{bitboard::Bitboard, game::Game, pawns, Color, Square};
+/// let game = Game::from_fen("4k3/8/8/8/8/8/P6P/4K3 w - - 0 1").unwrap();
+/// let attacks = pawns::attacks(&game, Color::WHITE);
+/// assert_eq!(attacks, Bitboard::from_squares([Square::B3, Square::G3]));
+/// ```
+pub fn attacks(game: &Game, color: Color) -> Bitboard {
+    let pawns = game.piece_bitboards[Piece::PAWN as usize] & game.color_bitboards[color as usize];
+    Bitboard::from_u64(pawn_attacks(pawns.0, color))
+}
+
+/// Returns the squares one rank in front of `b` from `color`'s point of view.
+fn push(b: u64, color: Color) -> u64 {
+    match color {
+        Color::WHITE => b << 8,
+        Color::BLACK => b >> 8,
+    }
+}
+
+/// Returns `b` and every square in front of it from `color`'s point of view.
+fn fill(mut b: u64, color: Color) -> u64 {
+    for shift in [8, 16, 32] {
+        b |= match color {
+            Color::WHITE => b << shift,
+            Color::BLACK => b >> shift,
+        };
+    }
+    b
+}
+
+/// Returns the squares in front of `b` from `color`'s point of view, without `b` itself.
+fn front_span(b: u64, color: Color) -> u64 {
+    fill(push(b, color), color)
+}
+
+/// Returns the whole files of the squares in `b`.
+fn file_fill(b: u64) -> u64 {
+    fill(b, Color::WHITE) | fill(b, Color::BLACK)
+}
+
+/// Returns the squares next to `b` on the same rank.
+fn sideways(b: u64) -> u64 {
+    ((b << 1) & !FILE_A) | ((b >> 1) & !FILE_H)
+}
+
+/// Returns the squares attacked by `color`'s pawns on `pawns`.
+fn pawn_attacks(pawns: u64, color: Color) -> u64 {
+    sideways(push(pawns, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn squares(squares: &[Square]) -> Bitboard {
+        Bitboard::from_squares(squares.iter().copied())
+    }
+
+    #[test]
+    fn finds_weak_and_strong_pawns() {
+        let game = Game::from_fen("4k3/p4p2/8/8/1P6/2P3P1/2P5/4K3 w - - 0 1").unwrap();
+        let pawns = PawnStructure::new(&game);
+        let [white, black] = [Color::WHITE as usize, Color::BLACK as usize];
+        assert_eq!(pawns.doubled[white], squares(&[Square::C2]));
+        assert_eq!(pawns.doubled[black], Bitboard::empty());
+        assert_eq!(pawns.isolated[white], squares(&[Square::G3]));
+        assert_eq!(pawns.isolated[black], squares(&[Square::A7, Square::F7]));
+        assert_eq!(pawns.connected[white], squares(&[Square::B4]));
+        // The pawns on a7 and b4 stop each other
+        assert_eq!(pawns.passed[white], squares(&[Square::C2, Square::C3]));
+        assert_eq!(pawns.passed[black], Bitboard::empty());
+        let files = |files: &[u32]| Bitboard::from_u64(files.iter().map(|f| FILE_A << f).sum());
+        assert_eq!(pawns.open_files, files(&[3, 4, 7]));
+        assert_eq!(pawns.half_open_files[white], files(&[0, 5]));
+        assert_eq!(pawns.half_open_files[black], files(&[1, 2, 6]));
+        assert_eq!(pawns.backward, [Bitboard::empty(); 2]);
+    }
+
+    #[test]
+    fn finds_backward_pawns() {
+        // The pawns on c4 and e4 have moved past the one on d3, and e5 keeps it from advancing
+        let game = Game::from_fen("4k3/8/8/4p3/2P1P3/3P4/8/4K3 w - - 0 1").unwrap();
+        let pawns = PawnStructure::new(&game);
+        assert_eq!(
+            pawns.backward[Color::WHITE as usize],
+            squares(&[Square::D3])
+        );
+        // The e5 pawn is isolated rather than backward
+        assert_eq!(pawns.backward[Color::BLACK as usize], Bitboard::empty());
+
+        let game = Game::from_fen("4k3/8/8/8/2P1P3/3P4/8/4K3 w - - 0 1").unwrap();
+        let pawns = PawnStructure::new(&game);
+        assert_eq!(pawns.backward[Color::WHITE as usize], Bitboard::empty());
+    }
+}
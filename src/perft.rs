@@ -0,0 +1,231 @@
+//! The standard perft positions with their known node counts, so that a change to the move
+//! generator is checked against all of them with a single call to [`verify`].
+//!
+//! Besides the starting position, Kiwipete and positions 3 to 6 from the Chess Programming
+//! Wiki, the suite holds small positions that each test one tricky rule: en passant captures
+//! that would expose the king, castling that gives check or isn't allowed, promotions and
+//! stalemates.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::perft;
+//! assert!(perft::verify(10_000).is_empty());
+//! ```
+
+use crate::{game::Game, movegen::perft};
+
+/// A position with the number of leaf nodes of its legal move tree at every depth.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PerftPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    /// The node counts from depth 1 on.
+    pub nodes: &'static [u64],
+}
+
+impl PerftPosition {
+    /// Returns the position.
+    pub fn game(&self) -> Game {
+        Game::from_fen(self.fen).expect("Perft positions are valid")
+    }
+}
+
+/// The positions of the suite.
+pub const POSITIONS: [PerftPosition; 22] = [
+    PerftPosition {
+        name: "start",
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        nodes: &[20, 400, 8902, 197281, 4865609],
+    },
+    PerftPosition {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        nodes: &[48, 2039, 97862, 4085603],
+    },
+    PerftPosition {
+        name: "position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        nodes: &[14, 191, 2812, 43238, 674624],
+    },
+    PerftPosition {
+        name: "position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        nodes: &[6, 264, 9467, 422333],
+    },
+    PerftPosition {
+        name: "position 4 mirrored",
+        fen: "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        nodes: &[6, 264, 9467, 422333],
+    },
+    PerftPosition {
+        name: "position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        nodes: &[44, 1486, 62379, 2103487],
+    },
+    PerftPosition {
+        name: "position 6",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        nodes: &[46, 2079, 89890, 3894594],
+    },
+    PerftPosition {
+        name: "en passant exposing the king on the rank",
+        fen: "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1",
+        nodes: &[18, 92, 1670, 10138, 185429, 1134888],
+    },
+    PerftPosition {
+        name: "en passant exposing the king on the diagonal",
+        fen: "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1",
+        nodes: &[13, 102, 1266, 10276, 135655, 1015133],
+    },
+    PerftPosition {
+        name: "en passant giving check",
+        fen: "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        nodes: &[15, 126, 1928, 13931, 206379, 1440467],
+    },
+    PerftPosition {
+        name: "short castling giving check",
+        fen: "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        nodes: &[15, 66, 1198, 6399, 120330, 661072],
+    },
+    PerftPosition {
+        name: "long castling giving check",
+        fen: "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+        nodes: &[16, 71, 1286, 7418, 141077, 803711],
+    },
+    PerftPosition {
+        name: "losing the castling rights",
+        fen: "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1",
+        nodes: &[26, 1141, 27826, 1274206],
+    },
+    PerftPosition {
+        name: "castling prevented",
+        fen: "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+        nodes: &[44, 1494, 50509, 1720476],
+    },
+    PerftPosition {
+        name: "promoting out of check",
+        fen: "2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1",
+        nodes: &[11, 133, 1442, 19174, 266199, 3821001],
+    },
+    PerftPosition {
+        name: "discovered check",
+        fen: "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1",
+        nodes: &[29, 165, 5160, 31961, 1004658],
+    },
+    PerftPosition {
+        name: "promoting to give check",
+        fen: "4k3/1P6/8/8/8/8/K7/8 w - - 0 1",
+        nodes: &[9, 40, 472, 2661, 38983, 217342],
+    },
+    PerftPosition {
+        name: "underpromoting to give check",
+        fen: "8/P1k5/K7/8/8/8/8/8 w - - 0 1",
+        nodes: &[6, 27, 273, 1329, 18135, 92683],
+    },
+    PerftPosition {
+        name: "self stalemate",
+        fen: "K1k5/8/P7/8/8/8/8/8 w - - 0 1",
+        nodes: &[2, 6, 13, 63, 382, 2217],
+    },
+    PerftPosition {
+        name: "stalemate and checkmate",
+        fen: "8/k1P5/8/1K6/8/8/8/8 w - - 0 1",
+        nodes: &[10, 25, 268, 926, 10857, 43261, 567584],
+    },
+    PerftPosition {
+        name: "stalemate and checkmate with the queen",
+        fen: "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1",
+        nodes: &[37, 183, 6559, 23527],
+    },
+    PerftPosition {
+        name: "promotions",
+        fen: "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        nodes: &[24, 496, 9483, 182838, 3605103],
+    },
+];
+
+/// A node count that differs from the expected one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The name of the position.
+    pub name: &'static str,
+    pub depth: u32,
+    pub expected: u64,
+    pub found: u64,
+}
+
+/// Displays the mismatch, e.g. `kiwipete at depth 3: expected 97862 nodes, found 97860`.
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at depth {}: expected {} nodes, found {}",
+            self.name, self.depth, self.expected, self.found
+        )
+    }
+}
+
+/// Counts the nodes of every one of [`POSITIONS`] at every depth with at most `max_nodes`
+/// nodes and returns the counts that differ from the expected ones, none if the move
+/// generator is right. A position only goes as deep as its first count over `max_nodes`, so a
+/// million nodes check every position to depth 3 or more within seconds in release builds.
+pub fn verify(max_nodes: u64) -> Vec<Mismatch> {
+    check(&POSITIONS, max_nodes)
+}
+
+fn check(positions: &[PerftPosition], max_nodes: u64) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    for position in positions {
+        let game = position.game();
+        let within = position
+            .nodes
+            .iter()
+            .take_while(|&&nodes| nodes <= max_nodes);
+        for (depth, &expected) in (1..).zip(within) {
+            let found = perft(&game, depth);
+            if found != expected {
+                mismatches.push(Mismatch {
+                    name: position.name,
+                    depth,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_wrong_counts() {
+        let wrong = PerftPosition {
+            name: "wrong",
+            nodes: &[20, 401, 8902],
+            ..POSITIONS[0]
+        };
+        let mismatches = check(&[wrong], 1000);
+        assert_eq!(
+            mismatches,
+            [Mismatch {
+                name: "wrong",
+                depth: 2,
+                expected: 401,
+                found: 400,
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "wrong at depth 2: expected 401 nodes, found 400"
+        );
+    }
+
+    #[test]
+    fn suite_passes() {
+        assert_eq!(verify(100_000), []);
+    }
+}
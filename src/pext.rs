@@ -0,0 +1,126 @@
+//! Slider attack lookups indexed with the BMI2 `pext` instruction instead of magic
+//! multiplication. The tables are built on first use, and only if the CPU supports BMI2.
+
+use std::arch::x86_64::_pext_u64;
+use std::sync::OnceLock;
+
+use crate::{
+    bitboard::Bitboard,
+    magics::{generate::slow_attacks, BISHOP_MAGICS, ROOK_MAGICS},
+    Piece, Square,
+};
+
+pub(crate) struct PextTables {
+    rook: SliderTable,
+    bishop: SliderTable,
+}
+
+struct SliderTable {
+    masks: [u64; 64],
+    offsets: [usize; 64],
+    attacks: Vec<u64>,
+}
+
+/// Returns the lookup tables, or `None` if the CPU doesn't support BMI2.
+#[inline]
+pub(crate) fn tables() -> Option<&'static PextTables> {
+    static TABLES: OnceLock<Option<PextTables>> = OnceLock::new();
+    TABLES
+        .get_or_init(|| {
+            if is_x86_feature_detected!("bmi2") {
+                Some(PextTables {
+                    rook: SliderTable::new(|s| ROOK_MAGICS[s].mask, Piece::ROOK),
+                    bishop: SliderTable::new(|s| BISHOP_MAGICS[s].mask, Piece::BISHOP),
+                })
+            } else {
+                None
+            }
+        })
+        .as_ref()
+}
+
+impl PextTables {
+    #[inline]
+    pub(crate) fn rook(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        self.rook.lookup(square, occupied)
+    }
+
+    #[inline]
+    pub(crate) fn bishop(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        self.bishop.lookup(square, occupied)
+    }
+}
+
+impl SliderTable {
+    fn new(mask: impl Fn(usize) -> u64, piece: Piece) -> Self {
+        let mut masks = [0; 64];
+        let mut offsets = [0; 64];
+        let mut attacks = Vec::new();
+        for square in Square::all() {
+            let s = square as usize;
+            masks[s] = mask(s);
+            offsets[s] = attacks.len();
+            attacks.resize(attacks.len() + (1 << masks[s].count_ones()), 0);
+
+            // Walk through all subsets of the mask
+            let mut subset = 0u64;
+            loop {
+                // SAFETY: tables are only built after BMI2 support was detected
+                let index = unsafe { pext(subset, masks[s]) } as usize;
+                attacks[offsets[s] + index] = slow_attacks(piece, square, subset);
+                subset = subset.wrapping_sub(masks[s]) & masks[s];
+                if subset == 0 {
+                    break;
+                }
+            }
+        }
+        Self {
+            masks,
+            offsets,
+            attacks,
+        }
+    }
+
+    #[inline]
+    fn lookup(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        let s = square as usize;
+        // SAFETY: `PextTables` only exist if BMI2 support was detected
+        let index = unsafe { pext(occupied.0, self.masks[s]) } as usize;
+        Bitboard::from_u64(self.attacks[self.offsets[s] + index])
+    }
+}
+
+#[target_feature(enable = "bmi2")]
+unsafe fn pext(value: u64, mask: u64) -> u64 {
+    _pext_u64(value, mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        magics::{BISHOP_MOVES, ROOK_MOVES},
+        movegen::magic_index,
+    };
+
+    #[test]
+    fn matches_magics() {
+        let Some(tables) = tables() else {
+            return;
+        };
+        // A simple xorshift generator for reproducible occupancies
+        let mut state = 0x9e3779b97f4a7c15u64;
+        for _ in 0..1000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let occupied = Bitboard::from_u64(state & state.rotate_left(17));
+            for s in Square::all() {
+                let magic_rook = ROOK_MOVES[magic_index(&ROOK_MAGICS[s as usize], occupied)];
+                let magic_bishop = BISHOP_MOVES[magic_index(&BISHOP_MAGICS[s as usize], occupied)];
+                assert_eq!(tables.rook(s, occupied).0, magic_rook);
+                assert_eq!(tables.bishop(s, occupied).0, magic_bishop);
+            }
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! Reading and writing games in Portable Game Notation, e.g. to build an opening book from a
+//! database or to save the games of a [match](crate::matches).
+//!
+//! The tag pairs, the moves in SAN and the result of every game are read. Comments,
+//! variations, move numbers and numeric annotation glyphs are skipped. A game that doesn't
+//! start from the standard position gives its start in a `FEN` tag. An [`index`] of the
+//! positions of a database finds the games that reached a position.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Outcome, pgn, Color};
+//! let input = r#"
+//! [Event "Casual game"]
+//! [Result "1-0"]
+//!
+//! 1. e4 e5 2. Bc4 {aiming at f7} Nc6 (2... Nf6) 3. Qh5 Nf6?? 4. Qxf7# 1-0
+//! "#;
+//! let games = pgn::parse(input);
+//! let game = games[0].as_ref().unwrap();
+//! assert_eq!(game.tag("Event"), Some("Casual game"));
+//! assert_eq!(game.moves.len(), 7);
+//! assert_eq!(game.outcome, Some(Outcome::DECISIVE { winner: Color::WHITE }));
+//! ```
+
+pub mod index;
+
+use crate::{
+    game::{Game, Outcome},
+    Color, Error, Move, Result,
+};
+
+/// The most characters in a line of moves written by [`PgnGame`]'s `Display`.
+pub const LINE_WIDTH: usize = 80;
+
+/// A game read from or written to PGN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    /// The tag pairs in the order they were given, e.g. `("White", "Morphy, Paul")`.
+    pub tags: Vec<(String, String)>,
+    /// The position the game starts from.
+    pub start: Game,
+    pub moves: Vec<Move>,
+    /// How the game ended, or `None` if it is unfinished.
+    pub outcome: Option<Outcome>,
+}
+
+impl PgnGame {
+    /// Returns the value of the tag `name`, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Writes the game in PGN: the tags as given, then the moves in SAN with lines of at most
+/// [`LINE_WIDTH`] characters, ending with the result. The moves must be legal.
+impl std::fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+        let mut words = Vec::new();
+        let mut game = self.start;
+        for (i, &m) in self.moves.iter().enumerate() {
+            if game.to_move == Color::WHITE {
+                words.push(format!("{}.", game.fullmove_clock));
+            } else if i == 0 {
+                words.push(format!("{}...", game.fullmove_clock));
+            }
+            words.push(game.san(m).map_err(|_| std::fmt::Error)?);
+            game.make_move(m);
+        }
+        words.push(format_result(self.outcome).to_string());
+        let mut width = 0;
+        for word in words {
+            if width > 0 && width + 1 + word.len() > LINE_WIDTH {
+                writeln!(f)?;
+                width = 0;
+            } else if width > 0 {
+                write!(f, " ")?;
+                width += 1;
+            }
+            write!(f, "{word}")?;
+            width += word.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Returns `outcome` as written in PGN, e.g. `1-0`, or `*` for an unfinished game.
+pub fn format_result(outcome: Option<Outcome>) -> &'static str {
+    match outcome {
+        Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        }) => "1-0",
+        Some(Outcome::DECISIVE {
+            winner: Color::BLACK,
+        }) => "0-1",
+        Some(Outcome::DRAW) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// Reads every game in `input`. A game that can't be read, e.g. because one of its moves is
+/// illegal, gives an [`Error::Line`] with the line of the offending move or tag, and reading
+/// goes on with the next game.
+pub fn parse(input: &str) -> Vec<Result<PgnGame>> {
+    let mut games = Vec::new();
+    let mut pending = Pending::default();
+    for token in Tokens::new(input) {
+        let (line, token) = match token {
+            Ok(token) => token,
+            Err(e) => {
+                pending.fail(e);
+                continue;
+            }
+        };
+        match token {
+            Token::Tag(name, value) => {
+                if pending.in_movetext {
+                    games.push(pending.finish(None));
+                    pending = Pending::default();
+                }
+                pending.tags.push((name, value));
+            }
+            Token::San(san) => pending.play(line, san),
+            Token::Result(outcome) => {
+                games.push(pending.finish(Some(outcome)));
+                pending = Pending::default();
+            }
+        }
+    }
+    if !pending.tags.is_empty() || pending.in_movetext {
+        games.push(pending.finish(None));
+    }
+    games
+}
+
+/// Parses a game result as written in PGN, `None` for an unfinished game.
+fn parse_result(result: &str) -> Option<Option<Outcome>> {
+    match result {
+        "1-0" => Some(Some(Outcome::DECISIVE {
+            winner: Color::WHITE,
+        })),
+        "0-1" => Some(Some(Outcome::DECISIVE {
+            winner: Color::BLACK,
+        })),
+        "1/2-1/2" => Some(Some(Outcome::DRAW)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+/// A game while it is being read.
+#[derive(Default)]
+struct Pending {
+    tags: Vec<(String, String)>,
+    /// The start and the current position, once the first move has been read.
+    positions: Option<(Game, Game)>,
+    moves: Vec<Move>,
+    in_movetext: bool,
+    /// The first error in the game, after which its moves are skipped.
+    error: Option<Error>,
+}
+
+impl Pending {
+    fn fail(&mut self, e: Error) {
+        self.error.get_or_insert(e);
+    }
+
+    fn start(&self) -> Result<Game> {
+        self.tags
+            .iter()
+            .find(|(name, _)| name == "FEN")
+            .map_or(Ok(Game::default()), |(_, fen)| Game::from_fen(fen))
+    }
+
+    fn play(&mut self, line: usize, san: &str) {
+        self.in_movetext = true;
+        if self.error.is_some() {
+            return;
+        }
+        let at_line = |e| Error::Line {
+            line,
+            source: Box::new(e),
+        };
+        if self.positions.is_none() {
+            match self.start() {
+                Ok(start) => self.positions = Some((start, start)),
+                Err(e) => return self.fail(at_line(e)),
+            }
+        }
+        let Some((_, game)) = &mut self.positions else {
+            return;
+        };
+        match game.parse_san(san) {
+            Ok(m) => {
+                game.make_move(m);
+                self.moves.push(m);
+            }
+            Err(e) => self.fail(at_line(e)),
+        }
+    }
+
+    /// Returns the game with `outcome` as read after the moves, or the one in the `Result` tag
+    /// if the moves weren't followed by a result.
+    fn finish(self, outcome: Option<Option<Outcome>>) -> Result<PgnGame> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let start = match self.positions {
+            Some((start, _)) => start,
+            None => self.start()?,
+        };
+        let outcome = outcome.or_else(|| {
+            let (_, result) = self.tags.iter().find(|(name, _)| name == "Result")?;
+            parse_result(result)
+        });
+        Ok(PgnGame {
+            tags: self.tags,
+            start,
+            moves: self.moves,
+            outcome: outcome.flatten(),
+        })
+    }
+}
+
+enum Token<'a> {
+    Tag(String, String),
+    San(&'a str),
+    Result(Option<Outcome>),
+}
+
+/// Splits PGN into tokens, each with the line it starts on, skipping everything that isn't
+/// a tag, a move or a result.
+struct Tokens<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            rest: input,
+            line: 1,
+        }
+    }
+
+    fn advance(&mut self, len: usize) -> &'a str {
+        let (skipped, rest) = self.rest.split_at(len);
+        self.line += skipped.matches('\n').count();
+        self.rest = rest;
+        skipped
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.advance(self.rest.len() - self.rest.trim_start().len());
+    }
+
+    fn error(&self, line: usize, reason: &str) -> Error {
+        Error::Line {
+            line,
+            source: Box::new(Error::InvalidPgn(reason.to_string())),
+        }
+    }
+
+    /// Skips past the next `end`, failing if there is none.
+    fn skip_past(&mut self, end: char, line: usize, what: &str) -> Result<()> {
+        match self.rest.find(end) {
+            Some(len) => {
+                self.advance(len + end.len_utf8());
+                Ok(())
+            }
+            None => {
+                self.advance(self.rest.len());
+                Err(self.error(line, &format!("unterminated {what}")))
+            }
+        }
+    }
+
+    /// Skips a variation, including the variations and comments inside it.
+    fn skip_variation(&mut self, line: usize) -> Result<()> {
+        let mut depth = 0;
+        while let Some(c) = self.rest.chars().next() {
+            match c {
+                '{' => self.skip_past('}', self.line, "comment")?,
+                '(' | ')' => {
+                    self.advance(1);
+                    depth += if c == '(' { 1 } else { -1 };
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {
+                    self.advance(c.len_utf8());
+                }
+            }
+        }
+        Err(self.error(line, "unterminated variation"))
+    }
+
+    fn tag(&mut self, line: usize) -> Result<Token<'a>> {
+        let invalid = |tokens: &Self| tokens.error(line, "expected a tag like [Name \"value\"]");
+        self.advance(1);
+        self.skip_whitespace();
+        let len = self
+            .rest
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(self.rest.len());
+        let name = self.advance(len).to_string();
+        self.skip_whitespace();
+        if name.is_empty() || !self.rest.starts_with('"') {
+            return Err(invalid(self));
+        }
+        self.advance(1);
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        let len = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => return Err(invalid(self)),
+                },
+                Some((_, '\n')) | None => return Err(invalid(self)),
+                Some((_, c)) => value.push(c),
+            }
+        };
+        self.advance(len + 1);
+        self.skip_whitespace();
+        if !self.rest.starts_with(']') {
+            return Err(invalid(self));
+        }
+        self.advance(1);
+        Ok(Token::Tag(name, value))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<(usize, Token<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            let skipped = match self.rest.chars().next()? {
+                '[' => return Some(self.tag(line).map(|tag| (line, tag))),
+                '{' => self.skip_past('}', line, "comment"),
+                '(' => self.skip_variation(line),
+                ';' | '%' => {
+                    self.advance(self.rest.find('\n').unwrap_or(self.rest.len()));
+                    Ok(())
+                }
+                c @ (')' | ']' | '}') => {
+                    self.advance(1);
+                    Err(self.error(line, &format!("unexpected '{c}'")))
+                }
+                _ => {
+                    let len = self
+                        .rest
+                        .find(|c: char| c.is_whitespace() || "[]{}();".contains(c))
+                        .unwrap_or(self.rest.len());
+                    let symbol = self.advance(len);
+                    if let Some(outcome) = parse_result(symbol) {
+                        return Some(Ok((line, Token::Result(outcome))));
+                    }
+                    // Drop a move number in front of the move, e.g. `12.` or `12...`
+                    let after_number = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+                    let san = match after_number.strip_prefix('.') {
+                        Some(san) => san.trim_start_matches('.'),
+                        None => symbol,
+                    };
+                    if !san.is_empty() && !san.starts_with('$') {
+                        return Some(Ok((line, Token::San(san))));
+                    }
+                    Ok(())
+                }
+            };
+            if let Err(e) = skipped {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_several_games() {
+        let input = "\
+[Event \"First\"]
+[White \"A \\\"quoted\\\" name\"]
+
+1.d4 d5 2. c4 $1 ; the Queen's Gambit
+2...e6 (2... dxc4 3. e3 {and (White} b5) 3. Nc3 1/2-1/2
+
+[Event \"Second\"]
+[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]
+
+1. O-O Kd7 *
+";
+        let games = parse(input);
+        assert_eq!(games.len(), 2);
+        let first = games[0].as_ref().unwrap();
+        assert_eq!(first.tag("White"), Some("A \"quoted\" name"));
+        assert_eq!(first.moves.len(), 5);
+        assert_eq!(first.outcome, Some(Outcome::DRAW));
+        let second = games[1].as_ref().unwrap();
+        assert_eq!(second.start.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(
+            second.moves,
+            [
+                Move::from_uci("e1g1").unwrap(),
+                Move::from_uci("e8d7").unwrap()
+            ]
+        );
+        assert_eq!(second.outcome, None);
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let input = "\
+[Event \"A \\\"quoted\\\" name\"]
+[FEN \"4k3/8/8/8/8/8/8/4K2R b K - 0 30\"]
+
+30... Kd7 31. O-O Kc6 32. Rf8 Kb5 33. Rf5+ Kb4 34. Rf4+ Kb3 35. Rf3+ Kb2 36.
+Rf2+ Kb1 37. Rf1+ Kb2 38. Rf2+ 1/2-1/2
+";
+        let game = parse(input).remove(0).unwrap();
+        assert_eq!(game.to_string(), input);
+        assert_eq!(parse(&game.to_string()), [Ok(game)]);
+    }
+
+    #[test]
+    fn reports_the_line_of_an_illegal_move() {
+        let input = "1. e4 e5\n2. Ke3 Nc6 0-1\n\n[Result \"1-0\"]\n1. e4 {unterminated";
+        let games = parse(input);
+        assert_eq!(games.len(), 2);
+        assert!(matches!(
+            &games[0],
+            Err(Error::Line { line: 2, source }) if matches!(**source, Error::InvalidSan(_))
+        ));
+        assert!(matches!(
+            &games[1],
+            Err(Error::Line { line: 5, source }) if matches!(**source, Error::InvalidPgn(_))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+//! An index of every position reached in a PGN database, to find the games that contain a
+//! position as database software does, e.g. all games through a line of an opening.
+//!
+//! The index holds an entry for every [key](Game::key) of a position a game reached and the
+//! number of the game, its place among the games [`pgn::parse`](super::parse) reads from the
+//! database counting from 0. An index file is a list of 12-byte entries sorted by key, each
+//! holding the key as a `u64` and the game as a `u32`, both big-endian like the entries of a
+//! [book](crate::book). Since the keys are those of this crate, an index is only read by the
+//! version of the crate that wrote it.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, pgn::{self, index::PositionIndex}, Move};
+//! let games = pgn::parse("1. e4 e5 2. Nf3 *\n\n1. Nf3 e5 2. e4 *\n\n1. d4 d5 *\n");
+//! let index = PositionIndex::from_games(&games);
+//! let mut game = Game::default();
+//! for uci in ["e2e4", "e7e5", "g1f3"] {
+//!     game.make_move(Move::from_uci(uci).unwrap());
+//! }
+//! // The second game transposes into the first
+//! assert_eq!(index.find(&game), [0, 1]);
+//! assert_eq!(index.find(&Game::default()), [0, 1, 2]);
+//! assert_eq!(PositionIndex::from_bytes(&index.to_bytes()).unwrap(), index);
+//! ```
+
+use super::PgnGame;
+use crate::{game::Game, Error, Result};
+
+/// The size of an entry in an index file in bytes.
+pub const ENTRY_SIZE: usize = 12;
+
+/// A position reached in a game of the database.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IndexEntry {
+    /// The [key](Game::key) of the position.
+    pub key: u64,
+    /// The number of the game, counting from 0.
+    pub game: u32,
+}
+
+impl IndexEntry {
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.game.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let [k0, k1, k2, k3, k4, k5, k6, k7, g0, g1, g2, g3] = *bytes;
+        Self {
+            key: u64::from_be_bytes([k0, k1, k2, k3, k4, k5, k6, k7]),
+            game: u32::from_be_bytes([g0, g1, g2, g3]),
+        }
+    }
+}
+
+/// An index of the positions of a database, its entries sorted by key and the entries of a
+/// position by game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl PositionIndex {
+    /// Creates an index from `entries` in any order, keeping every position of a game once.
+    pub fn new(mut entries: Vec<IndexEntry>) -> Self {
+        entries.sort_unstable();
+        entries.dedup();
+        Self { entries }
+    }
+
+    /// Indexes the positions of `games` as [`pgn::parse`](super::parse) read them. Games that
+    /// couldn't be read keep their number but have no positions.
+    pub fn from_games(games: &[Result<PgnGame>]) -> Self {
+        let mut entries = Vec::new();
+        for (number, game) in (0..).zip(games) {
+            let Ok(game) = game else {
+                continue;
+            };
+            let mut position = game.start;
+            entries.push(IndexEntry {
+                key: position.key(),
+                game: number,
+            });
+            for &m in &game.moves {
+                position.make_move(m);
+                entries.push(IndexEntry {
+                    key: position.key(),
+                    game: number,
+                });
+            }
+        }
+        Self::new(entries)
+    }
+
+    /// Reads an index from the contents of an index file, failing with
+    /// [`Error::InvalidEncoding`] if they aren't made of whole entries.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(Error::InvalidEncoding(format!(
+                "an index of {} bytes doesn't consist of {ENTRY_SIZE}-byte entries",
+                bytes.len()
+            )));
+        }
+        Ok(Self::new(
+            bytes
+                .chunks_exact(ENTRY_SIZE)
+                .map(|chunk| {
+                    IndexEntry::from_bytes(chunk.try_into().expect("Chunks are whole entries"))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Returns the contents of the index file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.to_bytes())
+            .collect()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the numbers of the games that reached `game`'s position, in ascending order.
+    pub fn find(&self, game: &Game) -> Vec<u32> {
+        let key = game.key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let len = self.entries[start..].partition_point(|entry| entry.key == key);
+        self.entries[start..start + len]
+            .iter()
+            .map(|entry| entry.game)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+
+    #[test]
+    fn indexes_every_position_once() {
+        // The knights go back and forth, repeating the starting position
+        let input = "1. Nf3 Nf6 2. Ng1 Ng8 *\n\n1. e4 e4 *\n\n\
+                     [FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n\n*\n";
+        let games = pgn::parse(input);
+        assert!(games[1].is_err());
+        let index = PositionIndex::from_games(&games);
+        assert_eq!(index.len(), 4 + 1);
+        assert_eq!(index.find(&Game::default()), [0]);
+        let bare_kings = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(index.find(&bare_kings), [2]);
+        let e4 = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert!(index.find(&e4.unwrap()).is_empty());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let index = PositionIndex::new(vec![
+            IndexEntry { key: 7, game: 1 },
+            IndexEntry { key: 3, game: 4 },
+            IndexEntry { key: 7, game: 0 },
+        ]);
+        let bytes = index.to_bytes();
+        assert_eq!(bytes.len(), 3 * ENTRY_SIZE);
+        assert_eq!(bytes[..ENTRY_SIZE], [0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 4]);
+        assert_eq!(PositionIndex::from_bytes(&bytes).unwrap(), index);
+        assert!(matches!(
+            PositionIndex::from_bytes(&bytes[1..]),
+            Err(Error::InvalidEncoding(_))
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+//! The phase of a game, from the opening through the middlegame to the endgame, e.g. for
+//! tapering an evaluation, spending time on the clock or splitting up a review.
+//!
+//! A [`Phase`] measures how far a position has progressed from the starting position by how
+//! much material has come off the board and how many knights and bishops have left their
+//! starting squares, and sorts it into one of the three [`GamePhase`]s.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, phase::GamePhase};
+//! let start = Game::default().phase();
+//! assert_eq!(start.kind, GamePhase::OPENING);
+//! assert_eq!(start.progress, 0.0);
+//! // After 1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. O-O
+//! let fen = "r1bqkb1r/pppp1ppp/2n2n2/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4";
+//! let ruy_lopez = Game::from_fen(fen).unwrap().phase();
+//! assert_eq!(ruy_lopez.kind, GamePhase::OPENING);
+//! assert!(ruy_lopez.progress > start.progress);
+//! let endgame = Game::from_fen("4k3/pp6/8/8/8/8/PP6/R3K3 w - - 0 30").unwrap().phase();
+//! assert_eq!(endgame.kind, GamePhase::ENDGAME);
+//! assert!(endgame.progress > 0.9);
+//! ```
+
+use crate::{
+    bitboard::Bitboard,
+    eval::{self, MAX_PHASE},
+    game::Game,
+    Color, Piece, Square,
+};
+
+/// The most majors and minors, i.e. knights, bishops, rooks and queens of both sides, left in
+/// an [`ENDGAME`](GamePhase::ENDGAME).
+pub const ENDGAME_PIECES: u32 = 6;
+/// The last full move of the [`OPENING`](GamePhase::OPENING).
+pub const OPENING_MOVES: usize = 12;
+
+/// The starting squares of the knights and bishops of both colors.
+const MINOR_SQUARES: Bitboard = Bitboard::empty()
+    .with(Square::B1)
+    .with(Square::C1)
+    .with(Square::F1)
+    .with(Square::G1)
+    .with(Square::B8)
+    .with(Square::C8)
+    .with(Square::F8)
+    .with(Square::G8);
+
+/// The part of the game a position belongs to.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamePhase {
+    /// The first [`OPENING_MOVES`] moves, as long as more than 10 majors and minors are left
+    /// and some knight or bishop still stands on its starting square.
+    OPENING,
+    MIDDLEGAME,
+    /// Once at most [`ENDGAME_PIECES`] majors and minors are left.
+    ENDGAME,
+}
+
+impl GamePhase {
+    /// Returns the phase of `game`, see [`Game::phase`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, phase::GamePhase};
+    /// assert_eq!(GamePhase::of(&Game::default()), GamePhase::OPENING);
+    /// let game = Game::from_fen("4k3/pp6/8/8/8/8/PP6/R3K3 w - - 0 30").unwrap();
+    /// assert_eq!(GamePhase::of(&game), GamePhase::ENDGAME);
+    /// ```
+    pub fn of(game: &Game) -> Self {
+        Phase::of(game).kind
+    }
+}
+
+/// How far a game has progressed, see [`Game::phase`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Phase {
+    /// How much non-pawn material is left, from [`MAX_PHASE`] in the starting position down to
+    /// 0 with only kings and pawns, as the evaluation tapers by, see [`eval::phase`].
+    pub material: i32,
+    /// The share of the knights and bishops that have left their starting squares, from 0.0
+    /// in the starting position to 1.0. Pieces that were captured count as developed.
+    pub development: f64,
+    /// The mean of the material traded off and the development, from 0.0 in the starting
+    /// position to 1.0 with only kings and pawns left.
+    pub progress: f64,
+    /// The phase the position falls into.
+    pub kind: GamePhase,
+}
+
+impl Phase {
+    /// Returns the phase of `game`.
+    pub fn of(game: &Game) -> Self {
+        let material = eval::phase(game);
+        let minors = game.piece_bitboards[Piece::KNIGHT as usize]
+            | game.piece_bitboards[Piece::BISHOP as usize];
+        let undeveloped = Color::both()
+            .into_iter()
+            .map(|color| {
+                let own = minors & game.color_bitboards[color as usize] & MINOR_SQUARES;
+                // Only the pieces on their own side's back rank count
+                let back_rank: u64 = match color {
+                    Color::WHITE => 0xff,
+                    Color::BLACK => 0xff << 56,
+                };
+                (own & back_rank).count_ones()
+            })
+            .sum::<u32>();
+        let development = 1.0 - undeveloped as f64 / MINOR_SQUARES.count_ones() as f64;
+        let traded = 1.0 - material as f64 / MAX_PHASE as f64;
+
+        let pieces: u32 = Color::both()
+            .into_iter()
+            .flat_map(|c| {
+                [Piece::KNIGHT, Piece::BISHOP, Piece::ROOK, Piece::QUEEN]
+                    .map(|piece| game.piece_count(c, piece))
+            })
+            .sum();
+        let kind = if pieces <= ENDGAME_PIECES {
+            GamePhase::ENDGAME
+        } else if pieces > 10 && game.fullmove_clock <= OPENING_MOVES && undeveloped > 0 {
+            GamePhase::OPENING
+        } else {
+            GamePhase::MIDDLEGAME
+        };
+        Self {
+            material,
+            development,
+            progress: (traded + development) / 2.0,
+            kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(fen: &str) -> Phase {
+        Game::from_fen(fen).unwrap().phase()
+    }
+
+    #[test]
+    fn development_ends_the_opening() {
+        let start = phase("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.material, MAX_PHASE);
+        assert_eq!(start.development, 0.0);
+        assert_eq!(start.progress, 0.0);
+
+        // The opening lasts until the last bishop has come out
+        let undeveloped =
+            phase("r1bq1rk1/ppp2ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 w - - 0 7");
+        assert_eq!(undeveloped.kind, GamePhase::OPENING);
+        let developed =
+            phase("r2q1rk1/ppp2ppp/2npbn2/2b1p1B1/2B1P3/2NP1N2/PPP2PPP/R2Q1RK1 w - - 2 8");
+        assert_eq!(developed.development, 1.0);
+        assert_eq!(developed.progress, 0.5);
+        assert_eq!(developed.kind, GamePhase::MIDDLEGAME);
+
+        // Moves past the opening are middlegame whatever the development
+        let late = phase("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 13");
+        assert_eq!(late.kind, GamePhase::MIDDLEGAME);
+    }
+
+    #[test]
+    fn trading_leads_to_the_endgame() {
+        // A knight on the other side's back rank isn't undeveloped
+        let game = phase("4k1N1/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(game.development, 1.0);
+        assert_eq!(game.kind, GamePhase::ENDGAME);
+
+        let rooks = phase("r4rk1/pp3ppp/2n5/8/8/2N5/PP3PPP/R4RK1 w - - 0 20");
+        assert_eq!(rooks.kind, GamePhase::ENDGAME);
+        assert_eq!(rooks.material, 10);
+        let queens = phase("r2q1rk1/pp3ppp/2n5/8/8/2N5/PP3PPP/R2Q1RK1 w - - 0 20");
+        assert_eq!(queens.kind, GamePhase::MIDDLEGAME);
+        assert!(queens.progress < rooks.progress);
+    }
+}
@@ -0,0 +1,228 @@
+//! Piece-square tables: a positional bonus for every piece on every square, with separate
+//! values for the middlegame and the endgame. Material is not included, see
+//! [`Piece::value`](crate::Piece::value).
+//!
+//! [`Game::pst`](crate::game::Game::pst) keeps the sum over all pieces up to date as pieces
+//! move.
+
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use crate::{Color, ColoredPiece, Square};
+
+/// A pair of middlegame and endgame scores in centipawns.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Self = Self { mg: 0, eg: 0 };
+
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.mg, -self.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+// The tables are laid out as seen from white's side of the board, starting with the eighth
+// rank, so a white piece on square `s` reads entry `s ^ 56`.
+
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    10,  10,  10,  10,  10,  10,  10,  10,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// The middlegame and endgame tables of every piece type, indexed by piece discriminant.
+const TABLES: [(&[i32; 64], &[i32; 64]); 6] = [
+    (&PAWN_MG, &PAWN_EG),
+    (&KNIGHT, &KNIGHT),
+    (&BISHOP, &BISHOP),
+    (&ROOK, &ROOK),
+    (&QUEEN, &QUEEN),
+    (&KING_MG, &KING_EG),
+];
+
+/// Returns the positional bonus of `piece` on `square` from white's point of view, so black
+/// pieces score negatively.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{pst, ColoredPiece, Color, Piece, Square};
+/// let knight = ColoredPiece::new(Piece::KNIGHT, Color::WHITE);
+/// assert!(pst::value(knight, Square::E4).mg > pst::value(knight, Square::A1).mg);
+/// let black_knight = ColoredPiece::new(Piece::KNIGHT, Color::BLACK);
+/// assert_eq!(pst::value(black_knight, Square::E5), -pst::value(knight, Square::E4));
+/// ```
+#[inline]
+pub fn value(piece: ColoredPiece, square: Square) -> Score {
+    let (mg, eg) = TABLES[piece.piece as usize];
+    match piece.color {
+        Color::WHITE => {
+            let i = square as usize ^ 56;
+            Score::new(mg[i], eg[i])
+        }
+        Color::BLACK => {
+            let i = square as usize;
+            Score::new(-mg[i], -eg[i])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Piece;
+
+    fn white_value(piece: Piece, square: Square) -> Score {
+        value(ColoredPiece::new(piece, Color::WHITE), square)
+    }
+
+    #[test]
+    fn tables_are_mirrored_for_black() {
+        for piece in Piece::ALL {
+            for s in Square::all() {
+                let mirrored = Square::from_u8(s as u8 ^ 56);
+                assert_eq!(
+                    value(ColoredPiece::new(piece, Color::BLACK), mirrored),
+                    -white_value(piece, s)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn castled_king_is_safer_in_the_middlegame() {
+        let g1 = white_value(Piece::KING, Square::G1);
+        let e4 = white_value(Piece::KING, Square::E4);
+        assert!(g1.mg > e4.mg);
+        assert!(g1.eg < e4.eg);
+    }
+}
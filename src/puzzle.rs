@@ -0,0 +1,219 @@
+//! Reading the puzzle database lichess publishes as CSV, e.g. to build a training app.
+//!
+//! Every line holds a puzzle with the columns `PuzzleId`, `FEN`, `Moves`, `Rating`,
+//! `RatingDeviation`, `Popularity`, `NbPlays`, `Themes`, `GameUrl` and `OpeningTags`, of which
+//! the last two may be missing. The FEN is the position before the opponent's move that sets
+//! up the puzzle, and the moves in UCI notation start with that move. Every move is checked
+//! to be legal while reading, so a [`Puzzle`] can be played through without further checks.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{puzzle, Move};
+//! let input = "\
+//! PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+//! 0000D,5rk1/1p3ppp/pq3b2/8/8/1P1Q1N2/P4PPP/3R2K1 w - - 2 27,d3d6 f8d8 d6d8 f6d8,1426,500,2,0,\
+//! advantage endgame short,https://lichess.org/F8M8OS71#53,
+//! ";
+//! let puzzles = puzzle::parse(input);
+//! let puzzle = puzzles[0].as_ref().unwrap();
+//! assert_eq!(puzzle.rating, 1426);
+//! assert!(puzzle.has_theme("endgame"));
+//! assert_eq!(puzzle.solution()[0], Move::from_uci("f8d8").unwrap());
+//! assert!(puzzle.is_correct(0, Move::from_uci("f8d8").unwrap()));
+//! ```
+
+use crate::{
+    game::{Game, Outcome},
+    movegen::all_legal_moves,
+    Error, Move, Result,
+};
+
+/// A puzzle of the lichess database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub id: String,
+    /// The position before the opponent's move.
+    pub game: Game,
+    /// The opponent's move followed by the moves of the solution, the last of which is the
+    /// solver's.
+    pub moves: Vec<Move>,
+    pub rating: u32,
+    pub rating_deviation: u32,
+    /// How much players liked the puzzle, from -100 to 100.
+    pub popularity: i32,
+    /// How often the puzzle was played.
+    pub plays: u32,
+    /// The themes, e.g. `fork` or `mateIn2`.
+    pub themes: Vec<String>,
+    /// The game the puzzle comes from, if given.
+    pub game_url: Option<String>,
+    /// The opening of that game, e.g. `Sicilian_Defense`.
+    pub opening_tags: Vec<String>,
+}
+
+impl Puzzle {
+    /// Parses a puzzle from a line of the database.
+    pub fn parse(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let [id, fen, moves, rating, rating_deviation, popularity, plays, themes, rest @ ..] =
+            fields.as_slice()
+        else {
+            return Err(Error::InvalidPuzzle(format!(
+                "expected at least 8 fields in '{line}'"
+            )));
+        };
+        let game = Game::from_fen(fen)?;
+        let mut position = game;
+        let mut parsed = Vec::new();
+        for uci in moves.split_whitespace() {
+            let m = Move::from_uci(uci)?;
+            if !all_legal_moves(&position).contains(&m) {
+                return Err(Error::IllegalMove(m));
+            }
+            position.make_move(m);
+            parsed.push(m);
+        }
+        if parsed.len() < 2 || parsed.len() % 2 == 1 {
+            return Err(Error::InvalidPuzzle(format!(
+                "expected an even number of moves, got '{moves}'"
+            )));
+        }
+        let words = |field: &str| field.split_whitespace().map(str::to_string).collect();
+        Ok(Self {
+            id: id.to_string(),
+            game,
+            moves: parsed,
+            rating: number(rating)?,
+            rating_deviation: number(rating_deviation)?,
+            popularity: number(popularity)?,
+            plays: number(plays)?,
+            themes: words(themes),
+            game_url: rest
+                .first()
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string()),
+            opening_tags: rest.get(1).map_or_else(Vec::new, |tags| words(tags)),
+        })
+    }
+
+    /// Returns the position the solver faces, after the opponent's move.
+    pub fn position(&self) -> Game {
+        let mut game = self.game;
+        game.make_move(self.moves[0]);
+        game
+    }
+
+    /// Returns the moves of the solution, the solver's and the opponent's answers in turn.
+    pub fn solution(&self) -> &[Move] {
+        &self.moves[1..]
+    }
+
+    /// Returns `true` if the puzzle has `theme`.
+    pub fn has_theme(&self, theme: &str) -> bool {
+        self.themes.iter().any(|t| t == theme)
+    }
+
+    /// Returns `true` if playing `m` instead of the move `ply` of the [solution](Self::solution)
+    /// solves the puzzle so far: the move of the solution or, like lichess accepts it, any
+    /// move that mates.
+    pub fn is_correct(&self, ply: usize, m: Move) -> bool {
+        let Some(&expected) = self.solution().get(ply) else {
+            return false;
+        };
+        if m == expected {
+            return true;
+        }
+        let mut game = self.position();
+        for &m in &self.solution()[..ply] {
+            game.make_move(m);
+        }
+        let mover = game.to_move;
+        all_legal_moves(&game).contains(&m) && {
+            game.make_move(m);
+            game.outcome() == Some(Outcome::DECISIVE { winner: mover })
+        }
+    }
+}
+
+/// Parses a numeric column.
+fn number<T: std::str::FromStr>(field: &str) -> Result<T> {
+    field
+        .parse()
+        .map_err(|_| Error::InvalidPuzzle(format!("expected a number, got '{field}'")))
+}
+
+/// Reads every non-empty line of `input` as a puzzle, skipping the header. A puzzle that can't
+/// be read gives an [`Error::Line`] with its 1-based line number, and reading goes on with the
+/// next line.
+pub fn parse(input: &str) -> Vec<Result<Puzzle>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with("PuzzleId,"))
+        .map(|(i, line)| {
+            Puzzle::parse(line).map_err(|e| Error::Line {
+                line: i + 1,
+                source: Box::new(e),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_puzzle() {
+        let line = "00008,r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24,\
+                    f2g3 e6e7 b2b1 b3c1 b1c1 h6c1,1913,75,94,6230,\
+                    crushing hangingPiece long middlegame,https://lichess.org/787zsVup/black#48,\
+                    Kings_Pawn_Game Kings_Pawn_Game_Other_variations";
+        let puzzle = Puzzle::parse(line).unwrap();
+        assert_eq!(puzzle.id, "00008");
+        assert_eq!(puzzle.solution().len(), 5);
+        assert_eq!(puzzle.popularity, 94);
+        assert_eq!(puzzle.plays, 6230);
+        assert_eq!(puzzle.themes.len(), 4);
+        assert_eq!(
+            puzzle.game_url.as_deref(),
+            Some("https://lichess.org/787zsVup/black#48")
+        );
+        assert_eq!(puzzle.opening_tags[0], "Kings_Pawn_Game");
+        assert_eq!(
+            puzzle.position().to_fen(),
+            "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2b1/PqP3PP/7K w - - 0 25"
+        );
+        assert!(!puzzle.is_correct(0, Move::from_uci("h6f8").unwrap()));
+        assert!(!puzzle.is_correct(5, puzzle.moves[5]));
+    }
+
+    #[test]
+    fn accepts_any_mate() {
+        // Both rooks mate on the back rank
+        let line = "1,6k1/5ppp/8/8/8/2r5/RR6/6K1 b - - 0 1,c3h3 a2a8,1000,80,90,10,mateIn1";
+        let puzzle = Puzzle::parse(line).unwrap();
+        assert_eq!(puzzle.game_url, None);
+        assert!(puzzle.is_correct(0, Move::from_uci("a2a8").unwrap()));
+        assert!(puzzle.is_correct(0, Move::from_uci("b2b8").unwrap()));
+        assert!(!puzzle.is_correct(0, Move::from_uci("b2b7").unwrap()));
+    }
+
+    #[test]
+    fn parse_reports_bad_lines() {
+        let input = "PuzzleId,FEN,Moves\n\n1,8/8/8 w - - 0 1,e2e4 e7e5,1,1,1,1,\n2,k7\n\
+                     3,4k3/8/8/8/8/8/8/4K3 w - - 0 1,e1e2,1,1,1,1,\n\
+                     4,4k3/8/8/8/8/8/8/4K3 w - - 0 1,e1e3 e8e7,1,1,1,1,\n";
+        let puzzles = parse(input);
+        assert_eq!(puzzles.len(), 4);
+        assert!(matches!(&puzzles[0], Err(Error::Line { line: 3, source })
+            if matches!(**source, Error::InvalidFen(_))));
+        assert!(matches!(&puzzles[1], Err(Error::Line { line: 4, source })
+            if matches!(**source, Error::InvalidPuzzle(_))));
+        assert!(matches!(&puzzles[2], Err(Error::Line { source, .. })
+            if matches!(**source, Error::InvalidPuzzle(_))));
+        assert!(matches!(&puzzles[3], Err(Error::Line { source, .. })
+            if matches!(**source, Error::IllegalMove(_))));
+    }
+}
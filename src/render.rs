@@ -0,0 +1,267 @@
+//! Text and image rendering of positions.
+
+use crate::{game::Game, ColoredPiece, File, Rank};
+
+#[cfg(feature = "ansi")]
+pub mod ansi;
+pub mod svg;
+
+/// Variation selector requesting the text presentation of the preceding character.
+/// Without it some terminals draw ♟ as a double-width emoji, which shifts the rest of the rank.
+const TEXT_PRESENTATION: char = '\u{fe0e}';
+
+/// The duck of Duck chess, drawn as in FENs.
+const DUCK: char = '*';
+
+/// How pieces are drawn by a [`BoardFormatter`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PieceStyle {
+    /// FEN letters, uppercase for white and lowercase for black.
+    ASCII,
+    /// Unicode chess symbols, see [`ColoredPiece::to_unicode`].
+    UNICODE,
+}
+
+/// Renders a position as text with configurable options.
+/// The default options reproduce the plain `Display` output of [`Game`].
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, render::BoardFormatter};
+/// let formatter = BoardFormatter {
+///     coordinates: true,
+///     flipped: true,
+///     ..Default::default()
+/// };
+/// let board = formatter.format(&Game::default());
+/// assert!(board.starts_with("\n1 R N B K Q B N R \n"));
+/// assert!(board.ends_with("  h g f e d c b a \n"));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BoardFormatter {
+    /// Show rank numbers to the left of and file letters below the board.
+    pub coordinates: bool,
+    /// Draw the board from black's perspective.
+    pub flipped: bool,
+    pub piece_style: PieceStyle,
+    /// The character drawn on empty squares.
+    pub empty_square: char,
+    /// Append the position's FEN string below the board.
+    pub fen_footer: bool,
+}
+
+impl Default for BoardFormatter {
+    fn default() -> Self {
+        Self {
+            coordinates: false,
+            flipped: false,
+            piece_style: PieceStyle::ASCII,
+            empty_square: '.',
+            fen_footer: false,
+        }
+    }
+}
+
+impl BoardFormatter {
+    /// Returns a formatter drawing Unicode chess symbols, with a middle dot on empty squares
+    /// so they line up with the glyphs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, render::BoardFormatter};
+    /// let board = BoardFormatter::unicode().format(&Game::default());
+    /// assert!(board.contains("\n· · · · · · · · \n"));
+    /// assert!(board.ends_with("\n♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ \n"));
+    /// ```
+    pub fn unicode() -> Self {
+        Self {
+            piece_style: PieceStyle::UNICODE,
+            empty_square: '·',
+            ..Default::default()
+        }
+    }
+
+    /// Renders `game` into a new `String`.
+    pub fn format(&self, game: &Game) -> String {
+        let mut out = String::new();
+        self.write(game, &mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Renders `game` into `f`.
+    pub fn write(&self, game: &Game, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        writeln!(f)?;
+        for rank in ranks(game, self.flipped) {
+            if self.coordinates {
+                write!(f, "{rank} ")?;
+            }
+            for file in files(game, self.flipped) {
+                let square = crate::Square::from_coords(file, rank);
+                match game.piece_at(square) {
+                    Some(piece) => write_piece(self.piece_style, piece, f)?,
+                    None if game.duck() == Some(square) => write!(f, "{DUCK}")?,
+                    None => write!(f, "{}", self.empty_square)?,
+                }
+                write!(f, " ")?;
+            }
+            writeln!(f)?;
+        }
+
+        if self.coordinates {
+            write!(f, "  ")?;
+            for file in files(game, self.flipped) {
+                write!(f, "{file} ")?;
+            }
+            writeln!(f)?;
+        }
+        if self.fen_footer {
+            writeln!(f, "{}", game.fen())?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the ranks of `game`'s board, which may be smaller than 8x8, from the top of the
+/// board to the bottom.
+fn ranks(game: &Game, flipped: bool) -> Box<dyn Iterator<Item = Rank>> {
+    let ranks = (0..game.rules().board_size().1).map(Rank::from_u8);
+    if flipped {
+        Box::new(ranks)
+    } else {
+        Box::new(ranks.rev())
+    }
+}
+
+/// Returns the files of `game`'s board from the left of the board to the right.
+fn files(game: &Game, flipped: bool) -> Box<dyn Iterator<Item = File>> {
+    let files = (0..game.rules().board_size().0).map(File::from_u8);
+    if flipped {
+        Box::new(files.rev())
+    } else {
+        Box::new(files)
+    }
+}
+
+fn write_piece(
+    style: PieceStyle,
+    piece: ColoredPiece,
+    f: &mut impl std::fmt::Write,
+) -> std::fmt::Result {
+    match style {
+        PieceStyle::ASCII => write!(f, "{}", piece.to_char()),
+        PieceStyle::UNICODE => {
+            let glyph = piece.to_unicode();
+            // ♟ is the only chess symbol that also has an emoji form
+            if glyph == '♟' {
+                write!(f, "{glyph}{TEXT_PRESENTATION}")
+            } else {
+                write!(f, "{glyph}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_display() {
+        let game = Game::default();
+        assert_eq!(BoardFormatter::default().format(&game), game.to_string());
+    }
+
+    #[test]
+    fn coordinates() {
+        let formatter = BoardFormatter {
+            coordinates: true,
+            ..Default::default()
+        };
+        let board = formatter.format(&Game::default());
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[1], "8 r n b q k b n r ");
+        assert_eq!(lines[8], "1 R N B Q K B N R ");
+        assert_eq!(lines[9], "  a b c d e f g h ");
+    }
+
+    #[test]
+    fn flipped_with_custom_empty_square() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let formatter = BoardFormatter {
+            flipped: true,
+            empty_square: '-',
+            ..Default::default()
+        };
+        let board = formatter.format(&game);
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[1], "R - - K - - - - ");
+        assert_eq!(lines[8], "- - - k - - - - ");
+    }
+
+    #[test]
+    fn unicode_pieces() {
+        let formatter = BoardFormatter {
+            piece_style: PieceStyle::UNICODE,
+            ..Default::default()
+        };
+        let board = formatter.format(&Game::default());
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[1], "♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜ ");
+        assert_eq!(lines[2], "♟\u{fe0e} ".repeat(8));
+        assert_eq!(lines[7], "♙ ".repeat(8));
+        assert_eq!(lines[8], "♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ ");
+    }
+
+    #[test]
+    fn unicode_preset() {
+        let board = BoardFormatter::unicode().format(&Game::default());
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[4], "· · · · · · · · ");
+        // Every rank has the same number of visible glyphs
+        for line in &lines[1..] {
+            let visible = line.chars().filter(|c| *c != TEXT_PRESENTATION).count();
+            assert_eq!(visible, 16);
+        }
+    }
+
+    #[test]
+    fn duck() {
+        let game =
+            Game::from_fen("rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let lines: Vec<String> = game.to_string().lines().map(String::from).collect();
+        assert_eq!(lines[4], ". . . . * . . . ");
+        let board = BoardFormatter::unicode().format(&game);
+        assert_eq!(board.lines().nth(4), Some("· · · · * · · · "));
+    }
+
+    #[test]
+    fn minichess() {
+        let formatter = BoardFormatter {
+            coordinates: true,
+            ..Default::default()
+        };
+        let board =
+            formatter.format(&Game::from_fen("rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1").unwrap());
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[1], "5 r n b q k ");
+        assert_eq!(lines[5], "1 R N B Q K ");
+        assert_eq!(lines[6], "  a b c d e ");
+    }
+
+    #[test]
+    fn fen_footer() {
+        let formatter = BoardFormatter {
+            fen_footer: true,
+            ..Default::default()
+        };
+        let board = formatter.format(&Game::default());
+        assert_eq!(
+            board.lines().last(),
+            Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+        );
+    }
+}
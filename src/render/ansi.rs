@@ -0,0 +1,192 @@
+//! Colored board rendering for terminals supporting 256-color ANSI escape codes.
+
+use super::{files, ranks, write_piece, PieceStyle, DUCK};
+use crate::{game::Game, Color, Move, Square};
+
+const RESET: &str = "\x1b[0m";
+
+/// The 256-color palette indices used by a [`TerminalFormatter`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Palette {
+    pub light_square: u8,
+    pub dark_square: u8,
+    pub last_move_light: u8,
+    pub last_move_dark: u8,
+    pub check: u8,
+    pub white_piece: u8,
+    pub black_piece: u8,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            light_square: 180,
+            dark_square: 137,
+            last_move_light: 186,
+            last_move_dark: 143,
+            check: 160,
+            white_piece: 231,
+            black_piece: 16,
+        }
+    }
+}
+
+/// Renders a position with colored squares, highlighting the last move and a king in check.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, render::ansi::TerminalFormatter, Move, Square};
+/// let mut game = Game::default();
+/// let m = Move::new(Square::E2, Square::E4);
+/// game.make_move(m);
+/// let formatter = TerminalFormatter {
+///     last_move: Some(m),
+///     ..Default::default()
+/// };
+/// println!("{}", formatter.format(&game));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TerminalFormatter {
+    /// Show rank numbers to the left of and file letters below the board.
+    pub coordinates: bool,
+    /// Draw the board from black's perspective.
+    pub flipped: bool,
+    pub piece_style: PieceStyle,
+    /// The move leading to the position, whose origin and destination are highlighted.
+    pub last_move: Option<Move>,
+    pub palette: Palette,
+}
+
+impl Default for TerminalFormatter {
+    fn default() -> Self {
+        Self {
+            coordinates: true,
+            flipped: false,
+            piece_style: PieceStyle::UNICODE,
+            last_move: None,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl TerminalFormatter {
+    /// Renders `game` into a new `String`.
+    pub fn format(&self, game: &Game) -> String {
+        let mut out = String::new();
+        self.write(game, &mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Renders `game` into `f`. Every square is three columns wide, every rank ends with a reset.
+    pub fn write(&self, game: &Game, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let checked_king = if game.is_check() {
+            game.king_square(game.to_move)
+        } else {
+            None
+        };
+
+        for rank in ranks(game, self.flipped) {
+            if self.coordinates {
+                write!(f, "{rank} ")?;
+            }
+            for file in files(game, self.flipped) {
+                let square = Square::from_coords(file, rank);
+                let background = if checked_king == Some(square) {
+                    self.palette.check
+                } else {
+                    self.background(square)
+                };
+                write!(f, "\x1b[48;5;{background}m")?;
+                match game.piece_at(square) {
+                    Some(piece) => {
+                        let foreground = match piece.color {
+                            Color::WHITE => self.palette.white_piece,
+                            Color::BLACK => self.palette.black_piece,
+                        };
+                        write!(f, "\x1b[38;5;{foreground}m ")?;
+                        write_piece(self.piece_style, piece, f)?;
+                        write!(f, " ")?;
+                    }
+                    None if game.duck() == Some(square) => {
+                        write!(f, "\x1b[38;5;{}m {DUCK} ", self.palette.black_piece)?;
+                    }
+                    None => write!(f, "   ")?,
+                }
+            }
+            writeln!(f, "{RESET}")?;
+        }
+
+        if self.coordinates {
+            write!(f, " ")?;
+            for file in files(game, self.flipped) {
+                write!(f, "  {file}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn background(&self, square: Square) -> u8 {
+        let light = (square.get_rank() as u8 + square.get_file() as u8) % 2 == 1;
+        let highlighted = self
+            .last_move
+            .is_some_and(|m| m.start == square || m.end == square);
+        match (light, highlighted) {
+            (true, false) => self.palette.light_square,
+            (false, false) => self.palette.dark_square,
+            (true, true) => self.palette.last_move_light,
+            (false, true) => self.palette.last_move_dark,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn background_at(board: &str, rank_line: usize, file: usize) -> String {
+        let line = board.lines().nth(rank_line).unwrap();
+        let square = line.split("\x1b[48;5;").nth(file + 1).unwrap();
+        square[..square.find('m').unwrap()].to_string()
+    }
+
+    #[test]
+    fn square_colors() {
+        let palette = Palette::default();
+        let board = TerminalFormatter::default().format(&Game::default());
+        // a8 is light, a1 is dark
+        assert_eq!(
+            background_at(&board, 0, 0),
+            palette.light_square.to_string()
+        );
+        assert_eq!(background_at(&board, 7, 0), palette.dark_square.to_string());
+        assert_eq!(
+            background_at(&board, 7, 1),
+            palette.light_square.to_string()
+        );
+        assert!(board.ends_with("   a  b  c  d  e  f  g  h\n"));
+    }
+
+    #[test]
+    fn last_move_and_check() {
+        let game = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        let formatter = TerminalFormatter {
+            last_move: Some(Move::new(Square::D8, Square::H4)),
+            ..Default::default()
+        };
+        let palette = formatter.palette;
+        let board = formatter.format(&game);
+        assert_eq!(
+            background_at(&board, 0, 3),
+            palette.last_move_dark.to_string()
+        );
+        assert_eq!(
+            background_at(&board, 4, 7),
+            palette.last_move_dark.to_string()
+        );
+        assert_eq!(background_at(&board, 7, 4), palette.check.to_string());
+    }
+}
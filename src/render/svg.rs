@@ -0,0 +1,298 @@
+//! SVG rendering of positions and animated games.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use super::{files, ranks};
+use crate::{game::Game, Move, Result, Square};
+
+/// Renders positions as SVG images, with pieces drawn as Unicode chess symbols.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, render::svg::SvgFormatter};
+/// let svg = SvgFormatter::default().format(&Game::default(), None);
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains("♔"));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SvgFormatter {
+    /// The side length of a square in pixels.
+    pub square_size: u32,
+    /// Draw the board from black's perspective.
+    pub flipped: bool,
+    /// Show rank numbers and file letters along the edges of the board.
+    pub coordinates: bool,
+    pub light_square: String,
+    pub dark_square: String,
+    /// The fill color of the last move's origin and destination.
+    pub highlight: String,
+    /// The fill color of the duck of Duck chess, drawn as a disc.
+    pub duck: String,
+}
+
+impl Default for SvgFormatter {
+    fn default() -> Self {
+        Self {
+            square_size: 45,
+            flipped: false,
+            coordinates: true,
+            light_square: "#f0d9b5".to_string(),
+            dark_square: "#b58863".to_string(),
+            highlight: "#cdd26a".to_string(),
+            duck: "#f2c12e".to_string(),
+        }
+    }
+}
+
+impl SvgFormatter {
+    /// Renders `game` as a standalone SVG image, highlighting `last_move` if given.
+    pub fn format(&self, game: &Game, last_move: Option<Move>) -> String {
+        let mut out = String::new();
+        self.open_svg(game, &mut out);
+        self.write_board(game, last_move, &mut out);
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Renders every position of a game as a standalone SVG image, starting with `start` and
+    /// followed by the position after each of `moves`.
+    /// Returns [`Error::IllegalMove`](crate::Error::IllegalMove) if a move is not legal.
+    pub fn frames(&self, start: &Game, moves: &[Move]) -> Result<Vec<String>> {
+        Ok(positions(start, moves)?
+            .iter()
+            .map(|(game, last_move)| self.format(game, *last_move))
+            .collect())
+    }
+
+    /// Renders a game as a single looping SVG animation showing every position for `delay`.
+    /// Returns [`Error::IllegalMove`](crate::Error::IllegalMove) if a move is not legal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use kritisch::{game::Game, render::svg::SvgFormatter, Move, Square};
+    /// let moves = [
+    ///     Move::new(Square::E2, Square::E4),
+    ///     Move::new(Square::E7, Square::E5),
+    /// ];
+    /// let svg = SvgFormatter::default()
+    ///     .animate(&Game::default(), &moves, Duration::from_millis(500))
+    ///     .unwrap();
+    /// assert!(svg.contains(r#"dur="1.5s""#));
+    /// ```
+    pub fn animate(&self, start: &Game, moves: &[Move], delay: Duration) -> Result<String> {
+        let positions = positions(start, moves)?;
+        let count = positions.len();
+        let total = delay.as_secs_f64() * count as f64;
+
+        let mut out = String::new();
+        self.open_svg(start, &mut out);
+        for (i, (game, last_move)) in positions.iter().enumerate() {
+            // Each frame is only visible during its slice of the loop
+            let show = i as f64 / count as f64;
+            let hide = (i + 1) as f64 / count as f64;
+            let initial = if i == 0 { "visible" } else { "hidden" };
+            writeln!(
+                out,
+                r#"<g visibility="{initial}"><animate attributeName="visibility" values="hidden;visible;hidden" keyTimes="0;{show};{hide}" calcMode="discrete" dur="{total}s" repeatCount="indefinite"/>"#
+            )
+            .unwrap();
+            self.write_board(game, *last_move, &mut out);
+            out.push_str("</g>\n");
+        }
+        out.push_str("</svg>\n");
+        Ok(out)
+    }
+
+    /// Returns the width and height of `game`'s board in pixels.
+    fn board_size(&self, game: &Game) -> (u32, u32) {
+        let (files, ranks) = game.rules().board_size();
+        (
+            self.square_size * files as u32,
+            self.square_size * ranks as u32,
+        )
+    }
+
+    fn open_svg(&self, game: &Game, out: &mut String) {
+        let (width, height) = self.board_size(game);
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )
+        .unwrap();
+    }
+
+    fn write_board(&self, game: &Game, last_move: Option<Move>, out: &mut String) {
+        let size = self.square_size;
+        let bottom_row = game.rules().board_size().1 as usize - 1;
+        for (row, rank) in ranks(game, self.flipped).enumerate() {
+            for (column, file) in files(game, self.flipped).enumerate() {
+                let square = Square::from_coords(file, rank);
+                let x = column as u32 * size;
+                let y = row as u32 * size;
+                let light = (rank as u8 + file as u8) % 2 == 1;
+                let highlighted = last_move.is_some_and(|m| m.start == square || m.end == square);
+                let fill = match (highlighted, light) {
+                    (true, _) => &self.highlight,
+                    (false, true) => &self.light_square,
+                    (false, false) => &self.dark_square,
+                };
+                writeln!(
+                    out,
+                    r#"<rect x="{x}" y="{y}" width="{size}" height="{size}" fill="{fill}"/>"#
+                )
+                .unwrap();
+
+                if let Some(piece) = game.piece_at(square) {
+                    writeln!(
+                        out,
+                        r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                        x + size / 2,
+                        y + size / 2,
+                        size * 4 / 5,
+                        piece.to_unicode()
+                    )
+                    .unwrap();
+                } else if game.duck() == Some(square) {
+                    writeln!(
+                        out,
+                        r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="black"/>"#,
+                        x + size / 2,
+                        y + size / 2,
+                        size / 3,
+                        self.duck
+                    )
+                    .unwrap();
+                }
+
+                // Coordinates go into the corners of the bottom row and left column, drawn in
+                // the color of the opposite square so they stay readable
+                let label_color = if light {
+                    &self.dark_square
+                } else {
+                    &self.light_square
+                };
+                if self.coordinates && column == 0 {
+                    writeln!(
+                        out,
+                        r#"<text x="{}" y="{}" font-size="{}" fill="{label_color}">{rank}</text>"#,
+                        x + 2,
+                        y + size / 4,
+                        size / 4
+                    )
+                    .unwrap();
+                }
+                if self.coordinates && row == bottom_row {
+                    writeln!(
+                        out,
+                        r#"<text x="{}" y="{}" font-size="{}" text-anchor="end" fill="{label_color}">{file}</text>"#,
+                        x + size - 2,
+                        y + size - 2,
+                        size / 4
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Returns every position of the game together with the move leading to it.
+fn positions(start: &Game, moves: &[Move]) -> Result<Vec<(Game, Option<Move>)>> {
+    let mut game = *start;
+    let mut positions = vec![(game, None)];
+    for &m in moves {
+        game.try_make_move(m)?;
+        positions.push((game, Some(m)));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn board() {
+        let svg = SvgFormatter::default().format(&Game::default(), None);
+        assert_eq!(svg.matches("<rect").count(), 64);
+        assert_eq!(svg.matches("♟").count(), 8);
+        assert!(svg.contains(r##"<rect x="0" y="0" width="45" height="45" fill="#f0d9b5"/>"##));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn duck() {
+        let game =
+            Game::from_fen("rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let svg = SvgFormatter::default().format(&game, None);
+        // e5 is the fifth square of the fourth row
+        assert!(
+            svg.contains(r##"<circle cx="202" cy="157" r="15" fill="#f2c12e" stroke="black"/>"##)
+        );
+        assert_eq!(
+            SvgFormatter::default()
+                .format(&Game::default(), None)
+                .matches("<circle")
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn minichess() {
+        let game = Game::from_fen("rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1").unwrap();
+        let svg = SvgFormatter::default().format(&game, None);
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="225" height="225""#)
+        );
+        assert_eq!(svg.matches("<rect").count(), 25);
+        assert_eq!(svg.matches("♟").count(), 5);
+        // The files along the bottom row end at e
+        assert!(svg.contains(">e</text>") && !svg.contains(">f</text>"));
+
+        let game = Game::from_fen("kqbnr/ppppp/5/5/PPPPP/RNBQK w - - 0 1").unwrap();
+        let svg = SvgFormatter::default().format(&game, None);
+        assert!(svg.contains(r#"width="225" height="270""#));
+    }
+
+    #[test]
+    fn frames_highlight_last_move() {
+        let m = Move::new(Square::G1, Square::F3);
+        let frames = SvgFormatter::default()
+            .frames(&Game::default(), &[m])
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(!frames[0].contains("#cdd26a"));
+        assert_eq!(frames[1].matches("#cdd26a").count(), 2);
+    }
+
+    #[test]
+    fn animation() {
+        let moves = [
+            Move::new(Square::E2, Square::E4),
+            Move::new(Square::E7, Square::E5),
+            Move::new(Square::G1, Square::F3),
+        ];
+        let svg = SvgFormatter::default()
+            .animate(&Game::default(), &moves, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(svg.matches("<animate ").count(), 4);
+        assert_eq!(svg.matches(r#"<g visibility="visible">"#).count(), 1);
+        assert!(svg.contains(r#"keyTimes="0;0.75;1""#));
+        assert!(svg.contains(r#"dur="4s""#));
+    }
+
+    #[test]
+    fn illegal_move() {
+        let m = Move::new(Square::E2, Square::E5);
+        assert_eq!(
+            SvgFormatter::default().frames(&Game::default(), &[m]),
+            Err(Error::IllegalMove(m))
+        );
+    }
+}
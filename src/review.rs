@@ -0,0 +1,439 @@
+//! Automated game review: an [`Engine`] searches every position of a game, and every move is
+//! judged by the centipawns it lost against the engine's best move, from the best move down
+//! to a blunder.
+//!
+//! The loss of a move is the score before it minus the score after it, both from the point of
+//! view of the side that made it, and never less than zero. Scores are capped at
+//! [`SCORE_CAP`] first, so a slower mate or a smaller win in a won position costs nothing.
+//! Playing the engine's best move costs nothing either, whatever the scores say.
+//!
+//! On top of the losses, the review rates the play of each side with an accuracy between 0
+//! and 100 percent, the way lichess does. Every move scores by how much it lowered the
+//! mover's [`win_percent`], see [`move_accuracy`]. A side's accuracy is the average of the
+//! harmonic mean of its moves' accuracies, which punishes the worst moves most, and of their
+//! mean weighted by how much the win percentages swung around each move, which makes the
+//! moves of a sharp game count more than those of a quiet one. The accuracy can also be taken
+//! over the moves of a single [`GamePhase`].
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{
+//!     pgn,
+//!     review::{review, Classification, ReviewOptions},
+//!     search::{SearchLimits, Searcher},
+//!     Color,
+//! };
+//! let game = pgn::parse("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0").remove(0).unwrap();
+//! let options = ReviewOptions { limits: SearchLimits::depth(4), ..ReviewOptions::default() };
+//! let report = review(&mut Searcher::default(), &game, &options);
+//! assert_eq!(report.moves[5].san, "Nf6");
+//! assert_eq!(report.moves[5].classification, Classification::BLUNDER);
+//! assert_eq!(report.moves[6].classification, Classification::BEST);
+//! assert!(report.accuracy(Color::WHITE).unwrap() > report.accuracy(Color::BLACK).unwrap());
+//! ```
+
+pub use crate::phase::{GamePhase, ENDGAME_PIECES, OPENING_MOVES};
+use crate::{
+    game::{Game, Outcome},
+    pgn::PgnGame,
+    search::{Engine, SearchLimits, MATE},
+    Color, Move,
+};
+
+/// The most centipawns a score counts for when computing the loss of a move, also for mates.
+pub const SCORE_CAP: i32 = 1000;
+
+/// How good a move was, judged by its loss.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Classification {
+    /// The engine's best move or one as good.
+    BEST,
+    /// A move that lost less than an inaccuracy.
+    GOOD,
+    INACCURACY,
+    MISTAKE,
+    BLUNDER,
+}
+
+/// How [`review`] searches and where it draws the lines between the [`Classification`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewOptions {
+    /// The limits of the search of every position.
+    pub limits: SearchLimits,
+    /// The least loss in centipawns of an inaccuracy.
+    pub inaccuracy: i32,
+    /// The least loss in centipawns of a mistake.
+    pub mistake: i32,
+    /// The least loss in centipawns of a blunder.
+    pub blunder: i32,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> Self {
+        Self {
+            limits: SearchLimits::nodes(200_000),
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+impl ReviewOptions {
+    /// Classifies a move that lost `loss` centipawns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::review::{Classification, ReviewOptions};
+    /// let options = ReviewOptions::default();
+    /// assert_eq!(options.classify(0), Classification::BEST);
+    /// assert_eq!(options.classify(30), Classification::GOOD);
+    /// assert_eq!(options.classify(120), Classification::MISTAKE);
+    /// ```
+    pub fn classify(&self, loss: i32) -> Classification {
+        if loss >= self.blunder {
+            Classification::BLUNDER
+        } else if loss >= self.mistake {
+            Classification::MISTAKE
+        } else if loss >= self.inaccuracy {
+            Classification::INACCURACY
+        } else if loss > 0 {
+            Classification::GOOD
+        } else {
+            Classification::BEST
+        }
+    }
+}
+
+/// The review of a single move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveReview {
+    /// The move played.
+    pub played: Move,
+    /// The move played in SAN.
+    pub san: String,
+    /// The side that played it.
+    pub color: Color,
+    /// The phase of the game before the move.
+    pub phase: GamePhase,
+    /// The engine's best move in the position before.
+    pub best_move: Option<Move>,
+    /// The score of the position after the move from White's point of view, see
+    /// [`mate_in`](crate::search::mate_in).
+    pub score: i32,
+    /// The centipawns the move lost.
+    pub loss: i32,
+    pub classification: Classification,
+    /// The accuracy of the move in percent, see [`move_accuracy`].
+    pub accuracy: f64,
+}
+
+/// The review of a whole game, returned by [`review`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Review {
+    /// The score of the starting position from White's point of view.
+    pub start_score: i32,
+    /// The review of every move, in the order they were played.
+    pub moves: Vec<MoveReview>,
+}
+
+impl Review {
+    /// Returns the average centipawn loss of the moves of `color`, or 0 if it didn't move.
+    pub fn average_loss(&self, color: Color) -> f64 {
+        let losses: Vec<i32> = self.moves_of(color).map(|m| m.loss).collect();
+        if losses.is_empty() {
+            return 0.0;
+        }
+        losses.iter().sum::<i32>() as f64 / losses.len() as f64
+    }
+
+    /// Returns how many moves of `color` got `classification`.
+    pub fn count(&self, color: Color, classification: Classification) -> usize {
+        self.moves_of(color)
+            .filter(|m| m.classification == classification)
+            .count()
+    }
+
+    /// Returns the accuracy in percent of the moves of `color`, or `None` if it didn't move.
+    pub fn accuracy(&self, color: Color) -> Option<f64> {
+        self.accuracy_of(|m| m.color == color)
+    }
+
+    /// Returns the accuracy in percent of the moves of `color` in `phase`, or `None` if it
+    /// didn't move in that phase.
+    pub fn phase_accuracy(&self, color: Color, phase: GamePhase) -> Option<f64> {
+        self.accuracy_of(|m| m.color == color && m.phase == phase)
+    }
+
+    fn moves_of(&self, color: Color) -> impl Iterator<Item = &MoveReview> {
+        self.moves.iter().filter(move |m| m.color == color)
+    }
+
+    /// Returns the average of the weighted and the harmonic mean of the accuracies of the
+    /// moves that match `filter`.
+    fn accuracy_of(&self, filter: impl Fn(&MoveReview) -> bool) -> Option<f64> {
+        let weights = self.volatilities();
+        let (accuracies, weights): (Vec<f64>, Vec<f64>) = self
+            .moves
+            .iter()
+            .zip(weights)
+            .filter(|(m, _)| filter(m))
+            .map(|(m, weight)| (m.accuracy, weight))
+            .unzip();
+        if accuracies.is_empty() {
+            return None;
+        }
+        let weighted = accuracies
+            .iter()
+            .zip(&weights)
+            .map(|(a, w)| a * w)
+            .sum::<f64>()
+            / weights.iter().sum::<f64>();
+        // A move with an accuracy of 0 makes the harmonic mean 0, as it should
+        let harmonic = accuracies.len() as f64 / accuracies.iter().map(|a| 1.0 / a).sum::<f64>();
+        Some((weighted + harmonic) / 2.0)
+    }
+
+    /// Returns the weight of every move, the standard deviation of White's win percentages in
+    /// a window of positions around it. The window grows with the length of the game from 2
+    /// to 8 positions.
+    fn volatilities(&self) -> Vec<f64> {
+        let wins: Vec<f64> = std::iter::once(self.start_score)
+            .chain(self.moves.iter().map(|m| m.score))
+            .map(win_percent)
+            .collect();
+        let size = (self.moves.len() / 10).clamp(2, 8);
+        (0..self.moves.len())
+            .map(|i| {
+                let start = i.saturating_sub(size - 2);
+                let window = &wins[start..(start + size).min(wins.len())];
+                let mean = window.iter().sum::<f64>() / window.len() as f64;
+                let variance =
+                    window.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / window.len() as f64;
+                variance.sqrt().clamp(0.5, 12.0)
+            })
+            .collect()
+    }
+}
+
+/// Returns the chances to win in percent for a score in centipawns, from the same point of
+/// view. Scores are capped at [`SCORE_CAP`], so a mate counts as about 97 percent.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{review::win_percent, search::MATE};
+/// assert_eq!(win_percent(0), 50.0);
+/// assert!(win_percent(100) > 58.0 && win_percent(100) < 60.0);
+/// assert!(win_percent(MATE) > 97.0);
+/// assert!((win_percent(-MATE) + win_percent(MATE) - 100.0).abs() < 1e-9);
+/// ```
+pub fn win_percent(score: i32) -> f64 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * cap(score) as f64).exp()) - 1.0)
+}
+
+/// Returns the accuracy in percent of a move that changed the mover's win percentage from
+/// `before` to `after`. It drops off exponentially with the win percentage lost, from 100
+/// for nothing lost to about 50 for 15 percent lost, and includes a point for the
+/// imprecision of the engine's scores.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::review::move_accuracy;
+/// assert_eq!(move_accuracy(60.0, 65.0), 100.0);
+/// assert!(move_accuracy(60.0, 50.0) > move_accuracy(60.0, 40.0));
+/// assert_eq!(move_accuracy(100.0, 0.0), 0.0);
+/// ```
+pub fn move_accuracy(before: f64, after: f64) -> f64 {
+    if after >= before {
+        return 100.0;
+    }
+    let accuracy = 103.1668 * (-0.04354 * (before - after)).exp() - 3.1669 + 1.0;
+    accuracy.clamp(0.0, 100.0)
+}
+
+/// Reviews every move of `game` with `engine`, searching each position within the limits of
+/// `options`. The moves must be legal. The engine starts a new game first and then keeps what
+/// it learns from one position to the next.
+pub fn review(engine: &mut dyn Engine, game: &PgnGame, options: &ReviewOptions) -> Review {
+    engine.new_game();
+    let mut position = game.start;
+    let (mut score, mut best_move) = evaluate(engine, &position, &options.limits);
+    let start_score = if position.to_move == Color::WHITE {
+        score
+    } else {
+        -score
+    };
+    let mut moves = Vec::with_capacity(game.moves.len());
+    for &played in &game.moves {
+        let color = position.to_move;
+        let phase = position.phase().kind;
+        let san = position.san(played).unwrap_or_else(|_| played.to_string());
+        position.make_move(played);
+        let (after, best_after) = evaluate(engine, &position, &options.limits);
+        let (loss, accuracy) = if Some(played) == best_move {
+            (0, 100.0)
+        } else {
+            (
+                (cap(score) + cap(after)).max(0),
+                move_accuracy(win_percent(score), win_percent(-after)),
+            )
+        };
+        moves.push(MoveReview {
+            played,
+            san,
+            color,
+            phase,
+            best_move,
+            score: if color == Color::WHITE { -after } else { after },
+            loss,
+            classification: options.classify(loss),
+            accuracy,
+        });
+        (score, best_move) = (after, best_after);
+    }
+    Review { start_score, moves }
+}
+
+/// Returns the score of `game` from the point of view of the side to move and the best move,
+/// scoring a game that has ended by its outcome.
+fn evaluate(engine: &mut dyn Engine, game: &Game, limits: &SearchLimits) -> (i32, Option<Move>) {
+    match game.outcome() {
+        Some(Outcome::DRAW) => (0, None),
+        Some(Outcome::DECISIVE { winner }) if winner == game.to_move => (MATE, None),
+        Some(Outcome::DECISIVE { .. }) => (-MATE, None),
+        None => {
+            let result = engine.search(game, limits.clone());
+            (result.score, result.best_move)
+        }
+    }
+}
+
+fn cap(score: i32) -> i32 {
+    score.clamp(-SCORE_CAP, SCORE_CAP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pgn, search::Searcher};
+
+    fn options() -> ReviewOptions {
+        ReviewOptions {
+            limits: SearchLimits::depth(4),
+            ..ReviewOptions::default()
+        }
+    }
+
+    #[test]
+    fn finds_the_blunder() {
+        let game = pgn::parse("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0")
+            .remove(0)
+            .unwrap();
+        let report = review(&mut Searcher::default(), &game, &options());
+        assert_eq!(report.moves.len(), 7);
+        let blunder = &report.moves[5];
+        assert_eq!(blunder.color, Color::BLACK);
+        assert_eq!(blunder.classification, Classification::BLUNDER);
+        // From about even to getting mated
+        assert_eq!(blunder.loss, SCORE_CAP - report.moves[4].score);
+        assert_ne!(blunder.best_move, Some(blunder.played));
+        assert_eq!(blunder.score, MATE - 1);
+        assert_eq!(report.moves[6].score, MATE);
+        assert_eq!(report.count(Color::BLACK, Classification::BLUNDER), 1);
+        assert_eq!(report.count(Color::WHITE, Classification::BLUNDER), 0);
+        assert!(report.average_loss(Color::BLACK) > report.average_loss(Color::WHITE));
+
+        assert!(blunder.accuracy < 15.0);
+        assert_eq!(report.moves[6].accuracy, 100.0);
+        let white = report.accuracy(Color::WHITE).unwrap();
+        assert!(white > report.accuracy(Color::BLACK).unwrap());
+        assert_eq!(
+            report.phase_accuracy(Color::WHITE, GamePhase::OPENING),
+            Some(white)
+        );
+        assert_eq!(
+            report.phase_accuracy(Color::WHITE, GamePhase::ENDGAME),
+            None
+        );
+    }
+
+    #[test]
+    fn accuracy_punishes_the_worst_moves() {
+        let played = Move::from_uci("e2e4").unwrap();
+        let reviewed = |color, accuracy| MoveReview {
+            played,
+            san: "e4".to_string(),
+            color,
+            phase: GamePhase::MIDDLEGAME,
+            best_move: Some(played),
+            score: 0,
+            loss: 0,
+            classification: Classification::BEST,
+            accuracy,
+        };
+        let report = Review {
+            start_score: 0,
+            moves: vec![
+                reviewed(Color::WHITE, 100.0),
+                reviewed(Color::BLACK, 100.0),
+                reviewed(Color::WHITE, 50.0),
+                reviewed(Color::BLACK, 0.0),
+            ],
+        };
+        // Without any swings all moves weigh the same, so the mean of 75 and the harmonic
+        // mean of 66.7 average out
+        let white = report.accuracy(Color::WHITE).unwrap();
+        assert!((white - (75.0 + 200.0 / 3.0) / 2.0).abs() < 1e-9);
+        // A single move without any accuracy zeroes the harmonic mean
+        assert_eq!(report.accuracy(Color::BLACK), Some(25.0));
+        assert_eq!(
+            report.phase_accuracy(Color::WHITE, GamePhase::MIDDLEGAME),
+            Some(white)
+        );
+        assert_eq!(
+            report.phase_accuracy(Color::WHITE, GamePhase::OPENING),
+            None
+        );
+    }
+
+    #[test]
+    fn a_slower_win_costs_nothing() {
+        // Queening instead of mating at once
+        let game = Game::from_fen("6k1/1P3ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let game = PgnGame {
+            tags: Vec::new(),
+            start: game,
+            moves: vec![Move::from_uci("b7b8q").unwrap()],
+            outcome: None,
+        };
+        let report = review(&mut Searcher::default(), &game, &options());
+        assert_eq!(
+            report.moves[0].best_move,
+            Some(Move::from_uci("a1a8").unwrap())
+        );
+        assert_eq!(report.moves[0].loss, 0);
+        assert_eq!(report.moves[0].classification, Classification::BEST);
+    }
+
+    #[test]
+    fn thresholds_are_inclusive() {
+        let options = ReviewOptions::default();
+        assert_eq!(options.classify(49), Classification::GOOD);
+        assert_eq!(options.classify(50), Classification::INACCURACY);
+        assert_eq!(options.classify(100), Classification::MISTAKE);
+        assert_eq!(options.classify(300), Classification::BLUNDER);
+        assert_eq!(
+            Review {
+                start_score: 0,
+                moves: Vec::new(),
+            }
+            .average_loss(Color::WHITE),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,1089 @@
+//! Negamax alpha-beta search with iterative deepening and a quiescence search over captures.
+//!
+//! Results are kept in a [`TranspositionTable`](tt::TranspositionTable). Nodes in check are
+//! searched one ply deeper, as is a move from the table that is singular, i.e. clearly better
+//! than all alternatives.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, search::{self, SearchLimits}, Move};
+//! // White mates with Ra8
+//! let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+//! let result = search::search(&game, SearchLimits::depth(3));
+//! assert_eq!(result.best_move, Some(Move::from_uci("a1a8").unwrap()));
+//! assert_eq!(search::mate_in(result.score), Some(1));
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+
+// Browsers have no clock std can read, so the search would panic when starting it
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+use crate::{
+    eval::Evaluator,
+    game::{Game, Termination},
+    movegen::all_legal_moves,
+    wdl::{wdl, Wdl},
+    Move, MoveList,
+};
+
+use ordering::{order_captures, Heuristics, MovePicker, PlayedMove, Stage};
+use skill::Skill;
+use time::{TimeControl, TimeManager};
+use tt::{score_from_tt, score_to_tt, Bound, TranspositionTable};
+
+pub mod bench;
+pub mod datagen;
+pub mod mcts;
+pub mod ordering;
+pub mod skill;
+pub mod suite;
+pub mod thread;
+pub mod time;
+pub mod tt;
+
+/// The score of delivering checkmate right now. Mate in `n` plies scores `MATE - n`.
+pub const MATE: i32 = 32_000;
+/// The deepest ply the search ever reaches, including the quiescence search.
+pub const MAX_PLY: usize = 128;
+const INFINITY: i32 = MATE + 1;
+/// How many nodes to search between looking at the clock and the stop flag.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+/// The shallowest depth at which moves from the transposition table are tested for being
+/// singular.
+const SINGULAR_DEPTH: u32 = 6;
+/// How far below the stored score, per ply of depth, all other moves must stay for the stored
+/// move to count as singular.
+const SINGULAR_MARGIN: i32 = 2;
+/// The shallowest depth at which nodes without a hash move are reduced.
+const IIR_DEPTH: u32 = 4;
+/// The deepest depth at which moves that lose material are pruned.
+const SEE_PRUNING_DEPTH: u32 = 3;
+/// How much material, per ply of depth, a capture may lose before it is pruned.
+const CAPTURE_SEE_MARGIN: i32 = 100;
+/// How much material, per ply of depth, a quiet move may lose before it is pruned.
+const QUIET_SEE_MARGIN: i32 = 60;
+
+/// When to stop searching. The search stops as soon as any of the set limits is reached and
+/// goes on until [`MAX_PLY`] if none is set.
+///
+/// Apart from the limits, the search can be restricted to some of the moves at the root, like
+/// UCI's `go searchmoves`.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, search::{search, SearchLimits}, Move};
+/// let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+/// let a1a2 = Move::from_uci("a1a2").unwrap();
+/// let limits = SearchLimits {
+///     depth: Some(3),
+///     search_moves: vec![a1a2],
+///     ..SearchLimits::default()
+/// };
+/// assert_eq!(search(&game, limits).best_move, Some(a1a2));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// The deepest iteration to complete.
+    pub depth: Option<u32>,
+    /// How many nodes to search at most.
+    pub nodes: Option<u64>,
+    /// How long to search at most.
+    pub time: Option<Duration>,
+    /// The clock to manage the time of the side to move with, see [`time`].
+    pub clock: Option<TimeControl>,
+    /// The moves to search at the root, all legal moves if empty. Moves that aren't legal are
+    /// ignored, and so is the whole list if none of them is.
+    pub search_moves: Vec<Move>,
+}
+
+impl SearchLimits {
+    /// Limits the search to `depth` plies.
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// Limits the search to `nodes` nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+
+    /// Limits the search to `time`.
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::default()
+        }
+    }
+
+    /// Limits the search to the time allotted to the next move on `clock`.
+    pub fn clock(clock: TimeControl) -> Self {
+        Self {
+            clock: Some(clock),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the legal moves of `game` that may be searched at the root.
+    fn root_moves(&self, game: &Game) -> MoveList {
+        let mut moves = all_legal_moves(game);
+        if moves.iter().any(|m| self.search_moves.contains(m)) {
+            moves.retain(|m| self.search_moves.contains(m));
+        }
+        moves
+    }
+}
+
+/// The progress of a search, reported after every completed iteration to the callback set
+/// with [`Searcher::on_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    /// The depth of the iteration.
+    pub depth: u32,
+    /// The most plies any line has been searched to so far, including quiescence.
+    pub seldepth: u32,
+    /// The score in centipawns from the point of view of the side to move, see [`mate_in`].
+    pub score: i32,
+    /// The number of nodes searched over all iterations so far.
+    pub nodes: u64,
+    /// Nodes searched per second.
+    pub nps: u64,
+    /// The time since the search started.
+    pub time: Duration,
+    /// The principal variation of this iteration, starting with its best move. Every move is
+    /// checked to be legal, and the line ends before the first one that isn't.
+    pub pv: Vec<Move>,
+    /// How full the transposition table is, in permille.
+    pub hashfull: u32,
+    /// The chances of the side to move that go with the score, see [`wdl`](crate::wdl).
+    pub wdl: Wdl,
+}
+
+/// Receives the [`SearchInfo`] of every completed iteration.
+pub type InfoCallback = Box<dyn FnMut(&SearchInfo) + Send>;
+
+/// The outcome of a search, taken from the deepest completed iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, or `None` if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The score in centipawns from the point of view of the side to move, see [`mate_in`]
+    /// for mate scores.
+    pub score: i32,
+    /// The depth of the deepest completed iteration.
+    pub depth: u32,
+    /// The number of nodes searched over all iterations.
+    pub nodes: u64,
+    /// The principal variation, starting with `best_move`.
+    pub pv: Vec<Move>,
+}
+
+/// Returns the number of moves until mate if `score` is a mate score, positive if the side to
+/// move delivers it and negative if it gets mated.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::search::{mate_in, MATE};
+/// assert_eq!(mate_in(MATE - 3), Some(2));
+/// assert_eq!(mate_in(-MATE + 2), Some(-1));
+/// assert_eq!(mate_in(150), None);
+/// ```
+pub fn mate_in(score: i32) -> Option<i32> {
+    let plies = MATE - score.abs();
+    if plies > MAX_PLY as i32 {
+        return None;
+    }
+    if score > 0 {
+        Some((plies + 1) / 2)
+    } else {
+        Some(-plies / 2)
+    }
+}
+
+/// A search that picks a move for a position within [`SearchLimits`], implemented by the
+/// alpha-beta [`Searcher`], by [`Mcts`](mcts::Mcts) and by external UCI engines, see the
+/// [`client`](crate::uci::client).
+pub trait Engine {
+    fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult;
+
+    /// Forgets what previous searches learned, before a new game.
+    fn new_game(&mut self) {}
+
+    /// Tells the engine the keys of the positions the game went through before the next
+    /// searched one, oldest first, see [`Searcher::history`]. Engines that can't use them
+    /// ignore them.
+    fn set_history(&mut self, _history: &[u64]) {}
+}
+
+/// Searches `game` within `limits` using the default [`Evaluator`].
+pub fn search(game: &Game, limits: SearchLimits) -> SearchResult {
+    Searcher::default().search(game, limits)
+}
+
+/// A reusable search with its own [`Evaluator`].
+pub struct Searcher {
+    pub evaluator: Evaluator,
+    /// Weakens the play if set, see [`skill`].
+    pub skill: Option<Skill>,
+    /// How much a draw is worse than an even position for the side to move at the root, in
+    /// centipawns. Positive values avoid draws, negative ones seek them.
+    pub contempt: i32,
+    /// The keys of the positions the game went through before the searched one, oldest first,
+    /// so that the search scores returning to them as a draw. Positions from before the last
+    /// capture or pawn move can't come back and may be left out.
+    pub history: Vec<u64>,
+    limits: SearchLimits,
+    start: Instant,
+    /// When to abort the search, the earlier of the time limit and the clock's hard limit.
+    deadline: Option<Duration>,
+    time_manager: Option<TimeManager>,
+    nodes: u64,
+    /// The highest ply reached in this search.
+    seldepth: usize,
+    stopped: bool,
+    /// Aborts the search when set from another thread, see [`Searcher::stop_flag`].
+    stop_flag: Arc<AtomicBool>,
+    /// The moves searched at the root, see [`SearchLimits::search_moves`].
+    root_moves: MoveList,
+    /// The root moves searched in the current iteration with their scores, only kept with a
+    /// [`Skill`] to pick from.
+    root_scores: Vec<(Move, i32)>,
+    on_info: Option<InfoCallback>,
+    /// Holds off the time limits while set, see [`Searcher::ponder_flag`].
+    ponder_flag: Arc<AtomicBool>,
+    /// Whether the search started pondering and the ponder flag hasn't been cleared since.
+    pondering: bool,
+    /// The principal variation of the previous iteration, tried first in the next one.
+    previous_pv: Vec<Move>,
+    pv: Box<PvTable>,
+    /// Kept between searches except for the killers, so the rest carries over from one move
+    /// of a game to the next.
+    heuristics: Heuristics,
+    /// Kept between searches like the heuristics.
+    pub tt: TranspositionTable,
+    /// The move made at every ply of the current line.
+    played: [Option<PlayedMove>; MAX_PLY],
+    /// The key of the position at every ply of the current line.
+    keys: [u64; MAX_PLY],
+    /// The move to skip at every ply, set while testing whether it is singular.
+    excluded: [Option<Move>; MAX_PLY],
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new(Evaluator::default())
+    }
+}
+
+impl Searcher {
+    pub fn new(evaluator: Evaluator) -> Self {
+        Self {
+            evaluator,
+            skill: None,
+            contempt: 0,
+            history: Vec::new(),
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            deadline: None,
+            time_manager: None,
+            nodes: 0,
+            seldepth: 0,
+            stopped: false,
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            ponder_flag: Arc::new(AtomicBool::new(false)),
+            pondering: false,
+            root_moves: MoveList::new(),
+            root_scores: Vec::new(),
+            on_info: None,
+            previous_pv: Vec::new(),
+            pv: Box::new(PvTable::new()),
+            heuristics: Heuristics::default(),
+            tt: TranspositionTable::default(),
+            excluded: [None; MAX_PLY],
+            played: [None; MAX_PLY],
+            keys: [0; MAX_PLY],
+        }
+    }
+
+    /// Returns a flag that aborts the running search when set, like reaching a limit does.
+    /// The search never clears it, so it must be cleared before the next search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::atomic::Ordering;
+    /// use kritisch::{game::Game, search::{Searcher, SearchLimits}};
+    /// let mut searcher = Searcher::default();
+    /// let stop = searcher.stop_flag();
+    /// let handle = std::thread::spawn(move || {
+    ///     searcher.search(&Game::default(), SearchLimits::default())
+    /// });
+    /// stop.store(true, Ordering::Relaxed);
+    /// let result = handle.join().unwrap();
+    /// assert!(result.best_move.is_some());
+    /// ```
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_flag)
+    }
+
+    /// Returns a flag that makes the search ponder, thinking on the opponent's time in the
+    /// expectation of a move, while it is set. Until it is cleared, the time limits don't
+    /// apply and the clock doesn't run. Clearing it is the ponder hit: the expected move was
+    /// played and the search continues, from then on under its time limits. Like the stop
+    /// flag, it must be set before the search starts and is never changed by the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::{sync::atomic::Ordering, time::Duration};
+    /// use kritisch::{game::Game, search::{Searcher, SearchLimits}};
+    /// let mut searcher = Searcher::default();
+    /// let ponder = searcher.ponder_flag();
+    /// ponder.store(true, Ordering::Relaxed);
+    /// let handle = std::thread::spawn(move || {
+    ///     let limits = SearchLimits::time(Duration::from_millis(10));
+    ///     searcher.search(&Game::default(), limits)
+    /// });
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert!(!handle.is_finished());
+    /// ponder.store(false, Ordering::Relaxed);
+    /// assert!(handle.join().unwrap().best_move.is_some());
+    /// ```
+    pub fn ponder_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.ponder_flag)
+    }
+
+    /// Calls `callback` with the [`SearchInfo`] of every iteration completed from now on,
+    /// replacing the previous callback. The callback runs on the searching thread, so it
+    /// should return quickly, e.g. by sending the info through a channel.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use kritisch::{game::Game, search::{Searcher, SearchLimits}};
+    /// let (sender, receiver) = mpsc::channel();
+    /// let mut searcher = Searcher::default();
+    /// searcher.on_info(move |info| sender.send(info.clone()).unwrap());
+    /// let result = searcher.search(&Game::default(), SearchLimits::depth(4));
+    /// let depths: Vec<u32> = receiver.try_iter().map(|info| info.depth).collect();
+    /// assert_eq!(depths, [1, 2, 3, 4]);
+    /// ```
+    pub fn on_info(&mut self, callback: impl FnMut(&SearchInfo) + Send + 'static) {
+        self.on_info = Some(Box::new(callback));
+    }
+
+    /// Forgets everything learned in previous searches, e.g. before a new game.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.heuristics = Heuristics::default();
+    }
+
+    /// Searches `game` with iterative deepening until one of `limits` is reached.
+    pub fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        self.limits = limits;
+        self.start = Instant::now();
+        self.pondering = self.ponder_flag.load(Ordering::Relaxed);
+        self.time_manager = self
+            .limits
+            .clock
+            .map(|clock| TimeManager::new(&clock, game.to_move));
+        self.deadline = [self.limits.time, self.time_manager.map(|t| t.hard_limit())]
+            .into_iter()
+            .flatten()
+            .min();
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.stopped = false;
+        self.previous_pv.clear();
+        self.tt.new_search();
+        self.heuristics.killers.clear();
+        self.heuristics.history.age();
+        self.heuristics.continuation.age();
+
+        self.root_moves = self.limits.root_moves(game);
+        let mut root_moves = self.root_moves.clone();
+        // Something sensible to play if not even the first iteration completes
+        order_captures(game, &mut root_moves);
+        let mut result = SearchResult {
+            best_move: root_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+        };
+        if root_moves.is_empty() {
+            result.score = self.terminal_score(game.rules().no_moves(game), 0);
+            return result;
+        }
+
+        let max_depth = [self.limits.depth, self.skill.map(|s| s.max_depth())]
+            .into_iter()
+            .flatten()
+            .fold(MAX_PLY as u32 - 1, u32::min);
+        let mut root_scores = Vec::new();
+        for depth in 1..=max_depth {
+            self.root_scores.clear();
+            let score = self.negamax(game, depth, 0, -INFINITY, INFINITY, true);
+            if self.stopped {
+                break;
+            }
+            std::mem::swap(&mut root_scores, &mut self.root_scores);
+            let pv = self.principal_variation(game, depth);
+            let best_move_changed = depth > 1 && pv.first() != result.best_move.as_ref();
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                depth,
+                nodes: self.nodes,
+                pv: pv.clone(),
+            };
+            if let Some(callback) = &mut self.on_info {
+                let time = self.start.elapsed();
+                callback(&SearchInfo {
+                    depth,
+                    seldepth: self.seldepth as u32,
+                    score,
+                    nodes: self.nodes,
+                    nps: (self.nodes as u128 * 1000 / time.as_millis().max(1)) as u64,
+                    time,
+                    pv: pv.clone(),
+                    hashfull: self.tt.hashfull(),
+                    wdl: wdl(game, score),
+                });
+            }
+            self.previous_pv = pv;
+            // No need to look deeper once a forced mate has been found
+            if mate_in(score).is_some_and(|n| n > 0) {
+                break;
+            }
+            // The next iteration would likely not finish in time anyway
+            if let Some(manager) = &mut self.time_manager {
+                manager.update(best_move_changed);
+                let soft_limit = manager.soft_limit();
+                if self.clock_time().is_some_and(|time| time >= soft_limit) {
+                    break;
+                }
+            }
+        }
+        result.nodes = self.nodes;
+        let picked = self
+            .skill
+            .and_then(|skill| skill.pick(game.key(), &root_scores));
+        if let Some((m, score)) = picked.filter(|&(m, _)| result.best_move != Some(m)) {
+            // The principal variation belongs to the best move
+            result.best_move = Some(m);
+            result.score = score;
+            result.pv = vec![m];
+        }
+        result
+    }
+
+    fn negamax(
+        &mut self,
+        game: &Game,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        on_pv: bool,
+    ) -> i32 {
+        self.pv.clear(ply);
+        let in_check = game.is_check();
+        // Check extension: don't stop searching while the king is attacked
+        let mut depth = if in_check { depth + 1 } else { depth };
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        let key = game.key();
+        self.keys[ply] = key;
+        let rules = game.rules();
+        if ply > 0 {
+            if let Some(termination) = rules.variant_termination(game) {
+                return self.terminal_score(termination, ply);
+            }
+            if game.halfmove_clock >= 100
+                || rules.is_insufficient_material(game)
+                || self.is_repetition(key, ply, game.halfmove_clock)
+            {
+                return self.draw_score(ply);
+            }
+        }
+
+        let excluded = self.excluded[ply];
+        let entry = if excluded.is_none() {
+            self.tt.probe(key)
+        } else {
+            None
+        };
+        if let Some(entry) = entry.filter(|e| !on_pv && e.depth >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::EXACT => true,
+                Bound::LOWER => score >= beta,
+                Bound::UPPER => score <= alpha,
+            };
+            if cutoff {
+                return score;
+            }
+        }
+
+        let moves = if ply == 0 {
+            self.root_moves.clone()
+        } else {
+            all_legal_moves(game)
+        };
+        if moves.is_empty() {
+            return self.terminal_score(rules.no_moves(game), ply);
+        }
+        let tt_move = entry.and_then(|e| e.best_move);
+        let pv_move = if on_pv {
+            self.previous_pv.get(ply).copied()
+        } else {
+            None
+        };
+        let hash_move = pv_move.or(tt_move);
+        // Internal iterative reduction: without a move to try first this node is likely to be
+        // ordered badly, so search it shallower and leave the table a move for next time
+        if hash_move.is_none() && depth >= IIR_DEPTH && excluded.is_none() {
+            depth -= 1;
+        }
+
+        let singular_move = match entry {
+            Some(entry) if ply > 0 && depth >= SINGULAR_DEPTH => {
+                self.singular_move(game, entry, depth, ply, &moves)
+            }
+            _ => None,
+        };
+        if self.stopped {
+            return 0;
+        }
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut quiets_tried = MoveList::new();
+        let previous = self.previous_moves(ply);
+        let mut picker = MovePicker::new(game, moves, hash_move, ply, previous);
+        while let Some(m) = picker.next(&self.heuristics) {
+            if Some(m) == excluded {
+                continue;
+            }
+            let is_quiet = !game.is_capture(m);
+            // Near the leaves, skip moves that lose material once a move has been found that
+            // doesn't get mated
+            if ply > 0
+                && !in_check
+                && depth <= SEE_PRUNING_DEPTH
+                && best > -MATE + MAX_PLY as i32
+                && matches!(picker.stage(), Stage::QUIETS | Stage::BAD_CAPTURES)
+            {
+                let margin = if is_quiet {
+                    QUIET_SEE_MARGIN
+                } else {
+                    CAPTURE_SEE_MARGIN
+                };
+                if game.see(m) < -margin * depth as i32 {
+                    continue;
+                }
+            }
+            let extension = u32::from(Some(m) == singular_move);
+            self.played[ply] = Some(PlayedMove::new(game, m));
+            self.tt.prefetch(game.key_after(m));
+            let mut child = *game;
+            child.make_move(m);
+            let score = -self.negamax(
+                &child,
+                depth - 1 + extension,
+                ply + 1,
+                -beta,
+                -alpha,
+                on_pv && pv_move == Some(m),
+            );
+            if self.stopped {
+                return 0;
+            }
+            if ply == 0 && self.skill.is_some() {
+                self.root_scores.push((m, score));
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(m);
+                    self.pv.update(ply, m);
+                    if alpha >= beta {
+                        if is_quiet {
+                            self.record_cutoff(game, m, &quiets_tried, depth, ply);
+                        }
+                        break;
+                    }
+                }
+            }
+            if is_quiet {
+                quiets_tried.push(m);
+            }
+        }
+
+        if excluded.is_none() {
+            let bound = if best >= beta {
+                Bound::LOWER
+            } else if best > original_alpha {
+                Bound::EXACT
+            } else {
+                Bound::UPPER
+            };
+            self.tt.store(tt::Entry {
+                key,
+                best_move,
+                score: score_to_tt(best, ply),
+                depth,
+                bound,
+            });
+        }
+        best
+    }
+
+    /// Returns `true` if the position at `ply` with `key` occurred before in the current line
+    /// or the [history](Searcher::history), within the last `halfmove_clock` plies. Going back
+    /// to a position is scored as a draw already, since the side that could avoid a third
+    /// time would have done so the second time.
+    fn is_repetition(&self, key: u64, ply: usize, halfmove_clock: usize) -> bool {
+        self.keys[..ply]
+            .iter()
+            .rev()
+            .chain(self.history.iter().rev())
+            .take(halfmove_clock)
+            // Only every other position has the same side to move
+            .skip(1)
+            .step_by(2)
+            .any(|&k| k == key)
+    }
+
+    /// Returns the score of a draw at `ply` for the side to move there, see
+    /// [`Searcher::contempt`].
+    fn draw_score(&self, ply: usize) -> i32 {
+        if ply.is_multiple_of(2) {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// Returns the score of a game that ended with `termination` at `ply`, from the point of
+    /// view of the side to move.
+    fn terminal_score(&self, termination: Termination, ply: usize) -> i32 {
+        match termination {
+            Termination::CHECKMATE | Termination::VARIANT_LOSS => -MATE + ply as i32,
+            Termination::VARIANT_WIN => MATE - ply as i32,
+            _ => self.draw_score(ply),
+        }
+    }
+
+    /// Returns the moves played one and two plies before `ply`.
+    fn previous_moves(&self, ply: usize) -> [Option<PlayedMove>; 2] {
+        let back = |n: usize| ply.checked_sub(n).and_then(|p| self.played[p]);
+        [back(1), back(2)]
+    }
+
+    /// Teaches the move ordering that the quiet move `m` caused a beta cutoff after the
+    /// quiet moves in `tried` did not.
+    fn record_cutoff(&mut self, game: &Game, m: Move, tried: &[Move], depth: u32, ply: usize) {
+        let previous = self.previous_moves(ply);
+        let heuristics = &mut self.heuristics;
+        heuristics.killers.add(ply, m);
+        heuristics.history.update(game.to_move, m, tried, depth);
+        if let Some(p) = previous[0] {
+            heuristics.counter_moves.set(p, m);
+        }
+        let tried = tried.iter().map(|&t| PlayedMove::new(game, t));
+        heuristics
+            .continuation
+            .update(&previous, PlayedMove::new(game, m), tried, depth);
+    }
+
+    /// Returns the move of `entry` if it is singular: searching all other moves of `game` to
+    /// half the depth, none of them comes close to its stored score.
+    fn singular_move(
+        &mut self,
+        game: &Game,
+        entry: tt::Entry,
+        depth: u32,
+        ply: usize,
+        moves: &MoveList,
+    ) -> Option<Move> {
+        let m = entry.best_move.filter(|m| moves.contains(m))?;
+        if entry.bound == Bound::UPPER || entry.depth + 3 < depth {
+            return None;
+        }
+        let tt_score = score_from_tt(entry.score, ply);
+        if mate_in(tt_score).is_some() {
+            return None;
+        }
+        let singular_beta = tt_score - SINGULAR_MARGIN * depth as i32;
+        self.excluded[ply] = Some(m);
+        let score = self.negamax(
+            game,
+            (depth - 1) / 2,
+            ply,
+            singular_beta - 1,
+            singular_beta,
+            false,
+        );
+        self.excluded[ply] = None;
+        // The search above used this ply's slot of the principal variation
+        self.pv.clear(ply);
+        (score < singular_beta).then_some(m)
+    }
+
+    fn quiescence(&mut self, game: &Game, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if let Some(termination) = game.rules().variant_termination(game) {
+            return self.terminal_score(termination, ply);
+        }
+
+        let stand_pat = self.evaluate(game);
+        if stand_pat >= beta || ply >= MAX_PLY - 1 {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        // Captures that lose material are skipped altogether
+        let mut picker = MovePicker::captures(game, all_legal_moves(game));
+        while let Some(m) = picker.next(&self.heuristics) {
+            let mut child = *game;
+            child.make_move(m);
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Returns the static evaluation of `game`, with the noise of the skill if there is one.
+    fn evaluate(&self, game: &Game) -> i32 {
+        let noise = self.skill.map_or(0, |skill| skill.eval_noise(game.key()));
+        self.evaluator.evaluate(game) + noise
+    }
+
+    /// Returns the principal variation of the last iteration. It is cut at the first move
+    /// that isn't legal, and continued with the best moves in the transposition table if
+    /// cutoffs left it shorter than `depth`.
+    fn principal_variation(&self, game: &Game, depth: u32) -> Vec<Move> {
+        let mut pv = Vec::new();
+        let mut position = *game;
+        for m in self.pv.line(0) {
+            if position.try_make_move(m).is_err() {
+                break;
+            }
+            pv.push(m);
+        }
+        while pv.len() < depth as usize {
+            let Some(m) = self.tt.probe(position.key()).and_then(|e| e.best_move) else {
+                break;
+            };
+            if position.try_make_move(m).is_err() {
+                break;
+            }
+            pv.push(m);
+        }
+        pv
+    }
+
+    /// Returns `true` once a limit is reached, and from then on until the next search.
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.limits.nodes.is_some_and(|n| self.nodes >= n) {
+            self.stopped = true;
+        } else if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            self.stopped = self.stop_flag.load(Ordering::Relaxed)
+                || match (self.deadline, self.clock_time()) {
+                    (Some(deadline), Some(time)) => time >= deadline,
+                    _ => false,
+                };
+        }
+        self.stopped
+    }
+
+    /// Returns how long the search has been running on the clock, or `None` while it is
+    /// pondering. The clock starts over on the ponder hit.
+    fn clock_time(&mut self) -> Option<Duration> {
+        if self.pondering {
+            if self.ponder_flag.load(Ordering::Relaxed) {
+                return None;
+            }
+            self.pondering = false;
+            self.start = Instant::now();
+        }
+        Some(self.start.elapsed())
+    }
+}
+
+impl Engine for Searcher {
+    fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        Searcher::search(self, game, limits)
+    }
+
+    fn new_game(&mut self) {
+        self.clear();
+    }
+
+    fn set_history(&mut self, history: &[u64]) {
+        self.history.clear();
+        self.history.extend_from_slice(history);
+    }
+}
+
+/// The principal variation of every ply, each built from the best move at that ply and the
+/// principal variation of the next one.
+struct PvTable {
+    moves: [[Option<Move>; MAX_PLY]; MAX_PLY],
+    lengths: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    fn new() -> Self {
+        Self {
+            moves: [[None; MAX_PLY]; MAX_PLY],
+            lengths: [0; MAX_PLY],
+        }
+    }
+
+    fn clear(&mut self, ply: usize) {
+        self.lengths[ply] = 0;
+    }
+
+    fn update(&mut self, ply: usize, m: Move) {
+        let child_length = if ply + 1 < MAX_PLY {
+            self.lengths[ply + 1]
+        } else {
+            0
+        };
+        self.moves[ply][0] = Some(m);
+        for i in 0..child_length {
+            self.moves[ply][i + 1] = self.moves[ply + 1][i];
+        }
+        self.lengths[ply] = child_length + 1;
+    }
+
+    fn line(&self, ply: usize) -> Vec<Move> {
+        self.moves[ply][..self.lengths[ply]]
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn wins_a_hanging_queen() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = search(&game, SearchLimits::depth(3));
+        assert_eq!(result.best_move, Some(Move::from_uci("d2d5").unwrap()));
+        assert!(result.score > 300);
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        // Kb6 Kb8 Rh8#
+        let game = Game::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let result = search(&game, SearchLimits::depth(4));
+        assert_eq!(mate_in(result.score), Some(2), "{result:?}");
+    }
+
+    #[test]
+    fn extends_checks() {
+        // Re8+ Rxe8 Rxe8#, three plies but found at depth 2 because the checks are extended
+        let game = Game::from_fen("3r2k1/5ppp/8/8/8/8/4R3/4R1K1 w - - 0 1").unwrap();
+        let result = search(&game, SearchLimits::depth(2));
+        assert_eq!(mate_in(result.score), Some(2), "{result:?}");
+        assert_eq!(result.best_move, Some(Move::from_uci("e2e8").unwrap()));
+    }
+
+    #[test]
+    fn reuses_the_transposition_table() {
+        let game =
+            Game::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let mut searcher = Searcher::default();
+        let first = searcher.search(&game, SearchLimits::depth(5));
+        let second = searcher.search(&game, SearchLimits::depth(5));
+        assert!(
+            second.nodes < first.nodes,
+            "{} {}",
+            first.nodes,
+            second.nodes
+        );
+        assert_eq!(first.best_move, second.best_move);
+    }
+
+    #[test]
+    fn reduces_nodes_without_a_hash_move() {
+        let game =
+            Game::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let nodes = |depth, excluded| {
+            // A fresh table, so that there is no move to try first
+            let mut searcher = Searcher::default();
+            searcher.excluded[1] = excluded;
+            searcher.negamax(&game, depth, 1, -INFINITY, INFINITY, false);
+            searcher.nodes
+        };
+        assert_eq!(nodes(IIR_DEPTH, None), nodes(IIR_DEPTH - 1, None));
+        // The search for a singular move keeps its depth, so it takes more nodes even though
+        // it skips a move
+        let excluded = Some(all_legal_moves(&game)[0]);
+        assert!(nodes(IIR_DEPTH, excluded) > nodes(IIR_DEPTH, None));
+    }
+
+    #[test]
+    fn pv_reaches_the_depth() {
+        let result = search(&Game::default(), SearchLimits::depth(5));
+        assert_eq!(result.pv.len(), 5);
+        let san = Game::default().san_line(&result.pv).unwrap();
+        assert_eq!(san.len(), 5);
+    }
+
+    #[test]
+    fn scores_draws_with_contempt() {
+        // Every move reaches the fifty-move rule
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        let mut searcher = Searcher::default();
+        for contempt in [-30, 0, 30] {
+            searcher.clear();
+            searcher.contempt = contempt;
+            let result = searcher.search(&game, SearchLimits::depth(3));
+            assert_eq!(result.score, -contempt);
+        }
+    }
+
+    #[test]
+    fn contempt_decides_repetitions() {
+        // Black can go back to the starting position
+        let mut game = Game::default();
+        let mut history = Vec::new();
+        for uci in ["g1f3", "g8f6", "f3g1"] {
+            history.push(game.key());
+            game.make_move(Move::from_uci(uci).unwrap());
+        }
+        let repeat = Move::from_uci("f6g8").unwrap();
+        let mut searcher = Searcher {
+            history,
+            ..Searcher::default()
+        };
+        for (contempt, repeats) in [(-100, true), (100, false)] {
+            searcher.clear();
+            searcher.contempt = contempt;
+            let result = searcher.search(&game, SearchLimits::depth(4));
+            assert_eq!(result.best_move == Some(repeat), repeats, "{contempt}");
+        }
+    }
+
+    #[test]
+    fn pv_is_playable() {
+        let game =
+            Game::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+                .unwrap();
+        let result = search(&game, SearchLimits::depth(3));
+        // Scholar's mate
+        assert_eq!(result.best_move, Some(Move::from_uci("h5f7").unwrap()));
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+        let mut position = game;
+        for m in &result.pv {
+            assert!(
+                all_legal_moves(&position).contains(m),
+                "{m} in {:?}",
+                result.pv
+            );
+            position.make_move(*m);
+        }
+    }
+
+    #[test]
+    fn respects_node_limit() {
+        let result = search(&Game::default(), SearchLimits::nodes(500));
+        assert!(result.nodes <= 500);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn ignores_illegal_search_moves() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            search_moves: vec![Move::from_uci("e2e4").unwrap()],
+            ..SearchLimits::default()
+        };
+        let result = search(&game, limits.clone());
+        assert_eq!(result.best_move, Some(Move::from_uci("a1a8").unwrap()));
+
+        let g1f1 = Move::from_uci("g1f1").unwrap();
+        let limits = SearchLimits {
+            search_moves: vec![Move::from_uci("e2e4").unwrap(), g1f1],
+            ..limits
+        };
+        let result = search(&game, limits);
+        assert_eq!(
+            (result.best_move, result.pv.first()),
+            (Some(g1f1), Some(&g1f1))
+        );
+    }
+
+    #[test]
+    fn manages_the_clock() {
+        let clock = TimeControl {
+            time: [Duration::from_secs(1); 2],
+            ..TimeControl::default()
+        };
+        let hard_limit = TimeManager::new(&clock, Color::WHITE).hard_limit();
+        let start = Instant::now();
+        let result = search(&Game::default(), SearchLimits::clock(clock));
+        assert!(start.elapsed() < hard_limit + Duration::from_millis(200));
+        assert!(result.depth >= 1 && result.best_move.is_some());
+    }
+
+    #[test]
+    fn no_moves() {
+        let mated = Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = search(&mated, SearchLimits::depth(2));
+        assert_eq!((result.best_move, result.score), (None, -MATE));
+        let stalemate = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = search(&stalemate, SearchLimits::depth(2));
+        assert_eq!((result.best_move, result.score), (None, 0));
+    }
+}
@@ -0,0 +1,68 @@
+//! A fixed workload for the search: the opening, middlegame and endgame positions of the
+//! [`corpus`] searched to a fixed depth. The total node count only changes when the search
+//! does, which makes it a signature of the engine version, and the nodes per second measure
+//! its speed.
+//!
+//! The binary runs it with `kritisch bench [depth]`, the UCI front end with `bench [depth]`.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::search::bench::bench;
+//! let result = bench(2);
+//! assert_eq!(result.nodes, bench(2).nodes);
+//! println!("{result}");
+//! ```
+
+use std::time::{Duration, Instant};
+
+use super::{SearchLimits, Searcher};
+use crate::corpus;
+
+/// The depth every position is searched to by default.
+pub const DEFAULT_DEPTH: u32 = 6;
+
+/// The outcome of [`bench`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    /// The nodes searched over all positions, the same in every run.
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl BenchResult {
+    /// Returns the nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.time.as_millis().max(1)) as u64
+    }
+}
+
+/// Displays the result in the format tools like OpenBench look for, e.g.
+/// `1234567 nodes 1500000 nps`.
+impl std::fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} nodes {} nps", self.nodes, self.nps())
+    }
+}
+
+/// Searches every one of the [`corpus::OPENINGS`], [`corpus::MIDDLEGAMES`] and
+/// [`corpus::ENDGAMES`] to `depth` with a fresh [`Searcher`], forgetting everything learned
+/// between positions so that each one is searched on its own.
+pub fn bench(depth: u32) -> BenchResult {
+    let mut searcher = Searcher::default();
+    let start = Instant::now();
+    let mut nodes = 0;
+    let positions = corpus::OPENINGS
+        .iter()
+        .chain(&corpus::MIDDLEGAMES)
+        .chain(&corpus::ENDGAMES);
+    for position in positions {
+        let game = position.game();
+        searcher.clear();
+        nodes += searcher.search(&game, SearchLimits::depth(depth)).nodes;
+    }
+    BenchResult {
+        nodes,
+        time: start.elapsed(),
+    }
+}
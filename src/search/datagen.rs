@@ -0,0 +1,287 @@
+//! Training data from self-play, for tuning the evaluation or training an NNUE network.
+//!
+//! Every game starts with a few random moves, so that no two games are alike, and then the
+//! [`Searcher`] plays both sides under the same limits until the game ends. Along the way every
+//! quiet position is recorded with the score of its search, and once the game is over with its
+//! result. Positions in check, positions whose best move is a capture and positions with a mate
+//! score are left out, since their static evaluation says little about the search score.
+//!
+//! A [`Sample`] is written either as a line of text, `<fen> | <score> | <result>` with the
+//! score in centipawns and the result as 1.0, 0.5 or 0.0, both from White's point of view, as
+//! most trainers read it, or in binary as the [`codec`] encoding of the position followed by
+//! the score as a little-endian `i16` and the result in half points for White.
+//!
+//! The binary runs it with `kritisch datagen [games]`, writing text to standard output.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::search::{datagen::{play_game, DatagenOptions}, SearchLimits, Searcher};
+//! let options = DatagenOptions {
+//!     limits: SearchLimits::depth(1),
+//!     max_plies: 20,
+//!     ..DatagenOptions::default()
+//! };
+//! let samples = play_game(&mut Searcher::default(), &options, 7);
+//! for sample in &samples {
+//!     println!("{sample}");
+//! }
+//! ```
+
+use std::io::{self, Write};
+
+use super::{mate_in, SearchLimits, Searcher};
+use crate::{
+    codec,
+    game::{Game, Outcome},
+    movegen::{random_legal_move, seeded_rng, Rng},
+    Color, Error, Result,
+};
+
+/// How games are played by [`play_game`] and [`generate`].
+#[derive(Debug, Clone)]
+pub struct DatagenOptions {
+    /// How many random moves start every game.
+    pub random_plies: u32,
+    /// The limits of the search for every move.
+    pub limits: SearchLimits,
+    /// The length in plies after which a game that hasn't ended counts as a draw.
+    pub max_plies: u32,
+    /// Seeds the random moves, so that the same seed plays the same games.
+    pub seed: u64,
+}
+
+impl Default for DatagenOptions {
+    fn default() -> Self {
+        Self {
+            random_plies: 8,
+            limits: SearchLimits::nodes(5000),
+            max_plies: 400,
+            seed: 0,
+        }
+    }
+}
+
+/// How [`generate`] writes the samples.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// One line per sample, see [`Sample`]'s `Display`.
+    #[default]
+    TEXT,
+    /// The bytes of [`Sample::to_bytes`], one sample after the other.
+    BINARY,
+}
+
+/// A position from a self-play game.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub game: Game,
+    /// The score of the search in centipawns from White's point of view.
+    pub score: i32,
+    /// How the game the position is from ended.
+    pub outcome: Outcome,
+}
+
+impl Sample {
+    /// Encodes the sample in the binary format, see the [module documentation](self).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = codec::encode_position(&self.game);
+        let score = self.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        bytes.extend_from_slice(&score.to_le_bytes());
+        bytes.push(match self.outcome {
+            Outcome::DECISIVE {
+                winner: Color::WHITE,
+            } => 2,
+            Outcome::DRAW => 1,
+            Outcome::DECISIVE {
+                winner: Color::BLACK,
+            } => 0,
+        });
+        bytes
+    }
+
+    /// Decodes a sample produced by [`Sample::to_bytes`], returning it together with the number
+    /// of bytes consumed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::{Game, Outcome}, search::datagen::Sample};
+    /// let sample = Sample { game: Game::default(), score: 25, outcome: Outcome::DRAW };
+    /// let bytes = sample.to_bytes();
+    /// assert_eq!(Sample::from_bytes(&bytes).unwrap(), (sample, bytes.len()));
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize)> {
+        let (game, len) = codec::decode_position(bytes)?;
+        let Some(&[low, high, result]) = bytes.get(len..len + 3) else {
+            return Err(Error::InvalidEncoding("sample ends early".to_string()));
+        };
+        let outcome = match result {
+            0 => Outcome::DECISIVE {
+                winner: Color::BLACK,
+            },
+            1 => Outcome::DRAW,
+            2 => Outcome::DECISIVE {
+                winner: Color::WHITE,
+            },
+            _ => return Err(Error::InvalidEncoding(format!("invalid result {result}"))),
+        };
+        let score = i16::from_le_bytes([low, high]) as i32;
+        Ok((
+            Self {
+                game,
+                score,
+                outcome,
+            },
+            len + 3,
+        ))
+    }
+}
+
+/// Displays the sample as a line of the text format, e.g.
+/// `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 | -32 | 0.5`.
+impl std::fmt::Display for Sample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self.outcome {
+            Outcome::DECISIVE {
+                winner: Color::WHITE,
+            } => "1.0",
+            Outcome::DRAW => "0.5",
+            Outcome::DECISIVE {
+                winner: Color::BLACK,
+            } => "0.0",
+        };
+        write!(f, "{} | {} | {}", self.game.to_fen(), self.score, result)
+    }
+}
+
+/// Plays one self-play game with `searcher`, starting with random moves drawn from `seed`, and
+/// returns its samples. A game that ends during the random moves has none. The searcher's
+/// [history](Searcher::history) follows the game, so it sees repetitions of earlier positions.
+pub fn play_game(searcher: &mut Searcher, options: &DatagenOptions, seed: u64) -> Vec<Sample> {
+    searcher.clear();
+    searcher.history.clear();
+    let mut game = Game::default();
+    let mut rng = seeded_rng(seed);
+    for _ in 0..options.random_plies {
+        let Some(m) = random_legal_move(&game, &mut rng) else {
+            return Vec::new();
+        };
+        searcher.history.push(game.key());
+        game.make_move(m);
+    }
+
+    let mut positions = Vec::new();
+    let mut plies = options.random_plies;
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+        if plies >= options.max_plies {
+            break Outcome::DRAW;
+        }
+        let result = searcher.search(&game, options.limits.clone());
+        let Some(m) = result.best_move else {
+            break Outcome::DRAW;
+        };
+        if !game.is_check() && !game.is_capture(m) && mate_in(result.score).is_none() {
+            let score = match game.to_move {
+                Color::WHITE => result.score,
+                Color::BLACK => -result.score,
+            };
+            positions.push((game, score));
+        }
+        searcher.history.push(game.key());
+        game.make_move(m);
+        plies += 1;
+    };
+    positions
+        .into_iter()
+        .map(|(game, score)| Sample {
+            game,
+            score,
+            outcome,
+        })
+        .collect()
+}
+
+/// Plays `games` self-play games and writes their samples to `out` in `format`, returning how
+/// many it wrote. Every game gets its own seed drawn from [`DatagenOptions::seed`].
+pub fn generate<W: Write>(
+    out: &mut W,
+    options: &DatagenOptions,
+    games: usize,
+    format: Format,
+) -> io::Result<usize> {
+    let mut searcher = Searcher::default();
+    let mut rng = seeded_rng(options.seed);
+    let mut written = 0;
+    for _ in 0..games {
+        let samples = play_game(&mut searcher, options, rng.next_u64());
+        for sample in &samples {
+            match format {
+                Format::TEXT => writeln!(out, "{sample}")?,
+                Format::BINARY => out.write_all(&sample.to_bytes())?,
+            }
+        }
+        written += samples.len();
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> DatagenOptions {
+        DatagenOptions {
+            limits: SearchLimits::depth(2),
+            max_plies: 40,
+            ..DatagenOptions::default()
+        }
+    }
+
+    #[test]
+    fn plays_the_same_games_from_the_same_seed() {
+        let mut searcher = Searcher::default();
+        let samples = play_game(&mut searcher, &options(), 1);
+        assert!(!samples.is_empty());
+        assert_eq!(play_game(&mut searcher, &options(), 1), samples);
+        assert_ne!(play_game(&mut searcher, &options(), 2), samples);
+        let outcome = samples[0].outcome;
+        for sample in &samples {
+            assert_eq!(sample.outcome, outcome);
+            assert!(!sample.game.is_check());
+        }
+    }
+
+    #[test]
+    fn writes_both_formats() {
+        let mut text = Vec::new();
+        let written = generate(&mut text, &options(), 2, Format::TEXT).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().count(), written);
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split(" | ").collect();
+            assert!(Game::from_fen(fields[0]).is_ok());
+            assert!(fields[1].parse::<i32>().is_ok());
+            assert!(["1.0", "0.5", "0.0"].contains(&fields[2]));
+        }
+
+        let mut binary = Vec::new();
+        assert_eq!(
+            generate(&mut binary, &options(), 2, Format::BINARY).unwrap(),
+            written
+        );
+        let mut decoded = Vec::new();
+        let mut bytes = binary.as_slice();
+        while !bytes.is_empty() {
+            let (sample, len) = Sample::from_bytes(bytes).unwrap();
+            decoded.push(sample.to_string());
+            bytes = &bytes[len..];
+        }
+        assert_eq!(decoded, text.lines().collect::<Vec<_>>());
+    }
+}
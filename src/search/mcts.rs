@@ -0,0 +1,318 @@
+//! Monte Carlo tree search, an alternative to the alpha-beta [`Searcher`](super::Searcher).
+//!
+//! Every iteration walks down the tree picking children by the UCT formula, which balances
+//! exploiting moves that scored well so far against exploring moves that were tried less.
+//! The position it arrives at is added to the tree and scored by the [`Evaluator`], mapped to
+//! the expected result of the game. That score is then added to every node on the way back up.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, search::{mcts::Mcts, Engine, SearchLimits}, Move};
+//! let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+//! let result = Mcts::default().search(&game, SearchLimits::nodes(2000));
+//! assert_eq!(result.best_move, Some(Move::from_uci("d2d5").unwrap()));
+//! ```
+
+use std::time::{Duration, Instant};
+
+use super::{time::TimeManager, Engine, SearchLimits, SearchResult, MATE};
+use crate::{
+    eval::Evaluator,
+    game::{Game, Termination},
+    movegen::all_legal_moves,
+    Move, MoveList,
+};
+
+/// The exploration constant of the UCT formula, higher values try more moves.
+pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+/// How many iterations to run if `SearchLimits` sets no node, time or clock limit.
+pub const DEFAULT_NODES: u64 = 100_000;
+/// How many iterations to run between looking at the clock.
+const TIME_CHECK_INTERVAL: u64 = 64;
+/// The centipawn score at which the expected result is about 0.73.
+const SCORE_SCALE: f64 = 200.0;
+
+/// A node of the tree.
+#[derive(Debug, Clone)]
+struct Node {
+    /// The move leading here, `None` for the root.
+    m: Option<Move>,
+    /// The index of the first child. The children are stored next to each other.
+    first_child: usize,
+    children: usize,
+    expanded: bool,
+    visits: u32,
+    /// The summed results from the point of view of the side that made `m`.
+    total: f64,
+    /// The result from the point of view of the side that made `m` if the game is over here.
+    terminal: Option<f64>,
+}
+
+impl Node {
+    fn new(m: Option<Move>) -> Self {
+        Self {
+            m,
+            first_child: 0,
+            children: 0,
+            expanded: false,
+            visits: 0,
+            total: 0.0,
+            terminal: None,
+        }
+    }
+
+    /// Returns the average result from the point of view of the side that made `m`.
+    fn value(&self) -> f64 {
+        if self.visits == 0 {
+            0.5
+        } else {
+            self.total / self.visits as f64
+        }
+    }
+}
+
+/// A Monte Carlo tree search guided by an [`Evaluator`].
+///
+/// Of the [`SearchLimits`], only `nodes`, `time`, `clock` and `search_moves` apply, with
+/// `nodes` counting iterations. Without any of them the search stops after [`DEFAULT_NODES`] iterations. The
+/// tree is rebuilt for every search.
+pub struct Mcts {
+    pub evaluator: Evaluator,
+    pub exploration: f64,
+    nodes: Vec<Node>,
+    /// The moves searched at the root, see [`SearchLimits::search_moves`].
+    root_moves: MoveList,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new(Evaluator::default())
+    }
+}
+
+impl Mcts {
+    pub fn new(evaluator: Evaluator) -> Self {
+        Self {
+            evaluator,
+            exploration: DEFAULT_EXPLORATION,
+            nodes: Vec::new(),
+            root_moves: MoveList::new(),
+        }
+    }
+
+    /// Adds a node for every legal move of `game` below `node`, or marks it as terminal if
+    /// the game is over. Returns the result of `game` from the point of view of the side that
+    /// moved into it.
+    fn expand(&mut self, node: usize, game: &Game) -> f64 {
+        self.nodes[node].expanded = true;
+        let moves = if node == 0 {
+            self.root_moves.clone()
+        } else {
+            all_legal_moves(game)
+        };
+        let rules = game.rules();
+        let termination = match rules.variant_termination(game) {
+            Some(termination) => Some(termination),
+            None if moves.is_empty() => Some(rules.no_moves(game)),
+            None => None,
+        };
+        let terminal = match termination {
+            // Checkmate wins for the side that moved into it
+            Some(Termination::CHECKMATE | Termination::VARIANT_LOSS) => Some(1.0),
+            Some(Termination::VARIANT_WIN) => Some(0.0),
+            Some(_) => Some(0.5),
+            None if game.halfmove_clock >= 100 || rules.is_insufficient_material(game) => Some(0.5),
+            None => None,
+        };
+        if let Some(result) = terminal {
+            self.nodes[node].terminal = Some(result);
+            return result;
+        }
+
+        self.nodes[node].first_child = self.nodes.len();
+        self.nodes[node].children = moves.len();
+        self.nodes.extend(moves.iter().map(|&m| Node::new(Some(m))));
+        1.0 - expected_result(self.evaluator.evaluate(game))
+    }
+
+    /// Returns the child of `node` with the highest UCT score.
+    fn select(&self, node: usize) -> usize {
+        let parent = &self.nodes[node];
+        let log_visits = (parent.visits.max(1) as f64).ln();
+        let children = parent.first_child..parent.first_child + parent.children;
+        let uct = |child: &Node| {
+            if child.visits == 0 {
+                return f64::INFINITY;
+            }
+            child.value() + self.exploration * (log_visits / child.visits as f64).sqrt()
+        };
+        children
+            .max_by(|&a, &b| uct(&self.nodes[a]).total_cmp(&uct(&self.nodes[b])))
+            .expect("Tried to select from a node without children")
+    }
+
+    /// Returns the most visited child of `node`, or `None` if none was visited.
+    fn best_child(&self, node: usize) -> Option<usize> {
+        let parent = &self.nodes[node];
+        (parent.first_child..parent.first_child + parent.children)
+            .filter(|&child| self.nodes[child].visits > 0)
+            .max_by_key(|&child| self.nodes[child].visits)
+    }
+
+    /// Runs one iteration from the root, returning the depth it reached.
+    fn iterate(&mut self, root: &Game, path: &mut Vec<usize>) -> u32 {
+        let mut game = *root;
+        let mut node = 0;
+        path.clear();
+        path.push(node);
+        while self.nodes[node].expanded && self.nodes[node].terminal.is_none() {
+            node = self.select(node);
+            game.make_move(self.nodes[node].m.expect("Only the root has no move"));
+            path.push(node);
+        }
+        let mut result = match self.nodes[node].terminal {
+            Some(result) => result,
+            None => self.expand(node, &game),
+        };
+        for &n in path.iter().rev() {
+            self.nodes[n].visits += 1;
+            self.nodes[n].total += result;
+            result = 1.0 - result;
+        }
+        path.len() as u32 - 1
+    }
+}
+
+impl Engine for Mcts {
+    fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        let start = Instant::now();
+        let deadline: Option<Duration> = [
+            limits.time,
+            limits
+                .clock
+                .map(|clock| TimeManager::new(&clock, game.to_move).soft_limit()),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let max_nodes = match (limits.nodes, deadline) {
+            (Some(nodes), _) => nodes,
+            (None, Some(_)) => u64::MAX,
+            (None, None) => DEFAULT_NODES,
+        };
+
+        self.root_moves = limits.root_moves(game);
+        self.nodes.clear();
+        self.nodes.push(Node::new(None));
+        let mut path = Vec::new();
+        let mut depth = 0;
+        let mut iterations = 0;
+        while iterations < max_nodes {
+            if iterations.is_multiple_of(TIME_CHECK_INTERVAL)
+                && deadline.is_some_and(|deadline| start.elapsed() >= deadline)
+            {
+                break;
+            }
+            depth = depth.max(self.iterate(game, &mut path));
+            iterations += 1;
+            // Nothing to search below a finished game
+            if self.nodes[0].terminal.is_some() {
+                break;
+            }
+        }
+
+        let mut pv = Vec::new();
+        let mut node = 0;
+        while let Some(child) = self.best_child(node) {
+            pv.push(self.nodes[child].m.expect("Only the root has no move"));
+            node = child;
+        }
+        let score = match self.best_child(0).map(|child| &self.nodes[child]) {
+            Some(child) if child.terminal == Some(1.0) => MATE - 1,
+            Some(child) => centipawns(child.value()),
+            None if game.is_check() => -MATE,
+            None => 0,
+        };
+        SearchResult {
+            best_move: pv.first().copied(),
+            score,
+            depth,
+            nodes: iterations,
+            pv,
+        }
+    }
+}
+
+/// Maps a centipawn score to the expected result of the game, from 0 for a loss to 1 for a
+/// win.
+fn expected_result(score: i32) -> f64 {
+    1.0 / (1.0 + (-score as f64 / SCORE_SCALE).exp())
+}
+
+/// The inverse of [`expected_result`], limited to scores that aren't mistaken for mates.
+fn centipawns(result: f64) -> i32 {
+    let result = result.clamp(1e-6, 1.0 - 1e-6);
+    (SCORE_SCALE * (result / (1.0 - result)).ln()).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::mate_in;
+
+    #[test]
+    fn finds_mate_in_one() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = Mcts::default().search(&game, SearchLimits::nodes(500));
+        assert_eq!(result.best_move, Some(Move::from_uci("a1a8").unwrap()));
+        assert_eq!(mate_in(result.score), Some(1));
+    }
+
+    #[test]
+    fn respects_limits() {
+        let mut mcts = Mcts::default();
+        let result = mcts.search(&Game::default(), SearchLimits::nodes(300));
+        assert_eq!(result.nodes, 300);
+        assert!(result.depth >= 2);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+
+        let start = Instant::now();
+        let result = mcts.search(
+            &Game::default(),
+            SearchLimits::time(Duration::from_millis(50)),
+        );
+        assert!(start.elapsed() < Duration::from_millis(250));
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn searches_only_search_moves() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let search_moves = vec![
+            Move::from_uci("g1f2").unwrap(),
+            Move::from_uci("a1a2").unwrap(),
+        ];
+        let limits = SearchLimits {
+            nodes: Some(500),
+            search_moves: search_moves.clone(),
+            ..SearchLimits::default()
+        };
+        let result = Mcts::default().search(&game, limits);
+        assert!(search_moves.contains(&result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn no_moves() {
+        let mated = Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = Mcts::default().search(&mated, SearchLimits::nodes(10));
+        assert_eq!((result.best_move, result.score), (None, -MATE));
+    }
+
+    #[test]
+    fn scores_roundtrip() {
+        for score in [-900, -35, 0, 120, 2000] {
+            assert_eq!(centipawns(expected_result(score)), score);
+        }
+    }
+}
@@ -0,0 +1,602 @@
+//! Move ordering for the search. Alpha-beta cuts off the most nodes when the best move is
+//! tried first, so moves are sorted by how likely they are to cause a cutoff:
+//!
+//! 1. the move from the previous iteration's principal variation,
+//! 2. captures, most valuable victim and least valuable attacker first,
+//! 3. the [`Killers`] of the current ply,
+//! 4. the [`CounterMoves`] entry of the previous move,
+//! 5. the remaining quiet moves by their [`History`] score plus their
+//!    [`ContinuationHistory`] scores after the previous two moves.
+//!
+//! The [`MovePicker`] hands the moves out in this order one at a time, so the work of
+//! ordering the later ones is saved when an early one cuts off. It moves captures that lose
+//! material according to [`Game::see`] behind the quiet moves.
+
+use super::MAX_PLY;
+use crate::{game::Game, Color, ColoredPiece, Move, MoveList, Square, MAX_MOVES};
+
+const PV_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 28;
+const KILLER_SCORE: i32 = 1 << 26;
+const COUNTER_MOVE_SCORE: i32 = 1 << 25;
+/// The bound of history scores. The three scores of a quiet move add up to less than
+/// [`COUNTER_MOVE_SCORE`].
+pub const MAX_HISTORY: i32 = 1 << 14;
+
+/// The tables that order quiet moves, learned from the cutoffs of the search.
+#[derive(Debug, Clone, Default)]
+pub struct Heuristics {
+    pub killers: Killers,
+    pub history: History,
+    pub counter_moves: CounterMoves,
+    pub continuation: ContinuationHistory,
+}
+
+/// A move together with the piece that made it, as the [`CounterMoves`] and the
+/// [`ContinuationHistory`] tell moves apart.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PlayedMove {
+    pub piece: ColoredPiece,
+    pub to: Square,
+}
+
+impl PlayedMove {
+    /// Describes `m` in `game`, before it is made.
+    pub fn new(game: &Game, m: Move) -> Self {
+        Self {
+            piece: game
+                .moved_piece(m)
+                .expect("Tried to describe a move from an empty square"),
+            to: m.end,
+        }
+    }
+
+    fn index(self) -> usize {
+        (self.piece.color as usize * 6 + self.piece.piece as usize) * 64 + self.to as usize
+    }
+}
+
+/// Two quiet moves per ply that recently caused a beta cutoff. Sibling positions often have
+/// the same refutation, so they are tried right after the captures.
+#[derive(Debug, Clone)]
+pub struct Killers {
+    slots: [[Option<Move>; 2]; MAX_PLY],
+}
+
+impl Default for Killers {
+    fn default() -> Self {
+        Self {
+            slots: [[None; 2]; MAX_PLY],
+        }
+    }
+}
+
+impl Killers {
+    /// Records `m` as the newest killer of `ply`, moving the previous one to the second slot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{search::ordering::Killers, Move};
+    /// let mut killers = Killers::default();
+    /// let (a, b, c) = ("e2e4", "d2d4", "g1f3");
+    /// for uci in [a, b, b, c] {
+    ///     killers.add(3, Move::from_uci(uci).unwrap());
+    /// }
+    /// assert_eq!(killers.get(3), [Some(Move::from_uci(c).unwrap()), Some(Move::from_uci(b).unwrap())]);
+    /// assert_eq!(killers.get(2), [None, None]);
+    /// ```
+    pub fn add(&mut self, ply: usize, m: Move) {
+        let slots = &mut self.slots[ply];
+        if slots[0] != Some(m) {
+            slots[1] = slots[0];
+            slots[0] = Some(m);
+        }
+    }
+
+    /// Returns the killers of `ply`, newest first.
+    pub fn get(&self, ply: usize) -> [Option<Move>; 2] {
+        self.slots[ply]
+    }
+
+    pub fn clear(&mut self) {
+        self.slots = [[None; 2]; MAX_PLY];
+    }
+}
+
+/// How often each quiet move caused a beta cutoff, weighted by depth and indexed by the
+/// color, origin and destination of the move.
+#[derive(Debug, Clone)]
+pub struct History {
+    table: Box<[[[i32; 64]; 64]; 2]>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            table: Box::new([[[0; 64]; 64]; 2]),
+        }
+    }
+}
+
+impl History {
+    /// Rewards `m` for causing a cutoff at `depth`, and punishes the quiet moves in `tried`
+    /// that were searched before it without one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{search::ordering::History, Color, Move};
+    /// let mut history = History::default();
+    /// let good = Move::from_uci("g1f3").unwrap();
+    /// let bad = Move::from_uci("a2a3").unwrap();
+    /// history.update(Color::WHITE, good, &[bad], 4);
+    /// assert!(history.get(Color::WHITE, good) > 0);
+    /// assert!(history.get(Color::WHITE, bad) < 0);
+    /// assert_eq!(history.get(Color::BLACK, good), 0);
+    /// ```
+    pub fn update(&mut self, color: Color, m: Move, tried: &[Move], depth: u32) {
+        let bonus = (depth * depth).min(MAX_HISTORY as u32) as i32;
+        self.add(color, m, bonus);
+        for &t in tried {
+            self.add(color, t, -bonus);
+        }
+    }
+
+    pub fn get(&self, color: Color, m: Move) -> i32 {
+        self.table[color as usize][m.start as usize][m.end as usize]
+    }
+
+    /// Halves all scores, so that the next search favors what it learns itself.
+    pub fn age(&mut self) {
+        for entry in self.table.iter_mut().flatten().flatten() {
+            *entry /= 2;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self.table = [[[0; 64]; 64]; 2];
+    }
+
+    fn add(&mut self, color: Color, m: Move, bonus: i32) {
+        add_bonus(
+            &mut self.table[color as usize][m.start as usize][m.end as usize],
+            bonus,
+        );
+    }
+}
+
+/// The quiet move that last refuted each move, indexed by the piece and destination of the
+/// move it answers.
+#[derive(Debug, Clone)]
+pub struct CounterMoves {
+    table: Box<[Option<Move>; 12 * 64]>,
+}
+
+impl Default for CounterMoves {
+    fn default() -> Self {
+        Self {
+            table: Box::new([None; 12 * 64]),
+        }
+    }
+}
+
+impl CounterMoves {
+    /// Records `m` as the refutation of `previous`.
+    pub fn set(&mut self, previous: PlayedMove, m: Move) {
+        self.table[previous.index()] = Some(m);
+    }
+
+    pub fn get(&self, previous: PlayedMove) -> Option<Move> {
+        self.table[previous.index()]
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill(None);
+    }
+}
+
+/// Like [`History`], but indexed by the piece and destination of a quiet move together with
+/// those of a move played one or two plies before it. This learns follow-ups like recapturing
+/// or completing a maneuver.
+#[derive(Debug, Clone)]
+pub struct ContinuationHistory {
+    table: Box<[i32]>,
+}
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        Self {
+            table: vec![0; (12 * 64) * (12 * 64)].into_boxed_slice(),
+        }
+    }
+}
+
+impl ContinuationHistory {
+    /// Rewards `m` for causing a cutoff at `depth` after each of the `previous` moves, and
+    /// punishes the quiet moves in `tried` that were searched before it without one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, search::ordering::*, Move};
+    /// let mut game = Game::default();
+    /// let e4 = Move::from_uci("e2e4").unwrap();
+    /// let previous = PlayedMove::new(&game, e4);
+    /// game.make_move(e4);
+    /// let e5 = PlayedMove::new(&game, Move::from_uci("e7e5").unwrap());
+    /// let c5 = PlayedMove::new(&game, Move::from_uci("c7c5").unwrap());
+    ///
+    /// let mut continuation = ContinuationHistory::default();
+    /// continuation.update(&[Some(previous), None], e5, [c5], 6);
+    /// assert!(continuation.get(previous, e5) > 0);
+    /// assert!(continuation.get(previous, c5) < 0);
+    /// ```
+    pub fn update(
+        &mut self,
+        previous: &[Option<PlayedMove>],
+        m: PlayedMove,
+        tried: impl IntoIterator<Item = PlayedMove>,
+        depth: u32,
+    ) {
+        let bonus = (depth * depth).min(MAX_HISTORY as u32) as i32;
+        for &p in previous.iter().flatten() {
+            add_bonus(&mut self.table[Self::index(p, m)], bonus);
+        }
+        for t in tried {
+            for &p in previous.iter().flatten() {
+                add_bonus(&mut self.table[Self::index(p, t)], -bonus);
+            }
+        }
+    }
+
+    /// Returns the score of `m` played after `previous`.
+    pub fn get(&self, previous: PlayedMove, m: PlayedMove) -> i32 {
+        self.table[Self::index(previous, m)]
+    }
+
+    /// Halves all scores, like [`History::age`].
+    pub fn age(&mut self) {
+        for entry in self.table.iter_mut() {
+            *entry /= 2;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill(0);
+    }
+
+    fn index(previous: PlayedMove, m: PlayedMove) -> usize {
+        previous.index() * 12 * 64 + m.index()
+    }
+}
+
+/// Adds `bonus` to a history score, scaled down as the score approaches [`MAX_HISTORY`] so
+/// that it never leaves the bound.
+fn add_bonus(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+}
+
+/// Returns the sort key of `m` in `game` at `ply`, higher for moves to try first.
+/// `previous` holds the moves played one and two plies before.
+pub fn score_move(
+    game: &Game,
+    m: Move,
+    pv_move: Option<Move>,
+    heuristics: &Heuristics,
+    ply: usize,
+    previous: [Option<PlayedMove>; 2],
+) -> i32 {
+    if Some(m) == pv_move {
+        return PV_SCORE;
+    }
+    if let Some(score) = capture_score(game, m) {
+        return score;
+    }
+    match heuristics.killers.get(ply) {
+        [Some(k), _] if k == m => return KILLER_SCORE + 1,
+        [_, Some(k)] if k == m => return KILLER_SCORE,
+        _ => (),
+    }
+    if previous[0].is_some_and(|p| heuristics.counter_moves.get(p) == Some(m)) {
+        return COUNTER_MOVE_SCORE;
+    }
+    let played = PlayedMove::new(game, m);
+    let continuation: i32 = previous
+        .iter()
+        .flatten()
+        .map(|&p| heuristics.continuation.get(p, played))
+        .sum();
+    heuristics.history.get(game.to_move, m) + continuation
+}
+
+/// Sorts `moves` by [`score_move`], best first.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::all_legal_moves, search::ordering::*, Move};
+/// let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+/// let mut moves = all_legal_moves(&game);
+/// let mut heuristics = Heuristics::default();
+/// heuristics.killers.add(0, Move::from_uci("e1f1").unwrap());
+/// order_moves(&game, &mut moves, None, &heuristics, 0, [None; 2]);
+/// // The capture comes first, then the killer
+/// assert_eq!(moves[0], Move::from_uci("d2d5").unwrap());
+/// assert_eq!(moves[1], Move::from_uci("e1f1").unwrap());
+/// ```
+pub fn order_moves(
+    game: &Game,
+    moves: &mut MoveList,
+    pv_move: Option<Move>,
+    heuristics: &Heuristics,
+    ply: usize,
+    previous: [Option<PlayedMove>; 2],
+) {
+    moves.sort_unstable_by_key(|&m| -score_move(game, m, pv_move, heuristics, ply, previous));
+}
+
+/// Sorts captures by their victim and attacker, for the quiescence search.
+pub fn order_captures(game: &Game, moves: &mut MoveList) {
+    moves.sort_unstable_by_key(|&m| -capture_score(game, m).unwrap_or(0));
+}
+
+/// The stages of a [`MovePicker`], in the order it goes through them.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Stage {
+    /// The move from the principal variation or the transposition table.
+    HASH_MOVE,
+    /// Captures that don't lose material.
+    GOOD_CAPTURES,
+    QUIETS,
+    /// Captures that lose material, e.g. a queen taking a defended pawn.
+    BAD_CAPTURES,
+    DONE,
+}
+
+/// Hands out the legal moves of a position best first, scoring each stage only once the
+/// previous one is used up.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, movegen::all_legal_moves, search::ordering::*, Move};
+/// // Rxd4 loses the rook to exd4, Qxa4 wins a pawn
+/// let game = Game::from_fen("4k3/8/8/4p3/p2n4/8/3R4/3QK3 w - - 0 1").unwrap();
+/// let heuristics = Heuristics::default();
+/// let mut picker = MovePicker::new(&game, all_legal_moves(&game), None, 0, [None; 2]);
+/// assert_eq!(picker.next(&heuristics), Some(Move::from_uci("d1a4").unwrap()));
+/// assert_eq!(picker.stage(), Stage::GOOD_CAPTURES);
+/// let mut last = None;
+/// while let Some(m) = picker.next(&heuristics) {
+///     last = Some(m);
+/// }
+/// assert_eq!(last, Some(Move::from_uci("d2d4").unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MovePicker<'a> {
+    game: &'a Game,
+    /// All legal moves.
+    moves: MoveList,
+    hash_move: Option<Move>,
+    ply: usize,
+    previous: [Option<PlayedMove>; 2],
+    /// Only hand out captures that don't lose material.
+    captures_only: bool,
+    stage: Stage,
+    /// The moves of the current stage and their scores, handed out from `cursor` on. Only
+    /// the first [`MAX_MOVES`] moves are scored, the rest come last.
+    current: MoveList,
+    scores: [i32; MAX_MOVES],
+    cursor: usize,
+    bad_captures: MoveList,
+}
+
+impl<'a> MovePicker<'a> {
+    /// Creates a picker for the legal `moves` of `game` at `ply`, starting with `hash_move`.
+    /// `previous` holds the moves played one and two plies before.
+    pub fn new(
+        game: &'a Game,
+        moves: MoveList,
+        hash_move: Option<Move>,
+        ply: usize,
+        previous: [Option<PlayedMove>; 2],
+    ) -> Self {
+        Self {
+            game,
+            hash_move: hash_move.filter(|m| moves.contains(m)),
+            moves,
+            ply,
+            previous,
+            captures_only: false,
+            stage: Stage::HASH_MOVE,
+            current: MoveList::new(),
+            scores: [0; MAX_MOVES],
+            cursor: 0,
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// Creates a picker for the quiescence search that only hands out those of the legal
+    /// `moves` that capture without losing material.
+    pub fn captures(game: &'a Game, moves: MoveList) -> Self {
+        Self {
+            captures_only: true,
+            ..Self::new(game, moves, None, 0, [None; 2])
+        }
+    }
+
+    /// Returns the stage of the move handed out last.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Returns the next best move, or `None` once all have been handed out.
+    pub fn next(&mut self, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HASH_MOVE => {
+                    // The cursor tells whether the hash move was handed out already
+                    if let Some(m) = self.hash_move.filter(|_| self.cursor == 0) {
+                        self.cursor = 1;
+                        return Some(m);
+                    }
+                    self.enter(Stage::GOOD_CAPTURES, heuristics);
+                }
+                Stage::GOOD_CAPTURES => match self.select() {
+                    Some(m) if self.game.see(m) < 0 => self.bad_captures.push(m),
+                    Some(m) => return Some(m),
+                    None if self.captures_only => self.stage = Stage::DONE,
+                    None => self.enter(Stage::QUIETS, heuristics),
+                },
+                Stage::QUIETS => match self.select() {
+                    Some(m) => return Some(m),
+                    None => self.enter(Stage::BAD_CAPTURES, heuristics),
+                },
+                Stage::BAD_CAPTURES => match self.select() {
+                    Some(m) => return Some(m),
+                    None => self.stage = Stage::DONE,
+                },
+                Stage::DONE => return None,
+            }
+        }
+    }
+
+    /// Moves on to `stage`, collecting and scoring its moves.
+    fn enter(&mut self, stage: Stage, heuristics: &Heuristics) {
+        self.stage = stage;
+        self.cursor = 0;
+        self.current.clear();
+        if stage == Stage::BAD_CAPTURES {
+            // Already in the order they were captured in
+            self.current = self.bad_captures.clone();
+            self.scores[..self.current.len().min(MAX_MOVES)].fill(0);
+            return;
+        }
+        for &m in self.moves.iter() {
+            if Some(m) == self.hash_move {
+                continue;
+            }
+            let score = match (stage, capture_score(self.game, m)) {
+                (Stage::GOOD_CAPTURES, Some(score)) => score,
+                (Stage::QUIETS, None) => {
+                    score_move(self.game, m, None, heuristics, self.ply, self.previous)
+                }
+                _ => continue,
+            };
+            if let Some(slot) = self.scores.get_mut(self.current.len()) {
+                *slot = score;
+            }
+            self.current.push(m);
+        }
+    }
+
+    /// Hands out the best remaining move of the current stage.
+    fn select(&mut self) -> Option<Move> {
+        // The first of equally scored moves, like a stable sort
+        let score = |i| self.scores.get(i).copied().unwrap_or(i32::MIN);
+        let best =
+            (self.cursor..self.current.len()).min_by_key(|&i| std::cmp::Reverse(score(i)))?;
+        self.current.swap(self.cursor, best);
+        // Unscored moves are only picked once the scored ones are used up
+        if best < MAX_MOVES {
+            self.scores.swap(self.cursor, best);
+        }
+        self.cursor += 1;
+        Some(self.current[self.cursor - 1])
+    }
+}
+
+/// Returns the most valuable victim/least valuable attacker score of `m`, or `None` if it is
+/// not a capture.
+fn capture_score(game: &Game, m: Move) -> Option<i32> {
+    let victim = game.try_type_at(m.end).filter(|_| game.is_capture(m))?;
+    Some(CAPTURE_SCORE + 10 * victim.value() - game.type_at(m.start).value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_stays_bounded() {
+        let mut history = History::default();
+        let m = Move::from_uci("e2e4").unwrap();
+        for _ in 0..1000 {
+            history.update(Color::WHITE, m, &[], 20);
+        }
+        assert!(history.get(Color::WHITE, m) <= MAX_HISTORY);
+        history.age();
+        assert!(history.get(Color::WHITE, m) <= MAX_HISTORY / 2);
+    }
+
+    #[test]
+    fn pv_move_first() {
+        let game = Game::default();
+        let pv_move = Move::from_uci("b1c3").unwrap();
+        let mut heuristics = Heuristics::default();
+        let e4 = Move::from_uci("e2e4").unwrap();
+        heuristics.history.update(Color::WHITE, e4, &[], 10);
+        let mut moves = crate::movegen::all_legal_moves(&game);
+        order_moves(&game, &mut moves, Some(pv_move), &heuristics, 0, [None; 2]);
+        assert_eq!(moves[0], pv_move);
+        assert_eq!(moves[1], e4);
+    }
+
+    #[test]
+    fn captures_only_skips_losing_captures() {
+        // Qxa4 wins a pawn, Rxd4 loses the rook for a knight
+        let game = Game::from_fen("4k3/8/8/4p3/p2n4/8/3R4/3QK3 w - - 0 1").unwrap();
+        let moves = crate::movegen::all_legal_moves(&game);
+        let heuristics = Heuristics::default();
+
+        let mut picker = MovePicker::captures(&game, moves.clone());
+        assert_eq!(
+            picker.next(&heuristics),
+            Some(Move::from_uci("d1a4").unwrap())
+        );
+        assert_eq!(picker.next(&heuristics), None);
+
+        let mut picker = MovePicker::new(&game, moves.clone(), None, 0, [None; 2]);
+        let mut picked = Vec::new();
+        while let Some(m) = picker.next(&heuristics) {
+            picked.push((m, picker.stage()));
+        }
+        assert_eq!(picked.len(), moves.len());
+        assert!(picked.is_sorted_by_key(|(_, stage)| *stage));
+        assert_eq!(
+            picked.last(),
+            Some(&(Move::from_uci("d2d4").unwrap(), Stage::BAD_CAPTURES))
+        );
+    }
+
+    #[test]
+    fn counter_move_before_history() {
+        let mut game = Game::default();
+        let e4 = Move::from_uci("e2e4").unwrap();
+        let previous = PlayedMove::new(&game, e4);
+        game.make_move(e4);
+        let (c5, e5) = (
+            Move::from_uci("c7c5").unwrap(),
+            Move::from_uci("e7e5").unwrap(),
+        );
+
+        let mut heuristics = Heuristics::default();
+        heuristics.history.update(Color::BLACK, e5, &[], 10);
+        heuristics.counter_moves.set(previous, c5);
+        let mut moves = crate::movegen::all_legal_moves(&game);
+        order_moves(
+            &game,
+            &mut moves,
+            None,
+            &heuristics,
+            1,
+            [Some(previous), None],
+        );
+        assert_eq!(moves[..2], [c5, e5]);
+        // Without the previous move, there is no counter move to look up
+        order_moves(&game, &mut moves, None, &heuristics, 1, [None; 2]);
+        assert_eq!(moves[0], e5);
+    }
+}
@@ -0,0 +1,160 @@
+//! Playing weaker on purpose, to offer opponents that people can beat.
+//!
+//! A [`Skill`] aims at a strength between [`MIN_ELO`] and [`MAX_ELO`] in three ways: it limits
+//! how deep the search goes, adds noise to the evaluation, and finally picks one of the root
+//! moves at random among those that scored close to the best. The weaker the skill, the
+//! shallower the search, the louder the noise and the wider the margin. At [`MAX_ELO`] only the
+//! depth is limited.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, search::{skill::Skill, SearchLimits, Searcher}};
+//! let skill = Skill::new(1200);
+//! let mut searcher = Searcher::default();
+//! searcher.skill = Some(skill);
+//! let result = searcher.search(&Game::default(), SearchLimits::default());
+//! assert!(result.depth <= skill.max_depth());
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{zobrist::splitmix64, Move};
+
+/// The weakest strength a skill plays at.
+pub const MIN_ELO: u32 = 800;
+/// The strongest strength a skill plays at.
+pub const MAX_ELO: u32 = 2400;
+/// The depth limit at [`MAX_ELO`], which falls to 1 at [`MIN_ELO`].
+const MAX_DEPTH: u32 = 10;
+/// The evaluation noise at [`MIN_ELO`] in centipawns, which falls to 0 at [`MAX_ELO`].
+const MAX_NOISE: i32 = 150;
+/// How far below the best root move the picked move may score at [`MIN_ELO`], in centipawns.
+/// The margin falls to 0 at [`MAX_ELO`].
+const MAX_MARGIN: i32 = 200;
+
+/// A limit on the playing strength of a [`Searcher`](super::Searcher).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Skill {
+    /// The targeted strength, taken as [`MIN_ELO`] or [`MAX_ELO`] if outside of them.
+    pub elo: u32,
+    /// Seeds the noise and the move choice, so that two searches with the same seed play the
+    /// same moves.
+    pub seed: u64,
+}
+
+/// Returns a seed that differs from one call to the next, taken from the system time.
+pub(crate) fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+impl Skill {
+    /// Aims at `elo`, with a seed that differs from one call to the next.
+    pub fn new(elo: u32) -> Self {
+        Self {
+            elo,
+            seed: time_seed(),
+        }
+    }
+
+    /// Returns how strong the skill is on a scale from 0 at [`MIN_ELO`] to 1 at [`MAX_ELO`].
+    fn strength(&self) -> f64 {
+        let elo = self.elo.clamp(MIN_ELO, MAX_ELO);
+        (elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64
+    }
+
+    /// Scales `max` down to 0 as the strength goes up.
+    fn weakness(&self, max: i32) -> i32 {
+        ((1.0 - self.strength()) * max as f64).round() as i32
+    }
+
+    /// Returns the deepest iteration the search completes.
+    pub fn max_depth(&self) -> u32 {
+        1 + (self.strength() * (MAX_DEPTH - 1) as f64).round() as u32
+    }
+
+    /// Returns the most the evaluation is off by, in centipawns.
+    pub fn noise(&self) -> i32 {
+        self.weakness(MAX_NOISE)
+    }
+
+    /// Returns the noise added to the evaluation of the position with `key`. It stays the same
+    /// for a position, so the transposition table doesn't see two scores for it.
+    pub(crate) fn eval_noise(&self, key: u64) -> i32 {
+        let noise = self.noise();
+        if noise == 0 {
+            return 0;
+        }
+        let random = splitmix64(&mut (key ^ self.seed));
+        (random % (2 * noise as u64 + 1)) as i32 - noise
+    }
+
+    /// Picks one of the root moves `scored` with their scores from the position with `key`.
+    /// Every move gets a random bonus of up to the margin, and the highest total wins, so the
+    /// best move is always picked if no other comes within the margin.
+    pub(crate) fn pick(&self, key: u64, scored: &[(Move, i32)]) -> Option<(Move, i32)> {
+        let margin = self.weakness(MAX_MARGIN) as u64;
+        let mut state = key ^ self.seed;
+        scored
+            .iter()
+            .map(|&(m, score)| {
+                let bonus = splitmix64(&mut state) % (margin + 1);
+                (m, score, score as i64 + bonus as i64)
+            })
+            .reduce(|best, next| if next.2 > best.2 { next } else { best })
+            .map(|(m, score, _)| (m, score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::Game,
+        search::{SearchLimits, Searcher},
+    };
+
+    #[test]
+    fn scales_with_the_elo() {
+        let weakest = Skill { elo: 0, seed: 0 };
+        let strongest = Skill {
+            elo: MAX_ELO + 500,
+            seed: 0,
+        };
+        assert_eq!((weakest.max_depth(), weakest.noise()), (1, MAX_NOISE));
+        assert_eq!((strongest.max_depth(), strongest.noise()), (MAX_DEPTH, 0));
+        assert_eq!(strongest.eval_noise(12345), 0);
+        assert!(weakest.eval_noise(12345).abs() <= MAX_NOISE);
+    }
+
+    #[test]
+    fn never_picks_a_move_beyond_the_margin() {
+        let [a, b] = ["e2e4", "d2d4"].map(|uci| Move::from_uci(uci).unwrap());
+        for seed in 0..100 {
+            let skill = Skill { elo: 0, seed };
+            assert_eq!(skill.pick(0, &[(b, -MAX_MARGIN - 1), (a, 0)]), Some((a, 0)));
+        }
+    }
+
+    #[test]
+    fn plays_different_moves_when_weak() {
+        let game = Game::default();
+        let mut searcher = Searcher::default();
+        let mut played = |elo: u32| {
+            let mut moves: Vec<Move> = (0..20)
+                .filter_map(|seed| {
+                    searcher.clear();
+                    searcher.skill = Some(Skill { elo, seed });
+                    searcher.search(&game, SearchLimits::depth(3)).best_move
+                })
+                .collect();
+            moves.sort_by_key(|m| (m.start as u8, m.end as u8));
+            moves.dedup();
+            moves.len()
+        };
+        assert!(played(MIN_ELO) > 1);
+        assert_eq!(played(MAX_ELO), 1);
+    }
+}
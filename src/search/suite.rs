@@ -0,0 +1,315 @@
+//! Test suites in EPD, such as Win at Chess, the Strategic Test Suite or the Eigenmann Rapid
+//! Engine Test: positions with the moves to find, searched one after another within the same
+//! limits. The share of positions solved tracks the tactical and positional strength of the
+//! engine from one version to the next.
+//!
+//! Every line of a suite holds the first four fields of a FEN followed by EPD operations, of
+//! which these are read:
+//!
+//! - `bm`, the best moves in SAN, one of which has to be found,
+//! - `am`, the moves to avoid, none of which may be played,
+//! - `id`, the name of the position,
+//! - `c0`, if it scores moves like the Strategic Test Suite does, e.g. `c0 "Nf5=10, Qd2=4"`.
+//!
+//! A position is solved if the search picks a best move and no move to avoid. It scores the
+//! points `c0` gives the move picked, or one point if solved and none otherwise.
+//!
+//! The binary runs a suite with `kritisch suite <suite.epd> [milliseconds]`.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::search::{suite, SearchLimits};
+//! let input = r#"
+//! 6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "back rank";
+//! 6k1/8/8/8/8/8/6PP/3r2K1 w - - am Kf2; id "only move";
+//! "#;
+//! let positions = suite::parse(input).unwrap();
+//! let result = suite::run(&positions, &SearchLimits::depth(3));
+//! assert_eq!(result.solved(), 1);
+//! assert_eq!(result.results[0].id, "back rank");
+//! println!("{result}");
+//! ```
+
+use std::time::{Duration, Instant};
+
+use super::{SearchLimits, Searcher};
+use crate::{game::Game, Error, Move, Result};
+
+/// A position of a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestPosition {
+    /// The name given by the `id` operation, or the line number if there is none.
+    pub id: String,
+    pub game: Game,
+    /// The moves of which one has to be found, any move if empty.
+    pub best_moves: Vec<Move>,
+    /// The moves that must not be played.
+    pub avoid_moves: Vec<Move>,
+    /// The points every scored move is worth, given by the `c0` operation.
+    pub points: Vec<(Move, u32)>,
+}
+
+impl TestPosition {
+    /// Parses a line of EPD, naming the position `id` unless the line names it itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{search::suite::TestPosition, Move};
+    /// let line = r#"4k3/8/8/8/8/8/8/4K2R w K - bm O-O; c0 "O-O=10, Rh8+=5";"#;
+    /// let position = TestPosition::parse(line, "1").unwrap();
+    /// assert_eq!(position.id, "1");
+    /// assert_eq!(position.best_moves, [Move::from_uci("e1g1").unwrap()]);
+    /// assert_eq!(position.points[1], (Move::from_uci("h1h8").unwrap(), 5));
+    /// ```
+    pub fn parse(line: &str, id: &str) -> Result<Self> {
+        let line = line.trim();
+        let mut fields = line.split_whitespace();
+        let fen: Vec<&str> = fields.by_ref().take(4).collect();
+        if fen.len() < 4 {
+            return Err(Error::InvalidFen(format!(
+                "expected at least 4 fields in '{line}'"
+            )));
+        }
+        let game = Game::from_fen(&fen.join(" "))?;
+        let mut position = Self {
+            id: id.to_string(),
+            game,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            points: Vec::new(),
+        };
+        let parse_moves = |operands: &[String]| -> Result<Vec<Move>> {
+            operands.iter().map(|san| game.parse_san(san)).collect()
+        };
+        let rest = fields.collect::<Vec<&str>>().join(" ");
+        for (opcode, operands) in operations(&rest) {
+            match opcode.as_str() {
+                "bm" => position.best_moves = parse_moves(&operands)?,
+                "am" => position.avoid_moves = parse_moves(&operands)?,
+                "id" => {
+                    if let Some(id) = operands.first() {
+                        position.id = id.clone();
+                    }
+                }
+                "c0" => {
+                    // Other comments don't score moves and are ignored like any other opcode
+                    let scored = operands.first().map_or("", String::as_str).split(',');
+                    position.points = scored
+                        .filter_map(|entry| {
+                            let (san, points) = entry.split_once('=')?;
+                            let points = points.trim().parse().ok()?;
+                            Some(game.parse_san(san).map(|m| (m, points)))
+                        })
+                        .collect::<Result<_>>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(position)
+    }
+
+    /// Returns the most points a move scores in this position.
+    pub fn max_points(&self) -> u32 {
+        self.points
+            .iter()
+            .map(|&(_, points)| points)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Returns `true` if playing `m` solves the position.
+    pub fn is_solved_by(&self, m: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&m))
+            && !self.avoid_moves.contains(&m)
+    }
+
+    /// Returns the points playing `m` scores.
+    pub fn points_for(&self, m: Move) -> u32 {
+        if self.points.is_empty() {
+            return self.is_solved_by(m) as u32;
+        }
+        self.points
+            .iter()
+            .find(|&&(scored, _)| scored == m)
+            .map_or(0, |&(_, points)| points)
+    }
+}
+
+/// Parses every non-empty line of `input` that isn't a comment starting with `#`. Positions
+/// without an `id` are named after their 1-based line number. Lines that fail to parse produce
+/// an [`Error::Line`].
+pub fn parse(input: &str) -> Result<Vec<TestPosition>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            TestPosition::parse(line, &(i + 1).to_string()).map_err(|e| Error::Line {
+                line: i + 1,
+                source: Box::new(e),
+            })
+        })
+        .collect()
+}
+
+/// How the search did on one position of a suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionResult {
+    pub id: String,
+    /// The move the search picked, or `None` if there was no legal move.
+    pub best_move: Option<Move>,
+    pub solved: bool,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+/// The outcome of [`run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteResult {
+    /// The results in the order of the positions.
+    pub results: Vec<PositionResult>,
+    pub time: Duration,
+}
+
+impl SuiteResult {
+    /// Returns the number of positions solved.
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    /// Returns the points scored over all positions.
+    pub fn points(&self) -> u32 {
+        self.results.iter().map(|result| result.points).sum()
+    }
+
+    /// Returns the most points that could have been scored.
+    pub fn max_points(&self) -> u32 {
+        self.results.iter().map(|result| result.max_points).sum()
+    }
+}
+
+/// Displays the summary, e.g. `solved 250/300, 2714/3000 points`.
+impl std::fmt::Display for SuiteResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "solved {}/{}, {}/{} points",
+            self.solved(),
+            self.results.len(),
+            self.points(),
+            self.max_points()
+        )
+    }
+}
+
+/// Searches each of `positions` within `limits` with a fresh [`Searcher`], forgetting
+/// everything learned between positions like the [bench](super::bench) does.
+pub fn run(positions: &[TestPosition], limits: &SearchLimits) -> SuiteResult {
+    let mut searcher = Searcher::default();
+    let start = Instant::now();
+    let results = positions
+        .iter()
+        .map(|position| {
+            searcher.clear();
+            let best_move = searcher.search(&position.game, limits.clone()).best_move;
+            PositionResult {
+                id: position.id.clone(),
+                best_move,
+                solved: best_move.is_some_and(|m| position.is_solved_by(m)),
+                points: best_move.map_or(0, |m| position.points_for(m)),
+                max_points: position.max_points(),
+            }
+        })
+        .collect();
+    SuiteResult {
+        results,
+        time: start.elapsed(),
+    }
+}
+
+/// Splits EPD operations into their opcodes and operands, with the quotes around string
+/// operands removed. Semicolons inside quotes don't end an operation.
+fn operations(text: &str) -> Vec<(String, Vec<String>)> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                // An empty string operand still counts
+                if quoted {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                quoted = !quoted;
+            }
+            _ if quoted => token.push(c),
+            ';' | ' ' | '\t' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                if c == ';' && !tokens.is_empty() {
+                    let operands = tokens.split_off(1);
+                    operations.push((tokens.remove(0), operands));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_keep_quoted_strings_whole() {
+        let ops = operations(r#"bm Nf5 Qd2; id "a; b"; c0 "";  hmvc 0;"#);
+        let names: Vec<&str> = ops.iter().map(|(opcode, _)| opcode.as_str()).collect();
+        assert_eq!(names, ["bm", "id", "c0", "hmvc"]);
+        assert_eq!(ops[0].1, ["Nf5", "Qd2"]);
+        assert_eq!(ops[1].1, ["a; b"]);
+        assert_eq!(ops[2].1, [""]);
+        // An operation without the closing semicolon is incomplete
+        assert!(operations("bm e4").is_empty());
+    }
+
+    #[test]
+    fn parse_reports_bad_lines() {
+        let input = "# Win at Chess\n\n4k3/8/8/8/8/8/8/4K2R w K - bm O-O;\n8/8/8 w - - bm e4;\n";
+        assert!(matches!(parse(input), Err(Error::Line { line: 4, .. })));
+        let line = "4k3/8/8/8/8/8/8/4K2R w K - bm Ke8;";
+        assert!(matches!(
+            TestPosition::parse(line, "1"),
+            Err(Error::InvalidSan(_))
+        ));
+        let positions = parse(&input.replace("8/8/8 w - - bm e4;", "")).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].id, "3");
+    }
+
+    #[test]
+    fn scores_solved_positions() {
+        // A fork and a position scored like in the Strategic Test Suite
+        let input = r#"
+r3k3/8/8/3N4/8/8/8/4K3 w - - bm Nc7+; id "fork";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; c0 "Ra8#=10, Ra7=1"; id "sts";
+6k1/8/8/8/8/8/6PP/3r2K1 w - - am Kf2; id "avoid";
+"#;
+        let positions = parse(input).unwrap();
+        assert_eq!(positions[1].max_points(), 10);
+        let a1a7 = Move::from_uci("a1a7").unwrap();
+        assert!(!positions[1].is_solved_by(a1a7));
+        assert_eq!(positions[1].points_for(a1a7), 1);
+
+        let result = run(&positions, &SearchLimits::depth(4));
+        let solved: Vec<bool> = result.results.iter().map(|r| r.solved).collect();
+        // The only legal move is the one to avoid
+        assert_eq!(solved, [true, true, false]);
+        assert_eq!((result.points(), result.max_points()), (11, 12));
+        assert_eq!(result.to_string(), "solved 2/3, 11/12 points");
+    }
+}
@@ -0,0 +1,184 @@
+//! Searching on a background thread, so that protocol front ends like [`uci`](crate::uci)
+//! and [`cecp`](crate::cecp) keep reading commands while the engine thinks.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::mpsc;
+//! use kritisch::{game::Game, search::{thread::{SearchMode, SearchThread}, SearchLimits, Searcher}};
+//! let mut thread = SearchThread::new(Searcher::default());
+//! let (sender, receiver) = mpsc::channel();
+//! thread.start(&Game::default(), SearchLimits::default(), SearchMode::INFINITE, move |result| {
+//!     sender.send(result.best_move).unwrap();
+//! });
+//! let result = thread.stop().unwrap();
+//! assert_eq!(receiver.recv().unwrap(), result.best_move);
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{SearchLimits, SearchResult, Searcher};
+use crate::game::Game;
+
+/// When a search started by [`SearchThread::start`] reports its result.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum SearchMode {
+    /// As soon as it reaches its limits.
+    #[default]
+    LIMITED,
+    /// Only once stopped, even if it reached its limits before.
+    INFINITE,
+    /// Once stopped, or once it reaches its limits after [`SearchThread::ponderhit`], see
+    /// [`Searcher::ponder_flag`].
+    PONDER,
+}
+
+impl SearchMode {
+    /// Returns `true` if a search that reached its limits must wait to be stopped.
+    fn waits(self, ponder: &AtomicBool) -> bool {
+        match self {
+            SearchMode::LIMITED => false,
+            SearchMode::INFINITE => true,
+            SearchMode::PONDER => ponder.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A [`Searcher`] that searches on its own thread.
+pub struct SearchThread {
+    /// `None` while the searcher is busy on the search thread.
+    searcher: Option<Searcher>,
+    handle: Option<JoinHandle<(Searcher, SearchResult)>>,
+    mode: SearchMode,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+}
+
+impl SearchThread {
+    pub fn new(searcher: Searcher) -> Self {
+        Self {
+            stop: searcher.stop_flag(),
+            ponder: searcher.ponder_flag(),
+            searcher: Some(searcher),
+            handle: None,
+            mode: SearchMode::LIMITED,
+        }
+    }
+
+    /// Returns the searcher, stopping the running search first.
+    pub fn searcher(&mut self) -> &mut Searcher {
+        self.stop();
+        self.searcher.as_mut().expect("No search is running")
+    }
+
+    /// Stops the running search, if any, and starts searching `game`. Once the search is over
+    /// as decided by `mode`, `on_done` is called with the result on the search thread.
+    pub fn start(
+        &mut self,
+        game: &Game,
+        limits: SearchLimits,
+        mode: SearchMode,
+        on_done: impl FnOnce(&SearchResult) + Send + 'static,
+    ) {
+        self.stop();
+        let mut searcher = self.searcher.take().expect("No search is running");
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder
+            .store(mode == SearchMode::PONDER, Ordering::Relaxed);
+        self.mode = mode;
+        let game = *game;
+        let (stop, ponder) = (Arc::clone(&self.stop), Arc::clone(&self.ponder));
+        self.handle = Some(thread::spawn(move || {
+            let result = searcher.search(&game, limits);
+            while mode.waits(&ponder) && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            on_done(&result);
+            (searcher, result)
+        }));
+    }
+
+    /// Tells a pondering search that the expected move was played, so it goes on under its
+    /// time limits.
+    pub fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if a search is running that only ends when it is stopped: an infinite
+    /// one, or one that is still pondering.
+    pub fn waits_for_stop(&self) -> bool {
+        self.handle.is_some() && self.mode.waits(&self.ponder)
+    }
+
+    /// Returns `true` if a search was started and hasn't been waited for since.
+    pub fn is_searching(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Aborts the running search, if any, and returns its result once it is reported.
+    pub fn stop(&mut self) -> Option<SearchResult> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait()
+    }
+
+    /// Waits for the running search, if any, to end on its own and returns its result.
+    pub fn wait(&mut self) -> Option<SearchResult> {
+        let handle = self.handle.take()?;
+        let (searcher, result) = handle.join().expect("The search thread panicked");
+        self.searcher = Some(searcher);
+        Some(result)
+    }
+}
+
+impl Drop for SearchThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Helpers for the tests of the protocol front ends.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// An output that can be read while the protocol still owns it.
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedOutput {
+        /// Returns everything written so far.
+        pub(crate) fn read(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+
+        /// Forgets everything written so far.
+        pub(crate) fn clear(&self) {
+            self.0.lock().unwrap().clear();
+        }
+    }
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Feeds `input` to the front end's `run` and returns everything it answered.
+    pub(crate) fn run_script(
+        run: impl FnOnce(&[u8], SharedOutput) -> io::Result<()>,
+        input: &str,
+    ) -> String {
+        let output = SharedOutput::default();
+        run(input.as_bytes(), output.clone()).unwrap();
+        output.read()
+    }
+}
@@ -0,0 +1,162 @@
+//! Time management for games played on a clock, as given by the UCI `go` command's `wtime`,
+//! `btime`, `winc`, `binc` and `movestogo`.
+//!
+//! The [`TimeManager`] turns the clock into two deadlines. No new iteration is started after
+//! the soft one, which is pushed back while the best move keeps changing. The search is
+//! aborted at the hard one, even in the middle of an iteration.
+
+use std::time::Duration;
+
+use crate::Color;
+
+/// How many moves the remaining time is spread over when the clock doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// The most moves the remaining time is spread over, even if the next time control is
+/// further away.
+const MAX_MOVES_TO_GO: u32 = 50;
+/// Time kept in reserve for communication and other delays outside of the search.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/// How many times the soft deadline the hard deadline may be.
+const HARD_FACTOR: u32 = 4;
+/// How much of the remaining time a single move may take at most, in percent.
+const MAX_USAGE_PERCENT: u32 = 50;
+/// How much each recent change of the best move pushes back the soft deadline, in percent.
+const INSTABILITY_PERCENT: u32 = 50;
+
+/// The state of both players' clocks.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    /// The time left on the clocks, indexed by [`Color`].
+    pub time: [Duration; 2],
+    /// The increment per move, indexed by [`Color`].
+    pub increment: [Duration; 2],
+    /// The number of moves until the next time control, or `None` for sudden death.
+    pub moves_to_go: Option<u32>,
+}
+
+/// The deadlines of a search on the clock.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use kritisch::{search::time::{TimeControl, TimeManager}, Color};
+/// let clock = TimeControl {
+///     time: [Duration::from_secs(60), Duration::from_secs(10)],
+///     increment: [Duration::from_secs(1); 2],
+///     moves_to_go: None,
+/// };
+/// let white = TimeManager::new(&clock, Color::WHITE);
+/// let black = TimeManager::new(&clock, Color::BLACK);
+/// assert!(white.soft_limit() > black.soft_limit());
+/// assert!(white.soft_limit() < white.hard_limit());
+/// assert!(white.hard_limit() < Duration::from_secs(60));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimeManager {
+    soft: Duration,
+    hard: Duration,
+    /// Recent changes of the best move, in percent of a change, decaying by half with every
+    /// iteration.
+    instability: u32,
+}
+
+impl TimeManager {
+    /// Allots time to `color`'s next move.
+    pub fn new(clock: &TimeControl, color: Color) -> Self {
+        let time = clock.time[color as usize].saturating_sub(MOVE_OVERHEAD);
+        let increment = clock.increment[color as usize];
+        let moves_to_go = clock
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, MAX_MOVES_TO_GO);
+        let max = time * MAX_USAGE_PERCENT / 100;
+        let soft = (time / moves_to_go + increment * 3 / 4).min(max);
+        let hard = (soft * HARD_FACTOR).min(max);
+        Self {
+            soft,
+            hard,
+            instability: 0,
+        }
+    }
+
+    /// Returns how long the search may run before it must not start another iteration,
+    /// including the extension for an unstable best move.
+    pub fn soft_limit(&self) -> Duration {
+        (self.soft * (100 + self.instability) / 100).min(self.hard)
+    }
+
+    /// Returns how long the search may run at most.
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    /// Records whether the best move changed in the iteration that just completed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use kritisch::{search::time::{TimeControl, TimeManager}, Color};
+    /// let clock = TimeControl {
+    ///     time: [Duration::from_secs(60); 2],
+    ///     ..TimeControl::default()
+    /// };
+    /// let mut manager = TimeManager::new(&clock, Color::WHITE);
+    /// let stable = manager.soft_limit();
+    /// manager.update(true);
+    /// assert!(manager.soft_limit() > stable);
+    /// for _ in 0..10 {
+    ///     manager.update(false);
+    /// }
+    /// assert_eq!(manager.soft_limit(), stable);
+    /// ```
+    pub fn update(&mut self, best_move_changed: bool) {
+        self.instability /= 2;
+        if best_move_changed {
+            self.instability += INSTABILITY_PERCENT;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(seconds: u64, increment_ms: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl {
+            time: [Duration::from_secs(seconds); 2],
+            increment: [Duration::from_millis(increment_ms); 2],
+            moves_to_go,
+        }
+    }
+
+    #[test]
+    fn deadlines_stay_within_the_clock() {
+        for clock in [
+            clock(300, 0, None),
+            clock(1, 0, None),
+            clock(0, 0, None),
+            clock(2, 5000, None),
+            clock(10, 0, Some(1)),
+            clock(10, 0, Some(0)),
+        ] {
+            let manager = TimeManager::new(&clock, Color::WHITE);
+            assert!(manager.soft_limit() <= manager.hard_limit(), "{clock:?}");
+            assert!(
+                manager.hard_limit() < clock.time[0].max(MOVE_OVERHEAD),
+                "{clock:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn spends_more_with_fewer_moves_to_go() {
+        let sudden_death = TimeManager::new(&clock(60, 0, None), Color::WHITE);
+        let next_control = TimeManager::new(&clock(60, 0, Some(5)), Color::WHITE);
+        let with_increment = TimeManager::new(&clock(60, 2000, None), Color::WHITE);
+        assert!(next_control.soft_limit() > sudden_death.soft_limit());
+        assert!(with_increment.soft_limit() > sudden_death.soft_limit());
+        assert_eq!(sudden_death.soft_limit(), Duration::from_millis(59970) / 30);
+    }
+}
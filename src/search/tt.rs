@@ -0,0 +1,359 @@
+//! The transposition table: a fixed-size hash table of search results indexed by the
+//! [`Game::key`](crate::game::Game::key) of a position. Reaching a position again through a
+//! different move order, or in the next iteration, reuses what was found before.
+
+use super::{MATE, MAX_PLY};
+use crate::Move;
+
+/// The size of a new [`TranspositionTable`] in megabytes.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// How a stored score relates to the true score of the position.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Bound {
+    /// The score is exact.
+    EXACT,
+    /// The search failed high, the true score is at least this.
+    LOWER,
+    /// The search failed low, the true score is at most this.
+    UPPER,
+}
+
+/// The result of searching one position.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Entry {
+    /// The full key, to tell positions apart that share a slot.
+    pub key: u64,
+    /// The best move found, or `None` if the search failed low.
+    pub best_move: Option<Move>,
+    /// The score from the point of view of the side to move. Mate scores count the plies
+    /// from this position, not from the root.
+    pub score: i32,
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+/// How [`TranspositionTable::store`] makes room for a new entry.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Replacement {
+    /// Every position has one slot, and a new entry always takes it.
+    #[default]
+    ALWAYS,
+    /// Every position has one slot, and a new entry only takes it from an entry searched no
+    /// deeper, or from one left by an earlier search.
+    DEPTH_PREFERRED,
+    /// Every position has a bucket of two slots: a depth-preferred one, and one that takes
+    /// whatever the first one turns down or gives up.
+    TWO_TIER,
+}
+
+/// An entry together with the search that stored it.
+#[derive(Debug, Copy, Clone)]
+struct Slot {
+    entry: Entry,
+    generation: u8,
+}
+
+/// A transposition table with a configurable [`Replacement`] scheme.
+///
+/// Entries are aged by search: [`new_search`](Self::new_search) starts a new generation, and
+/// entries of older ones give way to new entries first and no longer count towards
+/// [`hashfull`](Self::hashfull). They can still be probed until they are replaced.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Slot>>,
+    replacement: Replacement,
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Creates an empty table that takes up about `megabytes` of memory, holding at least two
+    /// entries.
+    pub fn new(megabytes: usize) -> Self {
+        Self {
+            slots: vec![None; Self::len(megabytes)],
+            replacement: Replacement::default(),
+            generation: 0,
+        }
+    }
+
+    /// Makes the table take up about `megabytes` of memory, dropping all entries.
+    pub fn resize(&mut self, megabytes: usize) {
+        self.slots = vec![None; Self::len(megabytes)];
+    }
+
+    fn len(megabytes: usize) -> usize {
+        (megabytes * 1024 * 1024 / std::mem::size_of::<Option<Slot>>()).max(2)
+    }
+
+    /// Returns the number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn replacement(&self) -> Replacement {
+        self.replacement
+    }
+
+    /// Switches to the `replacement` scheme. The schemes lay out the table differently, so
+    /// this drops all entries.
+    pub fn set_replacement(&mut self, replacement: Replacement) {
+        self.replacement = replacement;
+        self.clear();
+    }
+
+    /// Starts a new generation of entries, at the start of every search.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Returns the entry stored for `key`, if it is still there.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{game::Game, search::tt::*, Move};
+    /// let mut tt = TranspositionTable::new(1);
+    /// let key = Game::default().key();
+    /// let entry = Entry {
+    ///     key,
+    ///     best_move: Some(Move::from_uci("e2e4").unwrap()),
+    ///     score: 30,
+    ///     depth: 5,
+    ///     bound: Bound::EXACT,
+    /// };
+    /// tt.store(entry);
+    /// assert_eq!(tt.probe(key), Some(entry));
+    /// assert_eq!(tt.probe(key ^ 1), None);
+    /// ```
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.slots[self.bucket(key)]
+            .iter()
+            .flatten()
+            .map(|slot| slot.entry)
+            .find(|entry| entry.key == key)
+    }
+
+    /// Asks the CPU to load the slots of `key` into its cache, so that probing or storing the
+    /// entry soon after doesn't wait for memory. Searchers call it with
+    /// [`Game::key_after`](crate::game::Game::key_after) before making a move. It does
+    /// nothing on targets without a prefetch instruction.
+    #[inline]
+    pub fn prefetch(&self, key: u64) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            let slot: *const Option<Slot> = &self.slots[self.bucket(key).start];
+            // SAFETY: prefetching only hints at memory to load and never faults
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(slot.cast()) };
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = key;
+    }
+
+    /// Stores `entry` if the [`Replacement`] scheme makes room for it. An entry of the same
+    /// position is always replaced, and if `entry` has no best move, the old one is kept for
+    /// move ordering.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::search::tt::*;
+    /// let mut tt = TranspositionTable::new(1);
+    /// tt.set_replacement(Replacement::DEPTH_PREFERRED);
+    /// let deep = Entry { key: 1, best_move: None, score: 0, depth: 8, bound: Bound::EXACT };
+    /// tt.store(deep);
+    /// // Keys 1 and 2 share a slot
+    /// tt.store(Entry { key: 2, depth: 3, ..deep });
+    /// assert_eq!(tt.probe(1), Some(deep));
+    /// tt.new_search();
+    /// tt.store(Entry { key: 2, depth: 3, ..deep });
+    /// assert_eq!(tt.probe(1), None);
+    /// ```
+    pub fn store(&mut self, mut entry: Entry) {
+        let generation = self.generation;
+        let range = self.bucket(entry.key);
+        let bucket = &mut self.slots[range];
+        if let Some(slot) = bucket
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|s| s.entry.key == entry.key))
+        {
+            if entry.best_move.is_none() {
+                entry.best_move = slot.map(|s| s.entry).and_then(|e| e.best_move);
+            }
+            *slot = Some(Slot { entry, generation });
+            return;
+        }
+
+        let new = Some(Slot { entry, generation });
+        let current = |slot: &Option<Slot>| slot.is_some_and(|s| s.generation == generation);
+        let replaceable =
+            !current(&bucket[0]) || bucket[0].is_some_and(|s| entry.depth >= s.entry.depth);
+        match self.replacement {
+            Replacement::ALWAYS => bucket[0] = new,
+            Replacement::DEPTH_PREFERRED => {
+                if replaceable {
+                    bucket[0] = new;
+                }
+            }
+            Replacement::TWO_TIER => {
+                if !replaceable {
+                    bucket[1] = new;
+                } else {
+                    // An entry of this search that loses its slot is still worth keeping
+                    if current(&bucket[0]) {
+                        bucket[1] = bucket[0];
+                    }
+                    bucket[0] = new;
+                }
+            }
+        }
+    }
+
+    /// Drops all entries.
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.generation = 0;
+    }
+
+    /// Returns how full the table is in permille, estimated from how many of its first entries
+    /// were stored by the current search.
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|slot| slot.is_some_and(|s| s.generation == self.generation))
+            .count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    /// Returns the range of slots `key` may be stored in.
+    fn bucket(&self, key: u64) -> std::ops::Range<usize> {
+        let size = match self.replacement {
+            Replacement::ALWAYS | Replacement::DEPTH_PREFERRED => 1,
+            Replacement::TWO_TIER => 2,
+        };
+        let buckets = self.slots.len() / size;
+        // Maps the key onto the table without a division
+        let start = ((key as u128 * buckets as u128) >> 64) as usize * size;
+        start..start + size
+    }
+}
+
+/// Converts a score relative to the root into one relative to the position at `ply`, so that
+/// mate scores stay right when the position is reached at a different ply.
+pub(crate) fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// The inverse of [`score_to_tt`].
+pub(crate) fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_scores_are_relative_to_the_node() {
+        // Mate in 3 plies from a node at ply 4 is mate in 7 plies from the root
+        let stored = score_to_tt(MATE - 7, 4);
+        assert_eq!(stored, MATE - 3);
+        assert_eq!(score_from_tt(stored, 2), MATE - 5);
+        assert_eq!(score_from_tt(score_to_tt(-MATE + 6, 6), 6), -MATE + 6);
+        assert_eq!(score_to_tt(120, 10), 120);
+    }
+
+    #[test]
+    fn keeps_the_best_move_of_a_fail_low() {
+        let mut tt = TranspositionTable::new(1);
+        let best_move = Some(Move::from_uci("d2d4").unwrap());
+        let entry = Entry {
+            key: 42,
+            best_move,
+            score: 10,
+            depth: 3,
+            bound: Bound::LOWER,
+        };
+        tt.store(entry);
+        // One of the first thousand entries is used
+        assert_eq!(tt.hashfull(), 1);
+        tt.store(Entry {
+            best_move: None,
+            depth: 4,
+            bound: Bound::UPPER,
+            ..entry
+        });
+        let stored = tt.probe(42).unwrap();
+        assert_eq!((stored.best_move, stored.depth), (best_move, 4));
+        tt.clear();
+        assert_eq!(tt.probe(42), None);
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn keeps_both_tiers() {
+        let mut tt = TranspositionTable::new(1);
+        tt.set_replacement(Replacement::TWO_TIER);
+        // Keys 1 to 3 share a bucket
+        let entry = |key: u64, depth: u32| Entry {
+            key,
+            best_move: None,
+            score: 0,
+            depth,
+            bound: Bound::EXACT,
+        };
+        tt.store(entry(1, 8));
+        tt.store(entry(2, 3));
+        assert!(tt.probe(1).is_some() && tt.probe(2).is_some());
+        // The always-replace tier gives way, the deep entry stays
+        tt.store(entry(3, 2));
+        assert_eq!(tt.probe(2), None);
+        assert!(tt.probe(1).is_some() && tt.probe(3).is_some());
+        // A deeper entry takes the first tier and moves the old one to the second
+        tt.store(entry(2, 9));
+        assert_eq!(
+            (tt.probe(1), tt.probe(2)),
+            (Some(entry(1, 8)), Some(entry(2, 9)))
+        );
+        assert_eq!(tt.probe(3), None);
+    }
+
+    #[test]
+    fn ages_entries() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(Entry {
+            key: 42,
+            best_move: None,
+            score: 0,
+            depth: 1,
+            bound: Bound::EXACT,
+        });
+        assert_eq!(tt.hashfull(), 1);
+        tt.new_search();
+        // Still there, but no longer counted
+        assert!(tt.probe(42).is_some());
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
@@ -0,0 +1,118 @@
+//! AVX2 versions of set-wise attack computations, filling four directions at once in the four
+//! 64-bit lanes of a 256-bit register.
+
+use std::arch::x86_64::*;
+use std::sync::OnceLock;
+
+use crate::movegen::SLIDING_DIRECTIONS;
+
+/// Returns `true` if the CPU supports AVX2. The check is only done once.
+#[inline]
+pub(crate) fn has_avx2() -> bool {
+    static AVX2: OnceLock<bool> = OnceLock::new();
+    *AVX2.get_or_init(|| is_x86_feature_detected!("avx2"))
+}
+
+/// AVX2 version of [`scalar_sliding_attacks`](crate::movegen::scalar_sliding_attacks).
+///
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn sliding_attacks(rooks: u64, bishops: u64, occupied: u64) -> u64 {
+    let [north, east, north_east, north_west] = SLIDING_DIRECTIONS;
+    let shift = _mm256_set_epi64x(
+        north_west.0 as i64,
+        north_east.0 as i64,
+        east.0 as i64,
+        north.0 as i64,
+    );
+    let left_mask = _mm256_set_epi64x(
+        north_west.1 as i64,
+        north_east.1 as i64,
+        east.1 as i64,
+        north.1 as i64,
+    );
+    let right_mask = _mm256_set_epi64x(
+        north_west.2 as i64,
+        north_east.2 as i64,
+        east.2 as i64,
+        north.2 as i64,
+    );
+    let sliders = _mm256_set_epi64x(bishops as i64, bishops as i64, rooks as i64, rooks as i64);
+    let empty = _mm256_set1_epi64x(!occupied as i64);
+
+    let shift2 = _mm256_add_epi64(shift, shift);
+    let shift4 = _mm256_add_epi64(shift2, shift2);
+
+    // Towards higher squares
+    let mut generator = sliders;
+    let mut propagator = _mm256_and_si256(empty, left_mask);
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_sllv_epi64(generator, shift)),
+    );
+    propagator = _mm256_and_si256(propagator, _mm256_sllv_epi64(propagator, shift));
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_sllv_epi64(generator, shift2)),
+    );
+    propagator = _mm256_and_si256(propagator, _mm256_sllv_epi64(propagator, shift2));
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_sllv_epi64(generator, shift4)),
+    );
+    let up = _mm256_and_si256(_mm256_sllv_epi64(generator, shift), left_mask);
+
+    // Towards lower squares
+    let mut generator = sliders;
+    let mut propagator = _mm256_and_si256(empty, right_mask);
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_srlv_epi64(generator, shift)),
+    );
+    propagator = _mm256_and_si256(propagator, _mm256_srlv_epi64(propagator, shift));
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_srlv_epi64(generator, shift2)),
+    );
+    propagator = _mm256_and_si256(propagator, _mm256_srlv_epi64(propagator, shift2));
+    generator = _mm256_or_si256(
+        generator,
+        _mm256_and_si256(propagator, _mm256_srlv_epi64(generator, shift4)),
+    );
+    let down = _mm256_and_si256(_mm256_srlv_epi64(generator, shift), right_mask);
+
+    let mut lanes = [0u64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr().cast(), _mm256_or_si256(up, down));
+    lanes[0] | lanes[1] | lanes[2] | lanes[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::scalar_sliding_attacks;
+
+    #[test]
+    fn matches_scalar() {
+        if !has_avx2() {
+            return;
+        }
+        // A simple xorshift generator for reproducible positions
+        let mut state = 0x853c49e6748fea9bu64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..10000 {
+            let occupied = next() & next();
+            let rooks = occupied & next() & next();
+            let bishops = occupied & next() & next();
+            // SAFETY: AVX2 support was detected above
+            let simd = unsafe { sliding_attacks(rooks, bishops, occupied) };
+            assert_eq!(simd, scalar_sliding_attacks(rooks, bishops, occupied));
+        }
+    }
+}
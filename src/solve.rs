@@ -0,0 +1,247 @@
+//! Exhaustive solving of "mate in N" problems, e.g. to validate puzzles before publishing
+//! them.
+//!
+//! [`solve`] proves by trying every legal move, not by searching with an evaluation, which
+//! first moves force mate in the given number of moves against every defense. A sound problem
+//! has exactly one such move, the key. Further keys and mates in fewer moves are cooks, which
+//! break the problem, while a dual, more than one way to go on after the key and a defense,
+//! only makes it less elegant.
+//!
+//! Mate means a win by the rules of the game's [variant](crate::variant), so in Antichess or
+//! Atomic the solver finds forced wins just the same. The work grows with the number of moves
+//! to the power of N, so anything beyond mate in three takes long.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, solve::solve, Move};
+//! // The rook mates on the back rank
+//! let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+//! let solution = solve(&game, 1);
+//! assert_eq!(solution.keys, [Move::from_uci("a1a8").unwrap()]);
+//! assert!(solution.is_sound());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    game::{Game, Outcome},
+    movegen::all_legal_moves,
+    variant::Variant,
+    Color, Move,
+};
+
+/// What [`solve`] found out about a "mate in N" problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// The number of moves the problem asks to mate in.
+    pub moves: u32,
+    /// Every first move that forces mate in [`moves`](Self::moves), in the order of the legal
+    /// moves. A sound problem has one, the key, and any other is a cook.
+    pub keys: Vec<Move>,
+    /// The fewest moves that force mate, if fewer than [`moves`](Self::moves), which cooks
+    /// the problem as well.
+    pub shorter: Option<u32>,
+    /// The defenses against the first key after which more than one move goes on to mate in
+    /// time.
+    pub duals: Vec<Dual>,
+}
+
+impl Solution {
+    /// Returns `true` if the problem has a single key and no shorter mate. Duals don't make a
+    /// problem unsound.
+    pub fn is_sound(&self) -> bool {
+        self.keys.len() == 1 && self.shorter.is_none()
+    }
+}
+
+/// A defense after the key that allows more than one continuation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dual {
+    /// The defender's reply to the key.
+    pub defense: Move,
+    /// Every move after the defense that still mates in time.
+    pub continuations: Vec<Move>,
+}
+
+/// Solves the problem of the side to move in `game` mating in `moves` moves, finding every key,
+/// the shortest mate and the duals after the first key.
+///
+/// # Panics
+///
+/// Panics if `moves` is zero.
+pub fn solve(game: &Game, moves: u32) -> Solution {
+    assert!(moves > 0, "a problem needs at least one move to mate in");
+    let mut solver = Solver::default();
+    let attacker = game.to_move;
+    let keys: Vec<Move> = all_legal_moves(game)
+        .into_iter()
+        .filter(|&m| solver.wins_after(&after(game, m), attacker, moves))
+        .collect();
+    let shorter = (1..moves).find(|&n| solver.wins(game, n));
+
+    let mut duals = Vec::new();
+    if let Some(&key) = keys.first().filter(|_| moves > 1) {
+        let position = after(game, key);
+        for defense in all_legal_moves(&position) {
+            let defended = after(&position, defense);
+            let continuations: Vec<Move> = all_legal_moves(&defended)
+                .into_iter()
+                .filter(|&m| solver.wins_after(&after(&defended, m), attacker, moves - 1))
+                .collect();
+            if continuations.len() > 1 {
+                duals.push(Dual {
+                    defense,
+                    continuations,
+                });
+            }
+        }
+    }
+
+    Solution {
+        moves,
+        keys,
+        shorter,
+        duals,
+    }
+}
+
+/// Returns `true` if the side to move in `game` can force mate in at most `moves` moves.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{game::Game, solve::forces_mate};
+/// let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+/// assert!(forces_mate(&game, 1));
+/// assert!(!forces_mate(&Game::default(), 2));
+/// ```
+pub fn forces_mate(game: &Game, moves: u32) -> bool {
+    moves > 0 && Solver::default().wins(game, moves)
+}
+
+/// Remembers which positions have been proven won or not, since defenses transpose often.
+#[derive(Debug, Default)]
+struct Solver {
+    proven: HashMap<(u64, u32), bool>,
+}
+
+impl Solver {
+    /// Returns `true` if the side to move in `game` mates in at most `moves` moves.
+    fn wins(&mut self, game: &Game, moves: u32) -> bool {
+        if let Some(&won) = self.proven.get(&(game.key(), moves)) {
+            return won;
+        }
+        let attacker = game.to_move;
+        let won = all_legal_moves(game).into_iter().any(|m| {
+            let position = after(game, m);
+            // In standard chess only a check can mate, which saves looking at the other moves
+            // on the last move
+            if moves == 1 && game.variant == Variant::STANDARD && !position.is_check() {
+                return false;
+            }
+            self.wins_after(&position, attacker, moves)
+        });
+        self.proven.insert((game.key(), moves), won);
+        won
+    }
+
+    /// Returns `true` if `attacker`, who has just moved to `position`, has won or wins against
+    /// every defense with the `moves` moves it had left, this one included.
+    fn wins_after(&mut self, position: &Game, attacker: Color, moves: u32) -> bool {
+        match position.outcome() {
+            Some(Outcome::DECISIVE { winner }) => return winner == attacker,
+            Some(Outcome::DRAW) => return false,
+            None if moves == 1 => return false,
+            None => {}
+        }
+        all_legal_moves(position).into_iter().all(|defense| {
+            let defended = after(position, defense);
+            match defended.outcome() {
+                Some(Outcome::DECISIVE { winner }) => winner == attacker,
+                Some(Outcome::DRAW) => false,
+                None => self.wins(&defended, moves - 1),
+            }
+        })
+    }
+}
+
+/// Returns the position after `m`.
+fn after(game: &Game, m: Move) -> Game {
+    let mut position = *game;
+    position.make_move(m);
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(uci: &[&str]) -> Vec<Move> {
+        uci.iter().map(|m| Move::from_uci(m).unwrap()).collect()
+    }
+
+    #[test]
+    fn finds_a_quiet_key() {
+        // The king steps aside for Rh1 mate
+        let game = Game::from_fen("7k/8/5K2/8/8/8/8/6R1 w - - 0 1").unwrap();
+        assert!(!forces_mate(&game, 1));
+        assert!(forces_mate(&game, 2));
+        let solution = solve(&game, 2);
+        assert_eq!(solution.keys, moves(&["f6f7"]));
+        assert!(solution.is_sound());
+        assert!(solution.duals.is_empty());
+    }
+
+    #[test]
+    fn finds_cooks() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1").unwrap();
+        let solution = solve(&game, 1);
+        assert_eq!(solution.keys, moves(&["a1a8", "b1b8"]));
+        assert!(!solution.is_sound());
+
+        // Each move of the rook along the first rank and the king both lead to mate
+        let game = Game::from_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert_eq!(solve(&game, 2).keys, moves(&["f6g6", "f6f7"]));
+
+        // Mating at once is also a mate in two, but cooks the problem
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let solution = solve(&game, 2);
+        assert_eq!(solution.keys, moves(&["a1a8"]));
+        assert_eq!(solution.shorter, Some(1));
+        assert!(!solution.is_sound());
+    }
+
+    #[test]
+    fn finds_duals() {
+        let game = Game::from_fen("7k/8/8/5K2/8/8/8/Q7 w - - 0 1").unwrap();
+        let solution = solve(&game, 2);
+        assert!(solution.is_sound());
+        assert_eq!(
+            solution.duals,
+            [Dual {
+                defense: Move::from_uci("h8g8").unwrap(),
+                continuations: moves(&["a1g7", "a1a8"]),
+            }]
+        );
+    }
+
+    #[test]
+    fn stalemate_is_no_mate() {
+        // Qg6 stalemates, so it can't be a key
+        let game = Game::from_fen("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        assert!(!solve(&game, 2)
+            .keys
+            .contains(&Move::from_uci("g1g6").unwrap()));
+        assert!(!forces_mate(&Game::default(), 2));
+    }
+
+    #[test]
+    fn wins_by_the_rules_of_the_variant() {
+        // In King of the Hill reaching the center of the board wins
+        let mut game = Game::from_fen("k7/8/8/8/8/2K5/8/8 w - - 0 1").unwrap();
+        assert!(!forces_mate(&game, 1));
+        game.variant = Variant::KING_OF_THE_HILL;
+        assert_eq!(solve(&game, 1).keys, moves(&["c3d4"]));
+    }
+}
@@ -0,0 +1,197 @@
+//! Counters for diagnosing move generation performance without a profiler, enabled by the
+//! `stats` feature. Without the feature, the counting compiles to nothing.
+//!
+//! The counters are global and shared by all threads, so a [`measure`] while other threads
+//! generate moves includes their work too.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, movegen::perft, stats};
+//! let (nodes, stats) = stats::measure(|| perft(&Game::default(), 3));
+//! assert_eq!(nodes, 8902);
+//! assert_eq!(stats.moves_made, 20 + 400);
+//! println!("{stats}");
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of all counters.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Calls to [`all_legal_moves`](crate::movegen::all_legal_moves).
+    pub move_generations: u64,
+    /// Pseudo-legal moves generated before the legality filter.
+    pub moves_generated: u64,
+    /// Pseudo-legal moves the legality filter removed because they left the king in check.
+    pub legality_rejections: u64,
+    /// Calls to [`Game::make_move`](crate::game::Game::make_move), i.e. nodes visited by a
+    /// tree walk.
+    pub moves_made: u64,
+    /// Rook and bishop attack lookups, whichever of the magic, `pext` or kindergarten tables
+    /// serves them.
+    pub slider_lookups: u64,
+    /// Heap allocations, only counted when [`CountingAllocator`] is the global allocator.
+    pub allocations: u64,
+    /// Bytes allocated on the heap, only counted when [`CountingAllocator`] is the global
+    /// allocator.
+    pub allocated_bytes: u64,
+}
+
+/// Subtracts counter by counter, saturating at zero in case of a [`reset`] in between.
+impl std::ops::Sub for Stats {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            move_generations: self.move_generations.saturating_sub(rhs.move_generations),
+            moves_generated: self.moves_generated.saturating_sub(rhs.moves_generated),
+            legality_rejections: self
+                .legality_rejections
+                .saturating_sub(rhs.legality_rejections),
+            moves_made: self.moves_made.saturating_sub(rhs.moves_made),
+            slider_lookups: self.slider_lookups.saturating_sub(rhs.slider_lookups),
+            allocations: self.allocations.saturating_sub(rhs.allocations),
+            allocated_bytes: self.allocated_bytes.saturating_sub(rhs.allocated_bytes),
+        }
+    }
+}
+
+/// Prints one counter per line.
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "move generations     {}", self.move_generations)?;
+        writeln!(f, "moves generated      {}", self.moves_generated)?;
+        writeln!(f, "legality rejections  {}", self.legality_rejections)?;
+        writeln!(f, "moves made           {}", self.moves_made)?;
+        writeln!(f, "slider lookups       {}", self.slider_lookups)?;
+        writeln!(f, "allocations          {}", self.allocations)?;
+        write!(f, "allocated bytes      {}", self.allocated_bytes)
+    }
+}
+
+pub(crate) struct Counters {
+    pub(crate) move_generations: AtomicU64,
+    pub(crate) moves_generated: AtomicU64,
+    pub(crate) legality_rejections: AtomicU64,
+    pub(crate) moves_made: AtomicU64,
+    pub(crate) slider_lookups: AtomicU64,
+    pub(crate) allocations: AtomicU64,
+    pub(crate) allocated_bytes: AtomicU64,
+}
+
+pub(crate) static COUNTERS: Counters = Counters {
+    move_generations: AtomicU64::new(0),
+    moves_generated: AtomicU64::new(0),
+    legality_rejections: AtomicU64::new(0),
+    moves_made: AtomicU64::new(0),
+    slider_lookups: AtomicU64::new(0),
+    allocations: AtomicU64::new(0),
+    allocated_bytes: AtomicU64::new(0),
+};
+
+impl Counters {
+    fn all(&self) -> [&AtomicU64; 7] {
+        [
+            &self.move_generations,
+            &self.moves_generated,
+            &self.legality_rejections,
+            &self.moves_made,
+            &self.slider_lookups,
+            &self.allocations,
+            &self.allocated_bytes,
+        ]
+    }
+}
+
+/// Returns the current value of all counters.
+pub fn snapshot() -> Stats {
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    Stats {
+        move_generations: load(&COUNTERS.move_generations),
+        moves_generated: load(&COUNTERS.moves_generated),
+        legality_rejections: load(&COUNTERS.legality_rejections),
+        moves_made: load(&COUNTERS.moves_made),
+        slider_lookups: load(&COUNTERS.slider_lookups),
+        allocations: load(&COUNTERS.allocations),
+        allocated_bytes: load(&COUNTERS.allocated_bytes),
+    }
+}
+
+/// Sets all counters to zero.
+pub fn reset() {
+    for counter in COUNTERS.all() {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Runs `f` and returns its result together with how much the counters grew meanwhile.
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, Stats) {
+    let before = snapshot();
+    let result = f();
+    (result, snapshot() - before)
+}
+
+/// A global allocator that counts allocations into [`Stats::allocations`] and
+/// [`Stats::allocated_bytes`] and forwards them to the wrapped allocator.
+///
+/// # Example
+///
+/// ```no_run
+/// use kritisch::stats::CountingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::system();
+/// ```
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A = System>(pub A);
+
+impl CountingAllocator {
+    /// Wraps the system allocator.
+    pub const fn system() -> Self {
+        Self(System)
+    }
+}
+
+// SAFETY: all calls are forwarded to the wrapped allocator unchanged
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count!(allocations);
+        count!(allocated_bytes, layout.size());
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count!(allocations);
+        count!(allocated_bytes, layout.size());
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count!(allocations);
+        count!(allocated_bytes, new_size.saturating_sub(layout.size()));
+        self.0.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::Game, movegen::all_legal_moves};
+
+    #[test]
+    fn counts_rejected_moves() {
+        // The pinned knight on e2 has no legal moves
+        let game = Game::from_fen("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        let (moves, stats) = measure(|| all_legal_moves(&game));
+        // Other tests run in parallel, so the counters may have grown by more
+        assert!(stats.move_generations >= 1);
+        assert!(stats.legality_rejections >= 6);
+        assert!(stats.moves_generated >= moves.len() as u64 + 6);
+    }
+}
@@ -0,0 +1,274 @@
+//! Detection of basic tactical motifs, e.g. for tagging puzzles or pointing them out to a
+//! learner.
+//!
+//! [`motifs`] finds the forks, pins and skewers one side has on the board, and [`move_motifs`]
+//! those a move sets up, discovered attacks included. A piece only counts as the target of a
+//! motif if attacking it threatens something, i.e. it is the king, worth more than its attacker
+//! or not defended. Like [`Game::see`] this goes by the attack maps alone and doesn't check
+//! whether the motif actually wins material.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{bitboard::Bitboard, game::Game, tactics::{motifs, Motif}, Color, Square};
+//! // The knight on c7 attacks the king and the rook
+//! let game = Game::from_fen("r3k3/2N5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+//! assert_eq!(
+//!     motifs(&game, Color::WHITE),
+//!     [Motif::FORK {
+//!         attacker: Square::C7,
+//!         targets: Bitboard::from_squares([Square::A8, Square::E8]),
+//!     }]
+//! );
+//! ```
+
+use crate::{
+    bitboard::Bitboard,
+    game::Game,
+    movegen::{bishop_attacks, rook_attacks},
+    Color, Move, Piece, Square,
+};
+
+/// A tactical motif, naming the squares of the pieces involved.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Motif {
+    /// A piece attacks two or more opposing pieces at once.
+    FORK { attacker: Square, targets: Bitboard },
+    /// A bishop, rook or queen attacks a piece that can't move away without exposing a more
+    /// valuable one behind it. The pin is absolute if the piece behind is the king, so the
+    /// pinned piece may not leave the line at all.
+    PIN {
+        attacker: Square,
+        pinned: Square,
+        behind: Square,
+        absolute: bool,
+    },
+    /// A bishop, rook or queen attacks a valuable piece, which has to move away and leave the
+    /// one behind it to be taken.
+    SKEWER {
+        attacker: Square,
+        front: Square,
+        behind: Square,
+    },
+    /// Moving a piece out of the way of a bishop, rook or queen lets it attack `targets`.
+    /// `moved` is the square the piece moved to.
+    DISCOVERED_ATTACK {
+        attacker: Square,
+        moved: Square,
+        targets: Bitboard,
+    },
+}
+
+/// Returns the forks, pins and skewers of `color`'s pieces against the opponent's, in the order
+/// of the attacking pieces' squares.
+pub fn motifs(game: &Game, color: Color) -> Vec<Motif> {
+    let enemies = game.color_bitboards[!color as usize];
+    let occupied = game.blockers();
+    let mut motifs = Vec::new();
+    for attacker in game.color_bitboards[color as usize].squares() {
+        let targets = Bitboard::from_squares(
+            (game.attacks_from(attacker) & enemies)
+                .squares()
+                .filter(|&target| threatens(game, attacker, target)),
+        );
+        if targets.count_ones() > 1 {
+            motifs.push(Motif::FORK { attacker, targets });
+        }
+
+        let piece = game.type_at(attacker);
+        let seen = line_attacks(piece, attacker, occupied);
+        for front in (seen & enemies).squares() {
+            // Taking away the front piece only lengthens the line it stands on
+            let xray = line_attacks(piece, attacker, occupied ^ front) & !seen;
+            let Some(behind) = (xray & enemies).squares().next() else {
+                continue;
+            };
+            let [front_piece, behind_piece] = [front, behind].map(|s| game.type_at(s));
+            let motif = if behind_piece == Piece::KING {
+                Motif::PIN {
+                    attacker,
+                    pinned: front,
+                    behind,
+                    absolute: true,
+                }
+            } else if !threatens(game, attacker, behind) {
+                continue;
+            } else if front_piece == Piece::KING || front_piece.value() > behind_piece.value() {
+                Motif::SKEWER {
+                    attacker,
+                    front,
+                    behind,
+                }
+            } else if behind_piece.value() > front_piece.value() {
+                Motif::PIN {
+                    attacker,
+                    pinned: front,
+                    behind,
+                    absolute: false,
+                }
+            } else {
+                continue;
+            };
+            motifs.push(motif);
+        }
+    }
+    motifs
+}
+
+/// Returns the motifs the side to move in `game` sets up by playing `m`: the forks, pins and
+/// skewers it has afterwards but didn't have before, and the discovered attacks of its pieces
+/// that didn't move.
+///
+/// # Example
+///
+/// ```
+/// use kritisch::{bitboard::Bitboard, game::Game, tactics::{move_motifs, Motif}, Move, Square};
+/// // Moving the knight away lets the rook attack the queen
+/// let game = Game::from_fen("6k1/4q3/8/8/4N3/8/8/4R1K1 w - - 0 1").unwrap();
+/// assert_eq!(
+///     move_motifs(&game, Move::from_uci("e4c5").unwrap()),
+///     [Motif::DISCOVERED_ATTACK {
+///         attacker: Square::E1,
+///         moved: Square::C5,
+///         targets: Bitboard::from_square(Square::E7),
+///     }]
+/// );
+/// ```
+pub fn move_motifs(game: &Game, m: Move) -> Vec<Motif> {
+    let color = game.to_move;
+    let mut after = *game;
+    after.make_move(m);
+    let before = motifs(game, color);
+    let mut found: Vec<Motif> = motifs(&after, color)
+        .into_iter()
+        .filter(|motif| !before.contains(motif))
+        .collect();
+
+    let enemies = after.color_bitboards[!color as usize];
+    for attacker in after.color_bitboards[color as usize].squares() {
+        if game.piece_at(attacker) != after.piece_at(attacker) {
+            continue;
+        }
+        let uncovered = after.attacks_from(attacker) & enemies & !game.attacks_from(attacker);
+        let targets = Bitboard::from_squares(
+            uncovered
+                .squares()
+                .filter(|&target| threatens(&after, attacker, target)),
+        );
+        if !targets.is_empty() {
+            found.push(Motif::DISCOVERED_ATTACK {
+                attacker,
+                moved: m.end,
+                targets,
+            });
+        }
+    }
+    found
+}
+
+/// Returns `true` if the piece on `attacker` threatens the one on `target`, i.e. the target is
+/// the king, worth more than the attacker or undefended. The king only threatens undefended
+/// pieces.
+fn threatens(game: &Game, attacker: Square, target: Square) -> bool {
+    let piece = game.type_at(attacker);
+    let victim = game.type_at(target);
+    let defended = game.is_attacked_by(game.color_at(target), target);
+    victim == Piece::KING || !defended || (piece != Piece::KING && victim.value() > piece.value())
+}
+
+/// Returns the squares a `piece` on `s` attacks along lines, or none if it isn't a slider.
+fn line_attacks(piece: Piece, s: Square, occupied: Bitboard) -> Bitboard {
+    match piece {
+        Piece::BISHOP => bishop_attacks(s, occupied),
+        Piece::ROOK => rook_attacks(s, occupied),
+        Piece::QUEEN => bishop_attacks(s, occupied) | rook_attacks(s, occupied),
+        _ => Bitboard::empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(fen: &str) -> Vec<Motif> {
+        motifs(&Game::from_fen(fen).unwrap(), Color::WHITE)
+    }
+
+    #[test]
+    fn finds_forks_of_valuable_or_undefended_pieces() {
+        // Defended knights are worth less than the bishop, defended rooks more
+        assert!(found("4k3/1p3p2/n3n3/8/2B5/8/8/4K3 b - - 0 1").is_empty());
+        assert_eq!(
+            found("4k3/1p3p2/r3r3/8/2B5/8/8/4K3 b - - 0 1"),
+            [Motif::FORK {
+                attacker: Square::C4,
+                targets: Bitboard::from_squares([Square::A6, Square::E6]),
+            }]
+        );
+        assert_eq!(
+            found("4k3/8/8/1n1r4/2P5/8/8/4K3 b - - 0 1"),
+            [Motif::FORK {
+                attacker: Square::C4,
+                targets: Bitboard::from_squares([Square::B5, Square::D5]),
+            }]
+        );
+    }
+
+    #[test]
+    fn tells_pins_from_skewers() {
+        assert_eq!(
+            found("4k3/4n3/8/8/8/8/8/4R1K1 b - - 0 1"),
+            [Motif::PIN {
+                attacker: Square::E1,
+                pinned: Square::E7,
+                behind: Square::E8,
+                absolute: true,
+            }]
+        );
+        assert_eq!(
+            found("4k3/3q4/2n5/1B6/8/8/8/6K1 b - - 0 1"),
+            [Motif::PIN {
+                attacker: Square::B5,
+                pinned: Square::C6,
+                behind: Square::D7,
+                absolute: false,
+            }]
+        );
+        assert_eq!(
+            found("q7/8/8/k7/8/8/8/R5K1 b - - 0 1"),
+            [Motif::SKEWER {
+                attacker: Square::A1,
+                front: Square::A5,
+                behind: Square::A8,
+            }]
+        );
+        assert_eq!(
+            found("4k2r/8/8/8/3q4/8/8/B3K3 b - - 0 1"),
+            [Motif::SKEWER {
+                attacker: Square::A1,
+                front: Square::D4,
+                behind: Square::H8,
+            }]
+        );
+        // Behind the queen the knight is defended and worth less than the bishop
+        assert!(found("4k3/8/5p2/4n3/3q4/8/8/B3K3 b - - 0 1").is_empty());
+    }
+
+    #[test]
+    fn move_motifs_leave_out_what_was_there_before() {
+        let game = Game::from_fen("4k3/4n3/8/8/8/8/8/4R1K1 w - - 0 1").unwrap();
+        assert!(move_motifs(&game, Move::from_uci("g1g2").unwrap()).is_empty());
+        // Only the pin arises from the move
+        let game = Game::from_fen("4k3/3q4/2n5/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        assert_eq!(
+            move_motifs(&game, Move::from_uci("f1b5").unwrap()),
+            [Motif::PIN {
+                attacker: Square::B5,
+                pinned: Square::C6,
+                behind: Square::D7,
+                absolute: false,
+            }]
+        );
+    }
+}
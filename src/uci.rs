@@ -0,0 +1,782 @@
+//! A front end for the Universal Chess Interface, the text protocol chess GUIs use to talk to
+//! engines. [`run`] reads commands line by line and answers them, searching on a background
+//! thread so that `stop` and `isready` are handled while a search is running.
+//!
+//! Supported commands are `uci`, `isready`, `ucinewgame`, `setoption`, `position`, `go`,
+//! `stop`, `ponderhit` and `quit`, as well as the non-standard `bench [depth]`, which runs the
+//! [bench](crate::search::bench) and reports its nodes and speed. The options are:
+//!
+//! - `Hash`, the size of the transposition table in megabytes.
+//! - `TTReplacement`, the [`Replacement`] scheme of the transposition table: `Always`,
+//!   `DepthPreferred` or `TwoTier`.
+//! - `Ponder`, which only tells the engine that the GUI may ask it to ponder.
+//! - `UCI_LimitStrength` and `UCI_Elo`, which weaken the play to the given Elo, see
+//!   [`skill`](crate::search::skill).
+//! - `Contempt`, how many centipawns below an even position the engine scores a draw, see
+//!   [`Searcher::contempt`](crate::search::Searcher::contempt). Going back to a position
+//!   the moves of `position` went through is a draw too.
+//! - `UCI_ShowWDL`, which adds the win, draw and loss chances of the score to the `info`
+//!   lines, see [`wdl`](crate::wdl).
+//! - `UCI_Chess960`, which reads and writes castling moves as the king taking its own rook,
+//!   e.g. `e1h1`, as Chess960 positions do anyway, see [`Game::chess960`].
+//! - `UCI_Variant`, the [`Variant`] whose rules the following positions are played by.
+//! - `OwnBook` and `BookFile`, which play the moves of a Polyglot book without searching while
+//!   the game is in the book, see [`book`](crate::book). `BookDepth` is the last move number
+//!   played from the book and `BookVariety` how far below the best move a book move may be
+//!   weighted, see [`PickOptions`].
+//! - `JSON` with the `json` feature, which switches the search output to JSON lines, see the
+//!   `json` module.
+//! - `EvalFile` with the `nnue` feature, which installs a network and evaluates with it, see
+//!   the `nnue` module.
+//!
+//! The [`client`] module speaks the protocol from the GUI's side, to drive other engines.
+//!
+//! # Example
+//!
+//! ```
+//! let input = "uci\nposition startpos moves e2e4 e7e5\ngo depth 3\n";
+//! kritisch::uci::run(input.as_bytes(), std::io::stdout()).unwrap();
+//! ```
+
+pub mod client;
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    book::{Book, PickOptions},
+    game::Game,
+    search::{
+        bench::{bench, DEFAULT_DEPTH},
+        mate_in,
+        skill::{time_seed, Skill, MAX_ELO, MIN_ELO},
+        thread::{SearchMode, SearchThread},
+        time::TimeControl,
+        tt::{Replacement, DEFAULT_SIZE_MB},
+        SearchInfo, SearchLimits, SearchResult, Searcher,
+    },
+    variant::Variant,
+    zobrist::splitmix64,
+    Color, Error, Move, Result,
+};
+#[cfg(feature = "nnue")]
+use crate::{
+    eval::Evaluator,
+    nnue::{self, Network},
+};
+
+/// The name the engine reports to the GUI.
+pub const NAME: &str = concat!("kritisch ", env!("CARGO_PKG_VERSION"));
+pub const AUTHOR: &str = "g0dnerd";
+/// The largest transposition table the `Hash` option allows, in megabytes.
+pub(crate) const MAX_HASH_MB: usize = 65536;
+/// The largest `Contempt` in either direction, in centipawns.
+const MAX_CONTEMPT: i32 = 100;
+/// The default of the `UCI_Elo` option.
+const DEFAULT_ELO: u32 = 1500;
+/// The largest `BookDepth`, in moves.
+const MAX_BOOK_DEPTH: usize = 255;
+
+/// Speaks UCI over `input` and `output` until `quit` is received or `input` ends. At the end
+/// of the input, a running search is finished first unless it is infinite or pondering.
+pub fn run<R, W>(input: R, output: W) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        if !uci.handle(&line?)? {
+            uci.search.stop();
+            return Ok(());
+        }
+    }
+    if uci.search.waits_for_stop() {
+        uci.search.stop();
+    } else {
+        uci.search.wait();
+    }
+    Ok(())
+}
+
+/// The state of the protocol between commands.
+struct Uci<W> {
+    output: Arc<Mutex<W>>,
+    game: Game,
+    search: SearchThread,
+    /// Whether `UCI_LimitStrength` is set.
+    limit_strength: bool,
+    /// The strength set with `UCI_Elo`, only used with `UCI_LimitStrength`.
+    elo: u32,
+    /// Whether `UCI_ShowWDL` is set.
+    show_wdl: Arc<AtomicBool>,
+    /// Whether `UCI_Chess960` is set.
+    chess960: bool,
+    /// The variant set with `UCI_Variant`.
+    variant: Variant,
+    /// The book read from `BookFile`, only played from with `OwnBook`.
+    book: Option<Book>,
+    own_book: bool,
+    book_options: PickOptions,
+    /// The state of the random choice among book moves.
+    book_seed: u64,
+    /// Whether to write the search output as JSON lines.
+    #[cfg(feature = "json")]
+    json: Arc<AtomicBool>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    fn new(output: W) -> Self {
+        let output = Arc::new(Mutex::new(output));
+        #[cfg(feature = "json")]
+        let json = Arc::new(AtomicBool::new(false));
+        let mut searcher = Searcher::default();
+        let show_wdl = Arc::new(AtomicBool::new(false));
+        let info_output = Arc::clone(&output);
+        let info_show_wdl = Arc::clone(&show_wdl);
+        #[cfg(feature = "json")]
+        let info_json = Arc::clone(&json);
+        searcher.on_info(move |info| {
+            let mut output = info_output.lock().expect("Output lock poisoned");
+            // A failed write shows up again with the next response
+            #[cfg(feature = "json")]
+            if info_json.load(Ordering::Relaxed) {
+                let _ = write_json(&mut *output, &info.to_json());
+                return;
+            }
+            let _ = write_info(&mut *output, info, info_show_wdl.load(Ordering::Relaxed));
+        });
+        Self {
+            output,
+            game: Game::default(),
+            search: SearchThread::new(searcher),
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+            show_wdl,
+            chess960: false,
+            variant: Variant::STANDARD,
+            book: None,
+            own_book: false,
+            book_options: PickOptions::default(),
+            book_seed: time_seed(),
+            #[cfg(feature = "json")]
+            json,
+        }
+    }
+
+    /// Handles one line of input, returning `false` on `quit`.
+    fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let line = line.trim();
+        let (command, args) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, args)| (command, args.trim()));
+        match command {
+            "uci" => {
+                self.send(&format!("id name {NAME}"))?;
+                self.send(&format!("id author {AUTHOR}"))?;
+                self.send(&format!(
+                    "option name Hash type spin default {DEFAULT_SIZE_MB} min 1 max {MAX_HASH_MB}"
+                ))?;
+                self.send(
+                    "option name TTReplacement type combo default Always \
+                     var Always var DepthPreferred var TwoTier",
+                )?;
+                self.send("option name Ponder type check default false")?;
+                self.send("option name UCI_LimitStrength type check default false")?;
+                self.send(&format!(
+                    "option name UCI_Elo type spin default {DEFAULT_ELO} min {MIN_ELO} max {MAX_ELO}"
+                ))?;
+                self.send(&format!(
+                    "option name Contempt type spin default 0 min -{MAX_CONTEMPT} max {MAX_CONTEMPT}"
+                ))?;
+                self.send("option name UCI_ShowWDL type check default false")?;
+                self.send("option name UCI_Chess960 type check default false")?;
+                let variants: String = Variant::ALL
+                    .iter()
+                    .map(|variant| format!(" var {variant}"))
+                    .collect();
+                self.send(&format!(
+                    "option name UCI_Variant type combo default {}{variants}",
+                    Variant::STANDARD
+                ))?;
+                self.send("option name OwnBook type check default false")?;
+                self.send("option name BookFile type string default <empty>")?;
+                let book = PickOptions::default();
+                self.send(&format!(
+                    "option name BookDepth type spin default {} min 1 max {MAX_BOOK_DEPTH}",
+                    book.depth
+                ))?;
+                self.send(&format!(
+                    "option name BookVariety type spin default {} min 0 max 100",
+                    book.variety
+                ))?;
+                #[cfg(feature = "json")]
+                self.send("option name JSON type check default false")?;
+                #[cfg(feature = "nnue")]
+                self.send("option name EvalFile type string default <empty>")?;
+                self.send("uciok")?;
+            }
+            "isready" => self.send("readyok")?,
+            "ucinewgame" => {
+                let searcher = self.search.searcher();
+                searcher.clear();
+                searcher.history.clear();
+                self.game = self.variant.starting_position();
+            }
+            "setoption" => {
+                if let Err(e) = self.set_option(args) {
+                    self.send(&format!("info string {e}"))?;
+                }
+            }
+            "position" => {
+                self.search.stop();
+                match parse_position(args, self.variant, self.chess960) {
+                    Ok((game, history)) => {
+                        self.game = game;
+                        self.search.searcher().history = history;
+                    }
+                    Err(e) => self.send(&format!("info string {e}"))?,
+                }
+            }
+            "go" => match parse_go(args) {
+                Ok(go) => self.go(go),
+                Err(e) => self.send(&format!("info string {e}"))?,
+            },
+            "stop" => {
+                self.search.stop();
+            }
+            // The search goes on, now on the clock
+            "ponderhit" => self.search.ponderhit(),
+            "bench" => {
+                self.search.stop();
+                let depth = if args.is_empty() {
+                    Ok(DEFAULT_DEPTH)
+                } else {
+                    args.parse()
+                };
+                match depth {
+                    Ok(depth) => self.send(&bench(depth).to_string())?,
+                    Err(_) => self.send(&format!("info string invalid depth '{args}'"))?,
+                }
+            }
+            "quit" => return Ok(false),
+            "" => (),
+            _ => self.send(&format!("info string unknown command '{command}'"))?,
+        }
+        Ok(true)
+    }
+
+    /// Starts searching the current position, reporting the best move when the search is
+    /// over. A book move is reported at once instead.
+    fn go(&mut self, go: Go) {
+        let output = Arc::clone(&self.output);
+        #[cfg(feature = "json")]
+        let json = Arc::clone(&self.json);
+        let report = move |result: &SearchResult| {
+            let mut output = output.lock().expect("Output lock poisoned");
+            #[cfg(feature = "json")]
+            if json.load(Ordering::Relaxed) {
+                let _ = write_json(&mut *output, &result.to_json());
+                return;
+            }
+            let _ = write_best_move(&mut *output, result);
+        };
+        if let Some(m) = self.book_move(&go) {
+            self.search.stop();
+            report(&SearchResult {
+                best_move: Some(m),
+                score: 0,
+                depth: 0,
+                nodes: 0,
+                pv: vec![m],
+            });
+            return;
+        }
+        self.search.start(&self.game, go.limits, go.mode, report);
+    }
+
+    /// Returns the book move to play with `OwnBook`, if any. Searches that are infinite,
+    /// ponder or are restricted to some moves don't take book moves.
+    fn book_move(&mut self, go: &Go) -> Option<Move> {
+        if !self.own_book || go.mode != SearchMode::LIMITED || !go.limits.search_moves.is_empty() {
+            return None;
+        }
+        // Polyglot books are for standard chess
+        if self.game.variant != Variant::STANDARD {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        book.pick(
+            &self.game,
+            &self.book_options,
+            splitmix64(&mut self.book_seed),
+        )
+    }
+
+    fn set_option(&mut self, args: &str) -> Result<()> {
+        let invalid = || Error::InvalidUci(format!("unsupported option '{args}'"));
+        let (name, value) = args
+            .strip_prefix("name ")
+            .and_then(|rest| rest.split_once(" value "))
+            .ok_or_else(invalid)?;
+        match name.trim() {
+            "Hash" => {
+                let megabytes: usize = value.trim().parse().map_err(|_| invalid())?;
+                self.search
+                    .searcher()
+                    .tt
+                    .resize(megabytes.clamp(1, MAX_HASH_MB));
+                Ok(())
+            }
+            "TTReplacement" => {
+                let replacement = match value.trim() {
+                    "Always" => Replacement::ALWAYS,
+                    "DepthPreferred" => Replacement::DEPTH_PREFERRED,
+                    "TwoTier" => Replacement::TWO_TIER,
+                    _ => return Err(invalid()),
+                };
+                self.search.searcher().tt.set_replacement(replacement);
+                Ok(())
+            }
+            // Pondering is up to the GUI, which sends `go ponder`
+            "Ponder" => Ok(()),
+            "UCI_LimitStrength" => {
+                self.limit_strength = value.trim().parse().map_err(|_| invalid())?;
+                self.update_skill();
+                Ok(())
+            }
+            "UCI_Elo" => {
+                let elo: u32 = value.trim().parse().map_err(|_| invalid())?;
+                self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+                self.update_skill();
+                Ok(())
+            }
+            "UCI_ShowWDL" => {
+                let show_wdl = value.trim().parse().map_err(|_| invalid())?;
+                self.show_wdl.store(show_wdl, Ordering::Relaxed);
+                Ok(())
+            }
+            "UCI_Chess960" => {
+                self.chess960 = value.trim().parse().map_err(|_| invalid())?;
+                Ok(())
+            }
+            "UCI_Variant" => {
+                self.variant = value.trim().parse()?;
+                self.game = self.variant.starting_position();
+                Ok(())
+            }
+            "OwnBook" => {
+                self.own_book = value.trim().parse().map_err(|_| invalid())?;
+                Ok(())
+            }
+            "BookFile" => {
+                let path = value.trim();
+                self.book = if path.is_empty() || path == "<empty>" {
+                    None
+                } else {
+                    let bytes = std::fs::read(path)
+                        .map_err(|err| Error::InvalidEncoding(format!("{path}: {err}")))?;
+                    Some(Book::from_bytes(&bytes)?)
+                };
+                Ok(())
+            }
+            "BookDepth" => {
+                let depth: usize = value.trim().parse().map_err(|_| invalid())?;
+                self.book_options.depth = depth.clamp(1, MAX_BOOK_DEPTH);
+                Ok(())
+            }
+            "BookVariety" => {
+                let variety: u32 = value.trim().parse().map_err(|_| invalid())?;
+                self.book_options.variety = variety.min(100);
+                Ok(())
+            }
+            "Contempt" => {
+                let contempt: i32 = value.trim().parse().map_err(|_| invalid())?;
+                self.search.searcher().contempt = contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+                Ok(())
+            }
+            #[cfg(feature = "json")]
+            "JSON" => {
+                let json = value.trim().parse().map_err(|_| invalid())?;
+                self.json.store(json, Ordering::Relaxed);
+                Ok(())
+            }
+            #[cfg(feature = "nnue")]
+            "EvalFile" => {
+                let path = value.trim();
+                let bytes = std::fs::read(path)
+                    .map_err(|err| Error::InvalidNetwork(format!("{path}: {err}")))?;
+                nnue::set_network(Network::from_bytes(&bytes)?)?;
+                self.game.refresh_accumulator();
+                self.search.searcher().evaluator = Evaluator::nnue();
+                Ok(())
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Sets the searcher's skill from `UCI_LimitStrength` and `UCI_Elo`.
+    fn update_skill(&mut self) {
+        self.search.searcher().skill = self.limit_strength.then(|| Skill::new(self.elo));
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        let mut output = self.output.lock().expect("Output lock poisoned");
+        writeln!(output, "{line}")?;
+        output.flush()
+    }
+}
+
+/// Writes the `info` line of a completed iteration, with the `wdl` of the score if
+/// `show_wdl`.
+fn write_info(w: &mut impl Write, info: &SearchInfo, show_wdl: bool) -> io::Result<()> {
+    let score = match mate_in(info.score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {}", info.score),
+    };
+    write!(
+        w,
+        "info depth {} seldepth {} score {score}",
+        info.depth, info.seldepth
+    )?;
+    if show_wdl {
+        write!(w, " wdl {}", info.wdl)?;
+    }
+    write!(
+        w,
+        " nodes {} nps {} hashfull {} time {}",
+        info.nodes,
+        info.nps,
+        info.hashfull,
+        info.time.as_millis()
+    )?;
+    if !info.pv.is_empty() {
+        write!(w, " pv")?;
+        for m in &info.pv {
+            write!(w, " {m}")?;
+        }
+    }
+    writeln!(w)?;
+    w.flush()
+}
+
+/// Writes one JSON line of search output.
+#[cfg(feature = "json")]
+fn write_json(w: &mut impl Write, json: &serde_json::Value) -> io::Result<()> {
+    writeln!(w, "{json}")?;
+    w.flush()
+}
+
+/// Writes the `bestmove` of a search.
+fn write_best_move(w: &mut impl Write, result: &SearchResult) -> io::Result<()> {
+    match (result.best_move, result.pv.get(1)) {
+        // The expected reply is what the GUI may ask to ponder on
+        (Some(m), Some(reply)) => writeln!(w, "bestmove {m} ponder {reply}")?,
+        (Some(m), None) => writeln!(w, "bestmove {m}")?,
+        // The protocol's null move, for positions without legal moves
+        (None, _) => writeln!(w, "bestmove 0000")?,
+    }
+    w.flush()
+}
+
+/// Parses the arguments of `position`: `startpos` or `fen <fen>`, optionally followed by
+/// `moves` and the moves played from there, by the rules of `variant`. With `chess960`, the
+/// position is played as Chess960 even if it is a standard one. Returns the position with the
+/// keys of the positions before it, see [`Searcher::history`](crate::search::Searcher::history).
+fn parse_position(args: &str, variant: Variant, chess960: bool) -> Result<(Game, Vec<u64>)> {
+    let (position, moves) = match args.split_once("moves") {
+        Some((position, moves)) => (position.trim(), moves),
+        None => (args.trim(), ""),
+    };
+    let mut game = if position == "startpos" {
+        variant.starting_position()
+    } else if let Some(fen) = position.strip_prefix("fen ") {
+        Game::from_fen(fen.trim())?
+    } else {
+        return Err(Error::InvalidUci(format!("invalid position '{args}'")));
+    };
+    game.variant = variant;
+    game.chess960 |= chess960;
+    let mut history = Vec::new();
+    for uci in moves.split_whitespace() {
+        history.push(game.key());
+        game.try_make_move(Move::from_uci(uci)?)?;
+    }
+    Ok((game, history))
+}
+
+/// The arguments of `go`.
+#[derive(Debug, Default, PartialEq)]
+struct Go {
+    limits: SearchLimits,
+    /// Whether to search until `stop`, or on the opponent's time until `ponderhit`.
+    mode: SearchMode,
+}
+
+/// Parses the arguments of `go`. Times are in milliseconds and unknown arguments are ignored.
+fn parse_go(args: &str) -> Result<Go> {
+    let mut go = Go::default();
+    let limits = &mut go.limits;
+    let mut clock = TimeControl::default();
+    let mut on_clock = false;
+    let mut tokens = args.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let mut value = || -> Result<i64> {
+            let value = tokens.next().unwrap_or_default();
+            value
+                .parse()
+                .map_err(|_| Error::InvalidUci(format!("invalid value '{value}' for {token}")))
+        };
+        // Some GUIs send negative times when a player is out of time
+        let millis = |value: i64| Duration::from_millis(value.max(0) as u64);
+        match token {
+            "depth" => limits.depth = Some(value()?.max(1) as u32),
+            "nodes" => limits.nodes = Some(value()?.max(0) as u64),
+            "movetime" => limits.time = Some(millis(value()?)),
+            "wtime" => (clock.time[Color::WHITE as usize], on_clock) = (millis(value()?), true),
+            "btime" => (clock.time[Color::BLACK as usize], on_clock) = (millis(value()?), true),
+            "winc" => clock.increment[Color::WHITE as usize] = millis(value()?),
+            "binc" => clock.increment[Color::BLACK as usize] = millis(value()?),
+            "movestogo" => clock.moves_to_go = Some(value()?.max(1) as u32),
+            "infinite" => go.mode = SearchMode::INFINITE,
+            "ponder" if go.mode == SearchMode::LIMITED => go.mode = SearchMode::PONDER,
+            "searchmoves" => {
+                while let Some(m) = tokens.peek().and_then(|uci| Move::from_uci(uci).ok()) {
+                    limits.search_moves.push(m);
+                    tokens.next();
+                }
+            }
+            _ => (),
+        }
+    }
+    if on_clock && go.mode != SearchMode::INFINITE {
+        go.limits.clock = Some(clock);
+    }
+    Ok(go)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::thread::testing::{self, SharedOutput};
+
+    fn run_script(input: &str) -> String {
+        testing::run_script(|input, output| run(input, output), input)
+    }
+
+    #[test]
+    fn handshake() {
+        let output = run_script("uci\nisready\nfoo\nbench 1\nquit\nisready\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], format!("id name {NAME}"));
+        let options = [
+            "option name Hash type spin default 16 min 1 max 65536",
+            "option name TTReplacement type combo default Always var Always var DepthPreferred var TwoTier",
+            "option name Ponder type check default false",
+            "option name UCI_LimitStrength type check default false",
+            "option name UCI_Elo type spin default 1500 min 800 max 2400",
+            "option name Contempt type spin default 0 min -100 max 100",
+            "option name UCI_ShowWDL type check default false",
+            "option name UCI_Chess960 type check default false",
+            "option name UCI_Variant type combo default chess var chess var crazyhouse var atomic var kingofthehill var horde var antichess var duck var gardner var minichess5x6 var losalamos",
+            "option name OwnBook type check default false",
+            "option name BookFile type string default <empty>",
+            "option name BookDepth type spin default 20 min 1 max 255",
+            "option name BookVariety type spin default 50 min 0 max 100",
+        ];
+        assert_eq!(lines[2..2 + options.len()], options);
+        // Options of optional features come last
+        let uciok = lines.iter().position(|&line| line == "uciok").unwrap();
+        assert_eq!(lines[uciok + 1], "readyok");
+        assert_eq!(lines[uciok + 2], "info string unknown command 'foo'");
+        assert!(lines[uciok + 3].ends_with(" nps"), "{output}");
+        // Nothing is read after quit
+        assert_eq!(lines.len(), uciok + 4);
+    }
+
+    #[test]
+    fn shows_wdl() {
+        let output =
+            run_script("setoption name UCI_ShowWDL value true\nposition startpos\ngo depth 1\n");
+        let info = output.lines().next().unwrap();
+        let (_, rest) = info.split_once(" wdl ").unwrap();
+        let wdl: Vec<u32> = rest
+            .split(' ')
+            .take(3)
+            .map(|n| n.parse().unwrap())
+            .collect();
+        assert_eq!(wdl.iter().sum::<u32>(), 1000, "{info}");
+    }
+
+    #[test]
+    fn sets_the_variant() {
+        let output = run_script(
+            "setoption name UCI_Variant value chess\n\
+             setoption name UCI_Variant value nonsense\n",
+        );
+        assert_eq!(output, "info string unknown variant: nonsense\n");
+    }
+
+    #[test]
+    fn limits_strength() {
+        let output = run_script(
+            "setoption name UCI_LimitStrength value true\nsetoption name UCI_Elo value 100\n\
+             position startpos\ngo\n",
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        // The lowest Elo only searches one ply, even without limits
+        assert!(lines[0].starts_with("info depth 1 "), "{output}");
+        assert!(lines[1].starts_with("bestmove "), "{output}");
+    }
+
+    #[test]
+    fn searches_the_position() {
+        // The end of the input waits for the search to finish
+        let output = run_script("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("info depth 1 seldepth "), "{output}");
+        assert!(lines[0].contains(" score mate 1 "), "{output}");
+        assert!(lines[0].ends_with(" pv a1a8"), "{output}");
+        assert_eq!(lines[1], "bestmove a1a8");
+    }
+
+    #[test]
+    fn plays_book_moves() {
+        let output = SharedOutput::default();
+        let read = || output.read();
+        let mut uci = Uci::new(output.clone());
+        let game = Game::default();
+        let e4 = Move::from_uci("e2e4").unwrap();
+        uci.book = Some(Book::new(vec![crate::book::Entry {
+            key: crate::book::key(&game),
+            raw_move: crate::book::encode_move(&game, e4),
+            weight: 1,
+            learn: 0,
+        }]));
+        uci.handle("position startpos").unwrap();
+        uci.handle("go depth 1").unwrap();
+        uci.search.wait();
+        assert!(read().starts_with("info depth 1 "), "{}", read());
+
+        // The book move comes without a search
+        output.clear();
+        uci.handle("setoption name OwnBook value true").unwrap();
+        uci.handle("go depth 30").unwrap();
+        assert_eq!(read(), "bestmove e2e4\n");
+        assert_eq!(uci.book_move(&parse_go("infinite").unwrap()), None);
+        assert_eq!(uci.book_move(&parse_go("searchmoves d2d4").unwrap()), None);
+    }
+
+    #[test]
+    fn stops_an_infinite_search() {
+        let output = run_script("position startpos moves e2e4\ngo infinite\nstop\n");
+        let bestmove = output.lines().last().unwrap();
+        let m = Move::from_uci(bestmove.split_whitespace().nth(1).unwrap()).unwrap();
+        let (game, _) = parse_position("startpos moves e2e4", Variant::STANDARD, false).unwrap();
+        assert!(crate::movegen::all_legal_moves(&game).contains(&m));
+    }
+
+    #[test]
+    fn ponders_until_ponderhit() {
+        let output = SharedOutput::default();
+        let read = || output.read();
+        let mut uci = Uci::new(output.clone());
+        uci.handle("position startpos moves e2e4").unwrap();
+        uci.handle("go movetime 10 ponder").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!read().contains("bestmove"));
+        uci.handle("ponderhit").unwrap();
+        uci.search.wait();
+        let output = read();
+        let bestmove = output.lines().last().unwrap();
+        assert!(bestmove.starts_with("bestmove ") && bestmove.contains(" ponder "));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_output() {
+        let output = run_script(
+            "setoption name JSON value maybe\nsetoption name JSON value true\n\
+             position startpos\ngo depth 2\n",
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("info string "), "{output}");
+        let json: Vec<serde_json::Value> = lines[1..]
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            (&json[0]["type"], &json[0]["depth"]),
+            (&"info".into(), &1.into())
+        );
+        assert_eq!(
+            (&json[1]["type"], &json[1]["depth"]),
+            (&"info".into(), &2.into())
+        );
+        assert_eq!(json[2]["type"], "bestmove");
+        assert_eq!(json[2]["bestMove"], json[1]["pv"][0]);
+        assert_eq!(json.len(), 3);
+    }
+
+    #[test]
+    fn positions() {
+        let (game, history) =
+            parse_position("startpos moves e2e4 e7e5 g1f3", Variant::STANDARD, false).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0], Game::default().key());
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        assert_eq!(
+            parse_position(&format!("fen {fen}"), Variant::STANDARD, false)
+                .unwrap()
+                .0
+                .to_fen(),
+            fen
+        );
+        let (game, _) =
+            parse_position(&format!("fen {fen} moves e1g1"), Variant::STANDARD, false).unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        let (game, _) =
+            parse_position(&format!("fen {fen} moves e1h1"), Variant::STANDARD, true).unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(matches!(
+            parse_position("startpos moves e2e5", Variant::STANDARD, false),
+            Err(Error::IllegalMove(_))
+        ));
+        assert!(parse_position("somewhere", Variant::STANDARD, false).is_err());
+    }
+
+    #[test]
+    fn go_arguments() {
+        let go = parse_go("wtime 60000 btime -20 winc 1000 binc 0 movestogo 20 ponder").unwrap();
+        let clock = go.limits.clock.unwrap();
+        assert_eq!(clock.time, [Duration::from_secs(60), Duration::ZERO]);
+        assert_eq!(
+            clock.increment[Color::WHITE as usize],
+            Duration::from_secs(1)
+        );
+        assert_eq!(clock.moves_to_go, Some(20));
+        assert_eq!(go.mode, SearchMode::PONDER);
+
+        let go = parse_go("depth 6 searchmoves e2e4 d2d4 nodes 1000 movetime 500").unwrap();
+        assert_eq!(go.limits.depth, Some(6));
+        assert_eq!(go.limits.nodes, Some(1000));
+        assert_eq!(go.limits.time, Some(Duration::from_millis(500)));
+        assert_eq!(go.limits.clock, None);
+        let search_moves = ["e2e4", "d2d4"].map(|uci| Move::from_uci(uci).unwrap());
+        assert_eq!(go.limits.search_moves, search_moves);
+        assert_eq!(go.mode, SearchMode::LIMITED);
+
+        let infinite = Go {
+            mode: SearchMode::INFINITE,
+            ..Go::default()
+        };
+        assert_eq!(parse_go("infinite").unwrap(), infinite);
+        assert!(parse_go("depth x").is_err());
+    }
+}
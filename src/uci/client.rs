@@ -0,0 +1,381 @@
+//! The other side of the protocol: a client that launches a third-party engine such as
+//! Stockfish as a child process and talks UCI to it, e.g. to play a
+//! [match](crate::matches) against it or to compare search results and perft counts with a
+//! reference.
+//!
+//! An [`ExternalEngine`] is an [`Engine`] like the engine of this crate. Its searches are
+//! sent as `position` and `go` and answered from the last `info` line before `bestmove`, with
+//! scores converted to the [`mate_in`](crate::search::mate_in) convention of the search.
+//! Every method waits for the engine's answer, so the [`SearchLimits`] must bound the search.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kritisch::{game::Game, search::SearchLimits, uci::client::ExternalEngine};
+//! let mut stockfish = ExternalEngine::spawn("stockfish", &[]).unwrap();
+//! stockfish.set_option("Threads", "2").unwrap();
+//! let result = stockfish.go(&Game::default(), &SearchLimits::depth(12)).unwrap();
+//! println!("{} plays {:?}", stockfish.name(), result.best_move);
+//! assert_eq!(stockfish.perft(&Game::default(), 4).unwrap(), 197_281);
+//! ```
+
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    game::Game,
+    search::{Engine, SearchLimits, SearchResult, MATE},
+    Color, Move,
+};
+
+/// How long a dropped engine gets to quit before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A UCI engine running as a child process.
+pub struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    name: String,
+    author: String,
+    options: Vec<String>,
+}
+
+impl ExternalEngine {
+    /// Launches `program` with `args` and waits for its `uciok`.
+    pub fn spawn(program: impl AsRef<OsStr>, args: &[&str]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut engine = Self {
+            child,
+            stdin,
+            stdout,
+            name: String::new(),
+            author: String::new(),
+            options: Vec::new(),
+        };
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = author.trim().to_string();
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                engine.options.push(option.trim().to_string());
+            } else if line.trim() == "uciok" {
+                return Ok(engine);
+            }
+        }
+    }
+
+    /// The name the engine reported with `id name`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The author the engine reported with `id author`.
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// The options the engine reported, each as the rest of its `option name` line, e.g.
+    /// `Hash type spin default 16 min 1 max 33554432`.
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Sets the option `name` to `value` and waits until the engine has applied it.
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))?;
+        self.is_ready()
+    }
+
+    /// Tells the engine that the next search is from a different game.
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Searches `game` within `limits` and waits for the `bestmove`. The depth, nodes and
+    /// principal variation of the result come from the last `info` line that has them, and a
+    /// best move of `0000` or `(none)` is `None`.
+    pub fn go(&mut self, game: &Game, limits: &SearchLimits) -> io::Result<SearchResult> {
+        self.send(&position(game))?;
+        self.send(&go(limits))?;
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+        };
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => parse_info(tokens, &mut result),
+                Some("bestmove") => {
+                    result.best_move = tokens.next().and_then(|uci| Move::from_uci(uci).ok());
+                    return Ok(result);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Counts the leaf nodes of the legal move tree `depth` plies below `game` with the
+    /// engine's `go perft`, which isn't part of the protocol. It reads the count from a line
+    /// like Stockfish's `Nodes searched: 197281`.
+    pub fn perft(&mut self, game: &Game, depth: u32) -> io::Result<u64> {
+        self.send(&position(game))?;
+        self.send(&format!("go perft {depth}"))?;
+        loop {
+            let line = self.read_line()?;
+            if let Some(nodes) = line.strip_prefix("Nodes searched:") {
+                return nodes.trim().parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid perft count '{line}'"),
+                    )
+                });
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    /// Reads the next line of the engine's output, failing if the engine has exited.
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the engine has exited",
+            ));
+        }
+        Ok(line)
+    }
+}
+
+/// An engine that fails to answer searches with an empty result, which has no best move.
+impl Engine for ExternalEngine {
+    fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        self.go(game, &limits).unwrap_or(SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+        })
+    }
+
+    fn new_game(&mut self) {
+        // A failure shows up at the next search
+        let _ = ExternalEngine::new_game(self);
+    }
+}
+
+/// Sends `quit` and kills the engine if it doesn't exit in time.
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let started = Instant::now();
+        while started.elapsed() < QUIT_TIMEOUT {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Returns the `position` command for `game`.
+fn position(game: &Game) -> String {
+    if *game == Game::default() {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", game.to_fen())
+    }
+}
+
+/// Returns the `go` command for `limits`, with times in milliseconds.
+fn go(limits: &SearchLimits) -> String {
+    let mut command = "go".to_string();
+    if let Some(depth) = limits.depth {
+        command += &format!(" depth {depth}");
+    }
+    if let Some(nodes) = limits.nodes {
+        command += &format!(" nodes {nodes}");
+    }
+    if let Some(time) = limits.time {
+        command += &format!(" movetime {}", time.as_millis());
+    }
+    if let Some(clock) = &limits.clock {
+        let [white, black] = [Color::WHITE as usize, Color::BLACK as usize];
+        command += &format!(
+            " wtime {} btime {} winc {} binc {}",
+            clock.time[white].as_millis(),
+            clock.time[black].as_millis(),
+            clock.increment[white].as_millis(),
+            clock.increment[black].as_millis()
+        );
+        if let Some(moves_to_go) = clock.moves_to_go {
+            command += &format!(" movestogo {moves_to_go}");
+        }
+    }
+    if !limits.search_moves.is_empty() {
+        command += " searchmoves";
+        for m in &limits.search_moves {
+            command += &format!(" {m}");
+        }
+    }
+    command
+}
+
+/// Takes the depth, nodes, score and principal variation of an `info` line into `result`.
+/// Lines without a score, such as `info string` or `info currmove`, are skipped.
+fn parse_info<'a>(mut tokens: impl Iterator<Item = &'a str>, result: &mut SearchResult) {
+    let mut info = SearchResult {
+        best_move: None,
+        score: 0,
+        depth: result.depth,
+        nodes: result.nodes,
+        pv: Vec::new(),
+    };
+    let mut score = None;
+    let value = |tokens: &mut dyn Iterator<Item = &'a str>| {
+        tokens.next().and_then(|value| value.parse::<i64>().ok())
+    };
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => info.depth = value(&mut tokens).unwrap_or_default() as u32,
+            "nodes" => info.nodes = value(&mut tokens).unwrap_or_default() as u64,
+            "score" => {
+                score = match tokens.next() {
+                    Some("cp") => value(&mut tokens).map(|cp| cp as i32),
+                    // Mate in n moves is mate in 2n - 1 plies, mated in n is 2n plies
+                    Some("mate") => value(&mut tokens).map(|moves| match moves as i32 {
+                        moves if moves > 0 => MATE - (2 * moves - 1),
+                        moves => -MATE - 2 * moves,
+                    }),
+                    _ => None,
+                }
+            }
+            "pv" => {
+                info.pv = tokens
+                    .by_ref()
+                    .map_while(|uci| Move::from_uci(uci).ok())
+                    .collect();
+            }
+            "string" => return,
+            _ => (),
+        }
+    }
+    let Some(score) = score else {
+        return;
+    };
+    info.score = score;
+    if info.pv.is_empty() {
+        info.pv = std::mem::take(&mut result.pv);
+    }
+    *result = info;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{mate_in, time::TimeControl};
+
+    #[test]
+    fn sends_limits_with_go() {
+        let mut limits = SearchLimits::depth(5);
+        limits.clock = Some(TimeControl {
+            time: [Duration::from_secs(60), Duration::from_secs(30)],
+            increment: [Duration::from_millis(500); 2],
+            moves_to_go: Some(20),
+        });
+        limits.search_moves = vec![Move::from_uci("e2e4").unwrap()];
+        assert_eq!(
+            go(&limits),
+            "go depth 5 wtime 60000 btime 30000 winc 500 binc 500 movestogo 20 searchmoves e2e4"
+        );
+        assert_eq!(position(&Game::default()), "position startpos");
+    }
+
+    #[test]
+    fn parses_info_lines() {
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+        };
+        let info = |line: &'static str, result: &mut SearchResult| {
+            parse_info(line.split_whitespace().skip(1), result)
+        };
+        info(
+            "info depth 3 nodes 120 score cp 35 pv e2e4 e7e5",
+            &mut result,
+        );
+        info("info depth 4 currmove d2d4 currmovenumber 2", &mut result);
+        info("info string NNUE enabled", &mut result);
+        assert_eq!((result.depth, result.nodes, result.score), (3, 120, 35));
+        assert_eq!(result.pv.len(), 2);
+
+        info("info depth 9 nodes 5000 score mate 2 pv d1h5", &mut result);
+        assert_eq!(mate_in(result.score), Some(2));
+        info(
+            "info depth 9 nodes 6000 score mate -3 lowerbound",
+            &mut result,
+        );
+        assert_eq!(mate_in(result.score), Some(-3));
+        assert_eq!(result.pv, [Move::from_uci("d1h5").unwrap()]);
+    }
+
+    /// Runs a script that answers like a UCI engine, always playing e2e4.
+    #[cfg(unix)]
+    #[test]
+    fn talks_to_a_child_process() {
+        let script = r#"while read -r command; do
+            case "$command" in
+                uci) echo "id name Fake"; echo "option name Hash type spin"; echo uciok ;;
+                isready) echo readyok ;;
+                "go perft"*) echo "e2e4: 1"; echo; echo "Nodes searched: 20" ;;
+                go*) echo "info depth 1 nodes 20 score cp 30 pv e2e4"; echo "bestmove e2e4" ;;
+                quit) exit ;;
+            esac
+        done"#;
+        let mut engine = ExternalEngine::spawn("sh", &["-c", script]).unwrap();
+        assert_eq!(engine.name(), "Fake");
+        assert_eq!(engine.options(), ["Hash type spin"]);
+        engine.set_option("Hash", "16").unwrap();
+        Engine::new_game(&mut engine);
+        let result = Engine::search(&mut engine, &Game::default(), SearchLimits::depth(1));
+        assert_eq!(result.best_move, Some(Move::from_uci("e2e4").unwrap()));
+        assert_eq!((result.depth, result.nodes, result.score), (1, 20, 30));
+        assert_eq!(engine.perft(&Game::default(), 1).unwrap(), 20);
+    }
+}
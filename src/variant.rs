@@ -0,0 +1,276 @@
+//! The rules of chess variants, so that a [`Game`] can be played by other rules without
+//! changing the board representation or the move generator.
+//!
+//! Every [`Game`] has a [`Variant`], whose [`Rules`] decide which moves are legal and when
+//! the game is over. The methods of [`Rules`] default to standard chess, so a variant only
+//! overrides what it changes: the size of the board, the pseudo-legal moves and the legality
+//! filter of [`all_legal_moves`], what pawns promote to, what happens to captured pieces, a win
+//! condition that ends the game before its moves run out, what having no legal move means and
+//! when the material left is insufficient.
+//!
+//! Adding a variant means adding a [`Variant`] and a type that implements [`Rules`] for it.
+//! The UCI front end offers every variant in its `UCI_Variant` option.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, movegen::all_legal_moves, variant::Variant};
+//! let variant: Variant = "chess".parse().unwrap();
+//! let game = variant.starting_position();
+//! assert_eq!(game, Game::default());
+//! assert_eq!(game.rules().name(), "chess");
+//! assert_eq!(all_legal_moves(&game).len(), 20);
+//! ```
+
+pub mod antichess;
+pub mod atomic;
+pub mod crazyhouse;
+pub mod duck;
+pub mod horde;
+pub mod king_of_the_hill;
+pub mod minichess;
+
+use crate::{
+    bitboard::Bitboard,
+    game::{Game, Termination},
+    movegen::{self, all_legal_moves},
+    Color, Error, MoveList, Piece, Result,
+};
+
+/// The FEN of the starting position of standard chess.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// What a variant changes about the rules of chess. Every method defaults to standard chess.
+pub trait Rules: std::fmt::Debug + Send + Sync {
+    /// The name of the variant in the `UCI_Variant` option, e.g. `chess`.
+    fn name(&self) -> &'static str;
+
+    /// The FEN of the position games of the variant start from.
+    fn starting_fen(&self) -> &'static str {
+        STARTING_FEN
+    }
+
+    /// Returns the number of files and ranks of the board. A smaller board than 8x8 takes up
+    /// the corner from a1 on, and its FENs only describe that corner.
+    fn board_size(&self) -> (u8, u8) {
+        (8, 8)
+    }
+
+    /// Returns the moves of the side to move that follow the movement rules of the pieces,
+    /// whether or not they are legal.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        movegen::pseudo_legal_moves(game)
+    }
+
+    /// Returns the pieces a pawn reaching the last rank may turn into.
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        &[Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT]
+    }
+
+    /// Returns `true` if captured pieces go to the capturer's pocket, to be dropped back on the
+    /// board as its own later.
+    fn has_pockets(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if a capture explodes the capturing piece and every piece but pawns next
+    /// to the square of the capture.
+    fn captures_explode(&self) -> bool {
+        false
+    }
+
+    /// Returns the squares on which `color`'s king would be in check, which it may not castle
+    /// out of or through.
+    fn checked_squares(&self, game: &Game, color: Color) -> Bitboard {
+        game.attacks(!color)
+    }
+
+    /// Keeps only the legal ones of the pseudo-legal `moves`. In standard chess a move is
+    /// legal if it doesn't leave the own king in check.
+    fn retain_legal(&self, game: &Game, moves: &mut MoveList) {
+        movegen::retain_king_safe(game, moves);
+    }
+
+    /// Returns how the game ended by a rule of the variant that ends it before the side to
+    /// move runs out of moves, e.g. a king reaching the center, or `None`. Called before the
+    /// moves are generated, so it must not need them.
+    fn variant_termination(&self, _game: &Game) -> Option<Termination> {
+        None
+    }
+
+    /// Returns how the game ended when the side to move has no legal move: checkmate if it is
+    /// in check and stalemate otherwise.
+    fn no_moves(&self, game: &Game) -> Termination {
+        if game.is_check() {
+            Termination::CHECKMATE
+        } else {
+            Termination::STALEMATE
+        }
+    }
+
+    /// Returns `true` if the material left can't decide the game anymore, see
+    /// [`Game::is_insufficient_material`].
+    fn is_insufficient_material(&self, game: &Game) -> bool {
+        game.is_insufficient_material()
+    }
+}
+
+/// The variant a [`Game`] is played in.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Variant {
+    #[default]
+    STANDARD,
+    /// See [`crazyhouse`].
+    CRAZYHOUSE,
+    /// See [`atomic`].
+    ATOMIC,
+    /// See [`king_of_the_hill`].
+    KING_OF_THE_HILL,
+    /// See [`horde`].
+    HORDE,
+    /// See [`antichess`].
+    ANTICHESS,
+    /// See [`duck`].
+    DUCK,
+    /// Gardner's 5x5 minichess, see [`minichess`].
+    GARDNER,
+    /// Minichess on 5x6, see [`minichess`].
+    FIVE_BY_SIX,
+    /// Los Alamos chess on 6x6, see [`minichess`].
+    LOS_ALAMOS,
+}
+
+impl Variant {
+    /// All variants, standard chess first.
+    pub const ALL: [Self; 10] = [
+        Self::STANDARD,
+        Self::CRAZYHOUSE,
+        Self::ATOMIC,
+        Self::KING_OF_THE_HILL,
+        Self::HORDE,
+        Self::ANTICHESS,
+        Self::DUCK,
+        Self::GARDNER,
+        Self::FIVE_BY_SIX,
+        Self::LOS_ALAMOS,
+    ];
+
+    /// Returns the rules of the variant.
+    pub fn rules(self) -> &'static dyn Rules {
+        match self {
+            Self::STANDARD => &Standard,
+            Self::CRAZYHOUSE => &crazyhouse::Crazyhouse,
+            Self::ATOMIC => &atomic::Atomic,
+            Self::KING_OF_THE_HILL => &king_of_the_hill::KingOfTheHill,
+            Self::HORDE => &horde::Horde,
+            Self::ANTICHESS => &antichess::Antichess,
+            Self::DUCK => &duck::Duck,
+            Self::GARDNER => &minichess::GARDNER,
+            Self::FIVE_BY_SIX => &minichess::FIVE_BY_SIX,
+            Self::LOS_ALAMOS => &minichess::LOS_ALAMOS,
+        }
+    }
+
+    /// Returns the position games of the variant start from.
+    pub fn starting_position(self) -> Game {
+        let mut game =
+            Game::from_fen(self.rules().starting_fen()).expect("starting FENs are valid");
+        game.variant = self;
+        game
+    }
+}
+
+/// Displays the variant by its [name](Rules::name).
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.rules().name())
+    }
+}
+
+/// Parses a variant from its [name](Rules::name).
+impl std::str::FromStr for Variant {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|variant| variant.rules().name() == s)
+            .ok_or_else(|| Error::InvalidVariant(s.to_string()))
+    }
+}
+
+/// Standard chess.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Standard;
+
+impl Rules for Standard {
+    fn name(&self) -> &'static str {
+        "chess"
+    }
+}
+
+/// Returns how `game` has ended by the rules of its variant, or `None` if it is still going,
+/// see [`Game::termination`].
+pub(crate) fn termination(game: &Game) -> Option<Termination> {
+    let rules = game.rules();
+    if let Some(termination) = rules.variant_termination(game) {
+        return Some(termination);
+    }
+    if all_legal_moves(game).is_empty() {
+        return Some(rules.no_moves(game));
+    }
+    if game.halfmove_clock >= 100 {
+        return Some(Termination::FIFTY_MOVE_RULE);
+    }
+    if rules.is_insufficient_material(game) {
+        return Some(Termination::INSUFFICIENT_MATERIAL);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    /// Chess where the side to move loses as soon as it has no queen.
+    #[derive(Debug)]
+    struct NoQueenLoses;
+
+    impl Rules for NoQueenLoses {
+        fn name(&self) -> &'static str {
+            "noqueenloses"
+        }
+
+        fn variant_termination(&self, game: &Game) -> Option<Termination> {
+            (game.piece_count(game.to_move, Piece::QUEEN) == 0).then_some(Termination::VARIANT_LOSS)
+        }
+    }
+
+    #[test]
+    fn parses_variant_names() {
+        for variant in Variant::ALL {
+            assert_eq!(variant.to_string().parse::<Variant>(), Ok(variant));
+        }
+        assert_eq!(
+            "nonsense".parse::<Variant>(),
+            Err(Error::InvalidVariant("nonsense".to_string()))
+        );
+    }
+
+    #[test]
+    fn rules_default_to_standard_chess() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1").unwrap();
+        let rules = NoQueenLoses;
+        let mut moves = rules.pseudo_legal_moves(&game);
+        rules.retain_legal(&game, &mut moves);
+        assert_eq!(moves, all_legal_moves(&game));
+        assert!(moves.contains(&Move::from_uci("e1d2").unwrap()));
+        assert_eq!(
+            rules.variant_termination(&game),
+            Some(Termination::VARIANT_LOSS)
+        );
+        assert_eq!(Standard.variant_termination(&game), None);
+    }
+}
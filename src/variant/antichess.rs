@@ -0,0 +1,157 @@
+//! Antichess, also known as Giveaway: the side that loses all its pieces or has no legal
+//! move wins. Capturing is compulsory, the king is an ordinary piece that can be captured,
+//! there is no check and no castling, and pawns may promote to kings as well.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::game::{Game, Outcome, Termination};
+//! use kritisch::{movegen::all_legal_moves, variant::Variant, Color, Move};
+//! let mut game = Variant::ANTICHESS.starting_position();
+//! for uci in ["e2e3", "b7b5"] {
+//!     game.make_move(Move::from_uci(uci).unwrap());
+//! }
+//! // The bishop has to take on b5
+//! assert_eq!(all_legal_moves(&game), [Move::from_uci("f1b5").unwrap()]);
+//!
+//! // White can't move and wins
+//! let mut game = Game::from_fen("8/8/8/8/8/p7/P7/8 w - - 0 1").unwrap();
+//! game.variant = Variant::ANTICHESS;
+//! assert_eq!(game.termination(), Some(Termination::VARIANT_WIN));
+//! assert_eq!(game.outcome(), Some(Outcome::DECISIVE { winner: Color::WHITE }));
+//! ```
+
+use super::Rules;
+use crate::{
+    bitboard::Bitboard,
+    game::{Game, Termination},
+    movegen, Color, MoveList, Piece,
+};
+
+/// The rules of Antichess.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Antichess;
+
+impl Rules for Antichess {
+    fn name(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    /// The moves of standard chess but castling. If any of them captures, only the captures
+    /// are left.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        let mut moves = movegen::pseudo_legal_moves(game);
+        moves.retain(|m| !game.is_castle(*m, game.type_at(m.start), game.to_move));
+        if moves.iter().any(|m| game.is_capture(*m)) {
+            moves.retain(|m| game.is_capture(*m));
+        }
+        moves
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        &[
+            Piece::QUEEN,
+            Piece::ROOK,
+            Piece::BISHOP,
+            Piece::KNIGHT,
+            Piece::KING,
+        ]
+    }
+
+    /// Without check every move that follows the movement rules is legal.
+    fn retain_legal(&self, _game: &Game, _moves: &mut MoveList) {}
+
+    /// The side to move has won once it has lost all its pieces.
+    fn variant_termination(&self, game: &Game) -> Option<Termination> {
+        game.color_bitboards[game.to_move as usize]
+            .is_empty()
+            .then_some(Termination::VARIANT_WIN)
+    }
+
+    /// Having no legal move wins.
+    fn no_moves(&self, _game: &Game) -> Termination {
+        Termination::VARIANT_WIN
+    }
+
+    /// Only bishops are left, and those of one side stand on squares of the other color than
+    /// those of the other side, so no piece can ever be captured again.
+    fn is_insufficient_material(&self, game: &Game) -> bool {
+        let dark = Bitboard::from_u64(0xaa55aa55aa55aa55);
+        let bishops = game.piece_bitboards[Piece::BISHOP as usize];
+        if game.all_pieces() != bishops {
+            return false;
+        }
+        let white = bishops & game.color_bitboards[Color::WHITE as usize];
+        let black = bishops & game.color_bitboards[Color::BLACK as usize];
+        ((white & !dark).is_empty() && (black & dark).is_empty())
+            || ((white & dark).is_empty() && (black & !dark).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::{Game, Outcome, Termination},
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Color, Move,
+    };
+
+    fn antichess(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.variant = Variant::ANTICHESS;
+        game
+    }
+
+    #[test]
+    fn perft_matches_reference() {
+        assert_eq!(perft(&Variant::ANTICHESS.starting_position(), 3), 8067);
+        let game = antichess("8/1p6/8/8/8/8/P7/8 w - - 0 1");
+        assert_eq!(
+            (1..=6).map(|depth| perft(&game, depth)).collect::<Vec<_>>(),
+            [2, 4, 4, 3, 1, 0]
+        );
+    }
+
+    #[test]
+    fn kings_are_captured_and_promoted_to() {
+        let mut game = antichess("8/1P6/8/8/8/8/8/k3K3 w - - 0 1");
+        let promotions = all_legal_moves(&game)
+            .iter()
+            .filter(|m| m.promotion.is_some())
+            .count();
+        assert_eq!(promotions, 5);
+        let promote = Move::from_uci("b7b8k").unwrap();
+        let before = game;
+        let undo = game.make_move(promote);
+        assert_eq!(game.to_fen(), "1K6/8/8/8/8/8/8/k3K3 b - - 0 1");
+        game.unmake_move(promote, undo);
+        assert_eq!(game, before);
+
+        // Both kings move, and one takes the other
+        let game = antichess("8/8/8/8/8/8/1k6/K3K3 w - - 0 1");
+        assert_eq!(all_legal_moves(&game), [Move::from_uci("a1b2").unwrap()]);
+    }
+
+    #[test]
+    fn losing_everything_wins() {
+        let mut game = antichess("8/8/8/8/8/8/1k6/K7 w - - 0 1");
+        game.make_move(Move::from_uci("a1b2").unwrap());
+        assert_eq!(game.termination(), Some(Termination::VARIANT_WIN));
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome::DECISIVE {
+                winner: Color::BLACK
+            })
+        );
+
+        let game = antichess("8/8/8/8/8/1b6/8/3B4 w - - 0 1");
+        assert_eq!(game.termination(), None);
+        let game = antichess("8/8/8/8/8/2b5/8/3B4 w - - 0 1");
+        assert_eq!(game.termination(), Some(Termination::INSUFFICIENT_MATERIAL));
+    }
+}
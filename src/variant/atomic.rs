@@ -0,0 +1,177 @@
+//! Atomic chess: every capture explodes, removing the capturing piece and every piece but
+//! pawns next to the square of the capture, of either color. Exploding the opponent's king
+//! wins the game, so kings may not capture, and a move that explodes the own king is illegal.
+//! While the two kings stand next to each other neither can be in check, since capturing one
+//! would explode the other.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Termination, variant::Variant, Move};
+//! let mut game = Variant::ATOMIC.starting_position();
+//! for uci in ["g1f3", "d7d5", "f3e5", "b8c6"] {
+//!     game.make_move(Move::from_uci(uci).unwrap());
+//! }
+//! // Taking on f7 explodes the king on e8 along with the pieces around it
+//! game.make_move(Move::from_uci("e5f7").unwrap());
+//! assert_eq!(game.to_fen(), "r1bq3r/ppp1p1pp/2n5/3p4/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 3");
+//! assert_eq!(game.termination(), Some(Termination::VARIANT_LOSS));
+//! ```
+
+use super::Rules;
+use crate::{
+    bitboard::Bitboard,
+    game::{Game, Termination},
+    movegen, Color, MoveList, Piece, Square,
+};
+
+/// The rules of atomic chess.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Atomic;
+
+impl Rules for Atomic {
+    fn name(&self) -> &'static str {
+        "atomic"
+    }
+
+    /// Kings may not capture, and a side whose king has exploded has no moves.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        let Some(king) = game.king_square(game.to_move) else {
+            return MoveList::new();
+        };
+        let mut moves = movegen::pseudo_legal_moves(game);
+        moves.retain(|m| m.start != king || !game.is_capture(*m));
+        moves
+    }
+
+    fn captures_explode(&self) -> bool {
+        true
+    }
+
+    /// The opponent's king gives no check, and neither does anything else next to it. Checks
+    /// count through the own king, since next to the opponent's king it may stand in the line
+    /// of a slider without being in check, but not once it has castled away from there.
+    fn checked_squares(&self, game: &Game, color: Color) -> Bitboard {
+        let (Some(king), Some(enemy_king)) = (game.king_square(color), game.king_square(!color))
+        else {
+            return Bitboard::empty();
+        };
+        let enemies = game.color_bitboards[!color as usize];
+        let pieces = |piece: Piece| enemies & game.piece_bitboards[piece as usize];
+        let queens = pieces(Piece::QUEEN);
+        let mut checked = movegen::sliding_attacks(
+            pieces(Piece::ROOK) | queens,
+            pieces(Piece::BISHOP) | queens,
+            game.all_pieces() ^ king,
+        );
+        let mut steppers = pieces(Piece::PAWN) | pieces(Piece::KNIGHT);
+        while !steppers.is_empty() {
+            checked |= game.attacks_from(Square::from_u8(steppers.trailing_zeros() as u8));
+            steppers.clear_lsb();
+        }
+        checked & !game.attacks_from(enemy_king)
+    }
+
+    /// A move is legal if the own king survives it and either the opponent's king doesn't or
+    /// the own king isn't left in check. Explosions can remove any number of pieces, so every
+    /// move is played out on a copy.
+    fn retain_legal(&self, game: &Game, moves: &mut MoveList) {
+        let color = game.to_move;
+        moves.retain(|m| {
+            let mut after = *game;
+            after.make_move(*m);
+            after.king_square(color).is_some()
+                && (after.king_square(!color).is_none() || !is_check(&after, color))
+        });
+    }
+
+    /// The side to move has lost once its king has exploded.
+    fn variant_termination(&self, game: &Game) -> Option<Termination> {
+        game.king_square(game.to_move)
+            .is_none()
+            .then_some(Termination::VARIANT_LOSS)
+    }
+
+    fn no_moves(&self, game: &Game) -> Termination {
+        if is_check(game, game.to_move) {
+            Termination::CHECKMATE
+        } else {
+            Termination::STALEMATE
+        }
+    }
+
+    /// Only bare kings can't win anymore, since a king can't capture. Any other piece may
+    /// still capture the opponent's king if it walks into it.
+    fn is_insufficient_material(&self, game: &Game) -> bool {
+        game.all_pieces() == game.piece_bitboards[Piece::KING as usize]
+    }
+}
+
+/// Returns `true` if `color`'s king is attacked by a piece other than the opponent's king and
+/// the two kings don't stand next to each other.
+pub fn is_check(game: &Game, color: Color) -> bool {
+    let (Some(king), Some(enemy_king)) = (game.king_square(color), game.king_square(!color)) else {
+        return false;
+    };
+    if game.attacks_from(king).contains(enemy_king) {
+        return false;
+    }
+    let attackers = game.attackers_to(king, game.all_pieces())
+        & game.color_bitboards[!color as usize]
+        & !Bitboard::from_square(enemy_king);
+    !attackers.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{movegen::all_legal_moves, movegen::perft, variant::Variant, Move};
+
+    fn atomic(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.variant = Variant::ATOMIC;
+        game
+    }
+
+    #[test]
+    fn explosions_unmake() {
+        let mut game = atomic("rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1");
+        let before = game;
+        for m in all_legal_moves(&game) {
+            let key = game.key_after(m);
+            let undo = game.make_move(m);
+            assert_eq!(game.key(), key);
+            assert_eq!(game, atomic(&game.to_fen()));
+            game.unmake_move(m, undo);
+            assert_eq!(game, before);
+        }
+    }
+
+    #[test]
+    fn kings_may_not_capture() {
+        let game = atomic("8/8/8/8/8/8/3pk3/4K3 w - - 0 1");
+        let moves = all_legal_moves(&game);
+        assert!(!moves.contains(&Move::from_uci("e1d2").unwrap()));
+        assert!(!moves.contains(&Move::from_uci("e1e2").unwrap()));
+        assert!(moves.contains(&Move::from_uci("e1f1").unwrap()));
+    }
+
+    #[test]
+    fn touching_kings_ignore_checks() {
+        // The rook can't take the king on e1 without exploding its own on d2
+        let game = atomic("4r3/8/8/8/8/8/P2k4/4K3 w - - 0 1");
+        assert!(!is_check(&game, Color::WHITE));
+        assert!(all_legal_moves(&game).contains(&Move::from_uci("a2a3").unwrap()));
+        let game = atomic("4r3/8/8/8/8/8/8/4K1k1 w - - 0 1");
+        assert!(is_check(&game, Color::WHITE));
+    }
+
+    #[test]
+    fn perft_matches_reference() {
+        // The king castles next to the opponent's king, which gives no check
+        let game = atomic("8/8/8/8/8/8/2k5/rR4KR w KQ - 0 1");
+        assert_eq!(perft(&game, 3), 4364);
+        let game = atomic("rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1");
+        assert_eq!(perft(&game, 3), 23353);
+    }
+}
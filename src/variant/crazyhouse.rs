@@ -0,0 +1,142 @@
+//! Crazyhouse: a captured piece changes sides and goes to the capturer's pocket, from where
+//! it can be dropped on any empty square instead of making a move. Pawns can't be dropped on
+//! the first or last rank, and a promoted piece goes back to the pocket as a pawn.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{movegen::all_legal_moves, variant::Variant, Color, Move, Piece};
+//! let mut game = Variant::CRAZYHOUSE.starting_position();
+//! for uci in ["e2e4", "d7d5", "e4d5"] {
+//!     game.make_move(Move::from_uci(uci).unwrap());
+//! }
+//! assert_eq!(game.pocket(Color::WHITE, Piece::PAWN), 1);
+//! assert_eq!(game.to_fen(), "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR[P] b KQkq - 0 2");
+//!
+//! game.make_move(Move::from_uci("d8d5").unwrap());
+//! let drop = Move::from_uci("P@e4").unwrap();
+//! assert!(all_legal_moves(&game).contains(&drop));
+//! assert_eq!(game.san(drop).unwrap(), "@e4");
+//! ```
+
+use super::Rules;
+use crate::{
+    game::Game,
+    movegen::{self, drop_moves},
+    MoveList,
+};
+
+/// The rules of Crazyhouse.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Crazyhouse;
+
+impl Rules for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "crazyhouse"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        let mut moves = movegen::pseudo_legal_moves(game);
+        drop_moves(game, &mut moves);
+        moves
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+
+    /// Every captured piece comes back, so there is always enough material to mate.
+    fn is_insufficient_material(&self, _game: &Game) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::Game,
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Color, Move, Piece, Square,
+    };
+
+    #[test]
+    fn pocket_fen_roundtrip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            "r1bqk2r/pppp1ppp/2n1p3/4P3/1b1Pn3/2NB1N2/PPP2PPP/R1BQK2R[PNbq] b KQkq - 0 7",
+            "4k3/1Q~6/8/8/4b3/8/Kpp5/8[] b - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.variant, Variant::CRAZYHOUSE);
+            assert_eq!(game.to_fen(), fen);
+        }
+        let ninth_rank = Game::from_fen("4k3/8/8/8/8/8/8/4K3/Qp w - - 0 1").unwrap();
+        assert_eq!(ninth_rank.to_fen(), "4k3/8/8/8/8/8/8/4K3[Qp] w - - 0 1");
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").is_err());
+        assert!(Game::from_fen("4k3/~7/8/8/8/8/8/4K3[] w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn captured_pieces_change_sides() {
+        let mut game = Game::from_fen("4k3/1Q~6/8/8/4b3/8/8/K7[] b - - 0 1").unwrap();
+        let before = game;
+        // The promoted queen goes to the pocket as a pawn
+        let bxb7 = Move::from_uci("e4b7").unwrap();
+        let key = game.key_after(bxb7);
+        let undo = game.make_move(bxb7);
+        assert_eq!(game.key(), key);
+        assert_eq!(game.pocket(Color::BLACK, Piece::PAWN), 1);
+        assert_eq!(game.pocket(Color::BLACK, Piece::QUEEN), 0);
+        assert!(game.promoted().is_empty());
+        assert_eq!(game.to_fen(), "4k3/1b6/8/8/8/8/8/K7[p] w - - 0 2");
+        assert_eq!(game, Game::from_fen(&game.to_fen()).unwrap());
+        game.unmake_move(bxb7, undo);
+        assert_eq!(game, before);
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/1q6/K7[] w - - 0 1").unwrap();
+        game.make_move(Move::from_uci("a1b2").unwrap());
+        let drops = all_legal_moves(&game)
+            .iter()
+            .filter(|m| m.is_drop())
+            .count();
+        assert_eq!(drops, 0);
+        game.make_move(Move::from_uci("e8d8").unwrap());
+        let drop = Move::new_drop(Piece::QUEEN, Square::D7);
+        assert_eq!(game.san(drop).unwrap(), "Q@d7+");
+        assert_eq!(game.parse_san("Q@d7").unwrap(), drop);
+        let key = game.key_after(drop);
+        let before = game;
+        let undo = game.make_move(drop);
+        assert_eq!(game.key(), key);
+        assert_eq!(game.to_fen(), "3k4/3Q4/8/8/8/8/1K6/8[] b - - 2 2");
+        game.unmake_move(drop, undo);
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn perft_with_drops() {
+        let game = Game::from_fen("2k5/8/8/8/8/8/8/4K3[Qn] w - - 0 1").unwrap();
+        assert_eq!(perft(&game, 1), 67);
+        assert_eq!(perft(&game, 3), 88634);
+        let game = Game::from_fen("2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1").unwrap();
+        assert_eq!(perft(&game, 1), 301);
+    }
+
+    #[test]
+    fn drops_block_checks() {
+        // A check by the rook can be blocked by a drop, the knight's check can't
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1").unwrap();
+        let blocks: Vec<Move> = all_legal_moves(&game)
+            .into_iter()
+            .filter(|m| m.is_drop())
+            .collect();
+        assert_eq!(blocks.len(), 3);
+        let game = Game::from_fen("4k3/8/8/8/8/3n4/8/4K3[N] w - - 0 1").unwrap();
+        assert!(all_legal_moves(&game).iter().all(|m| !m.is_drop()));
+    }
+}
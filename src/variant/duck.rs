@@ -0,0 +1,172 @@
+//! Duck chess: after every move the side to move puts the duck, a neutral piece that belongs
+//! to neither side, on any empty square other than the one it stood on. The duck blocks
+//! pieces like any other but can't be captured. There is no check, so the king may move into
+//! or stay in danger, and a game is won by capturing the opponent's king. A side that has no
+//! legal move wins.
+//!
+//! Every [`Move`](crate::Move) of Duck chess names the duck's new square, see
+//! [`Move::with_duck`](crate::Move::with_duck), and the duck is written as `*` in FENs.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Termination, movegen::all_legal_moves, variant::Variant, Move, Square};
+//! let mut game = Variant::DUCK.starting_position();
+//! // Each of the 20 moves with the duck on any of the 32 squares left empty after it
+//! assert_eq!(all_legal_moves(&game).len(), 20 * 32);
+//! game.make_move(Move::from_uci("e2e4,e4e5").unwrap());
+//! assert_eq!(game.duck(), Some(Square::E5));
+//! assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+//! // Nothing can go to the duck's square, so e7e5 is blocked
+//! assert!(!all_legal_moves(&game).iter().any(|m| m.end == Square::E5));
+//! assert_eq!(game.termination(), None);
+//! ```
+
+use super::Rules;
+use crate::{
+    bitboard::Bitboard,
+    game::{Game, Termination},
+    movegen, Color, MoveList, Square,
+};
+
+/// The rules of Duck chess.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Duck;
+
+impl Rules for Duck {
+    fn name(&self) -> &'static str {
+        "duck"
+    }
+
+    /// The moves of standard chess, each combined with every square the duck can go to. A
+    /// side whose king has been captured has no moves.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        let mut moves = MoveList::new();
+        if game.king_square(game.to_move).is_none() {
+            return moves;
+        }
+        let duck = game.duck();
+        for m in movegen::pseudo_legal_moves(game) {
+            if Some(m.end) == duck {
+                continue;
+            }
+            // The duck still stands on its old square after the move, so it has to leave it
+            let mut after = *game;
+            after.make_move(m);
+            let mut empty = !after.blockers();
+            while !empty.is_empty() {
+                moves.push(m.with_duck(Square::from_u8(empty.trailing_zeros() as u8)));
+                empty.clear_lsb();
+            }
+        }
+        moves
+    }
+
+    /// Without check the king may castle out of and through attacked squares.
+    fn checked_squares(&self, _game: &Game, _color: Color) -> Bitboard {
+        Bitboard::empty()
+    }
+
+    /// Without check every move that follows the movement rules is legal.
+    fn retain_legal(&self, _game: &Game, _moves: &mut MoveList) {}
+
+    /// The side to move has lost once its king has been captured.
+    fn variant_termination(&self, game: &Game) -> Option<Termination> {
+        game.king_square(game.to_move)
+            .is_none()
+            .then_some(Termination::VARIANT_LOSS)
+    }
+
+    /// Having no legal move wins.
+    fn no_moves(&self, _game: &Game) -> Termination {
+        Termination::VARIANT_WIN
+    }
+
+    /// Any piece, the king included, can still capture the opponent's king.
+    fn is_insufficient_material(&self, _game: &Game) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::Outcome,
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Move,
+    };
+
+    fn duck(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.variant = Variant::DUCK;
+        game
+    }
+
+    #[test]
+    fn the_duck_blocks_and_must_move() {
+        let game = duck("4k3/8/8/8/*7/8/8/R3K3 w Q - 0 1");
+        let moves = all_legal_moves(&game);
+        assert!(moves
+            .iter()
+            .all(|m| m.duck.is_some_and(|s| s != Square::A4)));
+        // The rook can't take the duck or pass it
+        let rook = |end| Move::new(Square::A1, end).with_duck(Square::H8);
+        assert!(moves.contains(&rook(Square::A3)));
+        assert!(!moves.contains(&rook(Square::A4)));
+        assert!(!moves.contains(&rook(Square::A8)));
+
+        // Castling is blocked by the duck, but not by attacks
+        let castle = Move::new(Square::E1, Square::C1).with_duck(Square::H8);
+        let game = duck("3rk3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        assert!(all_legal_moves(&game).contains(&castle));
+        let game = duck("4k3/8/8/8/8/8/8/R1*1K3 w Q - 0 1");
+        assert!(!all_legal_moves(&game).contains(&castle));
+    }
+
+    #[test]
+    fn moves_unmake() {
+        let mut game = duck("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        // The duck can go to any of the 32 empty squares, and a 33rd after the 8 captures
+        assert_eq!(perft(&game, 1), 48 * 32 + 8);
+        game.make_move(Move::from_uci("e2a6,a6e2").unwrap());
+        let before = game;
+        for m in all_legal_moves(&game).into_iter().step_by(7) {
+            let key = game.key_after(m);
+            let undo = game.make_move(m);
+            assert_eq!(game.key(), key);
+            assert_eq!(game, Game::from_fen(&game.to_fen()).unwrap());
+            assert_eq!(Move::from_uci(&m.to_string()), Ok(m));
+            assert_eq!(before.parse_san(&before.san(m).unwrap()), Ok(m));
+            game.unmake_move(m, undo);
+            assert_eq!(game, before);
+        }
+    }
+
+    #[test]
+    fn capturing_the_king_wins() {
+        let mut game = duck("4k3/8/8/8/8/8/4q3/4K3 w - - 0 1");
+        // Without check the king may stay next to the queen
+        let m = Move::new(Square::E1, Square::F1).with_duck(Square::A1);
+        assert!(all_legal_moves(&game).contains(&m));
+        game.make_move(m);
+        game.make_move(Move::new(Square::E2, Square::F1).with_duck(Square::A2));
+        assert_eq!(game.termination(), Some(Termination::VARIANT_LOSS));
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome::DECISIVE {
+                winner: Color::BLACK
+            })
+        );
+    }
+
+    #[test]
+    fn having_no_moves_wins() {
+        // The duck on g3 walls in the last of White's pieces
+        let game = duck("k7/8/8/8/5p1p/5P*P/5PPP/6BK w - - 0 1");
+        assert_eq!(game.termination(), Some(Termination::VARIANT_WIN));
+        let game = duck("k7/8/8/8/5p1p/5P1P/5PPP/6BK w - - 0 1");
+        assert_eq!(game.termination(), None);
+    }
+}
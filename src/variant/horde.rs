@@ -0,0 +1,92 @@
+//! Horde: White has 36 pawns and no king against Black's regular army. Black wins by
+//! capturing every white piece, White by checkmating as usual. White's pawns on the first
+//! rank may move two squares, like those on the second.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::game::{Game, Outcome, Termination};
+//! use kritisch::{movegen::all_legal_moves, variant::Variant, Color, Move, Piece};
+//! let game = Variant::HORDE.starting_position();
+//! assert_eq!(game.piece_count(Color::WHITE, Piece::PAWN), 36);
+//! assert_eq!(all_legal_moves(&game).len(), 8);
+//!
+//! // Taking the last pawn wins for Black
+//! let mut game = Game::from_fen("4k3/8/8/8/8/8/8/3q3P b - - 0 1").unwrap();
+//! game.variant = Variant::HORDE;
+//! game.make_move(Move::from_uci("d1h1").unwrap());
+//! assert_eq!(game.termination(), Some(Termination::VARIANT_LOSS));
+//! assert_eq!(game.outcome(), Some(Outcome::DECISIVE { winner: Color::BLACK }));
+//! ```
+
+use super::Rules;
+use crate::{
+    game::{Game, Termination},
+    Color,
+};
+
+/// The rules of Horde.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Horde;
+
+impl Rules for Horde {
+    fn name(&self) -> &'static str {
+        "horde"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+    }
+
+    /// White has lost once Black has captured all of its pieces.
+    fn variant_termination(&self, game: &Game) -> Option<Termination> {
+        (game.to_move == Color::WHITE && game.color_bitboards[Color::WHITE as usize].is_empty())
+            .then_some(Termination::VARIANT_LOSS)
+    }
+
+    /// Black can always still capture the horde, and the horde can still promote.
+    fn is_insufficient_material(&self, _game: &Game) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::{Game, Termination},
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Move,
+    };
+
+    fn horde(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.variant = Variant::HORDE;
+        game
+    }
+
+    #[test]
+    fn first_rank_pawns_move_two_squares() {
+        let game = horde("4k3/8/8/8/8/8/8/P6P w - - 0 1");
+        let moves = all_legal_moves(&game);
+        assert_eq!(moves.len(), 4);
+        assert!(moves.contains(&Move::from_uci("a1a3").unwrap()));
+        let game = horde("4k3/8/8/8/8/p7/8/P7 w - - 0 1");
+        assert_eq!(all_legal_moves(&game).len(), 1);
+    }
+
+    #[test]
+    fn perft_matches_reference() {
+        assert_eq!(perft(&Variant::HORDE.starting_position(), 2), 128);
+        let game = horde("4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1");
+        assert_eq!(perft(&game, 3), 6633);
+    }
+
+    #[test]
+    fn white_can_still_checkmate() {
+        let game = horde("k7/1PP5/PP6/8/8/8/8/8 b - - 0 1");
+        assert_eq!(game.termination(), Some(Termination::CHECKMATE));
+        let game = horde("k7/8/8/8/8/8/p7/P7 w - - 0 1");
+        assert_eq!(game.termination(), Some(Termination::STALEMATE));
+    }
+}
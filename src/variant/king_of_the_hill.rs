@@ -0,0 +1,95 @@
+//! King of the Hill: standard chess, except that a king reaching one of the four center
+//! squares wins the game on the spot. The king may not step into check to get there.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::{Game, Outcome, Termination}, variant::Variant, Color, Move};
+//! let mut game = Game::from_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+//! game.variant = Variant::KING_OF_THE_HILL;
+//! game.make_move(Move::from_uci("e3e4").unwrap());
+//! assert_eq!(game.termination(), Some(Termination::VARIANT_LOSS));
+//! assert_eq!(game.outcome(), Some(Outcome::DECISIVE { winner: Color::WHITE }));
+//! ```
+
+use super::Rules;
+use crate::{
+    bitboard::Bitboard,
+    game::{Game, Termination},
+    movegen, MoveList,
+};
+
+/// The squares d4, e4, d5 and e5.
+pub const CENTER: Bitboard = Bitboard::from_u64(0x0000_0018_1800_0000);
+
+/// The rules of King of the Hill.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct KingOfTheHill;
+
+impl Rules for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "kingofthehill"
+    }
+
+    /// Once a king has reached the center the game is over, so there are no moves left.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        if self.variant_termination(game).is_some() {
+            return MoveList::new();
+        }
+        movegen::pseudo_legal_moves(game)
+    }
+
+    /// The side to move has lost if the opponent's king stands in the center. Its own king
+    /// can't, since the game would have ended when it got there.
+    fn variant_termination(&self, game: &Game) -> Option<Termination> {
+        game.king_square(!game.to_move)
+            .is_some_and(|king| CENTER.contains(king))
+            .then_some(Termination::VARIANT_LOSS)
+    }
+
+    /// A lone king can still walk to the center.
+    fn is_insufficient_material(&self, _game: &Game) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Move,
+    };
+
+    fn king_of_the_hill(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.variant = Variant::KING_OF_THE_HILL;
+        game
+    }
+
+    #[test]
+    fn reaching_the_center_ends_the_game() {
+        let game = king_of_the_hill("8/8/8/8/8/4K3/8/k7 w - - 0 1");
+        assert_eq!(perft(&game, 1), 8);
+        // After Kd4 and Ke4 black has no moves left
+        assert_eq!(perft(&game, 2), 6 * 3);
+
+        let mut game = king_of_the_hill("4k3/8/8/3r4/8/4K3/8/8 w - - 0 1");
+        // The rook guards d4 and e5, but not e4
+        let moves = all_legal_moves(&game);
+        assert!(!moves.contains(&Move::from_uci("e3d4").unwrap()));
+        game.make_move(Move::from_uci("e3e4").unwrap());
+        assert_eq!(game.termination(), Some(Termination::VARIANT_LOSS));
+        assert!(all_legal_moves(&game).is_empty());
+    }
+
+    #[test]
+    fn bare_kings_play_on() {
+        let game = king_of_the_hill("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(game.termination(), None);
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+            .unwrap()
+            .is_insufficient_material());
+    }
+}
@@ -0,0 +1,194 @@
+//! Minichess on boards smaller than 8x8, such as Gardner's 5x5, a 5x6 board and the 6x6 of
+//! Los Alamos chess, small enough for teaching and for exhaustive analysis.
+//!
+//! The small board is the corner of the regular board from a1 on, so bitboards and squares
+//! stay the same and the move generator only has to keep the pieces inside it. The ranks and
+//! files past the small board are left empty. FENs only describe the small board, as other
+//! minichess programs write them, and [`Game::from_fen`] tells the variant by its size. Pawns
+//! move a single square only, there is no castling or en passant, and White's pawns promote on
+//! the last rank of the small board.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, movegen::all_legal_moves, variant::Variant, Move, Square};
+//! let mut game = Variant::GARDNER.starting_position();
+//! assert_eq!(game.to_fen(), "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1");
+//! assert_eq!(Game::from_fen("rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1").unwrap(), game);
+//! assert_eq!(all_legal_moves(&game).len(), 7);
+//! game.make_move(Move::from_uci("b2b3").unwrap());
+//! // The knight on b5 can't jump off the board to c7
+//! assert!(!all_legal_moves(&game).iter().any(|m| m.end == Square::C7));
+//! ```
+
+use super::Rules;
+use crate::{bitboard::Bitboard, game::Game, movegen, Color, Move, MoveList, Piece};
+
+/// The rules of a minichess variant played on the `files` by `ranks` corner of the board.
+#[derive(Debug, Copy, Clone)]
+pub struct Minichess {
+    name: &'static str,
+    starting_fen: &'static str,
+    files: u8,
+    ranks: u8,
+    promotion_pieces: &'static [Piece],
+}
+
+/// Gardner minichess on 5x5, the standard pieces without the queen's side rook, knight and
+/// bishop.
+pub const GARDNER: Minichess = Minichess {
+    name: "gardner",
+    starting_fen: "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1",
+    files: 5,
+    ranks: 5,
+    promotion_pieces: &[Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT],
+};
+
+/// Minichess on 5 files by 6 ranks, Gardner's pieces with two empty ranks between the pawns.
+pub const FIVE_BY_SIX: Minichess = Minichess {
+    name: "minichess5x6",
+    starting_fen: "kqbnr/ppppp/5/5/PPPPP/RNBQK w - - 0 1",
+    files: 5,
+    ranks: 6,
+    promotion_pieces: &[Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT],
+};
+
+/// Los Alamos chess on 6x6, without bishops, where pawns can't promote to them either.
+pub const LOS_ALAMOS: Minichess = Minichess {
+    name: "losalamos",
+    starting_fen: "rnqknr/pppppp/6/6/PPPPPP/RNQKNR w - - 0 1",
+    files: 6,
+    ranks: 6,
+    promotion_pieces: &[Piece::QUEEN, Piece::ROOK, Piece::KNIGHT],
+};
+
+impl Minichess {
+    /// Returns the squares of the small board.
+    pub const fn board(&self) -> Bitboard {
+        let rank = (1u64 << self.files) - 1;
+        let mut squares = 0;
+        let mut i = 0;
+        while i < self.ranks {
+            squares |= rank << (8 * i);
+            i += 1;
+        }
+        Bitboard::from_u64(squares)
+    }
+}
+
+impl Rules for Minichess {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        self.starting_fen
+    }
+
+    fn board_size(&self) -> (u8, u8) {
+        (self.files, self.ranks)
+    }
+
+    /// The moves of standard chess that stay on the small board, without pawns moving two
+    /// squares. A white pawn reaching the last rank of the small board promotes.
+    fn pseudo_legal_moves(&self, game: &Game) -> MoveList {
+        let board = self.board();
+        let last_rank = 8 * (self.ranks - 1);
+        let mut moves = MoveList::new();
+        for m in movegen::pseudo_legal_moves(game) {
+            if !board.contains(m.end) {
+                continue;
+            }
+            if game.type_at(m.start) != Piece::PAWN {
+                moves.push(m);
+            } else if (m.end as u8).abs_diff(m.start as u8) > 9 {
+                continue;
+            } else if game.to_move == Color::WHITE && m.end as u8 >= last_rank && last_rank < 56 {
+                for &piece in self.promotion_pieces {
+                    moves.push(Move::new_promotion(m.start, m.end, piece));
+                }
+            } else {
+                moves.push(m);
+            }
+        }
+        moves
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        self.promotion_pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        movegen::{all_legal_moves, perft},
+        variant::Variant,
+        Square,
+    };
+
+    #[test]
+    fn perft_regression() {
+        // The counts of this implementation. Those of the first two depths are checked by
+        // hand: after each of White's seven moves Black has its own seven, less the pawn
+        // blocked by White's and plus captures of the moved piece: 7 + 8 + 8 + 8 + 7 + 7 + 8
+        let game = Variant::GARDNER.starting_position();
+        assert_eq!(
+            (1..=4).map(|depth| perft(&game, depth)).collect::<Vec<_>>(),
+            [7, 53, 506, 4775]
+        );
+    }
+
+    #[test]
+    fn pieces_stay_on_the_small_board() {
+        assert_eq!(GARDNER.board(), Bitboard::from_u64(0x1f_1f1f_1f1f));
+        let game = Game::from_fen("4k/5/2Q2/5/K4 w - - 0 1").unwrap();
+        assert_eq!(game.variant, Variant::GARDNER);
+        let moves = all_legal_moves(&game);
+        assert!(moves.iter().all(|m| GARDNER.board().contains(m.end)));
+        assert!(moves.contains(&Move::new(Square::C3, Square::E5)));
+        assert!(!moves.contains(&Move::new(Square::C3, Square::F6)));
+    }
+
+    #[test]
+    fn pawns_promote_on_the_last_rank() {
+        let game = Game::from_fen("4k/1P3/5/p4/4K w - - 0 1").unwrap();
+        let promotions = |game: &Game| {
+            all_legal_moves(game)
+                .iter()
+                .filter(|m| m.promotion.is_some())
+                .count()
+        };
+        assert_eq!(promotions(&game), 4);
+        let mut black = game;
+        black.make_move(Move::from_uci("e1d1").unwrap());
+        assert_eq!(promotions(&black), 4);
+
+        let mut game = Variant::LOS_ALAMOS.starting_position();
+        game.make_move(Move::from_uci("a2a3").unwrap());
+        let moves = all_legal_moves(&game);
+        assert_eq!(moves.len(), 6 + 4);
+        let game = Game::from_fen("4k1/1P4/6/6/6/K5 w - - 0 1").unwrap();
+        assert_eq!(game.variant, Variant::LOS_ALAMOS);
+        assert_eq!(promotions(&game), 3);
+        assert_eq!(game.to_fen(), "4k1/1P4/6/6/6/K5 w - - 0 1");
+        assert!(Game::from_fen("4k1/1P4/6/6/K5 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn five_by_six() {
+        let game = Game::from_fen("kqbnr/ppppp/5/5/PPPPP/RNBQK w - - 0 1").unwrap();
+        assert_eq!(game.variant, Variant::FIVE_BY_SIX);
+        assert_eq!(game, Variant::FIVE_BY_SIX.starting_position());
+        assert_eq!(FIVE_BY_SIX.board(), Bitboard::from_u64(0x1f1f_1f1f_1f1f));
+        // A single step for each pawn and two for the knight
+        assert_eq!(all_legal_moves(&game).len(), 7);
+
+        let game = Game::from_fen("4k/1P3/5/5/5/K4 w - - 0 1").unwrap();
+        let moves = all_legal_moves(&game);
+        assert!(moves.contains(&Move::new_promotion(Square::B5, Square::B6, Piece::QUEEN)));
+        assert!(moves.iter().all(|m| FIVE_BY_SIX.board().contains(m.end)));
+        assert_eq!(game.to_fen(), "4k/1P3/5/5/5/K4 w - - 0 1");
+    }
+}
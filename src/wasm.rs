@@ -0,0 +1,178 @@
+//! Bindings for JavaScript through wasm-bindgen, enabled by the `wasm` feature, so that the
+//! crate can run a chessboard in the browser.
+//!
+//! A [`Position`] holds a game and is set up from a FEN or the starting position. Moves go in
+//! and come out in UCI notation, e.g. `e2e4`, and can be converted to and from SAN. Errors
+//! reach JavaScript as exceptions carrying the message of the [`Error`].
+//!
+//! ```js
+//! import { Position } from "kritisch";
+//! const position = new Position();
+//! position.makeMove("e4");
+//! position.legalMoves(); // ["a7a6", "a7a5", ...]
+//! position.search(8, 1000); // e.g. "c7c5"
+//! ```
+//!
+//! The crate builds as a Rust library only. The WebAssembly module comes from
+//!
+//! ```sh
+//! cargo rustc --lib --release --features wasm --target wasm32-unknown-unknown --crate-type cdylib
+//! ```
+//!
+//! and `wasm-bindgen` makes the package to import from its `.wasm` file.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::wasm::Position;
+//! let mut position = Position::new();
+//! position.make_move("e2e4").unwrap();
+//! assert_eq!(position.legal_moves().len(), 20);
+//! assert_eq!(position.fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+//! ```
+
+use std::time::Duration;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    game::Game,
+    movegen::all_legal_moves,
+    pgn,
+    search::{self, SearchLimits},
+    Error, Move, Result,
+};
+
+/// A game for JavaScript.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    game: Game,
+}
+
+#[wasm_bindgen]
+impl Position {
+    /// Returns the starting position.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a position from `fen`.
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: &str) -> std::result::Result<Position, JsError> {
+        Ok(Self {
+            game: Game::from_fen(fen).map_err(js_error)?,
+        })
+    }
+
+    /// Returns the position in FEN.
+    pub fn fen(&self) -> String {
+        self.game.to_fen()
+    }
+
+    /// Returns the side to move, `white` or `black`.
+    pub fn turn(&self) -> String {
+        self.game.to_move.to_string()
+    }
+
+    /// Returns the legal moves in UCI notation.
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        all_legal_moves(&self.game)
+            .iter()
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    /// Plays `text`, a legal move in UCI notation or SAN, and returns it in SAN.
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, text: &str) -> std::result::Result<String, JsError> {
+        let m = self.parse_move(text).map_err(js_error)?;
+        let san = self.game.san(m).map_err(js_error)?;
+        self.game.make_move(m);
+        Ok(san)
+    }
+
+    /// Converts the legal move `uci` to SAN.
+    #[wasm_bindgen(js_name = toSan)]
+    pub fn to_san(&self, uci: &str) -> std::result::Result<String, JsError> {
+        let m = Move::from_uci(uci).map_err(js_error)?;
+        self.game.san(m).map_err(js_error)
+    }
+
+    /// Converts the legal move `san` to UCI notation.
+    #[wasm_bindgen(js_name = fromSan)]
+    pub fn from_san(&self, san: &str) -> std::result::Result<String, JsError> {
+        let m = self.game.parse_san(san).map_err(js_error)?;
+        Ok(m.to_string())
+    }
+
+    /// Returns `true` if the side to move is in check.
+    #[wasm_bindgen(js_name = isCheck)]
+    pub fn is_check(&self) -> bool {
+        self.game.is_check()
+    }
+
+    /// Returns the result as written in PGN, e.g. `1-0`, or `*` while the game goes on.
+    pub fn result(&self) -> String {
+        pgn::format_result(self.game.outcome()).to_string()
+    }
+
+    /// Searches the position to `depth` for at most `millis` milliseconds, leaving out either
+    /// limit if it is 0, and returns the best move in UCI notation, or `undefined` if there is
+    /// no legal move.
+    pub fn search(&self, depth: u32, millis: u32) -> Option<String> {
+        let limits = SearchLimits {
+            depth: (depth > 0).then_some(depth),
+            time: (millis > 0).then(|| Duration::from_millis(millis.into())),
+            ..SearchLimits::default()
+        };
+        let result = search::search(&self.game, limits);
+        result.best_move.map(|m| m.to_string())
+    }
+}
+
+impl Position {
+    /// Parses a legal move given in UCI notation or SAN.
+    fn parse_move(&self, text: &str) -> Result<Move> {
+        match Move::from_uci(text) {
+            Ok(m) if all_legal_moves(&self.game).contains(&m) => Ok(m),
+            Ok(m) => Err(Error::IllegalMove(m)),
+            Err(_) => self.game.parse_san(text),
+        }
+    }
+}
+
+fn js_error(e: Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_moves_in_either_notation() {
+        let mut position = Position::new();
+        assert_eq!(position.make_move("e2e4").unwrap(), "e4");
+        assert_eq!(position.make_move("e5").unwrap(), "e5");
+        assert_eq!(position.to_san("g1f3").unwrap(), "Nf3");
+        assert_eq!(position.from_san("Nf3").unwrap(), "g1f3");
+        assert!(position.parse_move("e2e4").is_err());
+        for m in ["d1h5", "b8c6", "f1c4", "g8f6", "Qxf7#"] {
+            position.make_move(m).unwrap();
+        }
+        assert!(position.is_check());
+        assert_eq!(position.result(), "1-0");
+        assert!(position.legal_moves().is_empty());
+        assert_eq!(position.search(2, 0), None);
+    }
+
+    #[test]
+    fn searches_for_the_best_move() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(position.turn(), "white");
+        assert_eq!(position.search(3, 0).as_deref(), Some("a1a8"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Win, draw and loss probabilities for a score, e.g. for showing them next to the score or
+//! for reviewing games.
+//!
+//! The model is logistic: the probability of a win grows with the score along a curve that is
+//! even at [`WIN_SCORE`] and about as steep as [`SPREAD`] says, and the probability of a loss
+//! is the same curve for the negated score. The rest is a draw. A lead of the same size is
+//! worth more with less material on the board, so both parameters shrink with the
+//! [`phase`], from their middlegame values to their endgame ones.
+//!
+//! # Example
+//!
+//! ```
+//! use kritisch::{game::Game, wdl::wdl};
+//! let even = wdl(&Game::default(), 0);
+//! assert_eq!(even.win, even.loss);
+//! assert!(even.draw > 800);
+//!
+//! let ahead = wdl(&Game::default(), 300);
+//! assert!(ahead.win > ahead.draw && ahead.win + ahead.draw + ahead.loss == 1000);
+//! ```
+
+use crate::{
+    eval::{phase, MAX_PHASE},
+    game::Game,
+    search::mate_in,
+};
+
+/// The score in centipawns at which a win is as likely as not, in the endgame and in the
+/// middlegame.
+pub const WIN_SCORE: [f64; 2] = [150.0, 250.0];
+/// How many centipawns it takes to make a win about e times as likely, in the endgame and in
+/// the middlegame.
+pub const SPREAD: [f64; 2] = [50.0, 80.0];
+
+/// Win, draw and loss probabilities in permille, which add up to 1000.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+impl Wdl {
+    /// Returns the probabilities for `score` from the point of view of the side to move, in a
+    /// position of the game `phase` between 0 and [`MAX_PHASE`]. Mate scores are sure wins or
+    /// losses, see [`mate_in`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kritisch::{eval::MAX_PHASE, search::MATE, wdl::Wdl};
+    /// assert_eq!(Wdl::from_score(MATE - 5, MAX_PHASE), Wdl { win: 1000, draw: 0, loss: 0 });
+    /// // A pawn up is more likely to win in the endgame
+    /// assert!(Wdl::from_score(100, 0).win > Wdl::from_score(100, MAX_PHASE).win);
+    /// ```
+    pub fn from_score(score: i32, phase: i32) -> Self {
+        match mate_in(score) {
+            Some(moves) if moves > 0 => Self::win(),
+            Some(_) => Self::win().flip(),
+            None => {
+                let t = phase.clamp(0, MAX_PHASE) as f64 / MAX_PHASE as f64;
+                let lerp = |[endgame, middlegame]: [f64; 2]| endgame + t * (middlegame - endgame);
+                let (win_score, spread) = (lerp(WIN_SCORE), lerp(SPREAD));
+                let probability = |score: f64| {
+                    let p = 1.0 / (1.0 + ((win_score - score) / spread).exp());
+                    (p * 1000.0).round() as u32
+                };
+                let (mut win, mut loss) = (probability(score as f64), probability(-score as f64));
+                // Rounding both up must not push the sum past 1000
+                let excess = (win + loss).saturating_sub(1000);
+                if win < loss {
+                    win -= excess;
+                } else {
+                    loss -= excess;
+                }
+                Self {
+                    win,
+                    draw: 1000 - win - loss,
+                    loss,
+                }
+            }
+        }
+    }
+
+    /// Returns the probabilities from the opponent's point of view.
+    pub fn flip(self) -> Self {
+        Self {
+            win: self.loss,
+            draw: self.draw,
+            loss: self.win,
+        }
+    }
+
+    /// Returns the expected result between 0 for a sure loss and 1 for a sure win, with a draw
+    /// counting half.
+    pub fn expected_score(&self) -> f64 {
+        (self.win as f64 + self.draw as f64 / 2.0) / 1000.0
+    }
+
+    fn win() -> Self {
+        Self {
+            win: 1000,
+            draw: 0,
+            loss: 0,
+        }
+    }
+}
+
+/// Displays the probabilities as in UCI's `info wdl`, e.g. `120 850 30`.
+impl std::fmt::Display for Wdl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.win, self.draw, self.loss)
+    }
+}
+
+/// Returns the probabilities for `score` from the point of view of the side to move in
+/// `game`, taking the game phase from its material.
+pub fn wdl(game: &Game, score: i32) -> Wdl {
+    Wdl::from_score(score, phase(game))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MATE;
+
+    #[test]
+    fn is_symmetric_and_monotonic() {
+        for phase in 0..=MAX_PHASE {
+            let mut previous = Wdl::from_score(-2000, phase);
+            for score in (-1990..=2000).step_by(10) {
+                let wdl = Wdl::from_score(score, phase);
+                assert_eq!(wdl.win + wdl.draw + wdl.loss, 1000);
+                assert_eq!(Wdl::from_score(-score, phase), wdl.flip());
+                assert!(wdl.win >= previous.win && wdl.loss <= previous.loss);
+                previous = wdl;
+            }
+            assert_eq!(previous.win, 1000);
+        }
+    }
+
+    #[test]
+    fn mates_are_decided() {
+        assert_eq!(Wdl::from_score(MATE - 1, 10), Wdl::win());
+        assert_eq!(Wdl::from_score(-MATE + 2, 10), Wdl::win().flip());
+        assert_eq!(Wdl::win().expected_score(), 1.0);
+        assert_eq!(Wdl::from_score(0, 10).expected_score(), 0.5);
+    }
+}
@@ -0,0 +1,142 @@
+//! Zobrist hashing: a random key for every piece on every square, for each set of castling
+//! rights, each en passant file, for black to move, for the number of pieces of each kind
+//! in a Crazyhouse pocket and for the duck of Duck chess on every square. The key of a position is the XOR of the
+//! keys of everything in it, so it can be updated incrementally as pieces move.
+//!
+//! [`Game::key`](crate::game::Game::key) returns the key of a position.
+
+use crate::{CastlingRights, Color, ColoredPiece, Square};
+
+/// The seed of the key generator. Changing it changes every key.
+const SEED: u64 = 0x6b72_6974_6973_6368;
+
+const PIECE_SQUARE: [[[u64; 64]; 6]; 2] = {
+    let mut keys = [[[0; 64]; 6]; 2];
+    let mut state = SEED;
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 6 {
+            let mut square = 0;
+            while square < 64 {
+                keys[color][piece][square] = splitmix64(&mut state);
+                square += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    keys
+};
+
+const CASTLING: [u64; 16] = {
+    let mut keys = [0; 16];
+    let mut state = SEED ^ 1;
+    let mut i = 0;
+    while i < 16 {
+        keys[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    keys
+};
+
+const EN_PASSANT: [u64; 8] = {
+    let mut keys = [0; 8];
+    let mut state = SEED ^ 2;
+    let mut i = 0;
+    while i < 8 {
+        keys[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    keys
+};
+
+const BLACK_TO_MOVE: u64 = splitmix64(&mut (SEED ^ 3));
+
+/// The most pieces of one kind a pocket can hold with a distinct key.
+const MAX_POCKET: usize = 32;
+
+const POCKET: [[[u64; MAX_POCKET + 1]; 5]; 2] = {
+    let mut keys = [[[0; MAX_POCKET + 1]; 5]; 2];
+    let mut state = SEED ^ 4;
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 5 {
+            // An empty pocket adds nothing
+            let mut count = 1;
+            while count <= MAX_POCKET {
+                keys[color][piece][count] = splitmix64(&mut state);
+                count += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    keys
+};
+
+const DUCK: [u64; 64] = {
+    let mut keys = [0; 64];
+    let mut state = SEED ^ 5;
+    let mut i = 0;
+    while i < 64 {
+        keys[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    keys
+};
+
+/// Returns the key of `piece` standing on `square`.
+#[inline]
+pub const fn piece(piece: ColoredPiece, square: Square) -> u64 {
+    PIECE_SQUARE[piece.color as usize][piece.piece as usize][square as usize]
+}
+
+/// Returns the key of a set of castling rights.
+#[inline]
+pub const fn castling(rights: CastlingRights) -> u64 {
+    CASTLING[rights.bits() as usize]
+}
+
+/// Returns the key of an en passant square, which only depends on its file.
+#[inline]
+pub const fn en_passant(square: Square) -> u64 {
+    EN_PASSANT[square as usize % 8]
+}
+
+/// Returns the key of `color` being to move, zero for white.
+#[inline]
+pub const fn side(color: Color) -> u64 {
+    match color {
+        Color::WHITE => 0,
+        Color::BLACK => BLACK_TO_MOVE,
+    }
+}
+
+/// Returns the key of `count` pieces of `piece.piece` in the pocket of `piece.color`, zero
+/// for none. Counts above 32 share a key.
+#[inline]
+pub const fn pocket(piece: ColoredPiece, count: u8) -> u64 {
+    let count = if count as usize > MAX_POCKET {
+        MAX_POCKET
+    } else {
+        count as usize
+    };
+    POCKET[piece.color as usize][piece.piece as usize][count]
+}
+
+/// Returns the key of the duck standing on `square`.
+#[inline]
+pub const fn duck(square: Square) -> u64 {
+    DUCK[square as usize]
+}
+
+/// Advances `state` and returns the next pseudo-random number.
+pub(crate) const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}